@@ -10,7 +10,7 @@ uniform mat4 projection;
 uniform vec2 resolution;
 
 uniform float gamma = 2.2;
-uniform float exposure = 1.0;
+uniform float exposure = 1.0; //From combustion_common::photometry::Exposure::multiplier
 uniform float brightness = 1.0;
 uniform float saturation = 1.0;
 uniform float contrast = 1.0;
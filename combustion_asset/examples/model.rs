@@ -37,5 +37,6 @@ fn main() {
             }
         },
         pretty: true,
+        ..Default::default()
     }).unwrap();
 }
\ No newline at end of file
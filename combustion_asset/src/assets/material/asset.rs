@@ -0,0 +1,207 @@
+//! Material asset implementation
+
+use std::ops::{Deref, DerefMut};
+use std::ascii::AsciiExt;
+use std::io::BufReader;
+use std::mem;
+
+use protocols::material::{MaterialMap, Material};
+
+use ::error::{AssetResult, AssetError};
+use ::asset::{Asset, AssetMedium, AssetQuery, AssetFileFormat};
+use ::cache::AssetSize;
+
+use super::formats::MaterialFileFormat;
+
+/// Material Asset queries
+#[derive(Debug, Clone, Copy)]
+pub enum MaterialAssetQuery<'a> {
+    /// Check if a file extension for a material file is supported for importing
+    SupportedImportExtension(&'a str),
+    /// Check if a file extension for a material file is supported for exporting
+    SupportedExportExtension(&'a str),
+    /// Check if a file extension for a material file is supported for both importing and exporting
+    SupportedExtension(&'a str),
+}
+
+impl<'a> AssetQuery for MaterialAssetQuery<'a> {
+    type Arguments = MaterialAssetQuery<'a>;
+    type Result = bool;
+}
+
+/// Arguments for material save routines
+#[derive(Debug, Default, Clone)]
+pub struct MaterialAssetSaveArgs {
+    /// For serialization formats that support "pretty-printing", pretty-print the data
+    pub pretty: bool,
+}
+
+/// Material Asset, holding a map of named materials
+#[derive(Serialize, Deserialize)]
+pub struct MaterialAsset(MaterialMap);
+
+impl<'a> Asset<'a> for MaterialAsset {
+    type LoadArgs = ();
+    type SaveArgs = MaterialAssetSaveArgs;
+
+    type Query = MaterialAssetQuery<'a>;
+
+    fn query(query: MaterialAssetQuery<'a>) -> AssetResult<bool> {
+        Ok(match query {
+            MaterialAssetQuery::SupportedImportExtension(ext) => {
+                match MaterialFileFormat::from_extension(ext) {
+                    Some(format) if format.can_import() => true,
+                    _ => false
+                }
+            },
+            MaterialAssetQuery::SupportedExportExtension(ext) => {
+                match MaterialFileFormat::from_extension(ext) {
+                    Some(format) if format.can_export() => true,
+                    _ => false
+                }
+            },
+            MaterialAssetQuery::SupportedExtension(ext) => {
+                match MaterialFileFormat::from_extension(ext) {
+                    Some(format) if format.can_import() && format.can_export() => true,
+                    _ => false
+                }
+            },
+        })
+    }
+
+    fn load(medium: AssetMedium<'a>, _: ()) -> AssetResult<MaterialAsset> {
+        if let AssetMedium::File(path, vfs) = medium {
+            if let Some(ext) = path.extension() {
+                let ext = try_throw!(ext.to_str().ok_or(AssetError::InvalidValue)).to_ascii_lowercase();
+
+                let format = match MaterialFileFormat::from_extension(ext.as_str()) {
+                    Some(format) if format.can_import() => format,
+                    _ => throw!(AssetError::UnsupportedFormat),
+                };
+
+                match format {
+                    MaterialFileFormat::Standard(standard_format) => {
+                        let reader = BufReader::new(try_throw!(vfs.open(path)));
+
+                        return ::assets::standard::generic::load_standard_format(reader, standard_format);
+                    },
+                }
+            }
+        }
+
+        throw!(AssetError::UnsupportedMedium)
+    }
+
+    fn save(&self, medium: AssetMedium<'a>, args: MaterialAssetSaveArgs) -> AssetResult<()> {
+        if let AssetMedium::File(path, vfs) = medium {
+            if let Some(ext) = path.extension() {
+                let ext = try_throw!(ext.to_str().ok_or(AssetError::InvalidValue)).to_ascii_lowercase();
+
+                let format = match MaterialFileFormat::from_extension(ext.as_str()) {
+                    Some(format) if format.can_export() => format,
+                    _ => throw!(AssetError::UnsupportedFormat),
+                };
+
+                match format {
+                    MaterialFileFormat::Standard(standard_format) => {
+                        let writer = try_throw!(vfs.create_or_truncate(path));
+
+                        return ::assets::standard::generic::save_standard_format(writer, standard_format, self, args.pretty);
+                    },
+                }
+            }
+        }
+
+        throw!(AssetError::UnsupportedMedium)
+    }
+}
+
+impl MaterialAsset {
+    /// Wraps an existing material map
+    pub fn new(materials: MaterialMap) -> MaterialAsset {
+        MaterialAsset(materials)
+    }
+
+    /// Unwraps the material map
+    pub fn into_inner(self) -> MaterialMap {
+        self.0
+    }
+}
+
+impl AssetSize for MaterialAsset {
+    fn approximate_size(&self) -> usize {
+        self.0.materials.iter().map(|(name, material)| {
+            let paths = [&material.texture, &material.normal_map, &material.tangent_map,
+                &material.height_map, &material.roughness_map, &material.metallic_map];
+
+            let path_size: usize = paths.iter().map(|&path| path.as_ref().map_or(0, |path| path.as_os_str().len())).sum();
+
+            name.len() + mem::size_of::<Material>() + path_size + material.preset.as_ref().map_or(0, |preset| preset.len())
+        }).sum()
+    }
+}
+
+impl Deref for MaterialAsset {
+    type Target = MaterialMap;
+
+    fn deref(&self) -> &MaterialMap {
+        &self.0
+    }
+}
+
+impl DerefMut for MaterialAsset {
+    fn deref_mut(&mut self) -> &mut MaterialMap {
+        &mut self.0
+    }
+}
+
+#[cfg(all(test, feature = "json"))]
+mod test {
+    use std::env;
+    use std::fs;
+    use std::sync::Arc;
+
+    use json;
+
+    use common::vfs::BoxedVFS;
+    use common::vfs::default::DefaultFS;
+
+    use protocols::material::sample::sample;
+
+    use super::*;
+
+    #[test]
+    fn extensions() {
+        assert!(MaterialAsset::query(MaterialAssetQuery::SupportedExtension("json")).unwrap());
+        assert!(MaterialAsset::query(MaterialAssetQuery::SupportedImportExtension("json")).unwrap());
+        assert!(MaterialAsset::query(MaterialAssetQuery::SupportedExportExtension("json")).unwrap());
+        assert!(!MaterialAsset::query(MaterialAssetQuery::SupportedExtension("png")).unwrap());
+    }
+
+    #[test]
+    fn json_round_trip() {
+        let dir = env::temp_dir().join("combustion_material_asset_test");
+
+        fs::create_dir_all(&dir).unwrap();
+
+        let path = dir.join("sample.json");
+
+        let vfs: Arc<BoxedVFS> = Arc::new(box DefaultFS);
+
+        let asset = MaterialAsset::new(sample());
+
+        asset.save(AssetMedium::File(&path, vfs.clone()), MaterialAssetSaveArgs { pretty: true }).unwrap();
+
+        let loaded = MaterialAsset::load(AssetMedium::File(&path, vfs.clone()), ()).unwrap();
+
+        // The protocol structures don't implement `PartialEq`, so compare their serialized forms
+        let expected: json::Value = json::from_str(&json::to_string(&*asset).unwrap()).unwrap();
+        let actual: json::Value = json::from_str(&json::to_string(&*loaded).unwrap()).unwrap();
+
+        assert_eq!(actual, expected);
+
+        assert!(MaterialAsset::load(AssetMedium::File(&dir.join("sample.png"), vfs), ()).is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,31 @@
+//! Material asset formats
+
+use ::asset::AssetFileFormat;
+use ::assets::standard::formats::StandardFileFormat;
+
+/// Supported file formats
+///
+/// Materials have no native binary format, so only standard formats are supported.
+#[derive(Debug, Clone, Copy, PartialEq, Hash, PartialOrd)]
+pub enum MaterialFileFormat {
+    /// Any standard file format
+    Standard(StandardFileFormat)
+}
+
+impl AssetFileFormat for MaterialFileFormat {
+    fn from_extension(ext: &str) -> Option<MaterialFileFormat> {
+        StandardFileFormat::from_extension(ext).map(MaterialFileFormat::Standard)
+    }
+
+    fn can_import(&self) -> bool {
+        match *self {
+            MaterialFileFormat::Standard(standard_format) => standard_format.can_import(),
+        }
+    }
+
+    fn can_export(&self) -> bool {
+        match *self {
+            MaterialFileFormat::Standard(standard_format) => standard_format.can_export(),
+        }
+    }
+}
\ No newline at end of file
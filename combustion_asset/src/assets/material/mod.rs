@@ -0,0 +1,7 @@
+//! Material asset
+
+pub mod formats;
+pub mod asset;
+
+pub use self::formats::MaterialFileFormat;
+pub use self::asset::{MaterialAsset, MaterialAssetQuery, MaterialAssetSaveArgs};
\ No newline at end of file
@@ -3,6 +3,8 @@
 pub mod standard;
 pub mod texture;
 pub mod model;
+pub mod material;
+pub mod scene;
 
 /// TODO
 pub enum GenericAsset {
@@ -10,4 +12,8 @@ pub enum GenericAsset {
     Texture(texture::TextureAsset),
     /// Model asset
     Model(model::ModelAsset),
+    /// Material asset
+    Material(material::MaterialAsset),
+    /// Scene asset
+    Scene(scene::SceneAsset),
 }
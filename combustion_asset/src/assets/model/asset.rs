@@ -3,17 +3,24 @@
 use std::ops::{Deref, DerefMut};
 use std::ascii::AsciiExt;
 use std::io::BufReader;
+use std::mem;
+use std::sync::Arc;
 
-use capnp::serialize_packed;
-use capnp::message::ReaderOptions;
+use common::compression::CompressionOptions;
 
 use protocols::traits::Storage;
 use protocols::model::protocol;
 use protocols::model::data::Model;
 use protocols::model::storage;
+use protocols::mesh::data::{MeshVertices, Vertex, TexCoord};
+use protocols::material::MaterialMap;
+
+use nalgebra::{Point3, Vector3};
 
 use ::error::{AssetResult, AssetError};
 use ::asset::{Asset, AssetMedium, AssetQuery, AssetFileFormat};
+use ::cache::AssetSize;
+use ::native::{self, ReaderLimits};
 
 use super::formats::ModelFileFormat;
 
@@ -33,6 +40,20 @@ impl<'a> AssetQuery for ModelAssetQuery<'a> {
     type Result = bool;
 }
 
+/// Arguments for model load routines
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ModelAssetLoadArgs {
+    /// Limits for reading native models
+    pub reader_limits: ReaderLimits,
+}
+
+impl Default for ModelAssetLoadArgs {
+    fn default() -> ModelAssetLoadArgs {
+        // Node trees can be much deeper than anything in a texture
+        ModelAssetLoadArgs { reader_limits: ReaderLimits { nesting_limit: 1024, ..ReaderLimits::default() } }
+    }
+}
+
 /// Arguments for model save routines
 #[derive(Debug, Default, Clone)]
 pub struct ModelAssetSaveArgs {
@@ -40,6 +61,10 @@ pub struct ModelAssetSaveArgs {
     pub storage_args: storage::ModelSaveArgs,
     /// For serialization formats that support "pretty-printing", pretty-print the data
     pub pretty: bool,
+    /// Compress native models with LZ4 using these options. If `None`, they are only packed.
+    pub compression: Option<CompressionOptions>,
+    /// Materials to save alongside the model, for formats that store materials next to the model like OBJ
+    pub materials: Option<Arc<MaterialMap>>,
 }
 
 /// Model Asset
@@ -47,7 +72,7 @@ pub struct ModelAssetSaveArgs {
 pub struct ModelAsset(Model);
 
 impl<'a> Asset<'a> for ModelAsset {
-    type LoadArgs = ();
+    type LoadArgs = ModelAssetLoadArgs;
     type SaveArgs = ModelAssetSaveArgs;
 
     type Query = ModelAssetQuery<'a>;
@@ -75,7 +100,7 @@ impl<'a> Asset<'a> for ModelAsset {
         })
     }
 
-    fn load(medium: AssetMedium<'a>, _: ()) -> AssetResult<ModelAsset> {
+    fn load(medium: AssetMedium<'a>, args: ModelAssetLoadArgs) -> AssetResult<ModelAsset> {
         if let AssetMedium::File(path, vfs) = medium {
             if let Some(ext) = path.extension() {
                 let ext = try_throw!(ext.to_str().ok_or(AssetError::InvalidValue)).to_ascii_lowercase();
@@ -87,12 +112,7 @@ impl<'a> Asset<'a> for ModelAsset {
 
                 match format {
                     ModelFileFormat::Native => {
-                        let mut reader = BufReader::new(try_throw!(vfs.open(path)));
-
-                        let message_reader = try_throw!(serialize_packed::read_message(&mut reader, ReaderOptions {
-                            traversal_limit_in_words: u64::max_value(),
-                            nesting_limit: 1024,
-                        }));
+                        let message_reader = try_rethrow!(native::read_native_message(path, &vfs, args.reader_limits));
 
                         let model_reader = try_throw!(message_reader.get_root::<protocol::model::Reader>());
 
@@ -100,15 +120,20 @@ impl<'a> Asset<'a> for ModelAsset {
 
                         return Ok(ModelAsset(model));
                     },
-                    #[cfg(feature = "assimp")]
-                    ModelFileFormat::Assimp => {
-                        // Use custom IO for Assimp so it can use the virtual filesystem to interact with data
-                        let mut io = ::assimp::io::CustomIO::callback(move |path| vfs.open(path));
+                    ModelFileFormat::Obj => {
+                        let import = try_rethrow!(super::external::obj::import(path, &vfs, Default::default()));
 
-                        // Since Assimp only supports Triangles or Polygons, convert everything to triangles for importing
-                        let scene = try_rethrow!(::assimp::Scene::import_from(path, Some(::assimp::postprocess::TRIANGULATE), &mut io));
+                        return Ok(ModelAsset(import.model));
+                    },
+                    #[cfg(feature = "gltf")]
+                    ModelFileFormat::Gltf => {
+                        let (model, _) = try_rethrow!(super::external::gltf::load_model(path, &vfs));
 
-                        let model = try_rethrow!(super::external::assimp::scene_to_model(scene));
+                        return Ok(ModelAsset(model));
+                    },
+                    #[cfg(feature = "assimp")]
+                    ModelFileFormat::Assimp => {
+                        let (model, _) = try_rethrow!(super::external::assimp::import(path, &vfs));
 
                         return Ok(ModelAsset(model));
                     },
@@ -136,8 +161,6 @@ impl<'a> Asset<'a> for ModelAsset {
 
                 match format {
                     ModelFileFormat::Native => {
-                        let mut writer = try_throw!(vfs.create_or_truncate(path));
-
                         let mut message = ::capnp::message::Builder::new_default();
 
                         {
@@ -146,15 +169,18 @@ impl<'a> Asset<'a> for ModelAsset {
                             try_rethrow!(self.0.save_to_builder_args(model_builder, args.storage_args));
                         }
 
-                        try_throw!(serialize_packed::write_message(&mut writer, &message));
-
-                        return Ok(());
+                        return native::write_native_message(path, &vfs, &message, args.compression);
+                    },
+                    ModelFileFormat::Obj => {
+                        return super::external::obj::export(path, &vfs, &self.0, args.materials.as_ref().map(|materials| &**materials));
                     },
                     ModelFileFormat::Standard(standard_format) => {
                         let writer = try_throw!(vfs.create_or_truncate(path));
 
                         return ::assets::standard::generic::save_standard_format(writer, standard_format, self, args.pretty);
                     },
+                    #[cfg(feature = "gltf")]
+                    ModelFileFormat::Gltf => throw!(AssetError::UnsupportedFormat),
                     #[cfg(feature = "assimp")]
                     ModelFileFormat::Assimp => throw!(AssetError::UnsupportedFormat),
                 }
@@ -165,6 +191,25 @@ impl<'a> Asset<'a> for ModelAsset {
     }
 }
 
+impl AssetSize for ModelAsset {
+    fn approximate_size(&self) -> usize {
+        self.0.meshes.iter().map(|mesh| {
+            let vertices = match mesh.vertices {
+                MeshVertices::Interleaved(ref vertices) => vertices.len() * mem::size_of::<Vertex>(),
+                MeshVertices::Discrete(ref vertices) => {
+                    vertices.positions.len() * mem::size_of::<Point3<f32>>() +
+                        vertices.normals.as_ref().map_or(0, |normals| normals.len() * mem::size_of::<Vector3<f32>>()) +
+                        vertices.uvs.as_ref().map_or(0, |uvs| uvs.len() * mem::size_of::<TexCoord>())
+                }
+            };
+
+            let indices = mesh.indices.as_ref().map_or(0, |indices| indices.len() * mem::size_of::<u32>());
+
+            vertices + indices + mesh.materials.len() * mem::size_of::<u32>()
+        }).sum()
+    }
+}
+
 impl Deref for ModelAsset {
     type Target = Model;
 
@@ -1,17 +1,58 @@
 //! Routines for converting Assimp structures to Combustion structures
 
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
 use nalgebra::Vector3;
 
 use assimp::{self, Named};
+use assimp::material::TextureType;
+
+use common::color::Color;
+use common::vfs::BoxedVFS;
 
 use protocols::math::data::Transform;
 use protocols::mesh::protocol::MeshPrimitive;
 use protocols::mesh::data::{Mesh, MeshVertices, Vertices, TexCoord};
 use protocols::model::data::{Model, Node};
+use protocols::material::{Material, MaterialMap};
 
 use ::error::{AssetResult, AssetError};
 
+use super::obj::shininess_to_roughness;
+
+/// `AI_MATKEY_COLOR_DIFFUSE`
+const KEY_COLOR_DIFFUSE: &'static str = "$clr.diffuse";
+/// `AI_MATKEY_COLOR_EMISSIVE`
+const KEY_COLOR_EMISSIVE: &'static str = "$clr.emissive";
+/// `AI_MATKEY_OPACITY`
+const KEY_OPACITY: &'static str = "$mat.opacity";
+/// `AI_MATKEY_SHININESS`
+const KEY_SHININESS: &'static str = "$mat.shininess";
+/// `AI_MATKEY_REFRACTI`
+const KEY_REFRACTI: &'static str = "$mat.refracti";
+
+/// Imports a model and its materials from any file format Assimp supports
+pub fn import(path: &Path, vfs: &Arc<BoxedVFS>) -> AssetResult<(Model, MaterialMap)> {
+    let vfs = vfs.clone();
+
+    // Use custom IO for Assimp so it can use the virtual filesystem to interact with data
+    let mut io = assimp::io::CustomIO::callback(move |path| vfs.open(path));
+
+    // Since Assimp only supports Triangles or Polygons, convert everything to triangles for importing
+    let scene = try_rethrow!(assimp::Scene::import_from(path, Some(assimp::postprocess::TRIANGULATE), &mut io));
+
+    let materials = try_rethrow!(scene_materials(&scene, path));
+
+    let model = try_rethrow!(scene_to_model(scene));
+
+    Ok((model, materials))
+}
+
 /// Converts an Assimp `Scene` into a Combustion `Model`
+///
+/// Material names are filled in to match the `MaterialMap` returned by `scene_materials`.
 pub fn scene_to_model(scene: assimp::Scene) -> AssetResult<Model> {
     let raw_meshes = try_throw!(scene.meshes().ok_or(AssetError::UnsupportedFormat));
 
@@ -21,15 +62,126 @@ pub fn scene_to_model(scene: assimp::Scene) -> AssetResult<Model> {
         meshes.push(assimp_mesh_to_mesh(raw_mesh)?);
     }
 
+    let materials = material_names(&scene);
+
     let root = try_rethrow!(assimp_node_to_node(scene.root()));
 
     Ok(Model {
         meshes: meshes,
         root: root,
-        materials: Vec::new(),
+        materials: materials,
     })
 }
 
+/// Converts the materials of an Assimp `Scene` into a `MaterialMap`
+///
+/// Texture paths are resolved relative to `source`, the path of the file the scene was imported from.
+/// Textures embedded in the scene file are skipped.
+pub fn scene_materials(scene: &assimp::Scene, source: &Path) -> AssetResult<MaterialMap> {
+    let names = material_names(scene);
+
+    let mut materials = HashMap::new();
+
+    if let Some(raw_materials) = scene.materials() {
+        for (raw_material, name) in raw_materials.zip(names) {
+            materials.insert(name, assimp_material_to_material(&raw_material, source));
+        }
+    }
+
+    Ok(MaterialMap { materials: materials })
+}
+
+// Assimp material names may be empty or repeated, but they must be unique in a `MaterialMap`
+fn material_names(scene: &assimp::Scene) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+
+    if let Some(raw_materials) = scene.materials() {
+        for (index, raw_material) in raw_materials.enumerate() {
+            let mut name = raw_material.name().to_string();
+
+            if name.is_empty() {
+                name = format!("material{}", index);
+            }
+
+            if names.contains(&name) {
+                name = format!("{}_{}", name, index);
+            }
+
+            names.push(name);
+        }
+    }
+
+    names
+}
+
+fn resolve_texture_path(source: &Path, texture: &str) -> Option<PathBuf> {
+    // Embedded textures are referenced as `*index`
+    if texture.starts_with('*') {
+        warn!("Skipping embedded texture {} in {:?}", texture, source);
+
+        return None;
+    }
+
+    // Texture paths are often written with Windows separators
+    let texture = PathBuf::from(texture.replace('\\', "/"));
+
+    Some(match source.parent() {
+        Some(parent) if texture.is_relative() => parent.join(texture),
+        _ => texture,
+    })
+}
+
+fn assimp_material_to_material(raw_material: &assimp::Material, source: &Path) -> Material {
+    let mut material = Material::default();
+
+    let opacity = raw_material.get_float(KEY_OPACITY).unwrap_or(1.0);
+
+    if let Some(diffuse) = raw_material.get_color(KEY_COLOR_DIFFUSE) {
+        material.color = Color::new(diffuse.r, diffuse.g, diffuse.b, opacity);
+    }
+
+    if opacity < 1.0 {
+        material.translucency = Some(opacity);
+    }
+
+    if let Some(shininess) = raw_material.get_float(KEY_SHININESS) {
+        // A shininess of zero means the material has no specular highlights at all
+        if shininess > 0.0 {
+            material.roughness = Some(shininess_to_roughness(shininess));
+        }
+    }
+
+    if let Some(emissive) = raw_material.get_color(KEY_COLOR_EMISSIVE) {
+        let emission = emissive.r.max(emissive.g).max(emissive.b);
+
+        if emission > 0.0 {
+            material.emission = Some(emission);
+        }
+    }
+
+    if let Some(ior) = raw_material.get_float(KEY_REFRACTI) {
+        material.ior = Some(ior);
+    }
+
+    let texture = |kind: TextureType| {
+        raw_material.get_texture(kind, 0).and_then(|texture| resolve_texture_path(source, &texture))
+    };
+
+    material.texture = texture(TextureType::Diffuse);
+    material.normal_map = texture(TextureType::Normals);
+    material.height_map = texture(TextureType::Height);
+
+    // Shininess maps store glossiness rather than roughness, and reflection maps describe environment reflections,
+    // so neither can be used as a roughness or metallic map as-is.
+    for (kind, name) in vec![(TextureType::Shininess, "shininess"), (TextureType::Reflection, "reflection")] {
+        if let Some(texture) = texture(kind) {
+            warn!("Skipping {} texture {:?} in {:?}", name, texture, source);
+        }
+    }
+
+    material
+}
+
 fn assimp_mesh_to_mesh(mesh: assimp::Mesh) -> AssetResult<Mesh> {
     let vertices = MeshVertices::Discrete({
         let raw_positions = try_throw!(mesh.vertices().ok_or(AssetError::UnsupportedFormat));
@@ -52,7 +204,7 @@ fn assimp_mesh_to_mesh(mesh: assimp::Mesh) -> AssetResult<Mesh> {
     Ok(Mesh {
         vertices: vertices,
         indices: indices,
-        materials: Vec::new(),
+        materials: vec![mesh.material_index()],
         primitive: MeshPrimitive::Triangles,
     })
 }
@@ -74,8 +226,9 @@ fn assimp_node_to_node(node: assimp::Node) -> AssetResult<Node> {
         meshes: node.meshes().map(|mesh_indices| {
             mesh_indices.iter().cloned().map(From::from).collect()
         }).unwrap_or_else(Vec::new),
-        // Create a single-element Vec with the converted node transform
-        transforms: vec![Transform::Matrix(node.transformation().clone().into())],
+        // Create a single-element Vec with the converted node transform, decomposed if possible so it stays editable
+        transforms: vec![Transform::from_matrix(node.transformation().clone().into())],
         children: children,
+        lod: None,
     })
 }
\ No newline at end of file
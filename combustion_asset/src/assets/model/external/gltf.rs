@@ -0,0 +1,1029 @@
+//! Routines for importing glTF 2.0 models
+//!
+//! Both `.gltf` files, with external or embedded (data URI) buffers and images, and binary `.glb` files are supported.
+//!
+//! Every glTF mesh primitive becomes its own `Mesh`, and glTF meshes map to the list of `Mesh` indices in each `Node`.
+//! Vertex attributes without an equivalent in `Mesh`, like tangents, vertex colors and skinning data, are skipped.
+
+use std::collections::{HashMap, HashSet};
+use std::io::prelude::*;
+use std::mem;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use nalgebra::{Eye, Point3, Vector3, Matrix4, Quaternion};
+
+use image;
+
+use json;
+use base64;
+
+use common::color::Color;
+use common::vfs::BoxedVFS;
+
+use protocols::math::data::Transform;
+use protocols::mesh::protocol::MeshPrimitive;
+use protocols::mesh::data::{Mesh, MeshVertices, Vertices, TexCoord};
+use protocols::model::data::{Model, Node};
+use protocols::material::{Material, MaterialMap};
+use protocols::texture::EXTENSION as TEXTURE_EXTENSION;
+
+use ::error::{AssetResult, AssetError};
+use ::asset::{Asset, AssetMedium};
+use ::assets::texture::{TextureAsset, TextureAssetLoadArgs};
+
+/// Binary glTF magic bytes
+pub const GLB_MAGIC: &'static [u8; 4] = b"glTF";
+
+const GLB_CHUNK_JSON: u32 = 0x4E4F534A;
+const GLB_CHUNK_BIN: u32 = 0x004E4942;
+
+const COMPONENT_BYTE: u32 = 5120;
+const COMPONENT_UNSIGNED_BYTE: u32 = 5121;
+const COMPONENT_SHORT: u32 = 5122;
+const COMPONENT_UNSIGNED_SHORT: u32 = 5123;
+const COMPONENT_UNSIGNED_INT: u32 = 5125;
+const COMPONENT_FLOAT: u32 = 5126;
+
+/// Image imported from a glTF file
+pub struct GltfImage {
+    /// Path the materials use to refer to this image, relative to the glTF file.
+    ///
+    /// For external images this is the original URI. Embedded images are given a path with the
+    /// Combustion texture extension next to the glTF file, where they can be saved to.
+    pub path: PathBuf,
+    /// Decoded image
+    pub texture: TextureAsset,
+    /// Whether the image was embedded in the glTF file
+    pub embedded: bool,
+}
+
+/// Everything imported from a glTF file
+pub struct GltfImport {
+    /// Node hierarchy and meshes
+    pub model: Model,
+    /// Materials referenced by name from `model.materials`
+    pub materials: MaterialMap,
+    /// All images in the file
+    pub images: Vec<GltfImage>,
+}
+
+#[derive(Deserialize)]
+struct GltfAssetInfo {
+    version: String,
+}
+
+#[derive(Deserialize)]
+struct GltfScene {
+    #[serde(default)]
+    name: Option<String>,
+    #[serde(default)]
+    nodes: Vec<usize>,
+}
+
+#[derive(Deserialize)]
+struct GltfNode {
+    #[serde(default)]
+    name: Option<String>,
+    #[serde(default)]
+    children: Vec<usize>,
+    #[serde(default)]
+    mesh: Option<usize>,
+    #[serde(default)]
+    matrix: Option<[f32; 16]>,
+    #[serde(default)]
+    translation: Option<[f32; 3]>,
+    #[serde(default)]
+    rotation: Option<[f32; 4]>,
+    #[serde(default)]
+    scale: Option<[f32; 3]>,
+}
+
+fn default_mode() -> u32 { 4 }
+
+#[derive(Deserialize)]
+struct GltfPrimitive {
+    attributes: HashMap<String, usize>,
+    #[serde(default)]
+    indices: Option<usize>,
+    #[serde(default)]
+    material: Option<usize>,
+    #[serde(default = "default_mode")]
+    mode: u32,
+}
+
+#[derive(Deserialize)]
+struct GltfMesh {
+    primitives: Vec<GltfPrimitive>,
+}
+
+#[derive(Deserialize)]
+struct GltfAccessor {
+    #[serde(rename = "bufferView")]
+    #[serde(default)]
+    buffer_view: Option<usize>,
+    #[serde(rename = "byteOffset")]
+    #[serde(default)]
+    byte_offset: usize,
+    #[serde(rename = "componentType")]
+    component_type: u32,
+    #[serde(default)]
+    normalized: bool,
+    count: usize,
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    sparse: Option<json::Value>,
+}
+
+#[derive(Deserialize)]
+struct GltfBufferView {
+    buffer: usize,
+    #[serde(rename = "byteOffset")]
+    #[serde(default)]
+    byte_offset: usize,
+    #[serde(rename = "byteLength")]
+    byte_length: usize,
+    #[serde(rename = "byteStride")]
+    #[serde(default)]
+    byte_stride: Option<usize>,
+}
+
+#[derive(Deserialize)]
+struct GltfBuffer {
+    #[serde(default)]
+    uri: Option<String>,
+    #[serde(rename = "byteLength")]
+    byte_length: usize,
+}
+
+#[derive(Deserialize)]
+struct GltfTextureInfo {
+    index: usize,
+}
+
+#[derive(Deserialize)]
+struct GltfPbr {
+    #[serde(rename = "baseColorFactor")]
+    #[serde(default)]
+    base_color_factor: Option<[f32; 4]>,
+    #[serde(rename = "baseColorTexture")]
+    #[serde(default)]
+    base_color_texture: Option<GltfTextureInfo>,
+    #[serde(rename = "metallicFactor")]
+    #[serde(default)]
+    metallic_factor: Option<f32>,
+    #[serde(rename = "roughnessFactor")]
+    #[serde(default)]
+    roughness_factor: Option<f32>,
+    #[serde(rename = "metallicRoughnessTexture")]
+    #[serde(default)]
+    metallic_roughness_texture: Option<GltfTextureInfo>,
+}
+
+#[derive(Deserialize)]
+struct GltfMaterial {
+    #[serde(default)]
+    name: Option<String>,
+    #[serde(rename = "pbrMetallicRoughness")]
+    #[serde(default)]
+    pbr: Option<GltfPbr>,
+    #[serde(rename = "normalTexture")]
+    #[serde(default)]
+    normal_texture: Option<GltfTextureInfo>,
+    #[serde(rename = "emissiveFactor")]
+    #[serde(default)]
+    emissive_factor: Option<[f32; 3]>,
+}
+
+#[derive(Deserialize)]
+struct GltfTexture {
+    #[serde(default)]
+    source: Option<usize>,
+}
+
+#[derive(Deserialize)]
+struct GltfImageSource {
+    #[serde(default)]
+    uri: Option<String>,
+    #[serde(rename = "mimeType")]
+    #[serde(default)]
+    mime_type: Option<String>,
+    #[serde(rename = "bufferView")]
+    #[serde(default)]
+    buffer_view: Option<usize>,
+}
+
+#[derive(Deserialize)]
+struct GltfRoot {
+    asset: GltfAssetInfo,
+    #[serde(rename = "extensionsRequired")]
+    #[serde(default)]
+    extensions_required: Vec<String>,
+    #[serde(default)]
+    scene: Option<usize>,
+    #[serde(default)]
+    scenes: Vec<GltfScene>,
+    #[serde(default)]
+    nodes: Vec<GltfNode>,
+    #[serde(default)]
+    meshes: Vec<GltfMesh>,
+    #[serde(default)]
+    accessors: Vec<GltfAccessor>,
+    #[serde(rename = "bufferViews")]
+    #[serde(default)]
+    buffer_views: Vec<GltfBufferView>,
+    #[serde(default)]
+    buffers: Vec<GltfBuffer>,
+    #[serde(default)]
+    materials: Vec<GltfMaterial>,
+    #[serde(default)]
+    textures: Vec<GltfTexture>,
+    #[serde(default)]
+    images: Vec<GltfImageSource>,
+}
+
+fn le_u16(bytes: &[u8]) -> u16 {
+    bytes[0] as u16 | (bytes[1] as u16) << 8
+}
+
+fn le_u32(bytes: &[u8]) -> u32 {
+    bytes[0] as u32 | (bytes[1] as u32) << 8 | (bytes[2] as u32) << 16 | (bytes[3] as u32) << 24
+}
+
+fn component_size(component_type: u32) -> AssetResult<usize> {
+    Ok(match component_type {
+        COMPONENT_BYTE | COMPONENT_UNSIGNED_BYTE => 1,
+        COMPONENT_SHORT | COMPONENT_UNSIGNED_SHORT => 2,
+        COMPONENT_UNSIGNED_INT | COMPONENT_FLOAT => 4,
+        _ => throw!(AssetError::InvalidValue),
+    })
+}
+
+fn type_components(kind: &str) -> AssetResult<usize> {
+    Ok(match kind {
+        "SCALAR" => 1,
+        "VEC2" => 2,
+        "VEC3" => 3,
+        "VEC4" | "MAT2" => 4,
+        "MAT3" => 9,
+        "MAT4" => 16,
+        _ => throw!(AssetError::InvalidValue),
+    })
+}
+
+fn read_component(bytes: &[u8], component_type: u32, normalized: bool) -> f32 {
+    match component_type {
+        COMPONENT_BYTE => {
+            let value = bytes[0] as i8 as f32;
+            if normalized { (value / 127.0).max(-1.0) } else { value }
+        },
+        COMPONENT_UNSIGNED_BYTE => {
+            let value = bytes[0] as f32;
+            if normalized { value / 255.0 } else { value }
+        },
+        COMPONENT_SHORT => {
+            let value = le_u16(bytes) as i16 as f32;
+            if normalized { (value / 32767.0).max(-1.0) } else { value }
+        },
+        COMPONENT_UNSIGNED_SHORT => {
+            let value = le_u16(bytes) as f32;
+            if normalized { value / 65535.0 } else { value }
+        },
+        COMPONENT_UNSIGNED_INT => le_u32(bytes) as f32,
+        _ => unsafe { mem::transmute::<u32, f32>(le_u32(bytes)) },
+    }
+}
+
+// Checks that `count` elements of `element_size` bytes each, `stride` bytes apart and starting at `offset`, fit in `data`
+fn check_accessor_range(data: &[u8], offset: usize, stride: usize, element_size: usize, count: usize) -> AssetResult<()> {
+    if stride < element_size {
+        throw!(AssetError::InvalidValue);
+    }
+
+    if count == 0 {
+        return Ok(());
+    }
+
+    let end = stride.checked_mul(count - 1)
+        .and_then(|last| last.checked_add(element_size))
+        .and_then(|size| size.checked_add(offset));
+
+    match end {
+        Some(end) if end <= data.len() => Ok(()),
+        _ => throw!(AssetError::InvalidValue),
+    }
+}
+
+fn hex_value(digit: u8) -> Option<u8> {
+    match digit {
+        b'0'...b'9' => Some(digit - b'0'),
+        b'a'...b'f' => Some(digit - b'a' + 10),
+        b'A'...b'F' => Some(digit - b'A' + 10),
+        _ => None,
+    }
+}
+
+// Decode `%XX` escapes in relative URIs
+fn percent_decode(uri: &str) -> String {
+    let bytes = uri.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            if let (Some(high), Some(low)) = (hex_value(bytes[i + 1]), hex_value(bytes[i + 2])) {
+                decoded.push(high << 4 | low);
+                i += 3;
+                continue;
+            }
+        }
+
+        decoded.push(bytes[i]);
+        i += 1;
+    }
+
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// Resolve a relative URI in a glTF file located at `path`
+fn resolve_uri(path: &Path, uri: &str) -> PathBuf {
+    match path.parent() {
+        Some(parent) => parent.join(percent_decode(uri)),
+        None => PathBuf::from(percent_decode(uri)),
+    }
+}
+
+fn load_uri(path: &Path, vfs: &Arc<BoxedVFS>, uri: &str) -> AssetResult<Vec<u8>> {
+    if uri.starts_with("data:") {
+        let comma = try_throw!(uri.find(',').ok_or(AssetError::InvalidValue));
+
+        if !uri[..comma].ends_with(";base64") {
+            throw!(AssetError::Unimplemented("Non-base64 glTF data URIs"));
+        }
+
+        match base64::decode(&uri[comma + 1..]) {
+            Ok(data) => Ok(data),
+            Err(err) => throw!(AssetError::Other(format!("Invalid base64 data in glTF data URI: {}", err))),
+        }
+    } else {
+        let mut data = Vec::new();
+
+        try_throw!(try_throw!(vfs.open(&resolve_uri(path, uri))).read_to_end(&mut data));
+
+        Ok(data)
+    }
+}
+
+// Splits a binary glTF file into its JSON and binary chunks
+fn parse_glb(data: &[u8]) -> AssetResult<(&[u8], Option<&[u8]>)> {
+    if data.len() < 12 || &data[0..4] != GLB_MAGIC {
+        throw!(AssetError::UnsupportedFormat);
+    }
+
+    if le_u32(&data[4..8]) != 2 {
+        throw!(AssetError::UnsupportedFormat);
+    }
+
+    let length = (le_u32(&data[8..12]) as usize).min(data.len());
+
+    let mut json = None;
+    let mut bin = None;
+    let mut offset = 12;
+
+    while offset + 8 <= length {
+        let chunk_length = le_u32(&data[offset..offset + 4]) as usize;
+        let chunk_type = le_u32(&data[offset + 4..offset + 8]);
+
+        let start = offset + 8;
+        let end = start + chunk_length;
+
+        if end > length {
+            throw!(AssetError::InvalidValue);
+        }
+
+        match chunk_type {
+            GLB_CHUNK_JSON if json.is_none() => json = Some(&data[start..end]),
+            GLB_CHUNK_BIN if bin.is_none() => bin = Some(&data[start..end]),
+            // Unknown chunks must be ignored
+            _ => {}
+        }
+
+        // Chunks are padded to 4-byte boundaries
+        offset = (end + 3) & !3;
+    }
+
+    Ok((try_throw!(json.ok_or(AssetError::InvalidValue)), bin))
+}
+
+// glTF matrices are stored in column-major order
+fn matrix_from_columns(m: &[f32; 16]) -> Matrix4<f32> {
+    let mut matrix: Matrix4<f32> = Matrix4::new_identity(4);
+
+    matrix.m11 = m[0];
+    matrix.m21 = m[1];
+    matrix.m31 = m[2];
+    matrix.m41 = m[3];
+    matrix.m12 = m[4];
+    matrix.m22 = m[5];
+    matrix.m32 = m[6];
+    matrix.m42 = m[7];
+    matrix.m13 = m[8];
+    matrix.m23 = m[9];
+    matrix.m33 = m[10];
+    matrix.m43 = m[11];
+    matrix.m14 = m[12];
+    matrix.m24 = m[13];
+    matrix.m34 = m[14];
+    matrix.m44 = m[15];
+
+    matrix
+}
+
+fn convert_mode(mode: u32) -> AssetResult<MeshPrimitive> {
+    Ok(match mode {
+        0 => MeshPrimitive::Points,
+        1 => MeshPrimitive::Lines,
+        2 => MeshPrimitive::LineLoop,
+        3 => MeshPrimitive::LineStrip,
+        4 => MeshPrimitive::Triangles,
+        5 => MeshPrimitive::TriangleStrip,
+        6 => MeshPrimitive::TriangleFan,
+        _ => throw!(AssetError::InvalidValue),
+    })
+}
+
+struct Document<'a> {
+    root: GltfRoot,
+    buffers: Vec<Vec<u8>>,
+    path: &'a Path,
+    vfs: &'a Arc<BoxedVFS>,
+}
+
+impl<'a> Document<'a> {
+    fn buffer_view(&self, index: usize) -> AssetResult<(&[u8], Option<usize>)> {
+        let view = try_throw!(self.root.buffer_views.get(index).ok_or(AssetError::InvalidValue));
+        let buffer = try_throw!(self.buffers.get(view.buffer).ok_or(AssetError::InvalidValue));
+
+        let end = match view.byte_offset.checked_add(view.byte_length) {
+            Some(end) if end <= buffer.len() => end,
+            _ => throw!(AssetError::InvalidValue),
+        };
+
+        Ok((&buffer[view.byte_offset..end], view.byte_stride))
+    }
+
+    // Reads any accessor as a flat list of floats, returning the number of components per element as well
+    fn read_accessor(&self, index: usize) -> AssetResult<(usize, Vec<f32>)> {
+        let accessor = try_throw!(self.root.accessors.get(index).ok_or(AssetError::InvalidValue));
+
+        if accessor.sparse.is_some() {
+            throw!(AssetError::Unimplemented("Sparse glTF accessors"));
+        }
+
+        let components = type_components(&accessor.kind)?;
+        let size = component_size(accessor.component_type)?;
+
+        let element_size = components * size;
+
+        let buffer_view = match accessor.buffer_view {
+            Some(buffer_view) => buffer_view,
+            // Accessors without a buffer view are initialized with zeros
+            None => {
+                // The count isn't backed by any data, so bound it by what the buffers could have held instead
+                let available: usize = self.buffers.iter().map(|buffer| buffer.len()).sum();
+
+                match element_size.checked_mul(accessor.count) {
+                    Some(bytes) if bytes <= available => {},
+                    _ => throw!(AssetError::InvalidValue),
+                }
+
+                return Ok((components, vec![0.0; components * accessor.count]));
+            },
+        };
+
+        let (data, stride) = self.buffer_view(buffer_view)?;
+
+        let stride = stride.unwrap_or(element_size);
+
+        try_rethrow!(check_accessor_range(data, accessor.byte_offset, stride, element_size, accessor.count));
+
+        let mut values = Vec::with_capacity(components * accessor.count);
+
+        for element in 0..accessor.count {
+            let start = accessor.byte_offset + stride * element;
+
+            for component in 0..components {
+                let offset = start + component * size;
+
+                values.push(read_component(&data[offset..offset + size], accessor.component_type, accessor.normalized));
+            }
+        }
+
+        Ok((components, values))
+    }
+
+    fn read_indices(&self, index: usize) -> AssetResult<Vec<u32>> {
+        let accessor = try_throw!(self.root.accessors.get(index).ok_or(AssetError::InvalidValue));
+
+        if accessor.kind != "SCALAR" {
+            throw!(AssetError::InvalidValue);
+        }
+
+        let size = component_size(accessor.component_type)?;
+
+        let buffer_view = try_throw!(accessor.buffer_view.ok_or(AssetError::InvalidValue));
+
+        let (data, stride) = self.buffer_view(buffer_view)?;
+
+        let stride = stride.unwrap_or(size);
+
+        try_rethrow!(check_accessor_range(data, accessor.byte_offset, stride, size, accessor.count));
+
+        let mut indices = Vec::with_capacity(accessor.count);
+
+        for element in 0..accessor.count {
+            let offset = accessor.byte_offset + stride * element;
+
+            indices.push(match accessor.component_type {
+                COMPONENT_UNSIGNED_BYTE => data[offset] as u32,
+                COMPONENT_UNSIGNED_SHORT => le_u16(&data[offset..]) as u32,
+                COMPONENT_UNSIGNED_INT => le_u32(&data[offset..]),
+                _ => throw!(AssetError::InvalidValue),
+            });
+        }
+
+        Ok(indices)
+    }
+
+    fn convert_primitive(&self, primitive: &GltfPrimitive) -> AssetResult<Mesh> {
+        let positions = {
+            let index = try_throw!(primitive.attributes.get("POSITION").ok_or(AssetError::InvalidValue));
+
+            let (components, values) = self.read_accessor(*index)?;
+
+            if components != 3 {
+                throw!(AssetError::InvalidValue);
+            }
+
+            values.chunks(3).map(|p| Point3::new(p[0], p[1], p[2])).collect::<Vec<_>>()
+        };
+
+        let normals = match primitive.attributes.get("NORMAL") {
+            Some(index) => {
+                let (components, values) = self.read_accessor(*index)?;
+
+                if components != 3 {
+                    throw!(AssetError::InvalidValue);
+                }
+
+                Some(values.chunks(3).map(|n| Vector3::new(n[0], n[1], n[2])).collect::<Vec<_>>())
+            },
+            None => None,
+        };
+
+        let uvs = match primitive.attributes.get("TEXCOORD_0") {
+            Some(index) => {
+                let (components, values) = self.read_accessor(*index)?;
+
+                if components != 2 {
+                    throw!(AssetError::InvalidValue);
+                }
+
+                Some(values.chunks(2).map(|uv| TexCoord::new(uv[0], uv[1])).collect::<Vec<_>>())
+            },
+            None => None,
+        };
+
+        for attribute in primitive.attributes.keys() {
+            if attribute != "POSITION" && attribute != "NORMAL" && attribute != "TEXCOORD_0" {
+                debug!("Skipping unsupported glTF vertex attribute {}", attribute);
+            }
+        }
+
+        let indices = match primitive.indices {
+            Some(index) => Some(self.read_indices(index)?),
+            None => None,
+        };
+
+        if let Some(ref indices) = indices {
+            if indices.iter().any(|index| *index as usize >= positions.len()) {
+                throw!(AssetError::InvalidValue);
+            }
+        }
+
+        let materials = match primitive.material {
+            Some(material) if material < self.root.materials.len() => vec![material as u32],
+            Some(_) => throw!(AssetError::InvalidValue),
+            None => Vec::new(),
+        };
+
+        Ok(Mesh {
+            vertices: MeshVertices::Discrete(Vertices {
+                positions: positions,
+                normals: normals,
+                uvs: uvs,
+            }),
+            indices: indices,
+            materials: materials,
+            primitive: convert_mode(primitive.mode)?,
+        })
+    }
+
+    fn convert_node(&self, index: usize, mesh_map: &[Vec<u32>], depth: usize) -> AssetResult<Node> {
+        // glTF nodes must form strict trees, so deeper recursion than there are nodes means a cycle
+        if depth > self.root.nodes.len() {
+            throw!(AssetError::InvalidValue);
+        }
+
+        let node = try_throw!(self.root.nodes.get(index).ok_or(AssetError::InvalidValue));
+
+        let mut children = Vec::with_capacity(node.children.len());
+
+        for child in &node.children {
+            children.push(self.convert_node(*child, mesh_map, depth + 1)?);
+        }
+
+        let meshes = match node.mesh {
+            Some(mesh) => try_throw!(mesh_map.get(mesh).ok_or(AssetError::InvalidValue)).clone(),
+            None => Vec::new(),
+        };
+
+        let transforms = if let Some(ref matrix) = node.matrix {
+            vec![Transform::from_matrix(matrix_from_columns(matrix))]
+        } else if node.translation.is_some() || node.rotation.is_some() || node.scale.is_some() {
+            let t = node.translation.unwrap_or([0.0, 0.0, 0.0]);
+            let r = node.rotation.unwrap_or([0.0, 0.0, 0.0, 1.0]);
+            let s = node.scale.unwrap_or([1.0, 1.0, 1.0]);
+
+            vec![Transform::Trs {
+                translation: Vector3::new(t[0], t[1], t[2]),
+                // glTF quaternions are stored as (x, y, z, w)
+                rotation: Quaternion::new(r[3], r[0], r[1], r[2]),
+                scale: Vector3::new(s[0], s[1], s[2]),
+            }]
+        } else {
+            Vec::new()
+        };
+
+        Ok(Node {
+            name: node.name.clone().unwrap_or_else(|| format!("node{}", index)),
+            meshes: meshes,
+            children: children,
+            transforms: transforms,
+            lod: None,
+        })
+    }
+
+    fn convert_model(&self, name: &str, material_names: Vec<String>) -> AssetResult<Model> {
+        let mut meshes = Vec::new();
+        let mut mesh_map = Vec::with_capacity(self.root.meshes.len());
+
+        for mesh in &self.root.meshes {
+            let mut indices = Vec::with_capacity(mesh.primitives.len());
+
+            for primitive in &mesh.primitives {
+                indices.push(meshes.len() as u32);
+                meshes.push(self.convert_primitive(primitive)?);
+            }
+
+            mesh_map.push(indices);
+        }
+
+        let scene = match self.root.scene {
+            Some(scene) => Some(try_throw!(self.root.scenes.get(scene).ok_or(AssetError::InvalidValue))),
+            None => self.root.scenes.first(),
+        };
+
+        let root_nodes = match scene {
+            Some(scene) => scene.nodes.clone(),
+            // Without any scenes, use every node that isn't a child of another node
+            None => {
+                let children: HashSet<usize> = self.root.nodes.iter().flat_map(|node| node.children.iter().cloned()).collect();
+
+                (0..self.root.nodes.len()).filter(|node| !children.contains(node)).collect()
+            }
+        };
+
+        let mut children = Vec::with_capacity(root_nodes.len());
+
+        for node in root_nodes {
+            children.push(self.convert_node(node, &mesh_map, 0)?);
+        }
+
+        Ok(Model {
+            root: Node {
+                name: scene.and_then(|scene| scene.name.clone()).unwrap_or_else(|| name.to_string()),
+                children: children,
+                ..Node::default()
+            },
+            meshes: meshes,
+            materials: material_names,
+        })
+    }
+
+    fn image_path(&self, index: usize, name: &str) -> PathBuf {
+        match self.root.images[index].uri {
+            Some(ref uri) if !uri.starts_with("data:") => PathBuf::from(percent_decode(uri)),
+            _ => PathBuf::from(format!("{}_image{}.{}", name, index, TEXTURE_EXTENSION)),
+        }
+    }
+
+    fn texture_image(&self, info: &Option<GltfTextureInfo>) -> AssetResult<Option<usize>> {
+        match *info {
+            Some(ref info) => {
+                let texture = try_throw!(self.root.textures.get(info.index).ok_or(AssetError::InvalidValue));
+
+                match texture.source {
+                    Some(image) if image < self.root.images.len() => Ok(Some(image)),
+                    Some(_) => throw!(AssetError::InvalidValue),
+                    None => Ok(None),
+                }
+            },
+            None => Ok(None),
+        }
+    }
+
+    fn convert_materials(&self, name: &str) -> AssetResult<(Vec<String>, MaterialMap)> {
+        let mut names = Vec::with_capacity(self.root.materials.len());
+        let mut materials = HashMap::new();
+
+        for (index, gltf_material) in self.root.materials.iter().enumerate() {
+            let mut material_name = gltf_material.name.clone().unwrap_or_else(|| format!("{}_material{}", name, index));
+
+            // Names must be unique in the material map
+            if materials.contains_key(&material_name) {
+                material_name = format!("{}_{}", material_name, index);
+            }
+
+            let mut material = Material::default();
+
+            if let Some(ref pbr) = gltf_material.pbr {
+                let color = pbr.base_color_factor.unwrap_or([1.0, 1.0, 1.0, 1.0]);
+                let a = color[3];
+
+                material.color = Color::new(color[0], color[1], color[2], a);
+                material.metallic = Some(pbr.metallic_factor.unwrap_or(1.0));
+                material.roughness = Some(pbr.roughness_factor.unwrap_or(1.0));
+
+                if a < 1.0 {
+                    material.translucency = Some(a);
+                }
+
+                if let Some(image) = self.texture_image(&pbr.base_color_texture)? {
+                    material.texture = Some(self.image_path(image, name));
+                }
+
+                // glTF packs roughness into the green channel and metalness into the blue channel of the same image
+                if let Some(image) = self.texture_image(&pbr.metallic_roughness_texture)? {
+                    material.roughness_map = Some(self.image_path(image, name));
+                    material.metallic_map = Some(self.image_path(image, name));
+                }
+            }
+
+            if let Some(image) = self.texture_image(&gltf_material.normal_texture)? {
+                material.normal_map = Some(self.image_path(image, name));
+            }
+
+            if let Some(emissive) = gltf_material.emissive_factor {
+                let strength = emissive.iter().cloned().fold(0.0, f32::max);
+
+                if strength > 0.0 {
+                    material.emission = Some(strength);
+                }
+            }
+
+            names.push(material_name.clone());
+            materials.insert(material_name, material);
+        }
+
+        Ok((names, MaterialMap { materials: materials }))
+    }
+
+    fn convert_images(&self, name: &str) -> AssetResult<Vec<GltfImage>> {
+        // Only base color textures are stored in sRGB color space
+        let mut srgb = HashSet::new();
+
+        for material in &self.root.materials {
+            if let Some(ref pbr) = material.pbr {
+                if let Some(image) = self.texture_image(&pbr.base_color_texture)? {
+                    srgb.insert(image);
+                }
+            }
+        }
+
+        let mut images = Vec::with_capacity(self.root.images.len());
+
+        for (index, image) in self.root.images.iter().enumerate() {
+            let args = TextureAssetLoadArgs {
+                srgb: srgb.contains(&index),
+                ..TextureAssetLoadArgs::default()
+            };
+
+            let (texture, embedded) = match image.uri {
+                Some(ref uri) if !uri.starts_with("data:") => {
+                    let path = resolve_uri(self.path, uri);
+
+                    (try_rethrow!(TextureAsset::load(AssetMedium::File(&path, self.vfs.clone()), args)), false)
+                },
+                _ => {
+                    let data = match (image.uri.as_ref(), image.buffer_view) {
+                        (Some(uri), _) => load_uri(self.path, self.vfs, uri)?,
+                        (None, Some(buffer_view)) => self.buffer_view(buffer_view)?.0.to_vec(),
+                        (None, None) => throw!(AssetError::InvalidValue),
+                    };
+
+                    let decoded = match image.mime_type.as_ref().map(|mime| mime.as_str()) {
+                        Some("image/png") => image::load_from_memory_with_format(&data, image::ImageFormat::PNG),
+                        Some("image/jpeg") => image::load_from_memory_with_format(&data, image::ImageFormat::JPEG),
+                        _ => image::load_from_memory(&data),
+                    };
+
+                    (TextureAsset::from_image(try_throw!(decoded), args), true)
+                }
+            };
+
+            images.push(GltfImage {
+                path: self.image_path(index, name),
+                texture: texture,
+                embedded: embedded,
+            });
+        }
+
+        Ok(images)
+    }
+}
+
+/// Check if the data is a binary glTF file
+pub fn is_glb(data: &[u8]) -> bool {
+    data.len() >= 4 && &data[0..4] == GLB_MAGIC
+}
+
+/// Import the model and its materials from a `.gltf` or `.glb` file, without decoding any images
+pub fn load_model(path: &Path, vfs: &Arc<BoxedVFS>) -> AssetResult<(Model, MaterialMap)> {
+    let document = try_rethrow!(load_document(path, vfs));
+
+    let name = file_stem(path);
+
+    let (names, materials) = try_rethrow!(document.convert_materials(&name));
+
+    Ok((try_rethrow!(document.convert_model(&name, names)), materials))
+}
+
+/// Import the model, materials and images from a `.gltf` or `.glb` file
+pub fn import(path: &Path, vfs: &Arc<BoxedVFS>) -> AssetResult<GltfImport> {
+    let document = try_rethrow!(load_document(path, vfs));
+
+    let name = file_stem(path);
+
+    let (names, materials) = try_rethrow!(document.convert_materials(&name));
+
+    Ok(GltfImport {
+        model: try_rethrow!(document.convert_model(&name, names)),
+        materials: materials,
+        images: try_rethrow!(document.convert_images(&name)),
+    })
+}
+
+fn file_stem(path: &Path) -> String {
+    path.file_stem().map_or_else(|| "gltf".to_string(), |stem| stem.to_string_lossy().into_owned())
+}
+
+fn load_document<'a>(path: &'a Path, vfs: &'a Arc<BoxedVFS>) -> AssetResult<Document<'a>> {
+    let mut data = Vec::new();
+
+    try_throw!(try_throw!(vfs.open(path)).read_to_end(&mut data));
+
+    let (root, bin): (GltfRoot, Option<Vec<u8>>) = if is_glb(&data) {
+        let (json_chunk, bin_chunk) = parse_glb(&data)?;
+
+        (try_throw!(json::from_slice(json_chunk)), bin_chunk.map(|bin| bin.to_vec()))
+    } else {
+        (try_throw!(json::from_slice(&data)), None)
+    };
+
+    if !root.asset.version.starts_with("2.") {
+        throw!(AssetError::UnsupportedFormat);
+    }
+
+    if !root.extensions_required.is_empty() {
+        throw!(AssetError::Unimplemented("Required glTF extensions"));
+    }
+
+    let mut bin = bin;
+    let mut buffers = Vec::with_capacity(root.buffers.len());
+
+    for (index, buffer) in root.buffers.iter().enumerate() {
+        let data = match buffer.uri {
+            Some(ref uri) => load_uri(path, vfs, uri)?,
+            // The first buffer of a binary glTF file without a URI refers to the binary chunk
+            None if index == 0 => try_throw!(bin.take().ok_or(AssetError::InvalidValue)),
+            None => throw!(AssetError::InvalidValue),
+        };
+
+        if data.len() < buffer.byte_length {
+            throw!(AssetError::InvalidValue);
+        }
+
+        buffers.push(data);
+    }
+
+    Ok(Document { root: root, buffers: buffers, path: path, vfs: vfs })
+}
+
+#[cfg(test)]
+mod test {
+    use std::env;
+    use std::fs::{self, File};
+    use std::io::prelude::*;
+    use std::sync::Arc;
+
+    use common::vfs::BoxedVFS;
+    use common::vfs::default::DefaultFS;
+
+    use protocols::mesh::data::MeshVertices;
+
+    use super::*;
+
+    // A single triangle with one material, using an embedded buffer of three positions followed by three u16 indices
+    const TRIANGLE: &'static str = r#"{
+        "asset": { "version": "2.0" },
+        "scenes": [{ "nodes": [0] }],
+        "nodes": [{ "name": "triangle", "mesh": 0 }],
+        "meshes": [{ "primitives": [{ "attributes": { "POSITION": 0 }, "indices": 1, "material": 0 }] }],
+        "materials": [{ "name": "red", "pbrMetallicRoughness": { "baseColorFactor": [1.0, 0.0, 0.0, 1.0] } }],
+        "accessors": [
+            { "bufferView": 0, "componentType": 5126, "count": POSITIONS, "type": "VEC3" },
+            { "bufferView": 1, "componentType": 5123, "count": 3, "type": "SCALAR" }
+        ],
+        "bufferViews": [
+            { "buffer": 0, "byteLength": 36 },
+            { "buffer": 0, "byteOffset": 36, "byteLength": 6 }
+        ],
+        "buffers": [{
+            "byteLength": 44,
+            "uri": "data:application/octet-stream;base64,AAAAAAAAAAAAAAAAAACAPwAAAAAAAAAAAAAAAAAAgD8AAAAAAAABAAIAAAA="
+        }]
+    }"#;
+
+    fn load(name: &str, positions: &str) -> AssetResult<(Model, MaterialMap)> {
+        load_document(name, &TRIANGLE.replace("POSITIONS", positions))
+    }
+
+    fn load_document(name: &str, document: &str) -> AssetResult<(Model, MaterialMap)> {
+        let dir = env::temp_dir().join("combustion_gltf_test");
+
+        fs::create_dir_all(&dir).unwrap();
+
+        let path = dir.join(name);
+
+        File::create(&path).unwrap().write_all(document.as_bytes()).unwrap();
+
+        let vfs: Arc<BoxedVFS> = Arc::new(box DefaultFS);
+
+        load_model(&path, &vfs)
+    }
+
+    #[test]
+    fn embedded_triangle() {
+        let (model, materials) = load("triangle.gltf", "3").unwrap();
+
+        assert_eq!(model.materials, vec!["red".to_string()]);
+        assert_eq!(model.root.children[0].meshes, vec![0]);
+
+        let mesh = &model.meshes[0];
+
+        assert_eq!(mesh.indices, Some(vec![0, 1, 2]));
+        assert_eq!(mesh.materials, vec![0]);
+
+        match mesh.vertices {
+            MeshVertices::Discrete(ref vertices) => {
+                assert_eq!(vertices.positions, vec![Point3::new(0.0, 0.0, 0.0), Point3::new(1.0, 0.0, 0.0), Point3::new(0.0, 1.0, 0.0)]);
+            },
+            _ => panic!("Expected discrete vertices"),
+        }
+
+        assert_eq!(materials.get("red").unwrap().color, Color::new(1.0, 0.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn accessor_out_of_range() {
+        assert!(load("long.gltf", "4").is_err());
+        assert!(load("huge.gltf", "18446744073709551615").is_err());
+    }
+
+    #[test]
+    fn zero_accessor() {
+        let zeroed = |count: &str| TRIANGLE.replace(r#""bufferView": 0, "componentType": 5126, "count": POSITIONS"#,
+                                                    &format!(r#""componentType": 5126, "count": {}"#, count));
+
+        let (model, _) = load_document("zeroed.gltf", &zeroed("3")).unwrap();
+
+        match model.meshes[0].vertices {
+            MeshVertices::Discrete(ref vertices) => assert_eq!(vertices.positions, vec![Point3::new(0.0, 0.0, 0.0); 3]),
+            _ => panic!("Expected discrete vertices"),
+        }
+
+        assert!(load_document("zeroed_huge.gltf", &zeroed("1099511627776")).is_err());
+    }
+}
@@ -1,4 +1,9 @@
 //! External model import/export routines
 
+pub mod obj;
+
 #[cfg(feature = "assimp")]
-pub mod assimp;
\ No newline at end of file
+pub mod assimp;
+
+#[cfg(feature = "gltf")]
+pub mod gltf;
\ No newline at end of file
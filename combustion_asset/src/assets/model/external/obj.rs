@@ -0,0 +1,938 @@
+//! Routines for importing and exporting Wavefront OBJ models and MTL material libraries
+//!
+//! Objects (`o`) become children of the root `Node`, and groups (`g`) become children of their object.
+//! Faces, lines and points are split into one `Mesh` per node, material and primitive.
+//!
+//! OBJ has no concept of node transforms, so they are not written when exporting.
+
+use std::collections::HashMap;
+use std::io::prelude::*;
+use std::io::{BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+use std::str::{FromStr, SplitWhitespace};
+use std::sync::Arc;
+
+use nalgebra::{Point3, Vector3};
+
+use common::color::Color;
+use common::vfs::BoxedVFS;
+
+use protocols::mesh::protocol::MeshPrimitive;
+use protocols::mesh::data::{Mesh, MeshVertices, Vertices, TexCoord};
+use protocols::model::data::{Model, Node};
+use protocols::material::{Material, MaterialMap};
+
+use ::error::{AssetResult, AssetError};
+
+/// Options for reading OBJ files
+#[derive(Debug, Clone, Copy)]
+pub struct ObjLoadOptions {
+    /// Split faces with more than three vertices into triangles.
+    ///
+    /// If `false`, each such face is kept as its own `MeshPrimitive::Polygon` mesh.
+    pub triangulate: bool,
+}
+
+impl Default for ObjLoadOptions {
+    fn default() -> ObjLoadOptions {
+        ObjLoadOptions { triangulate: true }
+    }
+}
+
+/// Everything imported from an OBJ file and its material libraries
+pub struct ObjImport {
+    /// Node hierarchy and meshes
+    pub model: Model,
+    /// Materials from all referenced material libraries
+    pub materials: MaterialMap,
+    /// Paths of the material libraries referenced by the OBJ file
+    pub material_libraries: Vec<PathBuf>,
+}
+
+fn parse_error(line: usize) -> AssetError {
+    AssetError::Other(format!("Invalid OBJ/MTL data on line {}", line))
+}
+
+fn parse_next<T: FromStr>(tokens: &mut SplitWhitespace, line: usize) -> AssetResult<T> {
+    match tokens.next().map(str::parse) {
+        Some(Ok(value)) => Ok(value),
+        _ => throw!(parse_error(line)),
+    }
+}
+
+fn parse_optional<T: FromStr>(tokens: &mut SplitWhitespace, line: usize, default: T) -> AssetResult<T> {
+    match tokens.next().map(str::parse) {
+        Some(Ok(value)) => Ok(value),
+        Some(Err(_)) => throw!(parse_error(line)),
+        None => Ok(default),
+    }
+}
+
+// The rest of the line after the keyword, for names and paths that may contain spaces
+fn rest_of_line<'a>(line: &'a str, keyword: &str) -> &'a str {
+    line[keyword.len()..].trim()
+}
+
+// Resolve a 1-based or negative (relative to the end) OBJ index
+fn resolve_index(index: i64, len: usize, line: usize) -> AssetResult<usize> {
+    if index > 0 && index as usize <= len {
+        Ok(index as usize - 1)
+    } else if index < 0 && (-index) as usize <= len {
+        Ok(len - (-index) as usize)
+    } else {
+        throw!(parse_error(line))
+    }
+}
+
+/// Roughness from a Blinn-Phong specular exponent
+pub fn shininess_to_roughness(shininess: f32) -> f32 {
+    (2.0 / (shininess.max(0.0) + 2.0)).sqrt()
+}
+
+/// Blinn-Phong specular exponent from roughness, the inverse of `shininess_to_roughness`
+pub fn roughness_to_shininess(roughness: f32) -> f32 {
+    let roughness = roughness.max(1e-3);
+
+    (2.0 / (roughness * roughness) - 2.0).min(1000.0).max(0.0)
+}
+
+type VertexKey = (usize, Option<usize>, Option<usize>);
+
+struct MeshBuilder {
+    primitive: MeshPrimitive,
+    material: Option<u32>,
+    vertex_map: HashMap<VertexKey, u32>,
+    positions: Vec<Point3<f32>>,
+    normals: Vec<Vector3<f32>>,
+    uvs: Vec<TexCoord>,
+    has_normals: bool,
+    has_uvs: bool,
+    indices: Vec<u32>,
+}
+
+impl MeshBuilder {
+    fn new(primitive: MeshPrimitive, material: Option<u32>) -> MeshBuilder {
+        MeshBuilder {
+            primitive: primitive,
+            material: material,
+            vertex_map: HashMap::new(),
+            positions: Vec::new(),
+            normals: Vec::new(),
+            uvs: Vec::new(),
+            has_normals: false,
+            has_uvs: false,
+            indices: Vec::new(),
+        }
+    }
+
+    // OBJ indexes positions, normals and texture coordinates separately,
+    // so every unique combination becomes a single vertex
+    fn add_vertex(&mut self, key: VertexKey, positions: &[Point3<f32>], normals: &[Vector3<f32>], uvs: &[TexCoord]) {
+        let index = match self.vertex_map.get(&key) {
+            Some(&index) => index,
+            None => {
+                let index = self.positions.len() as u32;
+
+                self.positions.push(positions[key.0]);
+                self.uvs.push(key.1.map_or_else(TexCoord::default, |uv| uvs[uv]));
+                self.normals.push(key.2.map_or_else(|| Vector3::new(0.0, 0.0, 0.0), |normal| normals[normal]));
+
+                self.has_uvs |= key.1.is_some();
+                self.has_normals |= key.2.is_some();
+
+                index
+            }
+        };
+
+        self.vertex_map.insert(key, index);
+        self.indices.push(index);
+    }
+
+    fn into_mesh(self) -> Mesh {
+        Mesh {
+            vertices: MeshVertices::Discrete(Vertices {
+                positions: self.positions,
+                normals: if self.has_normals { Some(self.normals) } else { None },
+                uvs: if self.has_uvs { Some(self.uvs) } else { None },
+            }),
+            indices: Some(self.indices),
+            materials: self.material.map_or_else(Vec::new, |material| vec![material]),
+            primitive: self.primitive,
+        }
+    }
+}
+
+struct ObjGroup {
+    name: String,
+    meshes: Vec<usize>,
+    children: Vec<ObjGroup>,
+}
+
+struct ObjParser {
+    options: ObjLoadOptions,
+    positions: Vec<Point3<f32>>,
+    normals: Vec<Vector3<f32>>,
+    uvs: Vec<TexCoord>,
+    builders: Vec<MeshBuilder>,
+    objects: Vec<ObjGroup>,
+    // Whether the last node is a group within the last object
+    in_group: bool,
+    material: Option<u32>,
+    material_names: Vec<String>,
+    libraries: Vec<String>,
+}
+
+impl ObjParser {
+    fn current_node(&mut self) -> &mut ObjGroup {
+        if self.objects.is_empty() {
+            self.objects.push(ObjGroup { name: "default".to_string(), meshes: Vec::new(), children: Vec::new() });
+        }
+
+        let in_group = self.in_group;
+        let object = self.objects.last_mut().unwrap();
+
+        if in_group {
+            object.children.last_mut().unwrap()
+        } else {
+            object
+        }
+    }
+
+    // Find or create the builder for the current node, material and primitive
+    fn builder(&mut self, primitive: MeshPrimitive, unique: bool) -> usize {
+        let material = self.material;
+
+        if !unique {
+            let candidates = self.current_node().meshes.clone();
+
+            let existing = candidates.into_iter().find(|&index| {
+                self.builders[index].primitive == primitive && self.builders[index].material == material
+            });
+
+            if let Some(index) = existing {
+                return index;
+            }
+        }
+
+        let index = self.builders.len();
+
+        self.builders.push(MeshBuilder::new(primitive, material));
+        self.current_node().meshes.push(index);
+
+        index
+    }
+
+    fn parse_vertex(&self, token: &str, line: usize) -> AssetResult<VertexKey> {
+        let mut parts = token.split('/');
+
+        let parse = |part: Option<&str>, len: usize| -> AssetResult<Option<usize>> {
+            match part {
+                Some(part) if !part.is_empty() => {
+                    let index: i64 = try_throw!(part.parse().map_err(|_| parse_error(line)));
+
+                    resolve_index(index, len, line).map(Some)
+                },
+                _ => Ok(None),
+            }
+        };
+
+        let position = try_throw!(parse(parts.next(), self.positions.len())?.ok_or_else(|| parse_error(line)));
+        let uv = parse(parts.next(), self.uvs.len())?;
+        let normal = parse(parts.next(), self.normals.len())?;
+
+        Ok((position, uv, normal))
+    }
+
+    fn add_element(&mut self, builder: usize, keys: &[VertexKey]) {
+        for key in keys {
+            self.builders[builder].add_vertex(*key, &self.positions, &self.normals, &self.uvs);
+        }
+    }
+
+    fn parse_line(&mut self, line: &str, number: usize) -> AssetResult<()> {
+        let mut tokens = line.split_whitespace();
+
+        let keyword = match tokens.next() {
+            Some(keyword) => keyword,
+            None => return Ok(()),
+        };
+
+        match keyword {
+            "v" => {
+                let x = parse_next(&mut tokens, number)?;
+                let y = parse_next(&mut tokens, number)?;
+                let z = parse_next(&mut tokens, number)?;
+                let w: f32 = parse_optional(&mut tokens, number, 1.0)?;
+
+                self.positions.push(Point3::new(x / w, y / w, z / w));
+            },
+            "vn" => {
+                let x = parse_next(&mut tokens, number)?;
+                let y = parse_next(&mut tokens, number)?;
+                let z = parse_next(&mut tokens, number)?;
+
+                self.normals.push(Vector3::new(x, y, z));
+            },
+            "vt" => {
+                let u = parse_next(&mut tokens, number)?;
+                let v = parse_optional(&mut tokens, number, 0.0)?;
+
+                self.uvs.push(TexCoord::new(u, v));
+            },
+            "f" | "l" | "p" => {
+                let mut keys = Vec::new();
+
+                for token in tokens {
+                    keys.push(self.parse_vertex(token, number)?);
+                }
+
+                match keyword {
+                    "f" if keys.len() < 3 => throw!(parse_error(number)),
+                    "f" if keys.len() == 3 || self.options.triangulate => {
+                        let builder = self.builder(MeshPrimitive::Triangles, false);
+
+                        // Fan triangulation works for the convex polygons OBJ files are expected to contain
+                        for i in 1..keys.len() - 1 {
+                            self.add_element(builder, &[keys[0], keys[i], keys[i + 1]]);
+                        }
+                    },
+                    "f" => {
+                        let builder = self.builder(MeshPrimitive::Polygon, true);
+
+                        self.add_element(builder, &keys);
+                    },
+                    "l" if keys.len() < 2 => throw!(parse_error(number)),
+                    "l" => {
+                        let builder = self.builder(MeshPrimitive::Lines, false);
+
+                        for segment in keys.windows(2) {
+                            self.add_element(builder, segment);
+                        }
+                    },
+                    _ => {
+                        let builder = self.builder(MeshPrimitive::Points, false);
+
+                        self.add_element(builder, &keys);
+                    },
+                }
+            },
+            "o" => {
+                self.objects.push(ObjGroup { name: rest_of_line(line, keyword).to_string(), meshes: Vec::new(), children: Vec::new() });
+                self.in_group = false;
+            },
+            "g" => {
+                let name = rest_of_line(line, keyword).to_string();
+
+                // Make sure there is an object to add the group to
+                self.current_node();
+
+                self.objects.last_mut().unwrap().children.push(ObjGroup { name: name, meshes: Vec::new(), children: Vec::new() });
+                self.in_group = true;
+            },
+            "usemtl" => {
+                let name = rest_of_line(line, keyword);
+
+                self.material = Some(match self.material_names.iter().position(|existing| existing == name) {
+                    Some(index) => index as u32,
+                    None => {
+                        self.material_names.push(name.to_string());
+                        (self.material_names.len() - 1) as u32
+                    }
+                });
+            },
+            "mtllib" => {
+                self.libraries.push(rest_of_line(line, keyword).to_string());
+            },
+            _ => {
+                debug!("Skipping unsupported OBJ statement {}", keyword);
+            },
+        }
+
+        Ok(())
+    }
+
+    fn into_model(self) -> Model {
+        let mut meshes: Vec<Option<Mesh>> = self.builders.into_iter().map(|builder| Some(builder.into_mesh())).collect();
+
+        // Renumber meshes in node order, dropping any nodes that ended up empty
+        let mut ordered = Vec::with_capacity(meshes.len());
+
+        fn convert(group: ObjGroup, meshes: &mut Vec<Option<Mesh>>, ordered: &mut Vec<Mesh>) -> Option<Node> {
+            let mut indices = Vec::with_capacity(group.meshes.len());
+
+            for index in group.meshes {
+                if let Some(mesh) = meshes[index].take() {
+                    indices.push(ordered.len() as u32);
+                    ordered.push(mesh);
+                }
+            }
+
+            let children: Vec<Node> = group.children.into_iter().filter_map(|child| convert(child, meshes, ordered)).collect();
+
+            if indices.is_empty() && children.is_empty() {
+                None
+            } else {
+                Some(Node {
+                    name: group.name,
+                    meshes: indices,
+                    children: children,
+                    transforms: Vec::new(),
+                    lod: None,
+                })
+            }
+        }
+
+        let children = self.objects.into_iter().filter_map(|object| convert(object, &mut meshes, &mut ordered)).collect();
+
+        Model {
+            root: Node { children: children, ..Node::default() },
+            meshes: ordered,
+            materials: self.material_names,
+        }
+    }
+}
+
+/// Read a model from OBJ data.
+///
+/// Returns the model and the material library names referenced with `mtllib`.
+pub fn read_obj<R: BufRead>(reader: R, options: ObjLoadOptions) -> AssetResult<(Model, Vec<String>)> {
+    let mut parser = ObjParser {
+        options: options,
+        positions: Vec::new(),
+        normals: Vec::new(),
+        uvs: Vec::new(),
+        builders: Vec::new(),
+        objects: Vec::new(),
+        in_group: false,
+        material: None,
+        material_names: Vec::new(),
+        libraries: Vec::new(),
+    };
+
+    for (number, line) in reader.lines().enumerate() {
+        let line = try_throw!(line);
+
+        // Strip comments
+        let line = match line.find('#') {
+            Some(comment) => &line[..comment],
+            None => &line[..],
+        }.trim();
+
+        try_rethrow!(parser.parse_line(line, number + 1));
+    }
+
+    let libraries = parser.libraries.clone();
+
+    Ok((parser.into_model(), libraries))
+}
+
+// Texture statements may have options before the path, like `map_bump -bm 0.5 normal.png`
+fn texture_path(line: &str, keyword: &str) -> Option<PathBuf> {
+    let mut tokens = rest_of_line(line, keyword).split_whitespace().peekable();
+    let mut path = Vec::new();
+
+    while let Some(token) = tokens.next() {
+        if path.is_empty() && token.starts_with('-') {
+            // Skip any numeric or boolean option arguments
+            while let Some(&next) = tokens.peek() {
+                if next.parse::<f32>().is_ok() || next == "on" || next == "off" {
+                    tokens.next();
+                } else {
+                    break;
+                }
+            }
+        } else {
+            path.push(token);
+        }
+    }
+
+    if path.is_empty() { None } else { Some(PathBuf::from(path.join(" "))) }
+}
+
+/// Read a material library from MTL data
+pub fn read_mtl<R: BufRead>(reader: R) -> AssetResult<MaterialMap> {
+    let mut materials = HashMap::new();
+    let mut current: Option<(String, Material)> = None;
+
+    for (number, line) in reader.lines().enumerate() {
+        let line = try_throw!(line);
+        let number = number + 1;
+
+        let line = match line.find('#') {
+            Some(comment) => &line[..comment],
+            None => &line[..],
+        }.trim();
+
+        let mut tokens = line.split_whitespace();
+
+        let keyword = match tokens.next() {
+            Some(keyword) => keyword,
+            None => continue,
+        };
+
+        if keyword == "newmtl" {
+            if let Some((name, material)) = current.take() {
+                materials.insert(name, material);
+            }
+
+            current = Some((rest_of_line(line, keyword).to_string(), Material::default()));
+
+            continue;
+        }
+
+        let material = match current {
+            Some((_, ref mut material)) => material,
+            None => throw!(parse_error(number)),
+        };
+
+        match keyword {
+            "Kd" => {
+                let r = parse_next(&mut tokens, number)?;
+                let g = parse_optional(&mut tokens, number, r)?;
+                let b = parse_optional(&mut tokens, number, r)?;
+
+                let a = if material.color.is_none() { 1.0 } else { material.color.a };
+
+                material.color = Color::new(r, g, b, a);
+            },
+            "d" | "Tr" => {
+                let value: f32 = parse_next(&mut tokens, number)?;
+                let opacity = if keyword == "Tr" { 1.0 - value } else { value };
+
+                material.color.a = opacity;
+
+                if opacity < 1.0 {
+                    material.translucency = Some(opacity);
+                }
+            },
+            "Ns" => {
+                // Physically based roughness from `Pr` takes precedence
+                if material.roughness.is_none() {
+                    material.roughness = Some(shininess_to_roughness(parse_next(&mut tokens, number)?));
+                }
+            },
+            "Pr" => material.roughness = Some(parse_next(&mut tokens, number)?),
+            "Pm" => material.metallic = Some(parse_next(&mut tokens, number)?),
+            "Ni" => material.ior = Some(parse_next(&mut tokens, number)?),
+            "Ke" => {
+                let r: f32 = parse_next(&mut tokens, number)?;
+                let g = parse_optional(&mut tokens, number, r)?;
+                let b = parse_optional(&mut tokens, number, r)?;
+
+                let emission = r.max(g).max(b);
+
+                if emission > 0.0 {
+                    material.emission = Some(emission);
+                }
+            },
+            "map_Kd" => material.texture = texture_path(line, keyword),
+            "map_bump" | "map_Bump" | "bump" | "norm" => material.normal_map = texture_path(line, keyword),
+            "disp" | "map_disp" => material.height_map = texture_path(line, keyword),
+            "map_Pr" => material.roughness_map = texture_path(line, keyword),
+            "map_Pm" => material.metallic_map = texture_path(line, keyword),
+            _ => {
+                debug!("Skipping unsupported MTL statement {}", keyword);
+            }
+        }
+    }
+
+    if let Some((name, material)) = current.take() {
+        materials.insert(name, material);
+    }
+
+    Ok(MaterialMap { materials: materials })
+}
+
+fn write_faces<W: Write>(writer: &mut W, primitive: MeshPrimitive, indices: &[u32], format: &Fn(u32) -> String) -> AssetResult<()> {
+    let mut write_element = |keyword: &str, element: &[u32]| -> AssetResult<()> {
+        let vertices: Vec<String> = element.iter().map(|index| format(*index)).collect();
+
+        try_throw!(writeln!(writer, "{} {}", keyword, vertices.join(" ")));
+
+        Ok(())
+    };
+
+    match primitive {
+        MeshPrimitive::Points => {
+            try_rethrow!(write_element("p", indices));
+        },
+        MeshPrimitive::Lines => {
+            for line in indices.chunks(2).filter(|line| line.len() == 2) {
+                try_rethrow!(write_element("l", line));
+            }
+        },
+        MeshPrimitive::LineStrip => {
+            try_rethrow!(write_element("l", indices));
+        },
+        MeshPrimitive::LineLoop => {
+            let mut closed = indices.to_vec();
+
+            if let Some(&first) = indices.first() {
+                closed.push(first);
+            }
+
+            try_rethrow!(write_element("l", &closed));
+        },
+        MeshPrimitive::Triangles => {
+            for triangle in indices.chunks(3).filter(|triangle| triangle.len() == 3) {
+                try_rethrow!(write_element("f", triangle));
+            }
+        },
+        MeshPrimitive::TriangleStrip => {
+            for i in 0..indices.len().saturating_sub(2) {
+                // Every other triangle in a strip has reversed winding
+                if i % 2 == 0 {
+                    try_rethrow!(write_element("f", &[indices[i], indices[i + 1], indices[i + 2]]));
+                } else {
+                    try_rethrow!(write_element("f", &[indices[i + 1], indices[i], indices[i + 2]]));
+                }
+            }
+        },
+        MeshPrimitive::TriangleFan => {
+            for i in 1..indices.len().saturating_sub(1) {
+                try_rethrow!(write_element("f", &[indices[0], indices[i], indices[i + 1]]));
+            }
+        },
+        MeshPrimitive::Quads => {
+            for quad in indices.chunks(4).filter(|quad| quad.len() == 4) {
+                try_rethrow!(write_element("f", quad));
+            }
+        },
+        MeshPrimitive::QuadStrip => {
+            for i in (0..indices.len().saturating_sub(2)).filter(|i| i % 2 == 0) {
+                if i + 3 < indices.len() {
+                    try_rethrow!(write_element("f", &[indices[i], indices[i + 1], indices[i + 3], indices[i + 2]]));
+                }
+            }
+        },
+        MeshPrimitive::Polygon => {
+            try_rethrow!(write_element("f", indices));
+        },
+    }
+
+    Ok(())
+}
+
+struct ObjWriterState {
+    positions: usize,
+    uvs: usize,
+    normals: usize,
+}
+
+fn write_mesh<W: Write>(writer: &mut W, model: &Model, mesh: &Mesh, state: &mut ObjWriterState) -> AssetResult<()> {
+    let (positions, normals, uvs): (Vec<Point3<f32>>, Option<Vec<Vector3<f32>>>, Option<Vec<TexCoord>>) = match mesh.vertices {
+        MeshVertices::Discrete(ref vertices) => (vertices.positions.clone(), vertices.normals.clone(), vertices.uvs.clone()),
+        MeshVertices::Interleaved(ref vertices) => (
+            vertices.iter().map(|vertex| vertex.position).collect(),
+            Some(vertices.iter().map(|vertex| vertex.normal).collect()),
+            Some(vertices.iter().map(|vertex| vertex.uv).collect()),
+        ),
+    };
+
+    for position in &positions {
+        try_throw!(writeln!(writer, "v {} {} {}", position.x, position.y, position.z));
+    }
+
+    if let Some(ref uvs) = uvs {
+        for uv in uvs {
+            try_throw!(writeln!(writer, "vt {} {}", uv.u, uv.v));
+        }
+    }
+
+    if let Some(ref normals) = normals {
+        for normal in normals {
+            try_throw!(writeln!(writer, "vn {} {} {}", normal.x, normal.y, normal.z));
+        }
+    }
+
+    if let Some(name) = mesh.materials.first().and_then(|material| model.materials.get(*material as usize)) {
+        try_throw!(writeln!(writer, "usemtl {}", name));
+    }
+
+    let base = (state.positions + 1, state.uvs + 1, state.normals + 1);
+    let has_uvs = uvs.is_some();
+    let has_normals = normals.is_some();
+
+    let format = move |index: u32| -> String {
+        let index = index as usize;
+
+        match (has_uvs, has_normals) {
+            (true, true) => format!("{}/{}/{}", base.0 + index, base.1 + index, base.2 + index),
+            (true, false) => format!("{}/{}", base.0 + index, base.1 + index),
+            (false, true) => format!("{}//{}", base.0 + index, base.2 + index),
+            (false, false) => format!("{}", base.0 + index),
+        }
+    };
+
+    let sequential: Vec<u32>;
+
+    let indices = match mesh.indices {
+        Some(ref indices) => indices.as_slice(),
+        None => {
+            sequential = (0..positions.len() as u32).collect();
+            sequential.as_slice()
+        }
+    };
+
+    try_rethrow!(write_faces(writer, mesh.primitive, indices, &format));
+
+    state.positions += positions.len();
+    state.uvs += uvs.as_ref().map_or(0, |uvs| uvs.len());
+    state.normals += normals.as_ref().map_or(0, |normals| normals.len());
+
+    Ok(())
+}
+
+fn write_node<W: Write>(writer: &mut W, model: &Model, node: &Node, depth: usize, state: &mut ObjWriterState) -> AssetResult<()> {
+    // The root node has no equivalent in OBJ, its direct children are objects and everything below them are groups
+    if depth == 1 {
+        try_throw!(writeln!(writer, "o {}", node.name));
+    } else if depth > 1 {
+        try_throw!(writeln!(writer, "g {}", node.name));
+    }
+
+    for mesh in &node.meshes {
+        let mesh = try_throw!(model.meshes.get(*mesh as usize).ok_or(AssetError::InvalidValue));
+
+        try_rethrow!(write_mesh(writer, model, mesh, state));
+    }
+
+    for child in &node.children {
+        try_rethrow!(write_node(writer, model, child, depth + 1, state));
+    }
+
+    Ok(())
+}
+
+/// Write a model as OBJ data, optionally referencing a material library
+pub fn write_obj<W: Write>(mut writer: W, model: &Model, material_library: Option<&str>) -> AssetResult<()> {
+    if let Some(library) = material_library {
+        try_throw!(writeln!(writer, "mtllib {}", library));
+    }
+
+    let mut state = ObjWriterState { positions: 0, uvs: 0, normals: 0 };
+
+    try_rethrow!(write_node(&mut writer, model, &model.root, 0, &mut state));
+
+    try_throw!(writer.flush());
+
+    Ok(())
+}
+
+/// Write a material library as MTL data
+pub fn write_mtl<W: Write>(mut writer: W, materials: &MaterialMap) -> AssetResult<()> {
+    let mut names: Vec<&String> = materials.keys().collect();
+
+    // Sort for stable output
+    names.sort();
+
+    for name in names {
+        let material = &materials[name];
+
+        try_throw!(writeln!(writer, "newmtl {}", name));
+
+        if !material.color.is_none() {
+            try_throw!(writeln!(writer, "Kd {} {} {}", material.color.r, material.color.g, material.color.b));
+        }
+
+        if let Some(opacity) = material.translucency {
+            try_throw!(writeln!(writer, "d {}", opacity));
+        }
+
+        let roughness = material.roughness.or_else(|| material.smoothness.map(|smoothness| (1.0 - smoothness).powi(2)));
+
+        if let Some(roughness) = roughness {
+            try_throw!(writeln!(writer, "Ns {}", roughness_to_shininess(roughness)));
+            try_throw!(writeln!(writer, "Pr {}", roughness));
+        }
+
+        if let Some(metallic) = material.metallic {
+            try_throw!(writeln!(writer, "Pm {}", metallic));
+        }
+
+        if let Some(ior) = material.ior {
+            try_throw!(writeln!(writer, "Ni {}", ior));
+        }
+
+        if let Some(emission) = material.emission {
+            try_throw!(writeln!(writer, "Ke {} {} {}", emission, emission, emission));
+        }
+
+        let maps = [
+            ("map_Kd", &material.texture),
+            ("map_bump", &material.normal_map),
+            ("disp", &material.height_map),
+            ("map_Pr", &material.roughness_map),
+            ("map_Pm", &material.metallic_map),
+        ];
+
+        for &(keyword, path) in maps.iter() {
+            if let Some(ref path) = *path {
+                try_throw!(writeln!(writer, "{} {}", keyword, path.display()));
+            }
+        }
+
+        try_throw!(writeln!(writer, ""));
+    }
+
+    try_throw!(writer.flush());
+
+    Ok(())
+}
+
+fn resolve_relative(path: &Path, reference: &str) -> PathBuf {
+    match path.parent() {
+        Some(parent) => parent.join(reference),
+        None => PathBuf::from(reference),
+    }
+}
+
+/// Import a model from an OBJ file, along with the materials from any referenced material libraries.
+///
+/// Missing material libraries are skipped with a warning.
+pub fn import(path: &Path, vfs: &Arc<BoxedVFS>, options: ObjLoadOptions) -> AssetResult<ObjImport> {
+    let reader = BufReader::new(try_throw!(vfs.open(path)));
+
+    let (mut model, libraries) = try_rethrow!(read_obj(reader, options));
+
+    if let Some(stem) = path.file_stem() {
+        model.root.name = stem.to_string_lossy().into_owned();
+    }
+
+    let mut materials = HashMap::new();
+    let mut material_libraries = Vec::with_capacity(libraries.len());
+
+    for library in libraries {
+        let library_path = resolve_relative(path, &library);
+
+        match vfs.open(&library_path) {
+            Ok(stream) => {
+                let library_materials = try_rethrow!(read_mtl(BufReader::new(stream)));
+
+                materials.extend(library_materials.materials);
+            },
+            Err(err) => warn!("Could not open material library {:?}: {}", library_path, err),
+        }
+
+        material_libraries.push(library_path);
+    }
+
+    Ok(ObjImport {
+        model: model,
+        materials: MaterialMap { materials: materials },
+        material_libraries: material_libraries,
+    })
+}
+
+/// Export a model to an OBJ file.
+///
+/// If `materials` is given, they are written to an MTL file with the same name next to it.
+pub fn export(path: &Path, vfs: &Arc<BoxedVFS>, model: &Model, materials: Option<&MaterialMap>) -> AssetResult<()> {
+    let library = match materials {
+        Some(materials) => {
+            let library_path = path.with_extension("mtl");
+
+            let writer = BufWriter::new(try_throw!(vfs.create_or_truncate(&library_path)));
+
+            try_rethrow!(write_mtl(writer, materials));
+
+            library_path.file_name().map(|name| name.to_string_lossy().into_owned())
+        },
+        None => None,
+    };
+
+    let writer = BufWriter::new(try_throw!(vfs.create_or_truncate(path)));
+
+    write_obj(writer, model, library.as_ref().map(|library| library.as_str()))
+}
+
+#[cfg(test)]
+mod test {
+    use std::env;
+    use std::fs::{self, File};
+    use std::io::prelude::*;
+    use std::sync::Arc;
+
+    use common::vfs::BoxedVFS;
+    use common::vfs::default::DefaultFS;
+
+    use super::*;
+
+    const CUBE_SIDES: &'static str = "mtllib sides.mtl
+o front
+v 0 0 0
+v 1 0 0
+v 1 1 0
+v 0 1 0
+vt 0 0
+vt 1 0
+vt 1 1
+vt 0 1
+usemtl brick
+f 1/1 2/2 3/3 4/4
+o top
+v 0 1 0
+v 1 1 0
+v 1 1 1
+usemtl glass
+f 5 6 7
+";
+
+    const SIDES_MTL: &'static str = "newmtl brick
+Kd 0.5 0.25 0
+map_Kd brick.png
+
+newmtl glass
+Kd 0.9 0.9 1
+d 0.25
+";
+
+    fn positions(model: &Model) -> Vec<Vec<Point3<f32>>> {
+        model.meshes.iter().map(|mesh| match mesh.vertices {
+            MeshVertices::Discrete(ref vertices) => vertices.positions.clone(),
+            MeshVertices::Interleaved(ref vertices) => vertices.iter().map(|vertex| vertex.position).collect(),
+        }).collect()
+    }
+
+    #[test]
+    fn obj_round_trip() {
+        let dir = env::temp_dir().join("combustion_obj_test");
+
+        fs::create_dir_all(&dir).unwrap();
+
+        File::create(dir.join("sides.obj")).unwrap().write_all(CUBE_SIDES.as_bytes()).unwrap();
+        File::create(dir.join("sides.mtl")).unwrap().write_all(SIDES_MTL.as_bytes()).unwrap();
+
+        let vfs: Arc<BoxedVFS> = Arc::new(box DefaultFS);
+
+        let first = import(&dir.join("sides.obj"), &vfs, ObjLoadOptions::default()).unwrap();
+
+        assert_eq!(first.model.materials, vec!["brick".to_string(), "glass".to_string()]);
+        assert_eq!(first.materials.len(), 2);
+
+        export(&dir.join("saved.obj"), &vfs, &first.model, Some(&first.materials)).unwrap();
+
+        let second = import(&dir.join("saved.obj"), &vfs, ObjLoadOptions::default()).unwrap();
+
+        assert_eq!(second.material_libraries, vec![dir.join("saved.mtl")]);
+        assert_eq!(second.model.materials, first.model.materials);
+
+        let names = |model: &Model| model.root.children.iter().map(|node| node.name.clone()).collect::<Vec<_>>();
+
+        assert_eq!(names(&second.model), names(&first.model));
+        assert_eq!(positions(&second.model), positions(&first.model));
+
+        for (a, b) in first.model.meshes.iter().zip(second.model.meshes.iter()) {
+            assert_eq!(a.indices, b.indices);
+            assert_eq!(a.materials, b.materials);
+        }
+
+        for (name, material) in first.materials.iter() {
+            let saved = &second.materials[name];
+
+            assert_eq!(saved.color, material.color);
+            assert_eq!(saved.texture, material.texture);
+            assert_eq!(saved.translucency, material.translucency);
+        }
+    }
+}
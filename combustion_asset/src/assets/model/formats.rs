@@ -5,11 +5,29 @@ use protocols::model::EXTENSION;
 use ::asset::AssetFileFormat;
 use ::assets::standard::formats::StandardFileFormat;
 
+// Checked before Assimp so the pure-Rust importer is preferred
+#[cfg(feature = "gltf")]
+fn gltf_format(ext: &str) -> Option<ModelFileFormat> {
+    match ext {
+        "gltf" | "glb" => Some(ModelFileFormat::Gltf),
+        _ => None,
+    }
+}
+
+#[cfg(not(feature = "gltf"))]
+#[inline(always)]
+fn gltf_format(_: &str) -> Option<ModelFileFormat> { None }
+
 /// Supported file formats
 #[derive(Debug, Clone, Copy, PartialEq, Hash, PartialOrd)]
 pub enum ModelFileFormat {
     /// Native Combustion file format
     Native,
+    /// Wavefront OBJ
+    Obj,
+    /// glTF 2.0 (`.gltf` and `.glb`)
+    #[cfg(feature = "gltf")]
+    Gltf,
     /// Any format supported by Assimp
     #[cfg(feature = "assimp")]
     Assimp,
@@ -22,6 +40,10 @@ impl AssetFileFormat for ModelFileFormat {
     fn from_extension(ext: &str) -> Option<ModelFileFormat> {
         Some(if ext == EXTENSION {
             ModelFileFormat::Native
+        } else if ext == "obj" {
+            ModelFileFormat::Obj
+        } else if let Some(gltf_format) = gltf_format(ext) {
+            gltf_format
         } else if ::assimp::formats::is_extension_supported(ext) {
             ModelFileFormat::Assimp
         } else if let Some(standard_format) = StandardFileFormat::from_extension(ext) {
@@ -35,6 +57,10 @@ impl AssetFileFormat for ModelFileFormat {
     fn from_extension(ext: &str) -> Option<ModelFileFormat> {
         Some(if ext == EXTENSION {
             ModelFileFormat::Native
+        } else if ext == "obj" {
+            ModelFileFormat::Obj
+        } else if let Some(gltf_format) = gltf_format(ext) {
+            gltf_format
         } else if let Some(standard_format) = StandardFileFormat::from_extension(ext) {
             ModelFileFormat::Standard(standard_format)
         } else {
@@ -51,6 +77,8 @@ impl AssetFileFormat for ModelFileFormat {
 
     fn can_export(&self) -> bool {
         match *self {
+            #[cfg(feature = "gltf")]
+            ModelFileFormat::Gltf => false,
             #[cfg(feature = "assimp")]
             ModelFileFormat::Assimp => false,
             ModelFileFormat::Standard(standard_format) => standard_format.can_export(),
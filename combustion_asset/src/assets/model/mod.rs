@@ -5,4 +5,4 @@ pub mod external;
 pub mod asset;
 
 pub use self::formats::ModelFileFormat;
-pub use self::asset::{ModelAsset, ModelAssetQuery, ModelAssetSaveArgs};
\ No newline at end of file
+pub use self::asset::{ModelAsset, ModelAssetQuery, ModelAssetLoadArgs, ModelAssetSaveArgs};
\ No newline at end of file
@@ -0,0 +1,214 @@
+//! Scene asset implementation
+
+use std::ops::{Deref, DerefMut};
+use std::ascii::AsciiExt;
+use std::io::BufReader;
+use std::mem;
+
+use protocols::scene::{Scene, Node, NodePayload, Light};
+
+use ::error::{AssetResult, AssetError};
+use ::asset::{Asset, AssetMedium, AssetQuery, AssetFileFormat};
+use ::cache::AssetSize;
+
+use super::formats::SceneFileFormat;
+
+/// Scene Asset queries
+#[derive(Debug, Clone, Copy)]
+pub enum SceneAssetQuery<'a> {
+    /// Check if a file extension for a scene file is supported for importing
+    SupportedImportExtension(&'a str),
+    /// Check if a file extension for a scene file is supported for exporting
+    SupportedExportExtension(&'a str),
+    /// Check if a file extension for a scene file is supported for both importing and exporting
+    SupportedExtension(&'a str),
+}
+
+impl<'a> AssetQuery for SceneAssetQuery<'a> {
+    type Arguments = SceneAssetQuery<'a>;
+    type Result = bool;
+}
+
+/// Arguments for scene save routines
+#[derive(Debug, Default, Clone)]
+pub struct SceneAssetSaveArgs {
+    /// For serialization formats that support "pretty-printing", pretty-print the data
+    pub pretty: bool,
+}
+
+/// Scene Asset
+#[derive(Serialize, Deserialize)]
+pub struct SceneAsset(Scene);
+
+impl<'a> Asset<'a> for SceneAsset {
+    type LoadArgs = ();
+    type SaveArgs = SceneAssetSaveArgs;
+
+    type Query = SceneAssetQuery<'a>;
+
+    fn query(query: SceneAssetQuery<'a>) -> AssetResult<bool> {
+        Ok(match query {
+            SceneAssetQuery::SupportedImportExtension(ext) => {
+                match SceneFileFormat::from_extension(ext) {
+                    Some(format) if format.can_import() => true,
+                    _ => false
+                }
+            },
+            SceneAssetQuery::SupportedExportExtension(ext) => {
+                match SceneFileFormat::from_extension(ext) {
+                    Some(format) if format.can_export() => true,
+                    _ => false
+                }
+            },
+            SceneAssetQuery::SupportedExtension(ext) => {
+                match SceneFileFormat::from_extension(ext) {
+                    Some(format) if format.can_import() && format.can_export() => true,
+                    _ => false
+                }
+            },
+        })
+    }
+
+    fn load(medium: AssetMedium<'a>, _: ()) -> AssetResult<SceneAsset> {
+        if let AssetMedium::File(path, vfs) = medium {
+            if let Some(ext) = path.extension() {
+                let ext = try_throw!(ext.to_str().ok_or(AssetError::InvalidValue)).to_ascii_lowercase();
+
+                let format = match SceneFileFormat::from_extension(ext.as_str()) {
+                    Some(format) if format.can_import() => format,
+                    _ => throw!(AssetError::UnsupportedFormat),
+                };
+
+                match format {
+                    SceneFileFormat::Standard(standard_format) => {
+                        let reader = BufReader::new(try_throw!(vfs.open(path)));
+
+                        return ::assets::standard::generic::load_standard_format(reader, standard_format);
+                    },
+                }
+            }
+        }
+
+        throw!(AssetError::UnsupportedMedium)
+    }
+
+    fn save(&self, medium: AssetMedium<'a>, args: SceneAssetSaveArgs) -> AssetResult<()> {
+        if let AssetMedium::File(path, vfs) = medium {
+            if let Some(ext) = path.extension() {
+                let ext = try_throw!(ext.to_str().ok_or(AssetError::InvalidValue)).to_ascii_lowercase();
+
+                let format = match SceneFileFormat::from_extension(ext.as_str()) {
+                    Some(format) if format.can_export() => format,
+                    _ => throw!(AssetError::UnsupportedFormat),
+                };
+
+                match format {
+                    SceneFileFormat::Standard(standard_format) => {
+                        let writer = try_throw!(vfs.create_or_truncate(path));
+
+                        return ::assets::standard::generic::save_standard_format(writer, standard_format, self, args.pretty);
+                    },
+                }
+            }
+        }
+
+        throw!(AssetError::UnsupportedMedium)
+    }
+}
+
+impl SceneAsset {
+    /// Wraps an existing scene
+    pub fn new(scene: Scene) -> SceneAsset {
+        SceneAsset(scene)
+    }
+
+    /// Unwraps the scene
+    pub fn into_inner(self) -> Scene {
+        self.0
+    }
+}
+
+fn approximate_node_size(node: &Node) -> usize {
+    let properties: usize = node.properties.iter().map(|(key, value)| key.len() + value.len()).sum();
+
+    mem::size_of::<Node>() + properties +
+        node.payloads.len() * mem::size_of::<NodePayload>() +
+        node.children.iter().map(approximate_node_size).sum::<usize>()
+}
+
+impl AssetSize for SceneAsset {
+    fn approximate_size(&self) -> usize {
+        let lights: usize = self.0.lights.iter().map(|light| {
+            mem::size_of::<Light>() + light.properties.iter().map(|(key, value)| key.len() + value.len()).sum::<usize>()
+        }).sum();
+
+        let materials: usize = self.0.materials.iter().map(|material| material.name.len()).sum();
+
+        self.0.name.len() + lights + materials + approximate_node_size(&self.0.root)
+    }
+}
+
+impl Deref for SceneAsset {
+    type Target = Scene;
+
+    fn deref(&self) -> &Scene {
+        &self.0
+    }
+}
+
+impl DerefMut for SceneAsset {
+    fn deref_mut(&mut self) -> &mut Scene {
+        &mut self.0
+    }
+}
+
+#[cfg(all(test, feature = "json"))]
+mod test {
+    use std::env;
+    use std::fs;
+    use std::sync::Arc;
+
+    use json;
+
+    use common::vfs::BoxedVFS;
+    use common::vfs::default::DefaultFS;
+
+    use protocols::scene::sample::sample;
+
+    use super::*;
+
+    #[test]
+    fn extensions() {
+        assert!(SceneAsset::query(SceneAssetQuery::SupportedExtension("json")).unwrap());
+        assert!(SceneAsset::query(SceneAssetQuery::SupportedImportExtension("json")).unwrap());
+        assert!(SceneAsset::query(SceneAssetQuery::SupportedExportExtension("json")).unwrap());
+        assert!(!SceneAsset::query(SceneAssetQuery::SupportedExtension("png")).unwrap());
+    }
+
+    #[test]
+    fn json_round_trip() {
+        let dir = env::temp_dir().join("combustion_scene_asset_test");
+
+        fs::create_dir_all(&dir).unwrap();
+
+        let path = dir.join("sample.json");
+
+        let vfs: Arc<BoxedVFS> = Arc::new(box DefaultFS);
+
+        let asset = SceneAsset::new(sample());
+
+        asset.save(AssetMedium::File(&path, vfs.clone()), SceneAssetSaveArgs { pretty: true }).unwrap();
+
+        let loaded = SceneAsset::load(AssetMedium::File(&path, vfs.clone()), ()).unwrap();
+
+        // The protocol structures don't implement `PartialEq`, so compare their serialized forms
+        let expected: json::Value = json::from_str(&json::to_string(&*asset).unwrap()).unwrap();
+        let actual: json::Value = json::from_str(&json::to_string(&*loaded).unwrap()).unwrap();
+
+        assert_eq!(actual, expected);
+
+        assert!(SceneAsset::load(AssetMedium::File(&dir.join("sample.png"), vfs), ()).is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
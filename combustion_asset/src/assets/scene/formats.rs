@@ -0,0 +1,31 @@
+//! Scene asset formats
+
+use ::asset::AssetFileFormat;
+use ::assets::standard::formats::StandardFileFormat;
+
+/// Supported file formats
+///
+/// Scenes have no native binary format, so only standard formats are supported.
+#[derive(Debug, Clone, Copy, PartialEq, Hash, PartialOrd)]
+pub enum SceneFileFormat {
+    /// Any standard file format
+    Standard(StandardFileFormat)
+}
+
+impl AssetFileFormat for SceneFileFormat {
+    fn from_extension(ext: &str) -> Option<SceneFileFormat> {
+        StandardFileFormat::from_extension(ext).map(SceneFileFormat::Standard)
+    }
+
+    fn can_import(&self) -> bool {
+        match *self {
+            SceneFileFormat::Standard(standard_format) => standard_format.can_import(),
+        }
+    }
+
+    fn can_export(&self) -> bool {
+        match *self {
+            SceneFileFormat::Standard(standard_format) => standard_format.can_export(),
+        }
+    }
+}
\ No newline at end of file
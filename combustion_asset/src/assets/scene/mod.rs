@@ -0,0 +1,7 @@
+//! Scene asset
+
+pub mod formats;
+pub mod asset;
+
+pub use self::formats::SceneFileFormat;
+pub use self::asset::{SceneAsset, SceneAssetQuery, SceneAssetSaveArgs};
\ No newline at end of file
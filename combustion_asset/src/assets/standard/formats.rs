@@ -12,13 +12,19 @@ pub enum StandardFileFormat {
     #[cfg(feature = "json")] Json,
     /// YAML
     #[cfg(feature = "yaml")] Yaml,
+    /// TOML
+    ///
+    /// TOML has a single layout of one key per line with a header for each table,
+    /// so the `pretty` option of save routines has no effect on it.
+    /// `None` values can't be written, so optional fields have to be skipped when empty.
+    #[cfg(feature = "toml")] Toml,
     // Avoids errors when no standard formats are enabled
     #[doc(hidden)]
     __Invalid,
 }
 
 impl AssetFileFormat for StandardFileFormat {
-    #[cfg(any(feature = "bincode", feature = "json", feature = "yaml"))]
+    #[cfg(any(feature = "bincode", feature = "json", feature = "yaml", feature = "toml"))]
     fn from_extension(ext: &str) -> Option<StandardFileFormat> {
         Some(match ext {
             #[cfg(feature = "bincode")]
@@ -27,12 +33,14 @@ impl AssetFileFormat for StandardFileFormat {
             "json" => StandardFileFormat::Json,
             #[cfg(feature = "yaml")]
             "yaml" => StandardFileFormat::Yaml,
+            #[cfg(feature = "toml")]
+            "toml" => StandardFileFormat::Toml,
             _ => { return None; },
         })
     }
 
     // Simple version for when all standard formats are disabled
-    #[cfg(not(any(feature = "bincode", feature = "json", feature = "yaml")))]
+    #[cfg(not(any(feature = "bincode", feature = "json", feature = "yaml", feature = "toml")))]
     #[inline(always)]
     fn from_extension(_: &str) -> Option<StandardFileFormat> { None }
 
@@ -10,12 +10,21 @@ use ::asset::Asset;
 use ::assets::standard::formats::StandardFileFormat;
 
 /// Load any `T: Asset` from a standard deserializable format
-#[cfg_attr(not(feature = "bincode"), allow(unused_mut))]
-#[cfg_attr(not(any(feature = "json", feature = "yaml", feature = "bincode")), allow(unused_variables, unreachable_code))]
-pub fn load_standard_format<'a, T: 'a, R>(mut reader: R, format: StandardFileFormat) -> AssetResult<T>
+pub fn load_standard_format<'a, T: 'a, R>(reader: R, format: StandardFileFormat) -> AssetResult<T>
     where R: Read, T: Asset<'a> + Deserialize
 {
-    let asset = match format {
+    deserialize_standard_format(reader, format)
+}
+
+/// Deserialize any `T` from a standard deserializable format
+///
+/// This is useful for data structures that are not assets by themselves, but are referenced by them.
+#[cfg_attr(not(any(feature = "bincode", feature = "toml")), allow(unused_mut))]
+#[cfg_attr(not(any(feature = "json", feature = "yaml", feature = "bincode", feature = "toml")), allow(unused_variables, unreachable_code))]
+pub fn deserialize_standard_format<T, R>(mut reader: R, format: StandardFileFormat) -> AssetResult<T>
+    where R: Read, T: Deserialize
+{
+    let value = match format {
         #[cfg(feature = "bincode")]
         StandardFileFormat::Bincode => {
             use bincode::{deserialize_from, SizeLimit};
@@ -34,15 +43,25 @@ pub fn load_standard_format<'a, T: 'a, R>(mut reader: R, format: StandardFileFor
 
             try_throw!(from_reader(reader))
         },
+        #[cfg(feature = "toml")]
+        StandardFileFormat::Toml => {
+            use toml::from_str;
+
+            let mut text = String::new();
+
+            try_throw!(reader.read_to_string(&mut text));
+
+            try_throw!(from_str(&text))
+        },
         _ => throw!(AssetError::UnsupportedFormat),
     };
 
-    Ok(asset)
+    Ok(value)
 }
 
 /// Save any `T: Asset` to a standard serializable format
 #[cfg_attr(not(feature = "json"), allow(unused_variables))]
-#[cfg_attr(not(any(feature = "json", feature = "yaml", feature = "bincode")), allow(unused_mut, unreachable_code))]
+#[cfg_attr(not(any(feature = "json", feature = "yaml", feature = "bincode", feature = "toml")), allow(unused_mut, unreachable_code))]
 pub fn save_standard_format<'a, T: 'a, W>(mut writer: W, format: StandardFileFormat, asset: &T, pretty: bool) -> AssetResult<()>
     where W: Write, T: Asset<'a> + Serialize
 {
@@ -69,8 +88,63 @@ pub fn save_standard_format<'a, T: 'a, W>(mut writer: W, format: StandardFileFor
 
             try_throw!(to_writer(&mut writer, asset));
         },
+        #[cfg(feature = "toml")]
+        StandardFileFormat::Toml => {
+            use toml::Value;
+
+            // Serializing directly fails if a plain value follows a table, which is common in structures,
+            // but a `Value` always writes plain values before tables. See `StandardFileFormat::Toml` about `pretty`.
+            let value = try_throw!(Value::try_from(asset));
+
+            try_throw!(writer.write_all(value.to_string().as_bytes()));
+        },
         _ => throw!(AssetError::UnsupportedFormat),
     }
 
     Ok(())
-}
\ No newline at end of file
+}
+
+#[cfg(all(test, feature = "toml", feature = "json"))]
+mod test {
+    use serde::Serialize;
+
+    use json;
+
+    use protocols::material::MaterialMap;
+    use protocols::material::sample::sample as sample_material;
+    use protocols::scene::Scene;
+    use protocols::scene::sample::sample as sample_scene;
+
+    use ::assets::material::MaterialAsset;
+    use ::assets::scene::SceneAsset;
+
+    use super::*;
+
+    // Compares through JSON values, since the protocol structures don't implement `PartialEq`
+    fn json_value<T: Serialize>(value: &T) -> json::Value {
+        json::from_str(&json::to_string(value).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn toml_round_trip() {
+        let materials = MaterialAsset::new(sample_material());
+
+        let mut buffer = Vec::new();
+
+        save_standard_format(&mut buffer, StandardFileFormat::Toml, &materials, true).unwrap();
+
+        let loaded: MaterialMap = deserialize_standard_format(&buffer[..], StandardFileFormat::Toml).unwrap();
+
+        assert_eq!(json_value(&loaded), json_value(&*materials));
+
+        let scene = SceneAsset::new(sample_scene());
+
+        buffer.clear();
+
+        save_standard_format(&mut buffer, StandardFileFormat::Toml, &scene, false).unwrap();
+
+        let loaded: Scene = deserialize_standard_format(&buffer[..], StandardFileFormat::Toml).unwrap();
+
+        assert_eq!(json_value(&loaded), json_value(&*scene));
+    }
+}
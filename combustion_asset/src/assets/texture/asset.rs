@@ -2,25 +2,30 @@
 
 use std::ops::{Deref, DerefMut};
 use std::ascii::AsciiExt;
-use std::io::BufReader;
+use std::hash::{Hash, Hasher};
+use std::io::{BufReader, BufWriter};
+use std::borrow::Cow;
 
-use capnp::serialize_packed;
-use capnp::message::ReaderOptions;
+use image::{self, DynamicImage, GenericImage, ImageFormat, ImageDecoder, ColorType, DecodingResult};
 
-use image::{self, DynamicImage, GenericImage, ImageFormat};
+use common::compression::CompressionOptions;
 
 use protocols::traits::Storage;
 use protocols::texture::protocol;
-use protocols::texture::data::{texture, format};
+use protocols::texture::data::texture::{self, TextureInfo};
 use protocols::texture::storage::RootTextureQuery;
 
 use ::error::{AssetResult, AssetError};
 use ::asset::{Asset, AssetMedium, AssetQuery, AssetFileFormat};
+use ::cache::AssetSize;
+use ::native::{self, ReaderLimits};
 
 use super::formats::TextureFileFormat;
+use super::cubemap::FloatImage;
+use super::{bc6h, external, pixels};
 
 /// Load arguments for texture assets
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct TextureAssetLoadArgs {
     /// Only allow 2D textures
     pub only2d: bool,
@@ -32,11 +37,23 @@ pub struct TextureAssetLoadArgs {
     ///
     /// If the hint is `None`, it'll default to the Combustion texture format.
     pub format_hint: Option<ImageFormat>,
+    /// Limits for reading native textures
+    pub reader_limits: ReaderLimits,
+}
+
+// `ImageFormat` doesn't implement `Hash`, so hash its discriminant instead
+impl Hash for TextureAssetLoadArgs {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.only2d.hash(state);
+        self.srgb.hash(state);
+        self.format_hint.map(|format| format as u8).hash(state);
+        self.reader_limits.hash(state);
+    }
 }
 
 impl Default for TextureAssetLoadArgs {
     fn default() -> TextureAssetLoadArgs {
-        TextureAssetLoadArgs { only2d: false, srgb: false, format_hint: None }
+        TextureAssetLoadArgs { only2d: false, srgb: false, format_hint: None, reader_limits: ReaderLimits::default() }
     }
 }
 
@@ -54,6 +71,8 @@ pub struct TextureAssetSaveArgs {
     pub quality: u8,
     /// For serialization formats that support "pretty-printing", pretty-print the data
     pub pretty: bool,
+    /// Compress native textures with LZ4 using these options. If `None`, they are only packed.
+    pub compression: Option<CompressionOptions>,
 }
 
 impl Default for TextureAssetSaveArgs {
@@ -62,6 +81,7 @@ impl Default for TextureAssetSaveArgs {
             format_hint: None,
             quality: 95,
             pretty: false,
+            compression: None,
         }
     }
 }
@@ -70,12 +90,28 @@ impl Default for TextureAssetSaveArgs {
 #[derive(Debug, Clone)]
 pub enum TextureAssetQuery<'a> {
     /// Queries if a given medium is supported
-    SupportedMedium(AssetMedium<'a>)
+    SupportedMedium(AssetMedium<'a>),
+    /// Queries the dimensions, format, mipmap levels and layers of a texture,
+    /// as it would be loaded with the given arguments.
+    ///
+    /// DDS, KTX and most image files are probed by reading only their headers.
+    /// Native files have to be read whole, since packed messages can't be read in part, but their image data isn't copied.
+    /// Other formats are fully loaded.
+    Info(AssetMedium<'a>, TextureAssetLoadArgs),
+}
+
+/// Result of a `TextureAssetQuery`, with a variant for each query
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TextureAssetQueryResult {
+    /// Result of `TextureAssetQuery::SupportedMedium`
+    Supported(bool),
+    /// Result of `TextureAssetQuery::Info`
+    Info(TextureInfo),
 }
 
 impl<'a> AssetQuery for TextureAssetQuery<'a> {
     type Arguments = TextureAssetQuery<'a>;
-    type Result = bool;
+    type Result = TextureAssetQueryResult;
 }
 
 /// Texture Asset
@@ -88,10 +124,13 @@ impl<'a> Asset<'a> for TextureAsset {
 
     type Query = TextureAssetQuery<'a>;
 
-    fn query(query: TextureAssetQuery) -> AssetResult<bool> {
+    fn query(query: TextureAssetQuery<'a>) -> AssetResult<TextureAssetQueryResult> {
         Ok(match query {
             TextureAssetQuery::SupportedMedium(medium) => {
-                if let AssetMedium::File(..) = medium { true } else { false }
+                TextureAssetQueryResult::Supported(if let AssetMedium::File(..) = medium { true } else { false })
+            },
+            TextureAssetQuery::Info(medium, args) => {
+                TextureAssetQueryResult::Info(try_rethrow!(TextureAsset::probe(medium, args)))
             },
         })
     }
@@ -108,12 +147,7 @@ impl<'a> Asset<'a> for TextureAsset {
 
                 match format {
                     TextureFileFormat::Native => {
-                        let mut reader = BufReader::new(try_throw!(vfs.open(path)));
-
-                        let message_reader = try_throw!(serialize_packed::read_message(&mut reader, ReaderOptions {
-                            traversal_limit_in_words: u64::max_value(),
-                            nesting_limit: 64,
-                        }));
+                        let message_reader = try_rethrow!(native::read_native_message(path, &vfs, args.reader_limits));
 
                         let root_texture_reader = try_throw!(message_reader.get_root::<protocol::root_texture::Reader>());
 
@@ -127,41 +161,64 @@ impl<'a> Asset<'a> for TextureAsset {
 
                         return Ok(TextureAsset(root_texture));
                     },
+                    TextureFileFormat::Dds => {
+                        let reader = BufReader::new(try_throw!(vfs.open(path)));
+
+                        let root_texture = try_rethrow!(external::dds::read_dds(reader, args.srgb));
+
+                        return TextureAsset::from_container(root_texture, args);
+                    },
+                    TextureFileFormat::Ktx | TextureFileFormat::Ktx2 => {
+                        let reader = BufReader::new(try_throw!(vfs.open(path)));
+
+                        let root_texture = try_rethrow!(external::ktx::read_ktx(reader));
+
+                        return TextureAsset::from_container(root_texture, args);
+                    },
+                    TextureFileFormat::OpenExr => {
+                        let reader = BufReader::new(try_throw!(vfs.open(path)));
+
+                        let texture = try_rethrow!(external::exr::read_exr(reader));
+
+                        return Ok(TextureAsset::from_texture(texture, args));
+                    },
                     TextureFileFormat::Image(image_format) => {
-                        let mut reader = BufReader::new(try_throw!(vfs.open(path)));
+                        // The `image` crate reduces everything to 8-bit, so decode high bit depth images separately
+                        match image_format {
+                            ImageFormat::HDR => {
+                                let reader = BufReader::new(try_throw!(vfs.open(path)));
 
-                        // Load ordinary image into data structures
-                        let image: DynamicImage = try_throw!(image::load(&mut reader, image_format));
+                                let image = try_rethrow!(FloatImage::read_hdr(reader));
 
-                        let format = format::SpecificFormat {
-                            which: format::Which::None(format::Uncompressed {
-                                channels: match image {
-                                    DynamicImage::ImageLuma8(_) => protocol::Channels::R,
-                                    DynamicImage::ImageLumaA8(_) => protocol::Channels::Rg,
-                                    DynamicImage::ImageRgb8(_) => protocol::Channels::Rgb,
-                                    DynamicImage::ImageRgba8(_) => protocol::Channels::Rgba,
-                                },
-                                data_type: protocol::DataType::UnsignedByte,
-                            }),
-                            srgb: args.srgb
-                        };
+                                let format = external::uncompressed(protocol::Channels::Rgb, protocol::DataType::Float, false);
 
-                        let (width, height) = image.dimensions();
+                                return Ok(TextureAsset::from_texture(try_rethrow!(image.to_texture(format)), args));
+                            },
+                            ImageFormat::PNG => {
+                                let reader = BufReader::new(try_throw!(vfs.open(path)));
 
-                        let root_texture = texture::RootTexture::Texture(box texture::Texture {
-                            data: image.raw_pixels().into(),
-                            dimensions: texture::Dimensions::new(width, height, 0),
-                            kind: {
-                                if (width == 1 || height == 1) && !args.only2d {
-                                    protocol::TextureKind::Texture1D
-                                } else {
-                                    protocol::TextureKind::Texture2D
+                                if let Some(texture) = try_rethrow!(read_16bit(image::png::PNGDecoder::new(reader), args)) {
+                                    return Ok(TextureAsset::from_texture(texture, args));
                                 }
                             },
-                            format: format,
-                        });
+                            ImageFormat::TIFF => {
+                                let reader = BufReader::new(try_throw!(vfs.open(path)));
 
-                        return Ok(TextureAsset(root_texture));
+                                let decoder = try_throw!(image::tiff::TIFFDecoder::new(reader));
+
+                                if let Some(texture) = try_rethrow!(read_16bit(decoder, args)) {
+                                    return Ok(TextureAsset::from_texture(texture, args));
+                                }
+                            },
+                            _ => {}
+                        }
+
+                        let mut reader = BufReader::new(try_throw!(vfs.open(path)));
+
+                        // Load ordinary image into data structures
+                        let image: DynamicImage = try_throw!(image::load(&mut reader, image_format));
+
+                        return Ok(TextureAsset::from_image(image, args));
                     },
                     TextureFileFormat::StandardFormat(standard_format) => {
                         let reader = BufReader::new(try_throw!(vfs.open(path)));
@@ -187,8 +244,6 @@ impl<'a> Asset<'a> for TextureAsset {
 
                 match format {
                     TextureFileFormat::Native => {
-                        let mut writer = try_throw!(vfs.create_or_truncate(path));
-
                         let mut message = ::capnp::message::Builder::new_default();
 
                         {
@@ -197,55 +252,91 @@ impl<'a> Asset<'a> for TextureAsset {
                             try_rethrow!(self.0.save_to_builder(root_texture_builder));
                         }
 
-                        try_throw!(serialize_packed::write_message(&mut writer, &message));
+                        return native::write_native_message(path, &vfs, &message, args.compression);
+                    },
+                    TextureFileFormat::Dds => {
+                        let writer = BufWriter::new(try_throw!(vfs.create_or_truncate(path)));
 
-                        return Ok(());
+                        return external::dds::write_dds(writer, &self.0);
+                    },
+                    TextureFileFormat::Ktx => {
+                        let writer = BufWriter::new(try_throw!(vfs.create_or_truncate(path)));
+
+                        return external::ktx::write_ktx1(writer, &self.0);
+                    },
+                    TextureFileFormat::Ktx2 => {
+                        let writer = BufWriter::new(try_throw!(vfs.create_or_truncate(path)));
+
+                        return external::ktx::write_ktx2(writer, &self.0);
+                    },
+                    TextureFileFormat::OpenExr => {
+                        let texture = try_rethrow!(self.exportable_texture());
+
+                        let writer = BufWriter::new(try_throw!(vfs.create_or_truncate(path)));
+
+                        return external::exr::write_exr(writer, &texture);
                     },
                     TextureFileFormat::Image(image_format) => {
-                        if let texture::RootTexture::Texture(ref texture) = **self {
-                            if !texture.is_compressed() {
-                                if texture.kind == protocol::TextureKind::Texture2D || texture.kind == protocol::TextureKind::Texture1D {
-                                    if let Some(bit_depth) = texture.format.which.data_type().bit_depth() {
-                                        let mut writer = try_throw!(vfs.create_or_truncate(path));
-
-                                        let color_type = match texture.format.which.channels() {
-                                            protocol::Channels::R => image::ColorType::Gray(bit_depth),
-                                            protocol::Channels::Rg => image::ColorType::GrayA(bit_depth),
-                                            protocol::Channels::Rgb => image::ColorType::RGB(bit_depth),
-                                            protocol::Channels::Rgba => image::ColorType::RGBA(bit_depth),
-                                        };
-
-                                        let (width, height, _) = texture.dimensions.to_tuple();
-
-                                        let result = match image_format {
-                                            ImageFormat::ICO => {
-                                                image::ico::ICOEncoder::new(writer)
-                                                    .encode(texture.data.as_slice(), width, height, color_type)
-                                            },
-                                            ImageFormat::JPEG => {
-                                                image::jpeg::JPEGEncoder::new_with_quality(&mut writer, args.quality)
-                                                    .encode(texture.data.as_slice(), width, height, color_type)
-                                            },
-                                            ImageFormat::PNG => {
-                                                image::png::PNGEncoder::new(writer)
-                                                    .encode(texture.data.as_slice(), width, height, color_type)
-                                            },
-                                            ImageFormat::PPM => {
-                                                image::ppm::PPMEncoder::new(&mut writer)
-                                                    .encode(texture.data.as_slice(), width, height, color_type)
-                                            },
-                                            _ => {
-                                                throw!(AssetError::Unimplemented("Unsupported image format"));
-                                            }
-                                        };
-
-                                        try_throw!(result);
-
-                                        return Ok(());
-                                    } else { throw!(AssetError::Unimplemented("3D texture exporting to standard image formats")); }
-                                } else { throw!(AssetError::Unimplemented("Uneven or inapplicable bit depth texture exporting to standard image formats")); }
-                            } else { throw!(AssetError::Unimplemented("Saving compressed textures to standard image formats")); }
-                        } else { throw!(AssetError::Unimplemented("Saving multiple textures or cubemaps to standard image formats")); }
+                        let texture = try_rethrow!(self.exportable_texture());
+
+                        let mut writer = BufWriter::new(try_throw!(vfs.create_or_truncate(path)));
+
+                        match image_format {
+                            ImageFormat::TIFF => return external::tiff::write_tiff(writer, &texture),
+                            ImageFormat::HDR => {
+                                let image = try_rethrow!(FloatImage::from_texture(&texture));
+
+                                return external::hdr::write_hdr(writer, &image);
+                            },
+                            _ => {}
+                        }
+
+                        let (bit_depth, data) = match texture.format.which.data_type() {
+                            protocol::DataType::UnsignedByte | protocol::DataType::Byte => (8, Cow::Borrowed(texture.data.as_slice())),
+                            // Texture data is little-endian, but PNG samples are big-endian
+                            protocol::DataType::UnsignedShort if image_format == ImageFormat::PNG => {
+                                (16, Cow::Owned(pixels::swap_u16_bytes(texture.data.as_slice())))
+                            },
+                            protocol::DataType::Float => {
+                                throw!(AssetError::Unimplemented("Float texture exporting to formats other than HDR, TIFF and OpenEXR"));
+                            },
+                            _ => throw!(AssetError::Unimplemented("Uneven or inapplicable bit depth texture exporting to standard image formats")),
+                        };
+
+                        let color_type = match texture.format.which.channels() {
+                            protocol::Channels::R => image::ColorType::Gray(bit_depth),
+                            protocol::Channels::Rg => image::ColorType::GrayA(bit_depth),
+                            protocol::Channels::Rgb => image::ColorType::RGB(bit_depth),
+                            protocol::Channels::Rgba => image::ColorType::RGBA(bit_depth),
+                        };
+
+                        let (width, height) = (texture.dimensions.width, texture.dimensions.height.max(1));
+
+                        let result = match image_format {
+                            ImageFormat::ICO => {
+                                image::ico::ICOEncoder::new(writer)
+                                    .encode(&data, width, height, color_type)
+                            },
+                            ImageFormat::JPEG => {
+                                image::jpeg::JPEGEncoder::new_with_quality(&mut writer, args.quality)
+                                    .encode(&data, width, height, color_type)
+                            },
+                            ImageFormat::PNG => {
+                                image::png::PNGEncoder::new(writer)
+                                    .encode(&data, width, height, color_type)
+                            },
+                            ImageFormat::PPM => {
+                                image::ppm::PPMEncoder::new(&mut writer)
+                                    .encode(&data, width, height, color_type)
+                            },
+                            _ => {
+                                throw!(AssetError::Unimplemented("Unsupported image format"));
+                            }
+                        };
+
+                        try_throw!(result);
+
+                        return Ok(());
                     },
                     TextureFileFormat::StandardFormat(standard_format) => {
                         let writer = try_throw!(vfs.create_or_truncate(path));
@@ -260,6 +351,209 @@ impl<'a> Asset<'a> for TextureAsset {
     }
 }
 
+impl TextureAsset {
+    // Reads the shape of a texture without decoding its image data wherever possible
+    fn probe<'a>(medium: AssetMedium<'a>, args: TextureAssetLoadArgs) -> AssetResult<TextureInfo> {
+        if let AssetMedium::File(path, ref vfs) = medium {
+            if let Some(ext) = path.extension() {
+                let ext = try_throw!(ext.to_str().ok_or(AssetError::InvalidValue)).to_ascii_lowercase();
+
+                let format = match TextureFileFormat::from_extension(ext.as_str()) {
+                    Some(format) if format.can_import() => format,
+                    _ => throw!(AssetError::UnsupportedFormat),
+                };
+
+                match format {
+                    TextureFileFormat::Native => {
+                        let message_reader = try_rethrow!(native::read_native_message(path, &vfs, args.reader_limits));
+
+                        let root_texture_reader = try_throw!(message_reader.get_root::<protocol::root_texture::Reader>());
+
+                        return Ok(try_rethrow!(texture::RootTexture::query_info(root_texture_reader)));
+                    },
+                    TextureFileFormat::Dds => {
+                        let reader = BufReader::new(try_throw!(vfs.open(path)));
+
+                        return external::dds::read_dds_info(reader, args.srgb);
+                    },
+                    TextureFileFormat::Ktx | TextureFileFormat::Ktx2 => {
+                        let reader = BufReader::new(try_throw!(vfs.open(path)));
+
+                        return external::ktx::read_ktx_info(reader);
+                    },
+                    TextureFileFormat::Image(image_format) => {
+                        let reader = BufReader::new(try_throw!(vfs.open(path)));
+
+                        let header = match image_format {
+                            ImageFormat::PNG => Some(try_rethrow!(read_image_header(image::png::PNGDecoder::new(reader)))),
+                            ImageFormat::JPEG => Some(try_rethrow!(read_image_header(image::jpeg::JPEGDecoder::new(reader)))),
+                            ImageFormat::TIFF => Some(try_rethrow!(read_image_header(try_throw!(image::tiff::TIFFDecoder::new(reader))))),
+                            ImageFormat::HDR => {
+                                let metadata = try_throw!(image::hdr::HDRDecoder::new(reader)).metadata();
+
+                                Some((metadata.width, metadata.height, protocol::Channels::Rgb, protocol::DataType::Float))
+                            },
+                            _ => None,
+                        };
+
+                        if let Some((width, height, channels, data_type)) = header {
+                            let texture = texture::Texture {
+                                data: Vec::<u8>::new().into(),
+                                dimensions: texture::Dimensions::new(width, height, 0),
+                                kind: protocol::TextureKind::Texture2D,
+                                format: external::uncompressed(channels, data_type, args.srgb),
+                                mipmaps: Vec::new(),
+                            };
+
+                            // Apply the same adjustments as a full load would
+                            let TextureAsset(root_texture) = TextureAsset::from_texture(texture, args);
+
+                            return Ok(try_throw!(root_texture.info().ok_or(AssetError::InvalidValue)));
+                        }
+                    },
+                    _ => {}
+                }
+            }
+        }
+
+        // Formats without header probing have to be fully loaded
+        let asset = try_rethrow!(TextureAsset::load(medium, args));
+
+        Ok(try_throw!(asset.0.info().ok_or(AssetError::InvalidValue)))
+    }
+
+    // Texture containers can hold any kind of texture, so apply the `only2d` restriction afterwards
+    fn from_container(root_texture: texture::RootTexture, args: TextureAssetLoadArgs) -> AssetResult<TextureAsset> {
+        if args.only2d {
+            match root_texture {
+                texture::RootTexture::Texture(ref texture) if texture.kind != protocol::TextureKind::Texture3D => {},
+                _ => throw!(AssetError::InvalidValue),
+            }
+        }
+
+        Ok(TextureAsset(root_texture))
+    }
+
+    // Standard image formats hold a single uncompressed image, so BC6H textures are decoded first
+    fn exportable_texture(&self) -> AssetResult<Cow<texture::Texture>> {
+        let texture = match self.0 {
+            texture::RootTexture::Texture(ref texture) => &**texture,
+            _ => throw!(AssetError::Unimplemented("Saving multiple textures or cubemaps to standard image formats")),
+        };
+
+        if texture.kind == protocol::TextureKind::Texture3D {
+            throw!(AssetError::Unimplemented("3D texture exporting to standard image formats"));
+        }
+
+        if texture.is_compressed() {
+            if texture.format.which.float() {
+                return Ok(Cow::Owned(try_rethrow!(bc6h::decode_texture(texture))));
+            }
+
+            throw!(AssetError::Unimplemented("Saving compressed textures to standard image formats"));
+        }
+
+        Ok(Cow::Borrowed(texture))
+    }
+
+    /// Convert an already decoded texture into a texture asset, applying the load arguments
+    ///
+    /// Textures with a single row or column are treated as 1D textures unless `only2d` is set,
+    /// and integer formats are marked as sRGB if requested.
+    pub fn from_texture(mut texture: texture::Texture, args: TextureAssetLoadArgs) -> TextureAsset {
+        if (texture.dimensions.width == 1 || texture.dimensions.height == 1) && !args.only2d {
+            texture.kind = protocol::TextureKind::Texture1D;
+        }
+
+        texture.format.srgb = args.srgb && !texture.format.which.float();
+
+        TextureAsset(texture::RootTexture::Texture(box texture))
+    }
+
+    /// Convert an already decoded image into a texture asset
+    pub fn from_image(image: DynamicImage, args: TextureAssetLoadArgs) -> TextureAsset {
+        let channels = match image {
+            DynamicImage::ImageLuma8(_) => protocol::Channels::R,
+            DynamicImage::ImageLumaA8(_) => protocol::Channels::Rg,
+            DynamicImage::ImageRgb8(_) => protocol::Channels::Rgb,
+            DynamicImage::ImageRgba8(_) => protocol::Channels::Rgba,
+        };
+
+        let (width, height) = image.dimensions();
+
+        TextureAsset::from_texture(texture::Texture {
+            data: image.raw_pixels().into(),
+            dimensions: texture::Dimensions::new(width, height, 0),
+            kind: protocol::TextureKind::Texture2D,
+            format: external::uncompressed(channels, protocol::DataType::UnsignedByte, args.srgb),
+            mipmaps: Vec::new(),
+        }, args)
+    }
+}
+
+/// Reads the dimensions, channels and data type an image would be loaded with, without decoding it
+fn read_image_header<D: ImageDecoder>(mut decoder: D) -> AssetResult<(u32, u32, protocol::Channels, protocol::DataType)> {
+    let (width, height) = try_throw!(decoder.dimensions());
+
+    // Anything other than 16-bit images is converted to 8-bit by `image::load`, with palettes expanded to RGB
+    let (channels, bit_depth) = match try_throw!(decoder.colortype()) {
+        ColorType::Gray(bit_depth) => (protocol::Channels::R, bit_depth),
+        ColorType::GrayA(bit_depth) => (protocol::Channels::Rg, bit_depth),
+        ColorType::RGB(bit_depth) | ColorType::Palette(bit_depth) => (protocol::Channels::Rgb, bit_depth),
+        ColorType::RGBA(bit_depth) => (protocol::Channels::Rgba, bit_depth),
+    };
+
+    let data_type = if bit_depth == 16 { protocol::DataType::UnsignedShort } else { protocol::DataType::UnsignedByte };
+
+    Ok((width, height, channels, data_type))
+}
+
+/// Decodes 16-bit images, or returns `None` for any other bit depth
+fn read_16bit<D: ImageDecoder>(mut decoder: D, args: TextureAssetLoadArgs) -> AssetResult<Option<texture::Texture>> {
+    let channels = match try_throw!(decoder.colortype()) {
+        ColorType::Gray(16) => protocol::Channels::R,
+        ColorType::GrayA(16) => protocol::Channels::Rg,
+        ColorType::RGB(16) => protocol::Channels::Rgb,
+        ColorType::RGBA(16) => protocol::Channels::Rgba,
+        _ => return Ok(None),
+    };
+
+    let (width, height) = try_throw!(decoder.dimensions());
+
+    let values = match try_throw!(decoder.read_image()) {
+        DecodingResult::U16(values) => values,
+        _ => return Ok(None),
+    };
+
+    let mut data = Vec::with_capacity(values.len() * 2);
+
+    for value in values {
+        data.extend_from_slice(&[value as u8, (value >> 8) as u8]);
+    }
+
+    Ok(Some(texture::Texture {
+        data: data.into(),
+        dimensions: texture::Dimensions::new(width, height, 0),
+        kind: protocol::TextureKind::Texture2D,
+        format: external::uncompressed(channels, protocol::DataType::UnsignedShort, args.srgb),
+        mipmaps: Vec::new(),
+    }))
+}
+
+impl AssetSize for TextureAsset {
+    fn approximate_size(&self) -> usize {
+        match self.0 {
+            texture::RootTexture::Texture(ref texture) => texture.total_size(),
+            texture::RootTexture::Cubemap(ref cubemap) => {
+                cubemap.right.total_size() + cubemap.left.total_size() +
+                    cubemap.top.total_size() + cubemap.bottom.total_size() +
+                    cubemap.back.total_size() + cubemap.front.total_size()
+            },
+            texture::RootTexture::Array(ref array) => array.iter().map(|texture| texture.total_size()).sum(),
+        }
+    }
+}
+
 impl Deref for TextureAsset {
     type Target = texture::RootTexture;
 
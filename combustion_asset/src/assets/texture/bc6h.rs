@@ -0,0 +1,450 @@
+//! BC6H (BPTC float) block decoding
+//!
+//! Follows the decoding process of the Direct3D 11 specification. Each 4x4 block is 128 bits,
+//! starting with a mode that determines how the endpoints are packed, followed by the endpoints,
+//! an optional partition index and the per-pixel interpolation indices.
+
+use protocols::texture::protocol::{Bptc, Channels, DataType, TextureKind};
+use protocols::texture::data::format::Which;
+use protocols::texture::data::texture::Texture;
+
+use ::error::{AssetResult, AssetError};
+
+use super::external::uncompressed;
+use super::pixels::{encode_values, half_to_f32};
+
+// Endpoint components, indexed by `component * 4 + endpoint` where the endpoints are w, x, y and z
+const RW: u8 = 0;
+const RX: u8 = 1;
+const RY: u8 = 2;
+const RZ: u8 = 3;
+const GW: u8 = 4;
+const GX: u8 = 5;
+const GY: u8 = 6;
+const GZ: u8 = 7;
+const BW: u8 = 8;
+const BX: u8 = 9;
+const BY: u8 = 10;
+const BZ: u8 = 11;
+
+/// Describes how a mode packs its endpoints
+struct Mode {
+    /// Mode bits as read from the start of the block, either two or five bits long
+    code: u32,
+    /// Whether the x, y and z endpoints are stored relative to w
+    transformed: bool,
+    /// Whether the block has two regions
+    partitioned: bool,
+    /// Bits of precision in the w endpoint
+    endpoint_bits: u32,
+    /// Bits of precision in the other endpoints, per component
+    delta_bits: [u32; 3],
+    /// Endpoint bit fields in the order they are stored, as `(field, first bit, last bit)`.
+    ///
+    /// Consecutive bits read from the block go to `first` through `last`, which may be in descending order.
+    fields: &'static [(u8, u32, u32)],
+}
+
+static MODES: [Mode; 14] = [
+    Mode {
+        code: 0b00, transformed: true, partitioned: true, endpoint_bits: 10, delta_bits: [5, 5, 5],
+        fields: &[(GY, 4, 4), (BY, 4, 4), (BZ, 4, 4), (RW, 0, 9), (GW, 0, 9), (BW, 0, 9), (RX, 0, 4), (GZ, 4, 4),
+            (GY, 0, 3), (GX, 0, 4), (BZ, 0, 0), (GZ, 0, 3), (BX, 0, 4), (BZ, 1, 1), (BY, 0, 3), (RY, 0, 4),
+            (BZ, 2, 2), (RZ, 0, 4), (BZ, 3, 3)],
+    },
+    Mode {
+        code: 0b01, transformed: true, partitioned: true, endpoint_bits: 7, delta_bits: [6, 6, 6],
+        fields: &[(GY, 5, 5), (GZ, 4, 4), (GZ, 5, 5), (RW, 0, 6), (BZ, 0, 0), (BZ, 1, 1), (BY, 4, 4), (GW, 0, 6),
+            (BY, 5, 5), (BZ, 2, 2), (GY, 4, 4), (BW, 0, 6), (BZ, 3, 3), (BZ, 5, 5), (BZ, 4, 4), (RX, 0, 5),
+            (GY, 0, 3), (GX, 0, 5), (GZ, 0, 3), (BX, 0, 5), (BY, 0, 3), (RY, 0, 5), (RZ, 0, 5)],
+    },
+    Mode {
+        code: 0b00010, transformed: true, partitioned: true, endpoint_bits: 11, delta_bits: [5, 4, 4],
+        fields: &[(RW, 0, 9), (GW, 0, 9), (BW, 0, 9), (RX, 0, 4), (RW, 10, 10), (GY, 0, 3), (GX, 0, 3), (GW, 10, 10),
+            (BZ, 0, 0), (GZ, 0, 3), (BX, 0, 3), (BW, 10, 10), (BZ, 1, 1), (BY, 0, 3), (RY, 0, 4), (BZ, 2, 2),
+            (RZ, 0, 4), (BZ, 3, 3)],
+    },
+    Mode {
+        code: 0b00110, transformed: true, partitioned: true, endpoint_bits: 11, delta_bits: [4, 5, 4],
+        fields: &[(RW, 0, 9), (GW, 0, 9), (BW, 0, 9), (RX, 0, 3), (RW, 10, 10), (GZ, 4, 4), (GY, 0, 3), (GX, 0, 4),
+            (GW, 10, 10), (GZ, 0, 3), (BX, 0, 3), (BW, 10, 10), (BZ, 1, 1), (BY, 0, 3), (RY, 0, 3), (BZ, 0, 0),
+            (BZ, 2, 2), (RZ, 0, 3), (GY, 4, 4), (BZ, 3, 3)],
+    },
+    Mode {
+        code: 0b01010, transformed: true, partitioned: true, endpoint_bits: 11, delta_bits: [4, 4, 5],
+        fields: &[(RW, 0, 9), (GW, 0, 9), (BW, 0, 9), (RX, 0, 3), (RW, 10, 10), (BY, 4, 4), (GY, 0, 3), (GX, 0, 3),
+            (GW, 10, 10), (BZ, 0, 0), (GZ, 0, 3), (BX, 0, 4), (BW, 10, 10), (BY, 0, 3), (RY, 0, 3), (BZ, 1, 1),
+            (BZ, 2, 2), (RZ, 0, 3), (BZ, 4, 4), (BZ, 3, 3)],
+    },
+    Mode {
+        code: 0b01110, transformed: true, partitioned: true, endpoint_bits: 9, delta_bits: [5, 5, 5],
+        fields: &[(RW, 0, 8), (BY, 4, 4), (GW, 0, 8), (GY, 4, 4), (BW, 0, 8), (BZ, 4, 4), (RX, 0, 4), (GZ, 4, 4),
+            (GY, 0, 3), (GX, 0, 4), (BZ, 0, 0), (GZ, 0, 3), (BX, 0, 4), (BZ, 1, 1), (BY, 0, 3), (RY, 0, 4),
+            (BZ, 2, 2), (RZ, 0, 4), (BZ, 3, 3)],
+    },
+    Mode {
+        code: 0b10010, transformed: true, partitioned: true, endpoint_bits: 8, delta_bits: [6, 5, 5],
+        fields: &[(RW, 0, 7), (GZ, 4, 4), (BY, 4, 4), (GW, 0, 7), (BZ, 2, 2), (GY, 4, 4), (BW, 0, 7), (BZ, 3, 3),
+            (BZ, 4, 4), (RX, 0, 5), (GY, 0, 3), (GX, 0, 4), (BZ, 0, 0), (GZ, 0, 3), (BX, 0, 4), (BZ, 1, 1),
+            (BY, 0, 3), (RY, 0, 5), (RZ, 0, 5)],
+    },
+    Mode {
+        code: 0b10110, transformed: true, partitioned: true, endpoint_bits: 8, delta_bits: [5, 6, 5],
+        fields: &[(RW, 0, 7), (BZ, 0, 0), (BY, 4, 4), (GW, 0, 7), (GY, 5, 5), (GY, 4, 4), (BW, 0, 7), (GZ, 5, 5),
+            (BZ, 4, 4), (RX, 0, 4), (GZ, 4, 4), (GY, 0, 3), (GX, 0, 5), (GZ, 0, 3), (BX, 0, 4), (BZ, 1, 1),
+            (BY, 0, 3), (RY, 0, 4), (BZ, 2, 2), (RZ, 0, 4), (BZ, 3, 3)],
+    },
+    Mode {
+        code: 0b11010, transformed: true, partitioned: true, endpoint_bits: 8, delta_bits: [5, 5, 6],
+        fields: &[(RW, 0, 7), (BZ, 1, 1), (BY, 4, 4), (GW, 0, 7), (BY, 5, 5), (GY, 4, 4), (BW, 0, 7), (BZ, 5, 5),
+            (BZ, 4, 4), (RX, 0, 4), (GZ, 4, 4), (GY, 0, 3), (GX, 0, 4), (BZ, 0, 0), (GZ, 0, 3), (BX, 0, 5),
+            (BY, 0, 3), (RY, 0, 4), (BZ, 2, 2), (RZ, 0, 4), (BZ, 3, 3)],
+    },
+    Mode {
+        code: 0b11110, transformed: false, partitioned: true, endpoint_bits: 6, delta_bits: [6, 6, 6],
+        fields: &[(RW, 0, 5), (GZ, 4, 4), (BZ, 0, 0), (BZ, 1, 1), (BY, 4, 4), (GW, 0, 5), (GY, 5, 5), (BY, 5, 5),
+            (BZ, 2, 2), (GY, 4, 4), (BW, 0, 5), (GZ, 5, 5), (BZ, 3, 3), (BZ, 5, 5), (BZ, 4, 4), (RX, 0, 5),
+            (GY, 0, 3), (GX, 0, 5), (GZ, 0, 3), (BX, 0, 5), (BY, 0, 3), (RY, 0, 5), (RZ, 0, 5)],
+    },
+    Mode {
+        code: 0b00011, transformed: false, partitioned: false, endpoint_bits: 10, delta_bits: [10, 10, 10],
+        fields: &[(RW, 0, 9), (GW, 0, 9), (BW, 0, 9), (RX, 0, 9), (GX, 0, 9), (BX, 0, 9)],
+    },
+    Mode {
+        code: 0b00111, transformed: true, partitioned: false, endpoint_bits: 11, delta_bits: [9, 9, 9],
+        fields: &[(RW, 0, 9), (GW, 0, 9), (BW, 0, 9), (RX, 0, 8), (RW, 10, 10), (GX, 0, 8), (GW, 10, 10),
+            (BX, 0, 8), (BW, 10, 10)],
+    },
+    Mode {
+        code: 0b01011, transformed: true, partitioned: false, endpoint_bits: 12, delta_bits: [8, 8, 8],
+        fields: &[(RW, 0, 9), (GW, 0, 9), (BW, 0, 9), (RX, 0, 7), (RW, 11, 10), (GX, 0, 7), (GW, 11, 10),
+            (BX, 0, 7), (BW, 11, 10)],
+    },
+    Mode {
+        code: 0b01111, transformed: true, partitioned: false, endpoint_bits: 16, delta_bits: [4, 4, 4],
+        fields: &[(RW, 0, 9), (GW, 0, 9), (BW, 0, 9), (RX, 0, 3), (RW, 15, 10), (GX, 0, 3), (GW, 15, 10),
+            (BX, 0, 3), (BW, 15, 10)],
+    },
+];
+
+/// Two region partitions shared with BC7, as masks of the pixels in the second region
+static PARTITIONS: [u16; 32] = [
+    0xCCCC, 0x8888, 0xEEEE, 0xECC8, 0xC880, 0xFEEC, 0xFEC8, 0xEC80,
+    0xC800, 0xFFEC, 0xFE80, 0xE800, 0xFFE8, 0xFF00, 0xFFF0, 0xF000,
+    0xF710, 0x008E, 0x7100, 0x08CE, 0x008C, 0x7310, 0x3100, 0x8CCE,
+    0x088C, 0x3110, 0x6666, 0x366C, 0x17E8, 0x0FF0, 0x718E, 0x399C,
+];
+
+/// Anchor pixel of the second region of each partition
+static ANCHORS: [usize; 32] = [
+    15, 15, 15, 15, 15, 15, 15, 15,
+    15, 15, 15, 15, 15, 15, 15, 15,
+    15, 2, 8, 2, 2, 8, 8, 15,
+    2, 8, 2, 2, 8, 8, 2, 2,
+];
+
+static WEIGHTS3: [i32; 8] = [0, 9, 18, 27, 37, 46, 55, 64];
+static WEIGHTS4: [i32; 16] = [0, 4, 9, 13, 17, 21, 26, 30, 34, 38, 43, 47, 51, 55, 60, 64];
+
+struct BitReader {
+    low: u64,
+    high: u64,
+    position: u32,
+}
+
+impl BitReader {
+    fn new(block: &[u8]) -> BitReader {
+        let mut low = 0;
+        let mut high = 0;
+
+        for i in 0..8 {
+            low |= (block[i] as u64) << (i * 8);
+            high |= (block[i + 8] as u64) << (i * 8);
+        }
+
+        BitReader { low: low, high: high, position: 0 }
+    }
+
+    fn bit(&mut self) -> u32 {
+        let bit = if self.position < 64 {
+            self.low >> self.position
+        } else {
+            self.high >> (self.position - 64)
+        };
+
+        self.position += 1;
+
+        (bit & 1) as u32
+    }
+
+    fn bits(&mut self, count: u32) -> u32 {
+        let mut value = 0;
+
+        for i in 0..count {
+            value |= self.bit() << i;
+        }
+
+        value
+    }
+}
+
+#[inline]
+fn extend_sign(value: i32, bits: u32) -> i32 {
+    (value << (32 - bits)) >> (32 - bits)
+}
+
+fn unquantize(value: i32, bits: u32, signed: bool) -> i32 {
+    if !signed {
+        if bits >= 15 || value == 0 {
+            value
+        } else if value == (1 << bits) - 1 {
+            0xFFFF
+        } else {
+            ((value << 16) + 0x8000) >> bits
+        }
+    } else if bits >= 16 {
+        value
+    } else {
+        let magnitude = value.abs();
+
+        let unquantized = if magnitude == 0 {
+            0
+        } else if magnitude >= (1 << (bits - 1)) - 1 {
+            0x7FFF
+        } else {
+            ((magnitude << 15) + 0x4000) >> (bits - 1)
+        };
+
+        if value < 0 { -unquantized } else { unquantized }
+    }
+}
+
+// Scales interpolated values to the final half float bit pattern
+fn finish_unquantize(value: i32, signed: bool) -> f32 {
+    if !signed {
+        half_to_f32(((value * 31) >> 6) as u16)
+    } else if value < 0 {
+        half_to_f32(0x8000 | (((-value) * 31) >> 5) as u16)
+    } else {
+        half_to_f32(((value * 31) >> 5) as u16)
+    }
+}
+
+/// Decodes a single 16 byte block into 16 RGB pixels
+pub fn decode_block(block: &[u8], signed: bool) -> [[f32; 3]; 16] {
+    let mut pixels = [[0.0; 3]; 16];
+
+    let mut reader = BitReader::new(block);
+
+    let mut code = reader.bits(2);
+
+    if code > 1 {
+        code |= reader.bits(3) << 2;
+    }
+
+    let mode = match MODES.iter().find(|mode| mode.code == code) {
+        Some(mode) => mode,
+        // Reserved modes decode to black
+        None => return pixels,
+    };
+
+    let mut endpoints = [0i32; 12];
+
+    for &(field, first, last) in mode.fields {
+        if first <= last {
+            for bit in first..(last + 1) {
+                endpoints[field as usize] |= (reader.bit() << bit) as i32;
+            }
+        } else {
+            for bit in (last..(first + 1)).rev() {
+                endpoints[field as usize] |= (reader.bit() << bit) as i32;
+            }
+        }
+    }
+
+    let partition = if mode.partitioned { reader.bits(5) as usize } else { 0 };
+
+    let num_endpoints = if mode.partitioned { 4 } else { 2 };
+
+    for component in 0..3 {
+        let base = component * 4;
+
+        if signed {
+            endpoints[base] = extend_sign(endpoints[base], mode.endpoint_bits);
+        }
+
+        // Untransformed endpoints are absolute, and only signed if the format is
+        if mode.transformed || signed {
+            for endpoint in 1..num_endpoints {
+                endpoints[base + endpoint] = extend_sign(endpoints[base + endpoint], mode.delta_bits[component]);
+            }
+        }
+
+        if mode.transformed {
+            let mask = (1i32 << mode.endpoint_bits) - 1;
+
+            for endpoint in 1..num_endpoints {
+                let mut value = (endpoints[base + endpoint] + endpoints[base]) & mask;
+
+                if signed {
+                    value = extend_sign(value, mode.endpoint_bits);
+                }
+
+                endpoints[base + endpoint] = value;
+            }
+        }
+
+        for endpoint in 0..num_endpoints {
+            endpoints[base + endpoint] = unquantize(endpoints[base + endpoint], mode.endpoint_bits, signed);
+        }
+    }
+
+    for (i, pixel) in pixels.iter_mut().enumerate() {
+        // Anchor pixels have an implicit leading zero bit
+        let (region, weight) = if mode.partitioned {
+            let bits = if i == 0 || i == ANCHORS[partition] { 2 } else { 3 };
+
+            ((PARTITIONS[partition] >> i) as usize & 1, WEIGHTS3[reader.bits(bits) as usize])
+        } else {
+            (0, WEIGHTS4[reader.bits(if i == 0 { 3 } else { 4 }) as usize])
+        };
+
+        for component in 0..3 {
+            let a = endpoints[component * 4 + region * 2];
+            let b = endpoints[component * 4 + region * 2 + 1];
+
+            pixel[component] = finish_unquantize((a * (64 - weight) + b * weight + 32) >> 6, signed);
+        }
+    }
+
+    pixels
+}
+
+/// Decodes a BC6H texture, including its mipmaps, into an RGB float texture
+pub fn decode_texture(texture: &Texture) -> AssetResult<Texture> {
+    let signed = match texture.format.which {
+        Which::Bptc(Bptc::RgbFloatSigned) => true,
+        Which::Bptc(Bptc::RgbFloatUnsigned) => false,
+        _ => throw!(AssetError::InvalidValue),
+    };
+
+    if texture.kind == TextureKind::Texture3D {
+        throw!(AssetError::Unimplemented("Decoding compressed 3D textures"));
+    }
+
+    let mut levels = Vec::with_capacity(texture.levels() as usize);
+
+    for level in 0..texture.levels() {
+        let dimensions = texture.dimensions.mip_level(level);
+
+        let (width, height) = (dimensions.width.max(1) as usize, dimensions.height.max(1) as usize);
+
+        let data = match texture.level(level) {
+            Some(data) => data.as_slice(),
+            None => throw!(AssetError::InvalidValue),
+        };
+
+        let (blocks_x, blocks_y) = ((width + 3) / 4, (height + 3) / 4);
+
+        if data.len() < blocks_x * blocks_y * 16 {
+            throw!(AssetError::InvalidValue);
+        }
+
+        let mut values = vec![0.0; width * height * 3];
+
+        for block_y in 0..blocks_y {
+            for block_x in 0..blocks_x {
+                let offset = (block_y * blocks_x + block_x) * 16;
+
+                let pixels = decode_block(&data[offset..(offset + 16)], signed);
+
+                for (i, pixel) in pixels.iter().enumerate() {
+                    let (x, y) = (block_x * 4 + i % 4, block_y * 4 + i / 4);
+
+                    // Blocks on the right and bottom edges may extend past the image
+                    if x < width && y < height {
+                        let start = (y * width + x) * 3;
+
+                        values[start..(start + 3)].copy_from_slice(pixel);
+                    }
+                }
+            }
+        }
+
+        levels.push(try_rethrow!(encode_values(&values, DataType::Float)));
+    }
+
+    let mut levels = levels.into_iter();
+
+    Ok(Texture {
+        data: levels.next().unwrap_or_else(Vec::new).into(),
+        dimensions: texture.dimensions,
+        kind: texture.kind,
+        format: uncompressed(Channels::Rgb, DataType::Float, false),
+        mipmaps: levels.map(|level| level.into()).collect(),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // Expected values are half float bit patterns, computed independently from the specification
+    fn assert_block(block: &[u8; 16], signed: bool, expected: &[[u16; 3]; 16]) {
+        let pixels = decode_block(block, signed);
+
+        for (pixel, expected) in pixels.iter().zip(expected.iter()) {
+            for (&value, &half) in pixel.iter().zip(expected.iter()) {
+                assert_eq!(value, half_to_f32(half));
+            }
+        }
+    }
+
+    #[test]
+    fn unsigned_single_region() {
+        // Mode 11 with untransformed 10-bit endpoints, stepping through the index weights
+        let block = [
+            0x03, 0x00, 0x32, 0xFE, 0xFF, 0x9F, 0x3E, 0x00,
+            0x1A, 0x32, 0x54, 0x76, 0x98, 0xBA, 0xDC, 0xFE,
+        ];
+
+        assert_block(&block, false, &[
+            [0x28B0, 0x1C10, 0x534F], [0x07C0, 0x0F32, 0x743F], [0x1170, 0x12FB, 0x6A8F], [0x1930, 0x1602, 0x62CF],
+            [0x20F0, 0x1909, 0x5B0F], [0x28B0, 0x1C10, 0x534F], [0x3260, 0x1FD9, 0x499F], [0x3A20, 0x22E0, 0x41DF],
+            [0x41DF, 0x25E7, 0x3A20], [0x499F, 0x28EE, 0x3260], [0x534F, 0x2CB6, 0x28B0], [0x5B0F, 0x2FBD, 0x20F0],
+            [0x62CF, 0x32C4, 0x1930], [0x6A8F, 0x35CB, 0x1170], [0x743F, 0x3994, 0x07C0], [0x7BFF, 0x3C9B, 0x0000],
+        ]);
+    }
+
+    #[test]
+    fn signed_transformed() {
+        // Mode 12 with negative base endpoints and signed deltas
+        let block = [
+            0x87, 0x73, 0x2C, 0x03, 0x90, 0x11, 0xA7, 0x7F,
+            0x77, 0x5E, 0x3C, 0x1A, 0xF8, 0xD6, 0xB4, 0x92,
+        ];
+
+        assert_block(&block, true, &[
+            [0x8AF0, 0x43CC, 0xF5B5], [0x8954, 0x3D5D, 0xED7E], [0x867E, 0x3203, 0xDEFE], [0x8A2E, 0x40C5, 0xF1D8],
+            [0x8758, 0x356A, 0xE358], [0x8AF0, 0x43CC, 0xF5B5], [0x881A, 0x3871, 0xE735], [0x8BCA, 0x4734, 0xFA10],
+            [0x88F4, 0x3BD9, 0xEB8F], [0x861D, 0x307F, 0xDD0F], [0x89B5, 0x3EE0, 0xEF6D], [0x86F7, 0x33E7, 0xE169],
+            [0x8A8F, 0x4248, 0xF3C7], [0x87B9, 0x36EE, 0xE546], [0x8B51, 0x454F, 0xF7A4], [0x8893, 0x3A56, 0xE9A0],
+        ]);
+    }
+
+    #[test]
+    fn unsigned_two_regions() {
+        // Mode 10 using partition 13, which splits the block into top and bottom halves
+        let block = [
+            0x1E, 0x10, 0x65, 0x7F, 0xFC, 0x9F, 0x02, 0x00,
+            0x0A, 0xB4, 0x39, 0x87, 0x57, 0x31, 0x87, 0x57,
+        ];
+
+        assert_block(&block, false, &[
+            [0x22E0, 0x19CB, 0x591F], [0x3450, 0x1C84, 0x47AF], [0x6A8F, 0x24FE, 0x1170], [0x1170, 0x1711, 0x6A8F],
+            [0x47AF, 0x1F8B, 0x3450], [0x7BFF, 0x27B8, 0x0000], [0x22E0, 0x19CB, 0x591F], [0x591F, 0x2245, 0x22E0],
+            [0x0AA8, 0x7BFF, 0x3EF8], [0x2743, 0x47AF, 0x32B5], [0x44EE, 0x1170, 0x25FE], [0x1431, 0x6A8F, 0x3AE1],
+            [0x31DC, 0x3450, 0x2E2A], [0x4E78, 0x0000, 0x21E8], [0x1DBA, 0x591F, 0x36CB], [0x1431, 0x6A8F, 0x3AE1],
+        ]);
+    }
+}
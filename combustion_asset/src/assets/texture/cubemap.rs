@@ -0,0 +1,532 @@
+//! Cubemap construction and equirectangular projection
+//!
+//! Faces follow the OpenGL cubemap conventions, so a face texture can be uploaded as-is to the matching cubemap target.
+//! Panoramas are equirectangular, with the center of the image looking down -Z (the front face) and the top row looking up +Y.
+
+use std::f32::consts::PI;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+use std::sync::Arc;
+use std::ascii::AsciiExt;
+
+use image::{self, DynamicImage, GenericImage, ImageFormat};
+
+use common::vfs::BoxedVFS;
+
+use protocols::texture::protocol::{Channels, TextureKind};
+use protocols::texture::data::format::{SpecificFormat, Which};
+use protocols::texture::data::texture::{Texture, Cubemap, Dimensions, RootTexture};
+
+use ::error::{AssetResult, AssetError};
+use ::asset::{Asset, AssetMedium, AssetFileFormat};
+
+use super::asset::{TextureAsset, TextureAssetLoadArgs};
+use super::pixels::{decode_values, encode_values};
+use super::external::exr;
+use super::bc6h;
+
+/// Cubemap faces
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CubeFace {
+    /// Positive X
+    Right,
+    /// Negative X
+    Left,
+    /// Positive Y
+    Top,
+    /// Negative Y
+    Bottom,
+    /// Positive Z
+    Back,
+    /// Negative Z
+    Front,
+}
+
+impl CubeFace {
+    /// All faces in the order they are stored in cubemap containers
+    pub fn all() -> [CubeFace; 6] {
+        [CubeFace::Right, CubeFace::Left, CubeFace::Top, CubeFace::Bottom, CubeFace::Back, CubeFace::Front]
+    }
+
+    /// Index of the face in the order given by `CubeFace::all`
+    pub fn index(&self) -> usize {
+        match *self {
+            CubeFace::Right => 0,
+            CubeFace::Left => 1,
+            CubeFace::Top => 2,
+            CubeFace::Bottom => 3,
+            CubeFace::Back => 4,
+            CubeFace::Front => 5,
+        }
+    }
+
+    /// Computes the (unnormalized) direction through a point on the face,
+    /// where `s` and `t` go from -1 to 1 along the face's rows and columns.
+    pub fn direction(&self, s: f32, t: f32) -> (f32, f32, f32) {
+        match *self {
+            CubeFace::Right => (1.0, -t, -s),
+            CubeFace::Left => (-1.0, -t, s),
+            CubeFace::Top => (s, 1.0, t),
+            CubeFace::Bottom => (s, -1.0, -t),
+            CubeFace::Back => (s, -t, 1.0),
+            CubeFace::Front => (-s, -t, -1.0),
+        }
+    }
+
+    /// Finds the face a direction points through, and the `s` and `t` coordinates on that face
+    ///
+    /// This is the inverse of `CubeFace::direction`.
+    pub fn from_direction(x: f32, y: f32, z: f32) -> (CubeFace, f32, f32) {
+        let (ax, ay, az) = (x.abs(), y.abs(), z.abs());
+
+        if ax >= ay && ax >= az {
+            if x > 0.0 {
+                (CubeFace::Right, -z / ax, -y / ax)
+            } else {
+                (CubeFace::Left, z / ax, -y / ax)
+            }
+        } else if ay >= az {
+            if y > 0.0 {
+                (CubeFace::Top, x / ay, z / ay)
+            } else {
+                (CubeFace::Bottom, x / ay, -z / ay)
+            }
+        } else if z > 0.0 {
+            (CubeFace::Back, x / az, -y / az)
+        } else {
+            (CubeFace::Front, -x / az, -y / az)
+        }
+    }
+}
+
+/// Assembles a `Cubemap` from six face textures, checking that they fit together
+#[derive(Clone)]
+pub struct CubemapBuilder {
+    faces: Vec<Option<Texture>>,
+}
+
+impl CubemapBuilder {
+    /// Creates a builder with no faces
+    pub fn new() -> CubemapBuilder {
+        CubemapBuilder { faces: vec![None, None, None, None, None, None] }
+    }
+
+    /// Sets the texture for a face, replacing any previous one
+    pub fn face(mut self, face: CubeFace, texture: Texture) -> CubemapBuilder {
+        self.faces[face.index()] = Some(texture);
+
+        self
+    }
+
+    /// Sets a face from an already decoded image
+    pub fn image(self, face: CubeFace, image: DynamicImage, srgb: bool) -> CubemapBuilder {
+        let asset = TextureAsset::from_image(image, TextureAssetLoadArgs { only2d: true, srgb: srgb, ..TextureAssetLoadArgs::default() });
+
+        match *asset {
+            RootTexture::Texture(ref texture) => self.face(face, (**texture).clone()),
+            _ => unreachable!(),
+        }
+    }
+
+    /// Loads a face from any single texture asset file
+    pub fn load(self, face: CubeFace, path: &Path, vfs: Arc<BoxedVFS>, srgb: bool) -> AssetResult<CubemapBuilder> {
+        let asset = try_rethrow!(TextureAsset::load(AssetMedium::File(path, vfs), TextureAssetLoadArgs {
+            only2d: true,
+            srgb: srgb,
+            ..TextureAssetLoadArgs::default()
+        }));
+
+        Ok(match *asset {
+            RootTexture::Texture(ref texture) => self.face(face, (**texture).clone()),
+            _ => throw!(AssetError::InvalidValue),
+        })
+    }
+
+    /// Validates the faces and builds the cubemap
+    ///
+    /// All six faces must be square 2D textures with the same size, format and number of mipmap levels.
+    pub fn build(self) -> AssetResult<Cubemap> {
+        let mut faces = Vec::with_capacity(6);
+
+        for (face, texture) in CubeFace::all().iter().zip(self.faces.into_iter()) {
+            match texture {
+                Some(texture) => faces.push(texture),
+                None => throw!(AssetError::Other(format!("Missing cubemap face {:?}", face))),
+            }
+        }
+
+        {
+            let first = &faces[0];
+
+            if first.kind != TextureKind::Texture2D || first.dimensions.width != first.dimensions.height {
+                throw!(AssetError::Other("Cubemap faces must be square 2D textures".to_string()));
+            }
+
+            for (face, texture) in CubeFace::all().iter().zip(faces.iter()) {
+                if texture.kind != first.kind || texture.dimensions != first.dimensions {
+                    throw!(AssetError::Other(format!("Cubemap face {:?} has different dimensions", face)));
+                }
+
+                if texture.format != first.format || texture.levels() != first.levels() {
+                    throw!(AssetError::Other(format!("Cubemap face {:?} has a different format or mipmap count", face)));
+                }
+            }
+        }
+
+        let mut faces = faces.into_iter();
+
+        Ok(Cubemap {
+            right: faces.next().unwrap(),
+            left: faces.next().unwrap(),
+            top: faces.next().unwrap(),
+            bottom: faces.next().unwrap(),
+            back: faces.next().unwrap(),
+            front: faces.next().unwrap(),
+        })
+    }
+}
+
+/// Linear RGBA floating point image used for resampling on the CPU
+#[derive(Debug, Clone)]
+pub struct FloatImage {
+    /// Image width
+    pub width: u32,
+    /// Image height
+    pub height: u32,
+    /// Pixels in row-major order
+    pub pixels: Vec<[f32; 4]>,
+}
+
+impl FloatImage {
+    /// Creates a transparent black image
+    pub fn new(width: u32, height: u32) -> FloatImage {
+        FloatImage {
+            width: width,
+            height: height,
+            pixels: vec![[0.0; 4]; width as usize * height as usize],
+        }
+    }
+
+    /// Whether the image has no pixels
+    pub fn is_empty(&self) -> bool {
+        self.width == 0 || self.height == 0
+    }
+
+    /// Gets a pixel, clamping the coordinates to the image
+    ///
+    /// The image must not be empty.
+    #[inline]
+    pub fn pixel(&self, x: i64, y: i64) -> [f32; 4] {
+        let x = x.max(0).min(self.width as i64 - 1) as usize;
+        let y = y.max(0).min(self.height as i64 - 1) as usize;
+
+        self.pixels[y * self.width as usize + x]
+    }
+
+    /// Samples the image with bilinear filtering, where `(0, 0)` is the top left corner and `(1, 1)` the bottom right.
+    ///
+    /// If `wrap` is true, the horizontal coordinate wraps around, as it does in panoramas.
+    pub fn sample(&self, u: f32, v: f32, wrap: bool) -> [f32; 4] {
+        let x = u * self.width as f32 - 0.5;
+        let y = v * self.height as f32 - 0.5;
+
+        let (x0, y0) = (x.floor(), y.floor());
+        let (fx, fy) = (x - x0, y - y0);
+
+        let (mut x0, y0) = (x0 as i64, y0 as i64);
+        let mut x1 = x0 + 1;
+
+        if wrap {
+            let width = self.width as i64;
+
+            x0 = ((x0 % width) + width) % width;
+            x1 = ((x1 % width) + width) % width;
+        }
+
+        let (a, b, c, d) = (self.pixel(x0, y0), self.pixel(x1, y0), self.pixel(x0, y0 + 1), self.pixel(x1, y0 + 1));
+
+        let mut result = [0.0; 4];
+
+        for i in 0..4 {
+            let top = a[i] + (b[i] - a[i]) * fx;
+            let bottom = c[i] + (d[i] - c[i]) * fx;
+
+            result[i] = top + (bottom - top) * fy;
+        }
+
+        result
+    }
+
+    /// Converts a decoded image, scaling integer channels into the 0-1 range
+    pub fn from_image(image: &DynamicImage) -> FloatImage {
+        let (width, height) = image.dimensions();
+
+        let rgba = image.to_rgba();
+
+        FloatImage {
+            width: width,
+            height: height,
+            pixels: rgba.pixels().map(|pixel| {
+                [pixel.data[0] as f32 / 255.0, pixel.data[1] as f32 / 255.0, pixel.data[2] as f32 / 255.0, pixel.data[3] as f32 / 255.0]
+            }).collect(),
+        }
+    }
+
+    /// Reads a Radiance `.hdr` image without losing its range
+    pub fn read_hdr<R: BufRead>(reader: R) -> AssetResult<FloatImage> {
+        let decoder = try_throw!(image::hdr::HDRDecoder::new(reader));
+
+        let (width, height) = {
+            let metadata = decoder.metadata();
+
+            (metadata.width, metadata.height)
+        };
+
+        let pixels = try_throw!(decoder.read_image_hdr());
+
+        Ok(FloatImage {
+            width: width,
+            height: height,
+            pixels: pixels.into_iter().map(|pixel| [pixel.data[0], pixel.data[1], pixel.data[2], 1.0]).collect(),
+        })
+    }
+
+    /// Loads a panorama or face image from OpenEXR or any format the `image` crate can read,
+    /// keeping `.hdr` and `.exr` files in full range
+    pub fn load(path: &Path, vfs: &Arc<BoxedVFS>) -> AssetResult<FloatImage> {
+        let ext = match path.extension().and_then(|ext| ext.to_str()) {
+            Some(ext) => ext.to_ascii_lowercase(),
+            None => throw!(AssetError::UnsupportedFormat),
+        };
+
+        let format = match ImageFormat::from_extension(ext.as_str()) {
+            Some(format) if format.can_import() => Some(format),
+            None if ext == "exr" => None,
+            _ => throw!(AssetError::UnsupportedFormat),
+        };
+
+        let reader = BufReader::new(try_throw!(vfs.open(path)));
+
+        match format {
+            Some(ImageFormat::HDR) => FloatImage::read_hdr(reader),
+            Some(format) => Ok(FloatImage::from_image(&try_throw!(image::load(reader, format)))),
+            None => FloatImage::from_texture(&try_rethrow!(exr::read_exr(reader))),
+        }
+    }
+
+    /// Converts an uncompressed or BC6H compressed 2D texture, ignoring any mipmaps
+    ///
+    /// Single channel textures are treated as grayscale, and two channel textures as grayscale with alpha.
+    pub fn from_texture(texture: &Texture) -> AssetResult<FloatImage> {
+        if texture.is_compressed() && texture.format.which.float() {
+            return FloatImage::from_texture(&try_rethrow!(bc6h::decode_texture(texture)));
+        }
+
+        let uncompressed = match texture.format.which {
+            Which::None(uncompressed) => uncompressed,
+            _ => throw!(AssetError::Unimplemented("Resampling compressed textures")),
+        };
+
+        if texture.kind == TextureKind::Texture3D {
+            throw!(AssetError::Unimplemented("Resampling 3D textures"));
+        }
+
+        let (width, height) = (texture.dimensions.width, texture.dimensions.height.max(1));
+
+        let channels = uncompressed.channels.num_channels();
+
+        let values = try_rethrow!(decode_values(texture.data.as_slice(), uncompressed.data_type));
+
+        if values.len() != width as usize * height as usize * channels {
+            throw!(AssetError::InvalidValue);
+        }
+
+        Ok(FloatImage {
+            width: width,
+            height: height,
+            pixels: values.chunks(channels).map(|pixel| {
+                match channels {
+                    1 => [pixel[0], pixel[0], pixel[0], 1.0],
+                    2 => [pixel[0], pixel[0], pixel[0], pixel[1]],
+                    3 => [pixel[0], pixel[1], pixel[2], 1.0],
+                    _ => [pixel[0], pixel[1], pixel[2], pixel[3]],
+                }
+            }).collect(),
+        })
+    }
+
+    /// Converts the image into a 2D texture with the given uncompressed format
+    ///
+    /// Integer formats are clamped to the 0-1 range.
+    pub fn to_texture(&self, format: SpecificFormat) -> AssetResult<Texture> {
+        let uncompressed = match format.which {
+            Which::None(uncompressed) => uncompressed,
+            _ => throw!(AssetError::Unimplemented("Compressing resampled textures")),
+        };
+
+        let mut values = Vec::with_capacity(self.pixels.len() * uncompressed.channels.num_channels());
+
+        for pixel in &self.pixels {
+            match uncompressed.channels {
+                Channels::R => values.push(pixel[0]),
+                Channels::Rg => values.extend_from_slice(&[pixel[0], pixel[3]]),
+                Channels::Rgb => values.extend_from_slice(&pixel[..3]),
+                Channels::Rgba => values.extend_from_slice(&pixel[..]),
+            }
+        }
+
+        let data = try_rethrow!(encode_values(&values, uncompressed.data_type));
+
+        Ok(Texture {
+            data: data.into(),
+            dimensions: Dimensions::new(self.width, self.height, 0),
+            kind: TextureKind::Texture2D,
+            format: format,
+            mipmaps: Vec::new(),
+        })
+    }
+}
+
+/// Converts a direction into equirectangular texture coordinates
+fn direction_to_equirectangular(x: f32, y: f32, z: f32) -> (f32, f32) {
+    let length = (x * x + y * y + z * z).sqrt();
+
+    let u = 0.5 + x.atan2(-z) / (2.0 * PI);
+    let v = (y / length).max(-1.0).min(1.0).acos() / PI;
+
+    (u, v)
+}
+
+/// Converts equirectangular texture coordinates into a unit direction
+fn equirectangular_to_direction(u: f32, v: f32) -> (f32, f32, f32) {
+    let longitude = (u - 0.5) * 2.0 * PI;
+    let theta = v * PI;
+
+    (theta.sin() * longitude.sin(), theta.cos(), -theta.sin() * longitude.cos())
+}
+
+/// Projects an equirectangular panorama onto six square faces of the given size, in `CubeFace::all` order
+pub fn equirectangular_to_faces(panorama: &FloatImage, size: u32) -> AssetResult<Vec<FloatImage>> {
+    if panorama.is_empty() || size == 0 {
+        throw!(AssetError::InvalidValue);
+    }
+
+    Ok(CubeFace::all().iter().map(|face| {
+        let mut image = FloatImage::new(size, size);
+
+        for y in 0..size {
+            for x in 0..size {
+                let s = 2.0 * (x as f32 + 0.5) / size as f32 - 1.0;
+                let t = 2.0 * (y as f32 + 0.5) / size as f32 - 1.0;
+
+                let (dx, dy, dz) = face.direction(s, t);
+
+                let (u, v) = direction_to_equirectangular(dx, dy, dz);
+
+                image.pixels[(y * size + x) as usize] = panorama.sample(u, v, true);
+            }
+        }
+
+        image
+    }).collect())
+}
+
+/// Projects six cube faces, in `CubeFace::all` order, back into an equirectangular panorama
+pub fn faces_to_equirectangular(faces: &[FloatImage], width: u32, height: u32) -> AssetResult<FloatImage> {
+    if faces.len() != 6 || faces.iter().any(FloatImage::is_empty) || width == 0 || height == 0 {
+        throw!(AssetError::InvalidValue);
+    }
+
+    let mut panorama = FloatImage::new(width, height);
+
+    for y in 0..height {
+        for x in 0..width {
+            let u = (x as f32 + 0.5) / width as f32;
+            let v = (y as f32 + 0.5) / height as f32;
+
+            let (dx, dy, dz) = equirectangular_to_direction(u, v);
+
+            let (face, s, t) = CubeFace::from_direction(dx, dy, dz);
+
+            panorama.pixels[(y * width + x) as usize] = faces[face.index()].sample((s + 1.0) * 0.5, (t + 1.0) * 0.5, false);
+        }
+    }
+
+    Ok(panorama)
+}
+
+/// Projects an equirectangular panorama texture onto a cubemap with faces of the given size
+///
+/// The faces keep the format of the panorama.
+pub fn equirectangular_to_cubemap(panorama: &Texture, size: u32) -> AssetResult<Cubemap> {
+    let image = try_rethrow!(FloatImage::from_texture(panorama));
+
+    let mut builder = CubemapBuilder::new();
+
+    for (face, image) in CubeFace::all().iter().zip(try_rethrow!(equirectangular_to_faces(&image, size))) {
+        builder = builder.face(*face, try_rethrow!(image.to_texture(panorama.format)));
+    }
+
+    builder.build()
+}
+
+/// Projects a cubemap back into an equirectangular panorama texture with the format of the cubemap faces
+pub fn cubemap_to_equirectangular(cubemap: &Cubemap, width: u32, height: u32) -> AssetResult<Texture> {
+    let faces = [&cubemap.right, &cubemap.left, &cubemap.top, &cubemap.bottom, &cubemap.back, &cubemap.front];
+
+    let mut images = Vec::with_capacity(6);
+
+    for face in faces.iter() {
+        images.push(try_rethrow!(FloatImage::from_texture(face)));
+    }
+
+    let panorama = try_rethrow!(faces_to_equirectangular(&images, width, height));
+
+    panorama.to_texture(cubemap.right.format)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn face_directions() {
+        for face in CubeFace::all().iter() {
+            for &(s, t) in &[(0.0, 0.0), (0.5, -0.25), (-0.9, 0.9), (0.3, 0.7)] {
+                let (x, y, z) = face.direction(s, t);
+
+                let (found, found_s, found_t) = CubeFace::from_direction(x, y, z);
+
+                assert_eq!(found, *face);
+                assert!((found_s - s).abs() < 1e-5 && (found_t - t).abs() < 1e-5);
+            }
+        }
+    }
+
+    #[test]
+    fn constant_panorama() {
+        let color = [0.25, 0.5, 2.0, 1.0];
+
+        let mut panorama = FloatImage::new(16, 8);
+
+        for pixel in &mut panorama.pixels {
+            *pixel = color;
+        }
+
+        let faces = equirectangular_to_faces(&panorama, 4).unwrap();
+
+        assert_eq!(faces.len(), 6);
+
+        let back = faces_to_equirectangular(&faces, 16, 8).unwrap();
+
+        for pixel in faces.iter().flat_map(|face| face.pixels.iter()).chain(back.pixels.iter()) {
+            for (a, b) in pixel.iter().zip(color.iter()) {
+                assert!((a - b).abs() < 1e-5);
+            }
+        }
+
+        assert!(equirectangular_to_faces(&panorama, 0).is_err());
+        assert!(equirectangular_to_faces(&FloatImage::new(0, 0), 4).is_err());
+        assert!(faces_to_equirectangular(&faces, 0, 8).is_err());
+    }
+}
@@ -0,0 +1,457 @@
+//! DirectDraw Surface (`.dds`) reading and writing
+//!
+//! Both the legacy header and the DX10 extension header are supported for reading.
+//! Writing uses the legacy header for plain 8-bit RGB, which has no DXGI equivalent, and the DX10 header for everything else.
+
+use std::io::{Read, Write};
+
+use protocols::texture::protocol::{Channels, DataType, TextureKind};
+use protocols::texture::protocol::{Rgtc, Bptc, S3tc, BlockSize};
+use protocols::texture::data::format::SpecificFormat;
+use protocols::texture::data::texture::{RootTexture, TextureInfo, Dimensions};
+
+use ::error::{AssetResult, AssetError};
+
+use super::{build_root_texture, flatten_root_texture};
+use super::{uncompressed, s3tc, rgtc, bptc, astc, le_u32, push_u32, take_bytes};
+use super::checked_image_count;
+
+/// `"DDS "`
+pub const MAGIC: u32 = 0x20534444;
+
+const HEADER_SIZE: usize = 124;
+const PIXEL_FORMAT_SIZE: usize = 32;
+const DX10_HEADER_SIZE: usize = 20;
+
+const DDSD_CAPS: u32 = 0x1;
+const DDSD_HEIGHT: u32 = 0x2;
+const DDSD_WIDTH: u32 = 0x4;
+const DDSD_PITCH: u32 = 0x8;
+const DDSD_PIXELFORMAT: u32 = 0x1000;
+const DDSD_MIPMAPCOUNT: u32 = 0x20000;
+const DDSD_LINEARSIZE: u32 = 0x80000;
+const DDSD_DEPTH: u32 = 0x800000;
+
+const DDPF_ALPHAPIXELS: u32 = 0x1;
+const DDPF_FOURCC: u32 = 0x4;
+const DDPF_RGB: u32 = 0x40;
+const DDPF_LUMINANCE: u32 = 0x20000;
+
+const DDSCAPS_COMPLEX: u32 = 0x8;
+const DDSCAPS_TEXTURE: u32 = 0x1000;
+const DDSCAPS_MIPMAP: u32 = 0x400000;
+
+const DDSCAPS2_CUBEMAP: u32 = 0x200;
+const DDSCAPS2_CUBEMAP_ALLFACES: u32 = 0xFC00;
+const DDSCAPS2_VOLUME: u32 = 0x200000;
+
+const DIMENSION_TEXTURE1D: u32 = 2;
+const DIMENSION_TEXTURE2D: u32 = 3;
+const DIMENSION_TEXTURE3D: u32 = 4;
+
+const MISC_TEXTURECUBE: u32 = 0x4;
+
+/// Creates a FourCC code from four ASCII characters
+fn fourcc(code: &[u8; 4]) -> u32 {
+    le_u32(code, 0)
+}
+
+format_table!(from_dxgi, to_dxgi {
+    2   => uncompressed(Channels::Rgba, DataType::Float, false),
+    3   => uncompressed(Channels::Rgba, DataType::UnsignedInt, false),
+    4   => uncompressed(Channels::Rgba, DataType::Int, false),
+    6   => uncompressed(Channels::Rgb, DataType::Float, false),
+    7   => uncompressed(Channels::Rgb, DataType::UnsignedInt, false),
+    8   => uncompressed(Channels::Rgb, DataType::Int, false),
+    11  => uncompressed(Channels::Rgba, DataType::UnsignedShort, false),
+    13  => uncompressed(Channels::Rgba, DataType::Short, false),
+    12  => uncompressed(Channels::Rgba, DataType::UnsignedShort, false),
+    14  => uncompressed(Channels::Rgba, DataType::Short, false),
+    16  => uncompressed(Channels::Rg, DataType::Float, false),
+    17  => uncompressed(Channels::Rg, DataType::UnsignedInt, false),
+    18  => uncompressed(Channels::Rg, DataType::Int, false),
+    24  => uncompressed(Channels::Rgba, DataType::UnsignedInt2101010Rev, false),
+    28  => uncompressed(Channels::Rgba, DataType::UnsignedByte, false),
+    29  => uncompressed(Channels::Rgba, DataType::UnsignedByte, true),
+    31  => uncompressed(Channels::Rgba, DataType::Byte, false),
+    30  => uncompressed(Channels::Rgba, DataType::UnsignedByte, false),
+    32  => uncompressed(Channels::Rgba, DataType::Byte, false),
+    35  => uncompressed(Channels::Rg, DataType::UnsignedShort, false),
+    37  => uncompressed(Channels::Rg, DataType::Short, false),
+    36  => uncompressed(Channels::Rg, DataType::UnsignedShort, false),
+    38  => uncompressed(Channels::Rg, DataType::Short, false),
+    41  => uncompressed(Channels::R, DataType::Float, false),
+    42  => uncompressed(Channels::R, DataType::UnsignedInt, false),
+    43  => uncompressed(Channels::R, DataType::Int, false),
+    49  => uncompressed(Channels::Rg, DataType::UnsignedByte, false),
+    51  => uncompressed(Channels::Rg, DataType::Byte, false),
+    50  => uncompressed(Channels::Rg, DataType::UnsignedByte, false),
+    52  => uncompressed(Channels::Rg, DataType::Byte, false),
+    56  => uncompressed(Channels::R, DataType::UnsignedShort, false),
+    58  => uncompressed(Channels::R, DataType::Short, false),
+    57  => uncompressed(Channels::R, DataType::UnsignedShort, false),
+    59  => uncompressed(Channels::R, DataType::Short, false),
+    61  => uncompressed(Channels::R, DataType::UnsignedByte, false),
+    63  => uncompressed(Channels::R, DataType::Byte, false),
+    62  => uncompressed(Channels::R, DataType::UnsignedByte, false),
+    64  => uncompressed(Channels::R, DataType::Byte, false),
+    // BC1 always has a punch-through alpha bit in DXGI, so read it as RGBA
+    71  => s3tc(S3tc::Rgba1, false),
+    72  => s3tc(S3tc::Rgba1, true),
+    71  => s3tc(S3tc::Rgb1, false),
+    72  => s3tc(S3tc::Rgb1, true),
+    74  => s3tc(S3tc::Rgba3, false),
+    75  => s3tc(S3tc::Rgba3, true),
+    77  => s3tc(S3tc::Rgba5, false),
+    78  => s3tc(S3tc::Rgba5, true),
+    80  => rgtc(Rgtc::Red),
+    81  => rgtc(Rgtc::RedSigned),
+    83  => rgtc(Rgtc::Rg),
+    84  => rgtc(Rgtc::RgSigned),
+    85  => uncompressed(Channels::Rgb, DataType::UnsignedShort565, false),
+    95  => bptc(Bptc::RgbFloatUnsigned, false),
+    96  => bptc(Bptc::RgbFloatSigned, false),
+    98  => bptc(Bptc::Rgba, false),
+    99  => bptc(Bptc::Rgba, true),
+    134 => astc(BlockSize::B4x4, false),
+    135 => astc(BlockSize::B4x4, true),
+    138 => astc(BlockSize::B5x4, false),
+    139 => astc(BlockSize::B5x4, true),
+    142 => astc(BlockSize::B5x5, false),
+    143 => astc(BlockSize::B5x5, true),
+    146 => astc(BlockSize::B6x5, false),
+    147 => astc(BlockSize::B6x5, true),
+    150 => astc(BlockSize::B6x6, false),
+    151 => astc(BlockSize::B6x6, true),
+    154 => astc(BlockSize::B8x5, false),
+    155 => astc(BlockSize::B8x5, true),
+    158 => astc(BlockSize::B8x6, false),
+    159 => astc(BlockSize::B8x6, true),
+    162 => astc(BlockSize::B8x8, false),
+    163 => astc(BlockSize::B8x8, true),
+    166 => astc(BlockSize::B10x5, false),
+    167 => astc(BlockSize::B10x5, true),
+    170 => astc(BlockSize::B10x6, false),
+    171 => astc(BlockSize::B10x6, true),
+    174 => astc(BlockSize::B10x8, false),
+    175 => astc(BlockSize::B10x8, true),
+    178 => astc(BlockSize::B10x10, false),
+    179 => astc(BlockSize::B10x10, true),
+    182 => astc(BlockSize::B12x10, false),
+    183 => astc(BlockSize::B12x10, true),
+    186 => astc(BlockSize::B12x12, false),
+    187 => astc(BlockSize::B12x12, true),
+});
+
+/// Pixel layout of legacy uncompressed data that has to be converted to RGB order on load
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Swizzle {
+    None,
+    Bgr,
+    Bgra,
+    /// 32-bit pixels with an unused fourth byte, which is filled in as opaque alpha
+    Rgbx,
+    Bgrx,
+}
+
+/// Interprets the legacy `DDS_PIXELFORMAT` structure
+fn legacy_format(pixel_format: &[u8], srgb: bool) -> AssetResult<(SpecificFormat, Swizzle)> {
+    let flags = le_u32(pixel_format, 4);
+    let code = le_u32(pixel_format, 8);
+    let bit_count = le_u32(pixel_format, 12);
+    let masks = (le_u32(pixel_format, 16), le_u32(pixel_format, 20), le_u32(pixel_format, 24), le_u32(pixel_format, 28));
+
+    if flags & DDPF_FOURCC != 0 {
+        let format = if code == fourcc(b"DXT1") {
+            s3tc(if flags & DDPF_ALPHAPIXELS != 0 { S3tc::Rgba1 } else { S3tc::Rgb1 }, srgb)
+        } else if code == fourcc(b"DXT2") || code == fourcc(b"DXT3") {
+            s3tc(S3tc::Rgba3, srgb)
+        } else if code == fourcc(b"DXT4") || code == fourcc(b"DXT5") {
+            s3tc(S3tc::Rgba5, srgb)
+        } else if code == fourcc(b"ATI1") || code == fourcc(b"BC4U") {
+            rgtc(Rgtc::Red)
+        } else if code == fourcc(b"BC4S") {
+            rgtc(Rgtc::RedSigned)
+        } else if code == fourcc(b"ATI2") || code == fourcc(b"BC5U") {
+            rgtc(Rgtc::Rg)
+        } else if code == fourcc(b"BC5S") {
+            rgtc(Rgtc::RgSigned)
+        } else {
+            // Some writers store a `D3DFORMAT` value in place of a FourCC
+            match code {
+                36 => uncompressed(Channels::Rgba, DataType::UnsignedShort, false),
+                114 => uncompressed(Channels::R, DataType::Float, false),
+                115 => uncompressed(Channels::Rg, DataType::Float, false),
+                116 => uncompressed(Channels::Rgba, DataType::Float, false),
+                _ => throw!(AssetError::Unimplemented("DDS FourCC format")),
+            }
+        };
+
+        return Ok((format, Swizzle::None));
+    }
+
+    if flags & DDPF_RGB != 0 {
+        let has_alpha = flags & DDPF_ALPHAPIXELS != 0 && masks.3 != 0;
+
+        return Ok(match (bit_count, masks.0, masks.1, masks.2) {
+            (32, 0xFF, 0xFF00, 0xFF0000) => {
+                (uncompressed(Channels::Rgba, DataType::UnsignedByte, srgb), if has_alpha { Swizzle::None } else { Swizzle::Rgbx })
+            },
+            (32, 0xFF0000, 0xFF00, 0xFF) => {
+                (uncompressed(Channels::Rgba, DataType::UnsignedByte, srgb), if has_alpha { Swizzle::Bgra } else { Swizzle::Bgrx })
+            },
+            (24, 0xFF, 0xFF00, 0xFF0000) => (uncompressed(Channels::Rgb, DataType::UnsignedByte, srgb), Swizzle::None),
+            (24, 0xFF0000, 0xFF00, 0xFF) => (uncompressed(Channels::Rgb, DataType::UnsignedByte, srgb), Swizzle::Bgr),
+            (16, 0xF800, 0x7E0, 0x1F) => (uncompressed(Channels::Rgb, DataType::UnsignedShort565, false), Swizzle::None),
+            _ => throw!(AssetError::Unimplemented("DDS RGB bit layout")),
+        });
+    }
+
+    if flags & DDPF_LUMINANCE != 0 {
+        return Ok(match (bit_count, masks.0, masks.3) {
+            (8, 0xFF, _) => (uncompressed(Channels::R, DataType::UnsignedByte, false), Swizzle::None),
+            (16, 0xFF, 0xFF00) => (uncompressed(Channels::Rg, DataType::UnsignedByte, false), Swizzle::None),
+            (16, 0xFFFF, _) => (uncompressed(Channels::R, DataType::UnsignedShort, false), Swizzle::None),
+            _ => throw!(AssetError::Unimplemented("DDS luminance bit layout")),
+        });
+    }
+
+    throw!(AssetError::Unimplemented("DDS pixel format"))
+}
+
+fn apply_swizzle(data: &mut Vec<u8>, swizzle: Swizzle) {
+    match swizzle {
+        Swizzle::None => {},
+        Swizzle::Bgr => for pixel in data.chunks_mut(3) { pixel.swap(0, 2); },
+        Swizzle::Bgra => for pixel in data.chunks_mut(4) { pixel.swap(0, 2); },
+        Swizzle::Rgbx => for pixel in data.chunks_mut(4) { pixel[3] = 0xFF; },
+        Swizzle::Bgrx => for pixel in data.chunks_mut(4) { pixel.swap(0, 2); pixel[3] = 0xFF; },
+    }
+}
+
+/// Parses the DDS header and optional DX10 header extension, returning the layout of the images,
+/// the swizzle needed for their data and the offset of the first image
+fn read_header(data: &[u8], srgb: bool) -> AssetResult<(TextureInfo, Swizzle, usize)> {
+    let header = try_rethrow!(take_bytes(data, 0, 4 + HEADER_SIZE));
+
+    if le_u32(header, 0) != MAGIC || le_u32(header, 4) as usize != HEADER_SIZE {
+        throw!(AssetError::InvalidValue);
+    }
+
+    let flags = le_u32(header, 8);
+    let height = le_u32(header, 12);
+    let width = le_u32(header, 16);
+    let depth = le_u32(header, 24);
+    let mip_count = le_u32(header, 28);
+    let pixel_format = &header[76..76 + PIXEL_FORMAT_SIZE];
+    let caps2 = le_u32(header, 112);
+
+    let levels = if flags & DDSD_MIPMAPCOUNT != 0 { mip_count.max(1) } else { 1 };
+
+    let offset = 4 + HEADER_SIZE;
+
+    if le_u32(pixel_format, 4) & DDPF_FOURCC != 0 && le_u32(pixel_format, 8) == fourcc(b"DX10") {
+        let dx10 = try_rethrow!(take_bytes(data, offset, DX10_HEADER_SIZE));
+
+        let format = match from_dxgi(le_u32(dx10, 0)) {
+            Some(format) => format,
+            None => throw!(AssetError::Unimplemented("DXGI format")),
+        };
+
+        let cube = le_u32(dx10, 8) & MISC_TEXTURECUBE != 0;
+        let array_size = le_u32(dx10, 12).max(1);
+
+        let (kind, dimensions) = match le_u32(dx10, 4) {
+            DIMENSION_TEXTURE1D => (TextureKind::Texture1D, Dimensions::new(width, 0, 0)),
+            DIMENSION_TEXTURE2D => (TextureKind::Texture2D, Dimensions::new(width, height, 0)),
+            DIMENSION_TEXTURE3D => (TextureKind::Texture3D, Dimensions::new(width, height, depth)),
+            _ => throw!(AssetError::InvalidValue),
+        };
+
+        Ok((TextureInfo {
+            format: format,
+            kind: kind,
+            dimensions: dimensions,
+            levels: levels,
+            layers: if array_size > 1 { array_size } else { 0 },
+            faces: if cube { 6 } else { 1 },
+        }, Swizzle::None, offset + DX10_HEADER_SIZE))
+    } else {
+        let (format, swizzle) = try_rethrow!(legacy_format(pixel_format, srgb));
+
+        let (kind, dimensions) = if caps2 & DDSCAPS2_VOLUME != 0 {
+            (TextureKind::Texture3D, Dimensions::new(width, height, depth))
+        } else {
+            (TextureKind::Texture2D, Dimensions::new(width, height, 0))
+        };
+
+        let faces = if caps2 & DDSCAPS2_CUBEMAP != 0 {
+            if caps2 & DDSCAPS2_CUBEMAP_ALLFACES != DDSCAPS2_CUBEMAP_ALLFACES {
+                throw!(AssetError::Unimplemented("Partial DDS cubemaps"));
+            }
+
+            6
+        } else { 1 };
+
+        Ok((TextureInfo {
+            format: format,
+            kind: kind,
+            dimensions: dimensions,
+            levels: levels,
+            layers: 0,
+            faces: faces,
+        }, swizzle, offset))
+    }
+}
+
+/// Reads only the headers of a DDS file, describing its images without reading any image data
+pub fn read_dds_info<R: Read>(reader: R, srgb: bool) -> AssetResult<TextureInfo> {
+    let mut data = Vec::with_capacity(4 + HEADER_SIZE + DX10_HEADER_SIZE);
+
+    try_throw!(reader.take((4 + HEADER_SIZE + DX10_HEADER_SIZE) as u64).read_to_end(&mut data));
+
+    let (layout, _, _) = try_rethrow!(read_header(&data, srgb));
+
+    Ok(layout)
+}
+
+/// Reads a DDS file into a `RootTexture`
+///
+/// Legacy DDS files have no notion of color space, so `srgb` decides whether their color data is considered sRGB.
+pub fn read_dds<R: Read>(mut reader: R, srgb: bool) -> AssetResult<RootTexture> {
+    let mut data = Vec::new();
+
+    try_throw!(reader.read_to_end(&mut data));
+
+    let (layout, swizzle, mut offset) = try_rethrow!(read_header(&data, srgb));
+
+    let (images_per_level, image_count) = try_rethrow!(checked_image_count(&layout, data.len()));
+
+    let mut images = Vec::with_capacity(image_count);
+
+    // Each layer and face stores its full mipmap chain before the next one begins
+    for _ in 0..images_per_level {
+        for level in 0..layout.levels {
+            // Sizes that overflow saturate, so `take_bytes` rejects them
+            let size = layout.image_size(level);
+
+            let mut image = try_rethrow!(take_bytes(&data, offset, size)).to_vec();
+
+            apply_swizzle(&mut image, swizzle);
+
+            images.push(image);
+
+            offset += size;
+        }
+    }
+
+    build_root_texture(layout, images)
+}
+
+/// Writes a `RootTexture` as a DDS file
+pub fn write_dds<W: Write>(mut writer: W, root: &RootTexture) -> AssetResult<()> {
+    let (layout, textures) = try_rethrow!(flatten_root_texture(root));
+
+    let dxgi = to_dxgi(&layout.format);
+
+    let legacy_rgb = uncompressed(Channels::Rgb, DataType::UnsignedByte, layout.format.srgb);
+
+    if dxgi.is_none() {
+        if layout.format != legacy_rgb {
+            throw!(AssetError::Unimplemented("Texture format with no DDS equivalent"));
+        } else if layout.layers > 0 {
+            throw!(AssetError::Unimplemented("Arrays of 8-bit RGB textures in DDS"));
+        }
+    }
+
+    let Dimensions { width, height, depth } = layout.dimensions;
+
+    let mut flags = DDSD_CAPS | DDSD_HEIGHT | DDSD_WIDTH | DDSD_PIXELFORMAT;
+    let mut caps = DDSCAPS_TEXTURE;
+    let mut caps2 = 0;
+
+    let pitch_or_linear_size = if layout.format.is_compressed() {
+        flags |= DDSD_LINEARSIZE;
+
+        layout.image_size(0) as u32
+    } else {
+        flags |= DDSD_PITCH;
+
+        layout.format.image_size(width, 1, 1) as u32
+    };
+
+    if layout.levels > 1 {
+        flags |= DDSD_MIPMAPCOUNT;
+        caps |= DDSCAPS_COMPLEX | DDSCAPS_MIPMAP;
+    }
+
+    if layout.kind == TextureKind::Texture3D {
+        flags |= DDSD_DEPTH;
+        caps |= DDSCAPS_COMPLEX;
+        caps2 |= DDSCAPS2_VOLUME;
+    }
+
+    if layout.faces == 6 {
+        caps |= DDSCAPS_COMPLEX;
+        caps2 |= DDSCAPS2_CUBEMAP | DDSCAPS2_CUBEMAP_ALLFACES;
+    }
+
+    let mut header = Vec::with_capacity(4 + HEADER_SIZE + DX10_HEADER_SIZE);
+
+    push_u32(&mut header, MAGIC);
+    push_u32(&mut header, HEADER_SIZE as u32);
+    push_u32(&mut header, flags);
+    push_u32(&mut header, height.max(1));
+    push_u32(&mut header, width.max(1));
+    push_u32(&mut header, pitch_or_linear_size);
+    push_u32(&mut header, depth);
+    push_u32(&mut header, layout.levels);
+
+    // dwReserved1
+    for _ in 0..11 { push_u32(&mut header, 0); }
+
+    push_u32(&mut header, PIXEL_FORMAT_SIZE as u32);
+
+    if dxgi.is_some() {
+        push_u32(&mut header, DDPF_FOURCC);
+        push_u32(&mut header, fourcc(b"DX10"));
+
+        for _ in 0..5 { push_u32(&mut header, 0); }
+    } else {
+        push_u32(&mut header, DDPF_RGB);
+        push_u32(&mut header, 0);
+        push_u32(&mut header, 24);
+        push_u32(&mut header, 0xFF);
+        push_u32(&mut header, 0xFF00);
+        push_u32(&mut header, 0xFF0000);
+        push_u32(&mut header, 0);
+    }
+
+    push_u32(&mut header, caps);
+    push_u32(&mut header, caps2);
+
+    // dwCaps3, dwCaps4 and dwReserved2
+    for _ in 0..3 { push_u32(&mut header, 0); }
+
+    if let Some(dxgi) = dxgi {
+        push_u32(&mut header, dxgi);
+        push_u32(&mut header, match layout.kind {
+            TextureKind::Texture1D => DIMENSION_TEXTURE1D,
+            TextureKind::Texture2D => DIMENSION_TEXTURE2D,
+            TextureKind::Texture3D => DIMENSION_TEXTURE3D,
+        });
+        push_u32(&mut header, if layout.faces == 6 { MISC_TEXTURECUBE } else { 0 });
+        push_u32(&mut header, layout.layer_count());
+        push_u32(&mut header, 0);
+    }
+
+    try_throw!(writer.write_all(&header));
+
+    for texture in textures {
+        for level in 0..layout.levels {
+            try_throw!(writer.write_all(texture.level(level).unwrap().as_slice()));
+        }
+    }
+
+    Ok(())
+}
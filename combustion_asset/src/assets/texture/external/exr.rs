@@ -0,0 +1,362 @@
+//! OpenEXR import/export
+//!
+//! Only single-part scanline images without compression are supported.
+//! Textures are written with 32-bit float channels, while half, float and unsigned int channels can be read.
+//!
+//! Channels are mapped by name: `R`, `G`, `B` and `A` map to the texture channels of the same name,
+//! and a lone luminance channel `Y` is read as a red channel.
+
+use std::io::{Read, Write};
+
+use protocols::texture::protocol::{Channels, DataType, TextureKind};
+use protocols::texture::data::format::Which;
+use protocols::texture::data::texture::{Texture, Dimensions};
+
+use ::error::{AssetResult, AssetError};
+
+use super::super::pixels::{decode_values, encode_values, half_to_f32, f32_from_bits, f32_to_bits};
+use super::{uncompressed, le_u32, le_u64, push_u32, push_u64, take_bytes, checked_usize};
+
+/// Magic number at the start of every OpenEXR file
+pub const MAGIC: [u8; 4] = [0x76, 0x2F, 0x31, 0x01];
+
+const VERSION: u32 = 2;
+
+const FLAG_TILED: u32 = 0x200;
+const FLAG_DEEP: u32 = 0x800;
+const FLAG_MULTIPART: u32 = 0x1000;
+
+const PIXEL_TYPE_UINT: u32 = 0;
+const PIXEL_TYPE_HALF: u32 = 1;
+const PIXEL_TYPE_FLOAT: u32 = 2;
+
+const COMPRESSION_NONE: u8 = 0;
+
+/// A channel as described in the `channels` header attribute
+struct Channel {
+    name: String,
+    pixel_type: u32,
+}
+
+impl Channel {
+    fn size(&self) -> usize {
+        if self.pixel_type == PIXEL_TYPE_HALF { 2 } else { 4 }
+    }
+}
+
+fn push_attribute(header: &mut Vec<u8>, name: &str, kind: &str, value: &[u8]) {
+    header.extend_from_slice(name.as_bytes());
+    header.push(0);
+    header.extend_from_slice(kind.as_bytes());
+    header.push(0);
+
+    push_u32(header, value.len() as u32);
+
+    header.extend_from_slice(value);
+}
+
+/// Writes a single uncompressed 1D or 2D texture as an OpenEXR image with float channels, ignoring any mipmaps
+pub fn write_exr<W: Write>(mut writer: W, texture: &Texture) -> AssetResult<()> {
+    let channels = match texture.format.which {
+        Which::None(uncompressed) => uncompressed.channels,
+        _ => throw!(AssetError::Unimplemented("Saving compressed textures to standard image formats")),
+    };
+
+    if texture.kind == TextureKind::Texture3D {
+        throw!(AssetError::Unimplemented("3D texture exporting to standard image formats"));
+    }
+
+    let (width, height) = (texture.dimensions.width as usize, texture.dimensions.height.max(1) as usize);
+
+    let values = try_rethrow!(decode_values(texture.data.as_slice(), texture.format.which.data_type()));
+
+    let num_channels = channels.num_channels();
+
+    if values.len() != width * height * num_channels {
+        throw!(AssetError::InvalidValue);
+    }
+
+    // Channels must be stored in alphabetical order, paired with their index within a texture pixel
+    let names: &[(&str, usize)] = match channels {
+        Channels::R => &[("R", 0)][..],
+        Channels::Rg => &[("G", 1), ("R", 0)][..],
+        Channels::Rgb => &[("B", 2), ("G", 1), ("R", 0)][..],
+        Channels::Rgba => &[("A", 3), ("B", 2), ("G", 1), ("R", 0)][..],
+    };
+
+    let mut header = Vec::new();
+
+    header.extend_from_slice(&MAGIC);
+
+    push_u32(&mut header, VERSION);
+
+    {
+        let mut list = Vec::new();
+
+        for &(name, _) in names {
+            list.extend_from_slice(name.as_bytes());
+            list.push(0);
+
+            push_u32(&mut list, PIXEL_TYPE_FLOAT);
+
+            // Linear flag and reserved bytes, followed by x and y sampling
+            list.extend_from_slice(&[0, 0, 0, 0]);
+
+            push_u32(&mut list, 1);
+            push_u32(&mut list, 1);
+        }
+
+        list.push(0);
+
+        push_attribute(&mut header, "channels", "chlist", &list);
+    }
+
+    push_attribute(&mut header, "compression", "compression", &[COMPRESSION_NONE]);
+
+    let mut window = Vec::with_capacity(16);
+
+    for &value in &[0, 0, width as u32 - 1, height as u32 - 1] {
+        push_u32(&mut window, value);
+    }
+
+    push_attribute(&mut header, "dataWindow", "box2i", &window);
+    push_attribute(&mut header, "displayWindow", "box2i", &window);
+
+    // Increasing Y
+    push_attribute(&mut header, "lineOrder", "lineOrder", &[0]);
+
+    let mut one = Vec::with_capacity(4);
+
+    push_u32(&mut one, f32_to_bits(1.0));
+
+    push_attribute(&mut header, "pixelAspectRatio", "float", &one);
+    push_attribute(&mut header, "screenWindowCenter", "v2f", &[0; 8]);
+    push_attribute(&mut header, "screenWindowWidth", "float", &one);
+
+    header.push(0);
+
+    // One scanline per chunk, each with its y coordinate and size before the data
+    let chunk_size = 8 + width * names.len() * 4;
+
+    let table_end = header.len() + height * 8;
+
+    for y in 0..height {
+        push_u64(&mut header, (table_end + y * chunk_size) as u64);
+    }
+
+    try_throw!(writer.write_all(&header));
+
+    let mut chunk = Vec::with_capacity(chunk_size);
+
+    for (y, row) in values.chunks(width * num_channels).enumerate() {
+        chunk.clear();
+
+        push_u32(&mut chunk, y as u32);
+        push_u32(&mut chunk, (chunk_size - 8) as u32);
+
+        for &(_, index) in names {
+            for pixel in row.chunks(num_channels) {
+                push_u32(&mut chunk, f32_to_bits(pixel[index]));
+            }
+        }
+
+        try_throw!(writer.write_all(&chunk));
+    }
+
+    Ok(())
+}
+
+/// Reads a null-terminated string at `offset`, returning it and the offset after the terminator
+fn read_string(data: &[u8], offset: usize) -> AssetResult<(String, usize)> {
+    match data[offset.min(data.len())..].iter().position(|&byte| byte == 0) {
+        Some(len) => {
+            let string = try_throw!(String::from_utf8(data[offset..(offset + len)].to_vec()));
+
+            Ok((string, offset + len + 1))
+        },
+        None => throw!(AssetError::InvalidValue),
+    }
+}
+
+fn read_channels(value: &[u8]) -> AssetResult<Vec<Channel>> {
+    let mut channels = Vec::new();
+
+    let mut offset = 0;
+
+    while offset < value.len() && value[offset] != 0 {
+        let (name, next) = try_rethrow!(read_string(value, offset));
+
+        let description = try_rethrow!(take_bytes(value, next, 16));
+
+        if le_u32(description, 8) != 1 || le_u32(description, 12) != 1 {
+            throw!(AssetError::Unimplemented("Subsampled OpenEXR channels"));
+        }
+
+        let pixel_type = le_u32(description, 0);
+
+        if pixel_type > PIXEL_TYPE_FLOAT {
+            throw!(AssetError::InvalidValue);
+        }
+
+        channels.push(Channel { name: name, pixel_type: pixel_type });
+
+        offset = next + 16;
+    }
+
+    Ok(channels)
+}
+
+/// Reads an OpenEXR image into a float texture
+pub fn read_exr<R: Read>(mut reader: R) -> AssetResult<Texture> {
+    let mut data = Vec::new();
+
+    try_throw!(reader.read_to_end(&mut data));
+
+    if try_rethrow!(take_bytes(&data, 0, 4)) != &MAGIC[..] {
+        throw!(AssetError::InvalidValue);
+    }
+
+    let version = le_u32(try_rethrow!(take_bytes(&data, 4, 4)), 0);
+
+    if version & 0xFF != VERSION {
+        throw!(AssetError::UnsupportedFormat);
+    }
+
+    if version & (FLAG_TILED | FLAG_DEEP | FLAG_MULTIPART) != 0 {
+        throw!(AssetError::Unimplemented("Tiled, deep or multi-part OpenEXR images"));
+    }
+
+    let mut channels = None;
+    let mut compression = None;
+    let mut data_window = None;
+
+    let mut offset = 8;
+
+    loop {
+        let (name, next) = try_rethrow!(read_string(&data, offset));
+
+        if name.is_empty() {
+            offset = next;
+            break;
+        }
+
+        let (_, next) = try_rethrow!(read_string(&data, next));
+
+        let size = le_u32(try_rethrow!(take_bytes(&data, next, 4)), 0) as usize;
+
+        let value = try_rethrow!(take_bytes(&data, next + 4, size));
+
+        match name.as_str() {
+            "channels" => channels = Some(try_rethrow!(read_channels(value))),
+            "compression" if size == 1 => compression = Some(value[0]),
+            "dataWindow" if size == 16 => {
+                data_window = Some((le_u32(value, 0) as i32, le_u32(value, 4) as i32, le_u32(value, 8) as i32, le_u32(value, 12) as i32));
+            },
+            _ => {}
+        }
+
+        offset = next + 4 + size;
+    }
+
+    let (channels, data_window) = match (channels, data_window) {
+        (Some(channels), Some(data_window)) => (channels, data_window),
+        _ => throw!(AssetError::InvalidValue),
+    };
+
+    if compression != Some(COMPRESSION_NONE) {
+        throw!(AssetError::Unimplemented("Compressed OpenEXR images"));
+    }
+
+    let (min_x, min_y, max_x, max_y) = data_window;
+
+    if max_x < min_x || max_y < min_y {
+        throw!(AssetError::InvalidValue);
+    }
+
+    // The window can span the whole `i32` range, so its size may not fit in one
+    let width = try_rethrow!(checked_usize((max_x as i64 - min_x as i64 + 1) as u64));
+    let height = try_rethrow!(checked_usize((max_y as i64 - min_y as i64 + 1) as u64));
+
+    let find = |name: &str| channels.iter().position(|channel| channel.name == name);
+
+    // Indices into the file's channel list for each texture channel
+    let mapping = match (find("R"), find("G"), find("B"), find("A"), find("Y")) {
+        (Some(r), Some(g), Some(b), Some(a), _) => vec![r, g, b, a],
+        (Some(r), Some(g), Some(b), None, _) => vec![r, g, b],
+        (_, _, _, Some(a), Some(y)) => vec![y, a],
+        (_, _, _, None, Some(y)) => vec![y],
+        (Some(r), Some(g), None, _, None) => vec![r, g],
+        (Some(r), None, None, _, None) => vec![r],
+        _ => throw!(AssetError::Unimplemented("OpenEXR images without color channels")),
+    };
+
+    let texture_channels = match mapping.len() {
+        1 => Channels::R,
+        2 => Channels::Rg,
+        3 => Channels::Rgb,
+        _ => Channels::Rgba,
+    };
+
+    // Byte offset of each channel within a scanline
+    let mut channel_offsets = Vec::with_capacity(channels.len());
+    let mut line_size = 0;
+
+    for channel in &channels {
+        channel_offsets.push(line_size);
+
+        let channel_size = try_throw!(channel.size().checked_mul(width).ok_or(AssetError::InvalidValue));
+
+        line_size = try_throw!(line_size.checked_add(channel_size).ok_or(AssetError::InvalidValue));
+    }
+
+    // Make sure the offset table and every scanline can actually be present before allocating for them.
+    // Each value takes at least two bytes, so this also bounds the size of `values`.
+    let line_offsets = try_rethrow!(take_bytes(&data, offset, try_throw!(height.checked_mul(8).ok_or(AssetError::InvalidValue))));
+
+    match height.checked_mul(line_size) {
+        Some(size) if size <= data.len() => {},
+        _ => throw!(AssetError::InvalidValue),
+    }
+
+    let mut values = vec![0.0; width * height * mapping.len()];
+
+    for line in 0..height {
+        let chunk_offset = try_rethrow!(checked_usize(le_u64(line_offsets, line * 8)));
+
+        let chunk_header = try_rethrow!(take_bytes(&data, chunk_offset, 8));
+
+        let y = le_u32(chunk_header, 0) as i32;
+
+        if y < min_y || y > max_y || le_u32(chunk_header, 4) as usize != line_size {
+            throw!(AssetError::InvalidValue);
+        }
+
+        let scanline = try_rethrow!(take_bytes(&data, chunk_offset + 8, line_size));
+
+        let row = (y - min_y) as usize;
+
+        for (texture_channel, &index) in mapping.iter().enumerate() {
+            let channel = &channels[index];
+
+            for x in 0..width {
+                let at = channel_offsets[index] + x * channel.size();
+
+                let value = match channel.pixel_type {
+                    PIXEL_TYPE_HALF => half_to_f32(scanline[at] as u16 | (scanline[at + 1] as u16) << 8),
+                    PIXEL_TYPE_UINT => le_u32(scanline, at) as f32,
+                    _ => f32_from_bits(le_u32(scanline, at)),
+                };
+
+                values[(row * width + x) * mapping.len() + texture_channel] = value;
+            }
+        }
+    }
+
+    Ok(Texture {
+        data: try_rethrow!(encode_values(&values, DataType::Float)).into(),
+        dimensions: Dimensions::new(width as u32, height as u32, 0),
+        kind: TextureKind::Texture2D,
+        format: uncompressed(texture_channels, DataType::Float, false),
+        mipmaps: Vec::new(),
+    })
+}
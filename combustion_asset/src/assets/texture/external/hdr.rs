@@ -0,0 +1,72 @@
+//! Radiance RGBE (`.hdr`) export
+//!
+//! Reading is handled by the `image` crate's decoder, but it has no encoder.
+//! Scanlines are written with the run-length encoded layout where possible, storing every byte as a literal run,
+//! because the flat layout is ambiguous for pixels that happen to look like a run-length scanline header.
+
+use std::io::Write;
+
+use ::error::AssetResult;
+
+use super::super::cubemap::FloatImage;
+
+/// Longest run of literal bytes in a run-length encoded scanline
+const MAX_LITERAL_RUN: usize = 128;
+
+/// Converts a linear color to shared exponent RGBE
+fn to_rgbe(pixel: &[f32; 4]) -> [u8; 4] {
+    let (r, g, b) = (pixel[0].max(0.0), pixel[1].max(0.0), pixel[2].max(0.0));
+
+    let max = r.max(g).max(b);
+
+    if max < 1e-32 || !max.is_finite() {
+        return [0, 0, 0, 0];
+    }
+
+    let exponent = max.log2().floor() as i32 + 1;
+
+    let scale = 256.0 / 2.0f32.powi(exponent);
+
+    let encode = |value: f32| (value * scale).min(255.0) as u8;
+
+    [encode(r), encode(g), encode(b), (exponent + 128).max(0).min(255) as u8]
+}
+
+/// Writes an image as a Radiance `.hdr` file, discarding alpha
+pub fn write_hdr<W: Write>(mut writer: W, image: &FloatImage) -> AssetResult<()> {
+    let (width, height) = (image.width as usize, image.height as usize);
+
+    try_throw!(write!(writer, "#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y {} +X {}\n", height, width));
+
+    let run_length = width >= 8 && width <= 0x7FFF;
+
+    let mut scanline = Vec::with_capacity(width * 4 + 4 + (width * 4) / MAX_LITERAL_RUN + 4);
+
+    for row in image.pixels.chunks(width) {
+        scanline.clear();
+
+        let rgbe: Vec<[u8; 4]> = row.iter().map(to_rgbe).collect();
+
+        if run_length {
+            scanline.extend_from_slice(&[2, 2, (width >> 8) as u8, width as u8]);
+
+            // Each component is stored separately
+            for component in 0..4 {
+                let values: Vec<u8> = rgbe.iter().map(|pixel| pixel[component]).collect();
+
+                for run in values.chunks(MAX_LITERAL_RUN) {
+                    scanline.push(run.len() as u8);
+                    scanline.extend_from_slice(run);
+                }
+            }
+        } else {
+            for pixel in &rgbe {
+                scanline.extend_from_slice(pixel);
+            }
+        }
+
+        try_throw!(writer.write_all(&scanline));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,860 @@
+//! Khronos Texture (`.ktx` and `.ktx2`) reading and writing
+//!
+//! KTX1 stores OpenGL format enums, while KTX2 stores Vulkan formats along with a data format descriptor.
+//! Supercompressed KTX2 files are not supported.
+
+use std::io::{Read, Write};
+
+use protocols::texture::protocol::{Channels, DataType, TextureKind};
+use protocols::texture::protocol::{Rgtc, Bptc, S3tc, BlockSize};
+use protocols::texture::data::format::{SpecificFormat, Which};
+use protocols::texture::data::texture::{RootTexture, TextureInfo, Dimensions};
+
+use ::error::{AssetResult, AssetError};
+
+use super::{build_root_texture, flatten_root_texture};
+use super::{uncompressed, s3tc, rgtc, bptc, astc, le_u32, le_u64, push_u32, push_u64, take_bytes};
+use super::{checked_image_count, checked_usize};
+
+/// Identifier at the start of every KTX1 file
+pub const KTX1_IDENTIFIER: [u8; 12] = [0xAB, 0x4B, 0x54, 0x58, 0x20, 0x31, 0x31, 0xBB, 0x0D, 0x0A, 0x1A, 0x0A];
+
+/// Identifier at the start of every KTX2 file
+pub const KTX2_IDENTIFIER: [u8; 12] = [0xAB, 0x4B, 0x54, 0x58, 0x20, 0x32, 0x30, 0xBB, 0x0D, 0x0A, 0x1A, 0x0A];
+
+const ENDIANNESS: u32 = 0x04030201;
+const ENDIANNESS_SWAPPED: u32 = 0x01020304;
+
+const KTX1_HEADER_SIZE: usize = 64;
+const KTX2_HEADER_SIZE: usize = 80;
+const KTX2_LEVEL_INDEX_SIZE: usize = 24;
+
+const GL_RED: u32 = 0x1903;
+const GL_RG: u32 = 0x8227;
+const GL_RGB: u32 = 0x1907;
+const GL_RGBA: u32 = 0x1908;
+const GL_RED_INTEGER: u32 = 0x8D94;
+const GL_RG_INTEGER: u32 = 0x8228;
+const GL_RGB_INTEGER: u32 = 0x8D98;
+const GL_RGBA_INTEGER: u32 = 0x8D99;
+
+const GL_SRGB8: u32 = 0x8C41;
+const GL_SRGB8_ALPHA8: u32 = 0x8C43;
+
+format_table!(from_gl_compressed, to_gl_compressed {
+    0x83F0 => s3tc(S3tc::Rgb1, false),
+    0x83F1 => s3tc(S3tc::Rgba1, false),
+    0x83F2 => s3tc(S3tc::Rgba3, false),
+    0x83F3 => s3tc(S3tc::Rgba5, false),
+    0x8C4C => s3tc(S3tc::Rgb1, true),
+    0x8C4D => s3tc(S3tc::Rgba1, true),
+    0x8C4E => s3tc(S3tc::Rgba3, true),
+    0x8C4F => s3tc(S3tc::Rgba5, true),
+    0x8DBB => rgtc(Rgtc::Red),
+    0x8DBC => rgtc(Rgtc::RedSigned),
+    0x8DBD => rgtc(Rgtc::Rg),
+    0x8DBE => rgtc(Rgtc::RgSigned),
+    0x8E8C => bptc(Bptc::Rgba, false),
+    0x8E8D => bptc(Bptc::Rgba, true),
+    0x8E8E => bptc(Bptc::RgbFloatSigned, false),
+    0x8E8F => bptc(Bptc::RgbFloatUnsigned, false),
+    0x93B0 => astc(BlockSize::B4x4, false),
+    0x93B1 => astc(BlockSize::B5x4, false),
+    0x93B2 => astc(BlockSize::B5x5, false),
+    0x93B3 => astc(BlockSize::B6x5, false),
+    0x93B4 => astc(BlockSize::B6x6, false),
+    0x93B5 => astc(BlockSize::B8x5, false),
+    0x93B6 => astc(BlockSize::B8x6, false),
+    0x93B7 => astc(BlockSize::B8x8, false),
+    0x93B8 => astc(BlockSize::B10x5, false),
+    0x93B9 => astc(BlockSize::B10x6, false),
+    0x93BA => astc(BlockSize::B10x8, false),
+    0x93BB => astc(BlockSize::B10x10, false),
+    0x93BC => astc(BlockSize::B12x10, false),
+    0x93BD => astc(BlockSize::B12x12, false),
+    0x93D0 => astc(BlockSize::B4x4, true),
+    0x93D1 => astc(BlockSize::B5x4, true),
+    0x93D2 => astc(BlockSize::B5x5, true),
+    0x93D3 => astc(BlockSize::B6x5, true),
+    0x93D4 => astc(BlockSize::B6x6, true),
+    0x93D5 => astc(BlockSize::B8x5, true),
+    0x93D6 => astc(BlockSize::B8x6, true),
+    0x93D7 => astc(BlockSize::B8x8, true),
+    0x93D8 => astc(BlockSize::B10x5, true),
+    0x93D9 => astc(BlockSize::B10x6, true),
+    0x93DA => astc(BlockSize::B10x8, true),
+    0x93DB => astc(BlockSize::B10x10, true),
+    0x93DC => astc(BlockSize::B12x10, true),
+    0x93DD => astc(BlockSize::B12x12, true),
+});
+
+format_table!(from_vk, to_vk {
+    9   => uncompressed(Channels::R, DataType::UnsignedByte, false),
+    15  => uncompressed(Channels::R, DataType::UnsignedByte, true),
+    10  => uncompressed(Channels::R, DataType::Byte, false),
+    13  => uncompressed(Channels::R, DataType::UnsignedByte, false),
+    14  => uncompressed(Channels::R, DataType::Byte, false),
+    16  => uncompressed(Channels::Rg, DataType::UnsignedByte, false),
+    22  => uncompressed(Channels::Rg, DataType::UnsignedByte, true),
+    17  => uncompressed(Channels::Rg, DataType::Byte, false),
+    20  => uncompressed(Channels::Rg, DataType::UnsignedByte, false),
+    21  => uncompressed(Channels::Rg, DataType::Byte, false),
+    23  => uncompressed(Channels::Rgb, DataType::UnsignedByte, false),
+    29  => uncompressed(Channels::Rgb, DataType::UnsignedByte, true),
+    24  => uncompressed(Channels::Rgb, DataType::Byte, false),
+    27  => uncompressed(Channels::Rgb, DataType::UnsignedByte, false),
+    28  => uncompressed(Channels::Rgb, DataType::Byte, false),
+    37  => uncompressed(Channels::Rgba, DataType::UnsignedByte, false),
+    43  => uncompressed(Channels::Rgba, DataType::UnsignedByte, true),
+    38  => uncompressed(Channels::Rgba, DataType::Byte, false),
+    41  => uncompressed(Channels::Rgba, DataType::UnsignedByte, false),
+    42  => uncompressed(Channels::Rgba, DataType::Byte, false),
+    70  => uncompressed(Channels::R, DataType::UnsignedShort, false),
+    71  => uncompressed(Channels::R, DataType::Short, false),
+    74  => uncompressed(Channels::R, DataType::UnsignedShort, false),
+    75  => uncompressed(Channels::R, DataType::Short, false),
+    77  => uncompressed(Channels::Rg, DataType::UnsignedShort, false),
+    78  => uncompressed(Channels::Rg, DataType::Short, false),
+    81  => uncompressed(Channels::Rg, DataType::UnsignedShort, false),
+    82  => uncompressed(Channels::Rg, DataType::Short, false),
+    84  => uncompressed(Channels::Rgb, DataType::UnsignedShort, false),
+    85  => uncompressed(Channels::Rgb, DataType::Short, false),
+    88  => uncompressed(Channels::Rgb, DataType::UnsignedShort, false),
+    89  => uncompressed(Channels::Rgb, DataType::Short, false),
+    91  => uncompressed(Channels::Rgba, DataType::UnsignedShort, false),
+    92  => uncompressed(Channels::Rgba, DataType::Short, false),
+    95  => uncompressed(Channels::Rgba, DataType::UnsignedShort, false),
+    96  => uncompressed(Channels::Rgba, DataType::Short, false),
+    98  => uncompressed(Channels::R, DataType::UnsignedInt, false),
+    99  => uncompressed(Channels::R, DataType::Int, false),
+    100 => uncompressed(Channels::R, DataType::Float, false),
+    101 => uncompressed(Channels::Rg, DataType::UnsignedInt, false),
+    102 => uncompressed(Channels::Rg, DataType::Int, false),
+    103 => uncompressed(Channels::Rg, DataType::Float, false),
+    104 => uncompressed(Channels::Rgb, DataType::UnsignedInt, false),
+    105 => uncompressed(Channels::Rgb, DataType::Int, false),
+    106 => uncompressed(Channels::Rgb, DataType::Float, false),
+    107 => uncompressed(Channels::Rgba, DataType::UnsignedInt, false),
+    108 => uncompressed(Channels::Rgba, DataType::Int, false),
+    109 => uncompressed(Channels::Rgba, DataType::Float, false),
+    2   => uncompressed(Channels::Rgba, DataType::UnsignedShort4444, false),
+    4   => uncompressed(Channels::Rgb, DataType::UnsignedShort565, false),
+    6   => uncompressed(Channels::Rgba, DataType::UnsignedShort5551, false),
+    51  => uncompressed(Channels::Rgba, DataType::UnsignedInt8888Rev, false),
+    57  => uncompressed(Channels::Rgba, DataType::UnsignedInt8888Rev, true),
+    64  => uncompressed(Channels::Rgba, DataType::UnsignedInt2101010Rev, false),
+    131 => s3tc(S3tc::Rgb1, false),
+    132 => s3tc(S3tc::Rgb1, true),
+    133 => s3tc(S3tc::Rgba1, false),
+    134 => s3tc(S3tc::Rgba1, true),
+    135 => s3tc(S3tc::Rgba3, false),
+    136 => s3tc(S3tc::Rgba3, true),
+    137 => s3tc(S3tc::Rgba5, false),
+    138 => s3tc(S3tc::Rgba5, true),
+    139 => rgtc(Rgtc::Red),
+    140 => rgtc(Rgtc::RedSigned),
+    141 => rgtc(Rgtc::Rg),
+    142 => rgtc(Rgtc::RgSigned),
+    143 => bptc(Bptc::RgbFloatUnsigned, false),
+    144 => bptc(Bptc::RgbFloatSigned, false),
+    145 => bptc(Bptc::Rgba, false),
+    146 => bptc(Bptc::Rgba, true),
+    157 => astc(BlockSize::B4x4, false),
+    158 => astc(BlockSize::B4x4, true),
+    159 => astc(BlockSize::B5x4, false),
+    160 => astc(BlockSize::B5x4, true),
+    161 => astc(BlockSize::B5x5, false),
+    162 => astc(BlockSize::B5x5, true),
+    163 => astc(BlockSize::B6x5, false),
+    164 => astc(BlockSize::B6x5, true),
+    165 => astc(BlockSize::B6x6, false),
+    166 => astc(BlockSize::B6x6, true),
+    167 => astc(BlockSize::B8x5, false),
+    168 => astc(BlockSize::B8x5, true),
+    169 => astc(BlockSize::B8x6, false),
+    170 => astc(BlockSize::B8x6, true),
+    171 => astc(BlockSize::B8x8, false),
+    172 => astc(BlockSize::B8x8, true),
+    173 => astc(BlockSize::B10x5, false),
+    174 => astc(BlockSize::B10x5, true),
+    175 => astc(BlockSize::B10x6, false),
+    176 => astc(BlockSize::B10x6, true),
+    177 => astc(BlockSize::B10x8, false),
+    178 => astc(BlockSize::B10x8, true),
+    179 => astc(BlockSize::B10x10, false),
+    180 => astc(BlockSize::B10x10, true),
+    181 => astc(BlockSize::B12x10, false),
+    182 => astc(BlockSize::B12x10, true),
+    183 => astc(BlockSize::B12x12, false),
+    184 => astc(BlockSize::B12x12, true),
+});
+
+fn from_gl_type(gl_type: u32) -> Option<DataType> {
+    Some(match gl_type {
+        0x1400 => DataType::Byte,
+        0x1401 => DataType::UnsignedByte,
+        0x1402 => DataType::Short,
+        0x1403 => DataType::UnsignedShort,
+        0x1404 => DataType::Int,
+        0x1405 => DataType::UnsignedInt,
+        0x1406 => DataType::Float,
+        0x8032 => DataType::UnsignedByte332,
+        0x8362 => DataType::UnsignedByte233Rev,
+        0x8363 => DataType::UnsignedShort565,
+        0x8364 => DataType::UnsignedShort565Rev,
+        0x8033 => DataType::UnsignedShort4444,
+        0x8365 => DataType::UnsignedShort4444Rev,
+        0x8034 => DataType::UnsignedShort5551,
+        0x8366 => DataType::UnsignedShort1555Rev,
+        0x8035 => DataType::UnsignedInt8888,
+        0x8367 => DataType::UnsignedInt8888Rev,
+        0x8036 => DataType::UnsignedInt1010102,
+        0x8368 => DataType::UnsignedInt2101010Rev,
+        _ => { return None; }
+    })
+}
+
+fn to_gl_type(data_type: DataType) -> u32 {
+    match data_type {
+        DataType::Byte => 0x1400,
+        DataType::UnsignedByte | DataType::Unspecified => 0x1401,
+        DataType::Short => 0x1402,
+        DataType::UnsignedShort => 0x1403,
+        DataType::Int => 0x1404,
+        DataType::UnsignedInt => 0x1405,
+        DataType::Float => 0x1406,
+        DataType::UnsignedByte332 => 0x8032,
+        DataType::UnsignedByte233Rev => 0x8362,
+        DataType::UnsignedShort565 => 0x8363,
+        DataType::UnsignedShort565Rev => 0x8364,
+        DataType::UnsignedShort4444 => 0x8033,
+        DataType::UnsignedShort4444Rev => 0x8365,
+        DataType::UnsignedShort5551 => 0x8034,
+        DataType::UnsignedShort1555Rev => 0x8366,
+        DataType::UnsignedInt8888 => 0x8035,
+        DataType::UnsignedInt8888Rev => 0x8367,
+        DataType::UnsignedInt1010102 => 0x8036,
+        DataType::UnsignedInt2101010Rev => 0x8368,
+    }
+}
+
+/// Size of the values the data type is made of, used for endianness conversion
+fn type_size(data_type: DataType) -> u32 {
+    match data_type {
+        DataType::UnsignedByte | DataType::Byte | DataType::Unspecified |
+        DataType::UnsignedByte332 | DataType::UnsignedByte233Rev => 1,
+        DataType::UnsignedShort | DataType::Short |
+        DataType::UnsignedShort565 | DataType::UnsignedShort565Rev |
+        DataType::UnsignedShort4444 | DataType::UnsignedShort4444Rev |
+        DataType::UnsignedShort5551 | DataType::UnsignedShort1555Rev => 2,
+        _ => 4,
+    }
+}
+
+fn gl_base_format(channels: Channels) -> u32 {
+    match channels {
+        Channels::R => GL_RED,
+        Channels::Rg => GL_RG,
+        Channels::Rgb => GL_RGB,
+        Channels::Rgba => GL_RGBA,
+    }
+}
+
+fn gl_sized_internal_format(channels: Channels, data_type: DataType, srgb: bool) -> Option<u32> {
+    use self::Channels::*;
+    use self::DataType::*;
+
+    Some(match (channels, data_type, srgb) {
+        (R, UnsignedByte, false) => 0x8229,
+        (R, Byte, _) => 0x8F94,
+        (R, UnsignedShort, _) => 0x822A,
+        (R, Short, _) => 0x8F98,
+        (R, UnsignedInt, _) => 0x8236,
+        (R, Int, _) => 0x8235,
+        (R, Float, _) => 0x822E,
+        (Rg, UnsignedByte, false) => 0x822B,
+        (Rg, Byte, _) => 0x8F95,
+        (Rg, UnsignedShort, _) => 0x822C,
+        (Rg, Short, _) => 0x8F99,
+        (Rg, UnsignedInt, _) => 0x823C,
+        (Rg, Int, _) => 0x823B,
+        (Rg, Float, _) => 0x8230,
+        (Rgb, UnsignedByte, false) => 0x8051,
+        (Rgb, UnsignedByte, true) => GL_SRGB8,
+        (Rgb, Byte, _) => 0x8F96,
+        (Rgb, UnsignedShort, _) => 0x8054,
+        (Rgb, Short, _) => 0x8F9A,
+        (Rgb, UnsignedInt, _) => 0x8D71,
+        (Rgb, Int, _) => 0x8D83,
+        (Rgb, Float, _) => 0x8815,
+        (Rgb, UnsignedShort565, _) => 0x8D62,
+        (Rgb, UnsignedByte332, _) => 0x2A10,
+        (Rgba, UnsignedByte, false) => 0x8058,
+        (Rgba, UnsignedByte, true) => GL_SRGB8_ALPHA8,
+        (Rgba, Byte, _) => 0x8F97,
+        (Rgba, UnsignedShort, _) => 0x805B,
+        (Rgba, Short, _) => 0x8F9B,
+        (Rgba, UnsignedInt, _) => 0x8D70,
+        (Rgba, Int, _) => 0x8D82,
+        (Rgba, Float, _) => 0x8814,
+        (Rgba, UnsignedShort4444, _) => 0x8056,
+        (Rgba, UnsignedShort5551, _) => 0x8057,
+        (Rgba, UnsignedInt2101010Rev, _) => 0x8059,
+        _ => { return None; }
+    })
+}
+
+#[inline]
+fn align4(size: usize) -> usize {
+    (size + 3) & !3
+}
+
+fn swap_elements(data: &mut [u8], size: u32) {
+    match size {
+        2 => for element in data.chunks_mut(2) { element.swap(0, 1); },
+        4 => for element in data.chunks_mut(4) { element.swap(0, 3); element.swap(1, 2); },
+        _ => {},
+    }
+}
+
+/// KTX1 pads every row of uncompressed data to four bytes
+///
+/// Returns `(row bytes, padded row bytes, rows)` for uncompressed formats.
+fn ktx1_rows(layout: &TextureInfo, level: u32) -> AssetResult<Option<(usize, usize, usize)>> {
+    if layout.format.is_compressed() {
+        return Ok(None);
+    }
+
+    let dimensions = layout.dimensions.mip_level(level);
+
+    let row_bytes = try_throw!(layout.format.checked_image_size(dimensions.width, 1, 1).ok_or(AssetError::InvalidValue));
+    let padded_row_bytes = try_throw!(row_bytes.checked_add(3).map(|size| size & !3).ok_or(AssetError::InvalidValue));
+    let rows = try_throw!((dimensions.height.max(1) as usize).checked_mul(dimensions.depth.max(1) as usize).ok_or(AssetError::InvalidValue));
+
+    Ok(Some((row_bytes, padded_row_bytes, rows)))
+}
+
+fn ktx1_image_size(layout: &TextureInfo, level: u32) -> AssetResult<usize> {
+    let size = match try_rethrow!(ktx1_rows(layout, level)) {
+        Some((_, padded_row_bytes, rows)) => padded_row_bytes.checked_mul(rows),
+        None => {
+            let dimensions = layout.dimensions.mip_level(level);
+
+            layout.format.checked_image_size(dimensions.width, dimensions.height, dimensions.depth)
+        },
+    };
+
+    Ok(try_throw!(size.ok_or(AssetError::InvalidValue)))
+}
+
+fn texture_kind(dimensions: &Dimensions) -> TextureKind {
+    if dimensions.height == 0 {
+        TextureKind::Texture1D
+    } else if dimensions.depth == 0 {
+        TextureKind::Texture2D
+    } else {
+        TextureKind::Texture3D
+    }
+}
+
+/// Returns true if the data starts with the KTX2 identifier
+pub fn is_ktx2(data: &[u8]) -> bool {
+    data.starts_with(&KTX2_IDENTIFIER)
+}
+
+/// Reads a KTX1 or KTX2 file into a `RootTexture`, detecting the version from the file identifier
+pub fn read_ktx<R: Read>(mut reader: R) -> AssetResult<RootTexture> {
+    let mut data = Vec::new();
+
+    try_throw!(reader.read_to_end(&mut data));
+
+    if data.starts_with(&KTX1_IDENTIFIER) {
+        read_ktx1(&data)
+    } else if is_ktx2(&data) {
+        read_ktx2(&data)
+    } else {
+        throw!(AssetError::InvalidValue)
+    }
+}
+
+/// Reads only the header of a KTX1 or KTX2 file, describing its images without reading any image data
+pub fn read_ktx_info<R: Read>(reader: R) -> AssetResult<TextureInfo> {
+    let header_size = KTX1_HEADER_SIZE.max(KTX2_HEADER_SIZE);
+
+    let mut data = Vec::with_capacity(header_size);
+
+    try_throw!(reader.take(header_size as u64).read_to_end(&mut data));
+
+    if data.starts_with(&KTX1_IDENTIFIER) {
+        Ok(try_rethrow!(read_ktx1_header(&data)).layout)
+    } else if is_ktx2(&data) {
+        read_ktx2_header(&data)
+    } else {
+        throw!(AssetError::InvalidValue)
+    }
+}
+
+/// Parsed KTX1 header, along with the details needed to read the images after it
+struct Ktx1Header {
+    layout: TextureInfo,
+    swapped: bool,
+    gl_type_size: u32,
+    key_value_bytes: usize,
+}
+
+fn read_ktx1_header(data: &[u8]) -> AssetResult<Ktx1Header> {
+    let header = try_rethrow!(take_bytes(data, 0, KTX1_HEADER_SIZE));
+
+    let swapped = match le_u32(header, 12) {
+        ENDIANNESS => false,
+        ENDIANNESS_SWAPPED => true,
+        _ => throw!(AssetError::InvalidValue),
+    };
+
+    let field = |index: usize| {
+        let value = le_u32(header, 16 + index * 4);
+
+        if swapped { value.swap_bytes() } else { value }
+    };
+
+    let gl_type = field(0);
+    let gl_type_size = field(1);
+    let gl_format = field(2);
+    let gl_internal_format = field(3);
+    let dimensions = Dimensions::new(field(5), field(6), field(7));
+    let layers = field(8);
+    let faces = field(9);
+    let levels = field(10).max(1);
+    let key_value_bytes = field(11) as usize;
+
+    let format = if gl_type == 0 {
+        match from_gl_compressed(gl_internal_format) {
+            Some(format) => format,
+            None => throw!(AssetError::Unimplemented("KTX compressed format")),
+        }
+    } else {
+        let channels = match gl_format {
+            GL_RED | GL_RED_INTEGER => Channels::R,
+            GL_RG | GL_RG_INTEGER => Channels::Rg,
+            GL_RGB | GL_RGB_INTEGER => Channels::Rgb,
+            GL_RGBA | GL_RGBA_INTEGER => Channels::Rgba,
+            _ => throw!(AssetError::Unimplemented("KTX pixel format")),
+        };
+
+        let data_type = match from_gl_type(gl_type) {
+            Some(data_type) => data_type,
+            None => throw!(AssetError::Unimplemented("KTX pixel type")),
+        };
+
+        uncompressed(channels, data_type, gl_internal_format == GL_SRGB8 || gl_internal_format == GL_SRGB8_ALPHA8)
+    };
+
+    Ok(Ktx1Header {
+        layout: TextureInfo {
+            format: format,
+            kind: texture_kind(&dimensions),
+            dimensions: dimensions,
+            levels: levels,
+            layers: layers,
+            faces: faces,
+        },
+        swapped: swapped,
+        gl_type_size: gl_type_size,
+        key_value_bytes: key_value_bytes,
+    })
+}
+
+fn read_ktx1(data: &[u8]) -> AssetResult<RootTexture> {
+    let Ktx1Header { layout, swapped, gl_type_size, key_value_bytes } = try_rethrow!(read_ktx1_header(data));
+
+    let levels = layout.levels;
+
+    let (images_per_level, image_count) = try_rethrow!(checked_image_count(&layout, data.len()));
+
+    let mut images = vec![Vec::new(); image_count];
+
+    let mut offset = try_throw!(KTX1_HEADER_SIZE.checked_add(key_value_bytes).ok_or(AssetError::InvalidValue));
+
+    for level in 0..levels {
+        // imageSize is redundant with the header, so it's only skipped over
+        offset = try_throw!(offset.checked_add(4).ok_or(AssetError::InvalidValue));
+
+        let rows = try_rethrow!(ktx1_rows(&layout, level));
+        let size = try_rethrow!(ktx1_image_size(&layout, level));
+
+        for image in 0..images_per_level {
+            let bytes = try_rethrow!(take_bytes(data, offset, size));
+
+            let mut image_data = match rows {
+                Some((row_bytes, padded_row_bytes, rows)) => {
+                    let mut image_data = Vec::with_capacity(row_bytes * rows);
+
+                    for row in 0..rows {
+                        let start = row * padded_row_bytes;
+
+                        image_data.extend_from_slice(&bytes[start..start + row_bytes]);
+                    }
+
+                    image_data
+                },
+                None => bytes.to_vec(),
+            };
+
+            if swapped {
+                swap_elements(&mut image_data, gl_type_size);
+            }
+
+            images[image * levels as usize + level as usize] = image_data;
+
+            offset += size;
+        }
+
+        offset = align4(offset);
+    }
+
+    build_root_texture(layout, images)
+}
+
+fn read_ktx2_header(data: &[u8]) -> AssetResult<TextureInfo> {
+    let header = try_rethrow!(take_bytes(data, 0, KTX2_HEADER_SIZE));
+
+    let vk_format = le_u32(header, 12);
+    let dimensions = Dimensions::new(le_u32(header, 20), le_u32(header, 24), le_u32(header, 28));
+    let layers = le_u32(header, 32);
+    let faces = le_u32(header, 36);
+    let levels = le_u32(header, 40).max(1);
+
+    if le_u32(header, 44) != 0 {
+        throw!(AssetError::Unimplemented("KTX2 supercompression"));
+    }
+
+    let format = match from_vk(vk_format) {
+        Some(format) => format,
+        None => throw!(AssetError::Unimplemented("KTX2 Vulkan format")),
+    };
+
+    Ok(TextureInfo {
+        format: format,
+        kind: texture_kind(&dimensions),
+        dimensions: dimensions,
+        levels: levels,
+        layers: layers,
+        faces: faces,
+    })
+}
+
+fn read_ktx2(data: &[u8]) -> AssetResult<RootTexture> {
+    let layout = try_rethrow!(read_ktx2_header(data));
+
+    let levels = layout.levels;
+
+    let (images_per_level, image_count) = try_rethrow!(checked_image_count(&layout, data.len()));
+
+    // The image count check bounds the level count, so this can't overflow
+    let level_index = try_rethrow!(take_bytes(data, KTX2_HEADER_SIZE, KTX2_LEVEL_INDEX_SIZE * levels as usize));
+
+    let mut images = vec![Vec::new(); image_count];
+
+    for level in 0..levels {
+        let size = layout.image_size(level);
+
+        let level_offset = try_rethrow!(checked_usize(le_u64(level_index, level as usize * KTX2_LEVEL_INDEX_SIZE)));
+        let level_length = try_rethrow!(checked_usize(le_u64(level_index, level as usize * KTX2_LEVEL_INDEX_SIZE + 8)));
+
+        if size.checked_mul(images_per_level) != Some(level_length) {
+            throw!(AssetError::InvalidValue);
+        }
+
+        let level_data = try_rethrow!(take_bytes(data, level_offset, level_length));
+
+        for (image, image_data) in level_data.chunks(size).enumerate() {
+            images[image * levels as usize + level as usize] = image_data.to_vec();
+        }
+    }
+
+    build_root_texture(layout, images)
+}
+
+/// Writes a `RootTexture` as a KTX1 file
+pub fn write_ktx1<W: Write>(mut writer: W, root: &RootTexture) -> AssetResult<()> {
+    let (layout, textures) = try_rethrow!(flatten_root_texture(root));
+
+    let (gl_type, gl_type_size, gl_format, gl_internal_format) = match layout.format.which {
+        Which::None(uncompressed) => {
+            let gl_format = gl_base_format(uncompressed.channels);
+
+            let gl_internal_format = gl_sized_internal_format(uncompressed.channels, uncompressed.data_type, layout.format.srgb);
+
+            (to_gl_type(uncompressed.data_type), type_size(uncompressed.data_type), gl_format, gl_internal_format.unwrap_or(gl_format))
+        },
+        _ => match to_gl_compressed(&layout.format) {
+            Some(gl_internal_format) => (0, 1, 0, gl_internal_format),
+            None => throw!(AssetError::Unimplemented("Texture format with no KTX equivalent")),
+        },
+    };
+
+    let mut header = Vec::with_capacity(KTX1_HEADER_SIZE);
+
+    header.extend_from_slice(&KTX1_IDENTIFIER);
+
+    push_u32(&mut header, ENDIANNESS);
+    push_u32(&mut header, gl_type);
+    push_u32(&mut header, gl_type_size);
+    push_u32(&mut header, gl_format);
+    push_u32(&mut header, gl_internal_format);
+    push_u32(&mut header, gl_base_format(layout.format.which.channels()));
+    push_u32(&mut header, layout.dimensions.width);
+    push_u32(&mut header, layout.dimensions.height);
+    push_u32(&mut header, layout.dimensions.depth);
+    push_u32(&mut header, layout.layers);
+    push_u32(&mut header, layout.faces);
+    push_u32(&mut header, layout.levels);
+    push_u32(&mut header, 0);
+
+    try_throw!(writer.write_all(&header));
+
+    let padding = [0u8; 4];
+
+    for level in 0..layout.levels {
+        let rows = try_rethrow!(ktx1_rows(&layout, level));
+        let size = try_rethrow!(ktx1_image_size(&layout, level));
+
+        // Non-array cubemaps give the size of a single face, everything else the size of the whole level
+        let level_size = if layout.faces == 6 && layout.layers == 0 { size } else { size * textures.len() };
+
+        let mut level_size_bytes = Vec::with_capacity(4);
+
+        push_u32(&mut level_size_bytes, level_size as u32);
+
+        try_throw!(writer.write_all(&level_size_bytes));
+
+        for texture in &textures {
+            let image_data = texture.level(level).unwrap().as_slice();
+
+            match rows {
+                Some((row_bytes, padded_row_bytes, _)) => {
+                    for row in image_data.chunks(row_bytes) {
+                        try_throw!(writer.write_all(row));
+                        try_throw!(writer.write_all(&padding[..padded_row_bytes - row_bytes]));
+                    }
+                },
+                None => try_throw!(writer.write_all(image_data)),
+            }
+        }
+
+        try_throw!(writer.write_all(&padding[..align4(size * textures.len()) - size * textures.len()]));
+    }
+
+    Ok(())
+}
+
+fn gcd(a: usize, b: usize) -> usize {
+    if b == 0 { a } else { gcd(b, a % b) }
+}
+
+/// Builds the basic data format descriptor KTX2 requires to describe the texel layout
+fn data_format_descriptor(format: &SpecificFormat) -> AssetResult<Vec<u8>> {
+    // (bit offset, bit length, channel type, lower, upper)
+    let mut samples: Vec<(u32, u32, u8, u32, u32)> = Vec::new();
+
+    const QUALIFIER_FLOAT: u8 = 0x80;
+    const QUALIFIER_SIGNED: u8 = 0x40;
+    const CHANNEL_ALPHA: u8 = 15;
+
+    let color_model = match format.which {
+        Which::None(uncompressed) => {
+            let channel_ids = [0, 1, 2, CHANNEL_ALPHA];
+
+            let packed: &[(u32, u32)] = match uncompressed.data_type {
+                DataType::UnsignedShort565 => &[(11, 5), (5, 6), (0, 5)][..],
+                DataType::UnsignedShort4444 => &[(12, 4), (8, 4), (4, 4), (0, 4)][..],
+                DataType::UnsignedShort5551 => &[(11, 5), (6, 5), (1, 5), (0, 1)][..],
+                DataType::UnsignedInt8888Rev => &[(0, 8), (8, 8), (16, 8), (24, 8)][..],
+                DataType::UnsignedInt2101010Rev => &[(0, 10), (10, 10), (20, 10), (30, 2)][..],
+                _ => &[][..],
+            };
+
+            if !packed.is_empty() {
+                for (i, &(offset, length)) in packed.iter().enumerate() {
+                    samples.push((offset, length, channel_ids[i], 0, (1u32 << length) - 1));
+                }
+            } else {
+                let bits = type_size(uncompressed.data_type) * 8;
+
+                let (qualifiers, lower, upper) = match uncompressed.data_type {
+                    DataType::Float => (QUALIFIER_FLOAT | QUALIFIER_SIGNED, 0xBF800000, 0x3F800000),
+                    DataType::UnsignedInt => (0, 0, 1),
+                    DataType::Int => (QUALIFIER_SIGNED, 0xFFFFFFFF, 1),
+                    DataType::Byte | DataType::Short => {
+                        let max = (1u32 << (bits - 1)) - 1;
+
+                        (QUALIFIER_SIGNED, (!max).wrapping_add(1), max)
+                    },
+                    _ => (0, 0, ((1u64 << bits) - 1) as u32),
+                };
+
+                for i in 0..uncompressed.channels.num_channels() {
+                    samples.push((i as u32 * bits, bits, channel_ids[i] | qualifiers, lower, upper));
+                }
+            }
+
+            // KHR_DF_MODEL_RGBSDA
+            1
+        },
+        Which::S3tc(s3tc) => match s3tc {
+            S3tc::Rgb1 => { samples.push((0, 64, 0, 0, 0xFFFFFFFF)); 128 },
+            S3tc::Rgba1 => { samples.push((0, 64, 1, 0, 0xFFFFFFFF)); 128 },
+            S3tc::Rgba3 | S3tc::Rgba5 => {
+                samples.push((0, 64, CHANNEL_ALPHA, 0, 0xFFFFFFFF));
+                samples.push((64, 64, 0, 0, 0xFFFFFFFF));
+
+                if s3tc == S3tc::Rgba3 { 129 } else { 130 }
+            },
+        },
+        Which::Rgtc(rgtc) => {
+            let (qualifiers, lower, upper) = if format.which.signed() {
+                (QUALIFIER_SIGNED, 0x80000000, 0x7FFFFFFF)
+            } else {
+                (0, 0, 0xFFFFFFFF)
+            };
+
+            match rgtc {
+                Rgtc::Red | Rgtc::RedSigned => {
+                    samples.push((0, 64, qualifiers, lower, upper));
+
+                    131
+                },
+                Rgtc::Rg | Rgtc::RgSigned => {
+                    samples.push((0, 64, qualifiers, lower, upper));
+                    samples.push((64, 64, 1 | qualifiers, lower, upper));
+
+                    132
+                },
+            }
+        },
+        Which::Bptc(bptc) => match bptc {
+            Bptc::Rgba => { samples.push((0, 128, 0, 0, 0xFFFFFFFF)); 134 },
+            Bptc::RgbFloatSigned => { samples.push((0, 128, QUALIFIER_FLOAT | QUALIFIER_SIGNED, 0xBF800000, 0x3F800000)); 133 },
+            Bptc::RgbFloatUnsigned => { samples.push((0, 128, QUALIFIER_FLOAT, 0, 0x3F800000)); 133 },
+        },
+        Which::Astc(_) => {
+            samples.push((0, 128, 0, 0, 0xFFFFFFFF));
+
+            162
+        },
+    };
+
+    let (block_width, block_height) = format.which.block_dimensions();
+
+    let block_size = 24 + 16 * samples.len() as u32;
+
+    let mut descriptor = Vec::with_capacity(4 + block_size as usize);
+
+    push_u32(&mut descriptor, 4 + block_size);
+
+    // Khronos vendor and basic descriptor type
+    push_u32(&mut descriptor, 0);
+
+    // Version number 2 and descriptor block size
+    push_u32(&mut descriptor, 2 | block_size << 16);
+
+    // Color model, BT.709 primaries, transfer function and straight alpha
+    descriptor.extend_from_slice(&[color_model, 1, if format.srgb { 2 } else { 1 }, 0]);
+
+    descriptor.extend_from_slice(&[(block_width - 1) as u8, (block_height - 1) as u8, 0, 0]);
+
+    descriptor.extend_from_slice(&[format.which.block_bytes() as u8, 0, 0, 0, 0, 0, 0, 0]);
+
+    for (offset, length, channel_type, lower, upper) in samples {
+        push_u32(&mut descriptor, offset | (length - 1) << 16 | (channel_type as u32) << 24);
+        push_u32(&mut descriptor, 0);
+        push_u32(&mut descriptor, lower);
+        push_u32(&mut descriptor, upper);
+    }
+
+    Ok(descriptor)
+}
+
+/// Writes a `RootTexture` as a KTX2 file
+pub fn write_ktx2<W: Write>(mut writer: W, root: &RootTexture) -> AssetResult<()> {
+    let (layout, textures) = try_rethrow!(flatten_root_texture(root));
+
+    let vk_format = match to_vk(&layout.format) {
+        Some(vk_format) => vk_format,
+        None => throw!(AssetError::Unimplemented("Texture format with no KTX2 equivalent")),
+    };
+
+    let element_size = match layout.format.which {
+        Which::None(uncompressed) => type_size(uncompressed.data_type),
+        _ => 1,
+    };
+
+    let descriptor = try_rethrow!(data_format_descriptor(&layout.format));
+
+    let descriptor_offset = KTX2_HEADER_SIZE + KTX2_LEVEL_INDEX_SIZE * layout.levels as usize;
+
+    // Levels must be aligned to both the texel block size and four bytes
+    let block_bytes = layout.format.which.block_bytes();
+    let alignment = block_bytes * 4 / gcd(block_bytes, 4);
+
+    // Levels are stored smallest first, so compute their offsets in that order
+    let mut level_offsets = vec![0; layout.levels as usize];
+    let mut offset = descriptor_offset + descriptor.len();
+
+    for level in (0..layout.levels).rev() {
+        offset = (offset + alignment - 1) / alignment * alignment;
+
+        level_offsets[level as usize] = offset;
+
+        offset += layout.image_size(level) * textures.len();
+    }
+
+    let mut header = Vec::with_capacity(descriptor_offset + descriptor.len());
+
+    header.extend_from_slice(&KTX2_IDENTIFIER);
+
+    push_u32(&mut header, vk_format);
+    push_u32(&mut header, element_size);
+    push_u32(&mut header, layout.dimensions.width);
+    push_u32(&mut header, layout.dimensions.height);
+    push_u32(&mut header, layout.dimensions.depth);
+    push_u32(&mut header, layout.layers);
+    push_u32(&mut header, layout.faces);
+    push_u32(&mut header, layout.levels);
+    push_u32(&mut header, 0);
+
+    push_u32(&mut header, descriptor_offset as u32);
+    push_u32(&mut header, descriptor.len() as u32);
+    push_u32(&mut header, 0);
+    push_u32(&mut header, 0);
+    push_u64(&mut header, 0);
+    push_u64(&mut header, 0);
+
+    for level in 0..layout.levels {
+        let length = (layout.image_size(level) * textures.len()) as u64;
+
+        push_u64(&mut header, level_offsets[level as usize] as u64);
+        push_u64(&mut header, length);
+        push_u64(&mut header, length);
+    }
+
+    header.extend_from_slice(&descriptor);
+
+    try_throw!(writer.write_all(&header));
+
+    let mut position = header.len();
+
+    for level in (0..layout.levels).rev() {
+        let padding = level_offsets[level as usize] - position;
+
+        try_throw!(writer.write_all(&vec![0u8; padding]));
+
+        for texture in &textures {
+            try_throw!(writer.write_all(texture.level(level).unwrap().as_slice()));
+        }
+
+        position += padding + layout.image_size(level) * textures.len();
+    }
+
+    Ok(())
+}
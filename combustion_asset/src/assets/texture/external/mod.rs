@@ -0,0 +1,373 @@
+//! External texture container and image import/export routines
+//!
+//! DDS and KTX both store a flat list of images ordered by array layer, cubemap face and mipmap level,
+//! so the conversion to and from `RootTexture` is shared here.
+//!
+//! TIFF, Radiance HDR and OpenEXR cover high bit depth and float images the `image` crate can't write.
+
+// Must be defined before the submodules to be visible inside them
+/// Generates `$from` and `$to` functions mapping between container format codes and `SpecificFormat`s
+///
+/// When several codes map to the same format, the first one listed is used for writing.
+macro_rules! format_table {
+    ($from:ident, $to:ident { $($code:expr => $format:expr,)* }) => {
+        fn $from(code: u32) -> Option<SpecificFormat> {
+            $(if code == $code { return Some($format); })*
+
+            None
+        }
+
+        fn $to(format: &SpecificFormat) -> Option<u32> {
+            $(if *format == $format { return Some($code); })*
+
+            None
+        }
+    }
+}
+
+pub mod dds;
+pub mod ktx;
+pub mod tiff;
+pub mod hdr;
+pub mod exr;
+
+use protocols::texture::protocol::{Channels, DataType, TextureKind};
+use protocols::texture::protocol::{Rgtc, Bptc, S3tc, BlockSize};
+use protocols::texture::data::format::{SpecificFormat, Which, Uncompressed};
+use protocols::texture::data::texture::{RootTexture, Texture, TextureInfo, Cubemap, Dimensions};
+
+use ::error::{AssetResult, AssetError};
+
+/// Assembles a `RootTexture` from images ordered by layer, then face, then mipmap level
+pub fn build_root_texture(layout: TextureInfo, images: Vec<Vec<u8>>) -> AssetResult<RootTexture> {
+    let (_, expected) = try_rethrow!(checked_image_count(&layout, images.len()));
+
+    if images.len() != expected || layout.levels == 0 {
+        throw!(AssetError::InvalidValue);
+    }
+
+    let mut textures = Vec::with_capacity(expected / layout.levels as usize);
+
+    let mut images = images.into_iter();
+
+    for _ in 0..(expected / layout.levels as usize) {
+        let data = images.next().unwrap();
+
+        textures.push(Texture {
+            data: data.into(),
+            dimensions: layout.dimensions,
+            kind: layout.kind,
+            format: layout.format,
+            mipmaps: images.by_ref().take(layout.levels as usize - 1).map(|mipmap| mipmap.into()).collect(),
+        });
+    }
+
+    if layout.faces == 6 {
+        if layout.layers > 1 {
+            throw!(AssetError::Unimplemented("Arrays of cubemaps"));
+        }
+
+        let mut faces = textures.into_iter();
+
+        // Faces are always stored in +X, -X, +Y, -Y, +Z, -Z order
+        return Ok(RootTexture::Cubemap(box Cubemap {
+            right: faces.next().unwrap(),
+            left: faces.next().unwrap(),
+            top: faces.next().unwrap(),
+            bottom: faces.next().unwrap(),
+            back: faces.next().unwrap(),
+            front: faces.next().unwrap(),
+        }));
+    } else if layout.faces != 1 {
+        throw!(AssetError::InvalidValue);
+    }
+
+    Ok(if layout.layers == 0 {
+        RootTexture::Texture(box textures.pop().unwrap())
+    } else {
+        RootTexture::Array(textures)
+    })
+}
+
+/// Splits a `RootTexture` into its layout and its textures ordered by layer, then face
+///
+/// Every texture must share the same format, kind, dimensions and number of mipmap levels.
+pub fn flatten_root_texture(root: &RootTexture) -> AssetResult<(TextureInfo, Vec<&Texture>)> {
+    let (textures, layers, faces) = match *root {
+        RootTexture::Texture(ref texture) => (vec![&**texture], 0, 1),
+        RootTexture::Cubemap(ref cubemap) => {
+            (vec![&cubemap.right, &cubemap.left, &cubemap.top, &cubemap.bottom, &cubemap.back, &cubemap.front], 0, 6)
+        },
+        RootTexture::Array(ref array) => (array.iter().collect::<Vec<_>>(), array.len() as u32, 1),
+    };
+
+    let layout = match textures.first() {
+        Some(first) => TextureInfo {
+            format: first.format,
+            kind: first.kind,
+            dimensions: first.dimensions,
+            levels: first.levels(),
+            layers: layers,
+            faces: faces,
+        },
+        None => throw!(AssetError::InvalidValue),
+    };
+
+    for texture in &textures {
+        if texture.format != layout.format || texture.kind != layout.kind ||
+            texture.dimensions != layout.dimensions || texture.levels() != layout.levels {
+            throw!(AssetError::Unimplemented("Textures with mismatched formats or dimensions in a single container"));
+        }
+
+        for level in 0..layout.levels {
+            if texture.level(level).map(|data| data.len()) != Some(layout.image_size(level)) {
+                throw!(AssetError::InvalidValue);
+            }
+        }
+    }
+
+    Ok((layout, textures))
+}
+
+/// Creates an uncompressed `SpecificFormat`
+pub fn uncompressed(channels: Channels, data_type: DataType, srgb: bool) -> SpecificFormat {
+    SpecificFormat { which: Which::None(Uncompressed::new(channels, data_type)), srgb: srgb }
+}
+
+/// Creates an S3TC `SpecificFormat`
+pub fn s3tc(s3tc: S3tc, srgb: bool) -> SpecificFormat {
+    SpecificFormat { which: Which::S3tc(s3tc), srgb: srgb }
+}
+
+/// Creates an RGTC `SpecificFormat`
+pub fn rgtc(rgtc: Rgtc) -> SpecificFormat {
+    SpecificFormat { which: Which::Rgtc(rgtc), srgb: false }
+}
+
+/// Creates a BPTC `SpecificFormat`
+pub fn bptc(bptc: Bptc, srgb: bool) -> SpecificFormat {
+    SpecificFormat { which: Which::Bptc(bptc), srgb: srgb }
+}
+
+/// Creates an ASTC `SpecificFormat`
+pub fn astc(blocksize: BlockSize, srgb: bool) -> SpecificFormat {
+    SpecificFormat { which: Which::Astc(blocksize), srgb: srgb }
+}
+
+/// Reads a little-endian `u32` at `offset`
+#[inline]
+pub fn le_u32(bytes: &[u8], offset: usize) -> u32 {
+    bytes[offset] as u32 | (bytes[offset + 1] as u32) << 8 | (bytes[offset + 2] as u32) << 16 | (bytes[offset + 3] as u32) << 24
+}
+
+/// Reads a little-endian `u64` at `offset`
+#[inline]
+pub fn le_u64(bytes: &[u8], offset: usize) -> u64 {
+    le_u32(bytes, offset) as u64 | (le_u32(bytes, offset + 4) as u64) << 32
+}
+
+/// Appends a little-endian `u32`
+#[inline]
+pub fn push_u32(buffer: &mut Vec<u8>, value: u32) {
+    buffer.extend_from_slice(&[value as u8, (value >> 8) as u8, (value >> 16) as u8, (value >> 24) as u8]);
+}
+
+/// Appends a little-endian `u64`
+#[inline]
+pub fn push_u64(buffer: &mut Vec<u8>, value: u64) {
+    push_u32(buffer, value as u32);
+    push_u32(buffer, (value >> 32) as u32);
+}
+
+/// Takes `len` bytes from `data` at `offset`, failing if the data is too short
+pub fn take_bytes(data: &[u8], offset: usize, len: usize) -> AssetResult<&[u8]> {
+    match offset.checked_add(len) {
+        Some(end) if end <= data.len() => Ok(&data[offset..end]),
+        _ => throw!(AssetError::InvalidValue),
+    }
+}
+
+/// Number of images per mipmap level and in total described by `layout`
+///
+/// Every image takes at least one byte, so layouts with more images than `available` bytes are rejected
+/// before anything is allocated for them.
+pub fn checked_image_count(layout: &TextureInfo, available: usize) -> AssetResult<(usize, usize)> {
+    let per_level = (layout.layer_count() as usize).checked_mul(layout.faces as usize);
+    let total = per_level.and_then(|per_level| per_level.checked_mul(layout.levels as usize));
+
+    match (per_level, total) {
+        (Some(per_level), Some(total)) if total <= available => Ok((per_level, total)),
+        _ => throw!(AssetError::InvalidValue),
+    }
+}
+
+/// Converts an offset or length read from a file into a `usize`
+pub fn checked_usize(value: u64) -> AssetResult<usize> {
+    if value > usize::max_value() as u64 {
+        throw!(AssetError::InvalidValue);
+    }
+
+    Ok(value as usize)
+}
+
+#[cfg(test)]
+mod test {
+    use protocols::texture::protocol::{Channels, DataType, TextureKind};
+    use protocols::texture::data::texture::{RootTexture, Texture, Dimensions};
+
+    use super::*;
+
+    // 3x3 RGB rows aren't four byte aligned, which exercises KTX1 row padding
+    fn sample_texture() -> RootTexture {
+        let format = uncompressed(Channels::Rgb, DataType::UnsignedByte, true);
+
+        RootTexture::Array((0..2).map(|layer| Texture {
+            data: (0..27).map(|i| i + layer).collect::<Vec<u8>>().into(),
+            dimensions: Dimensions::new(3, 3, 0),
+            kind: TextureKind::Texture2D,
+            format: format,
+            mipmaps: vec![vec![layer, 1, 2].into()],
+        }).collect())
+    }
+
+    fn assert_same(a: &RootTexture, b: &RootTexture) {
+        let (a_layout, a_textures) = flatten_root_texture(a).unwrap();
+        let (b_layout, b_textures) = flatten_root_texture(b).unwrap();
+
+        assert_eq!(a_layout, b_layout);
+
+        for (a, b) in a_textures.iter().zip(b_textures.iter()) {
+            for level in 0..a_layout.levels {
+                assert_eq!(a.level(level).unwrap().as_slice(), b.level(level).unwrap().as_slice());
+            }
+        }
+    }
+
+    #[test]
+    fn container_round_trip() {
+        let texture = sample_texture();
+
+        let mut ktx1 = Vec::new();
+        let mut ktx2 = Vec::new();
+
+        ktx::write_ktx1(&mut ktx1, &texture).unwrap();
+        ktx::write_ktx2(&mut ktx2, &texture).unwrap();
+
+        assert_same(&texture, &ktx::read_ktx(&ktx1[..]).unwrap());
+        assert_same(&texture, &ktx::read_ktx(&ktx2[..]).unwrap());
+
+        // Plain RGB is written with the legacy header, which can't represent arrays
+        if let RootTexture::Array(mut array) = texture {
+            let single = RootTexture::Texture(box array.remove(0));
+
+            let mut dds = Vec::new();
+
+            dds::write_dds(&mut dds, &single).unwrap();
+
+            assert_same(&single, &dds::read_dds(&dds[..], true).unwrap());
+        }
+    }
+
+    #[test]
+    fn container_info() {
+        let texture = sample_texture();
+
+        let mut ktx1 = Vec::new();
+        let mut ktx2 = Vec::new();
+
+        ktx::write_ktx1(&mut ktx1, &texture).unwrap();
+        ktx::write_ktx2(&mut ktx2, &texture).unwrap();
+
+        let info = texture.info().unwrap();
+
+        assert_eq!(info.layers, 2);
+        assert_eq!(info.levels, 2);
+
+        assert_eq!(ktx::read_ktx_info(&ktx1[..]).unwrap(), info);
+        assert_eq!(ktx::read_ktx_info(&ktx2[..]).unwrap(), info);
+
+        if let RootTexture::Array(mut array) = texture {
+            let single = RootTexture::Texture(box array.remove(0));
+
+            let mut dds = Vec::new();
+
+            dds::write_dds(&mut dds, &single).unwrap();
+
+            // Only the headers are needed, so truncated files still work
+            assert_eq!(dds::read_dds_info(&dds[..128], true).unwrap(), single.info().unwrap());
+        }
+    }
+
+    fn patch_u32(data: &[u8], offset: usize, value: u32) -> Vec<u8> {
+        let mut data = data.to_vec();
+
+        data[offset..offset + 4].copy_from_slice(&[value as u8, (value >> 8) as u8, (value >> 16) as u8, (value >> 24) as u8]);
+
+        data
+    }
+
+    #[test]
+    fn container_malformed_headers() {
+        let texture = sample_texture();
+
+        let mut ktx1 = Vec::new();
+        let mut ktx2 = Vec::new();
+
+        ktx::write_ktx1(&mut ktx1, &texture).unwrap();
+        ktx::write_ktx2(&mut ktx2, &texture).unwrap();
+
+        // KTX1 layer, face, level and key/value data counts, then the width
+        for &offset in &[48, 52, 56, 60, 36] {
+            assert!(ktx::read_ktx(&patch_u32(&ktx1, offset, u32::max_value())[..]).is_err());
+        }
+
+        assert!(ktx::read_ktx(&ktx1[..ktx1.len() - 1]).is_err());
+
+        // KTX2 layer and level counts, then the low and high words of the first level's offset and length
+        for &offset in &[32, 40, 80, 84, 88, 92] {
+            assert!(ktx::read_ktx(&patch_u32(&ktx2, offset, u32::max_value())[..]).is_err());
+        }
+
+        assert!(ktx::read_ktx(&ktx2[..ktx2.len() - 1]).is_err());
+
+        if let RootTexture::Array(mut array) = texture {
+            let single = RootTexture::Texture(box array.remove(0));
+
+            let mut dds = Vec::new();
+
+            dds::write_dds(&mut dds, &single).unwrap();
+
+            // Mipmap count, width and height
+            for &offset in &[32, 20, 16] {
+                assert!(dds::read_dds(&patch_u32(&dds, offset, u32::max_value())[..], true).is_err());
+            }
+
+            assert!(dds::read_dds(&dds[..dds.len() - 1], true).is_err());
+        }
+    }
+
+    #[test]
+    fn exr_round_trip() {
+        let values = [0.0, 0.25, 1.5, 1.0, -2.0, 100.0, 0.5, 0.0, 3.0, 2.0, 1.0, 0.75];
+
+        let texture = Texture {
+            data: ::assets::texture::pixels::encode_values(&values, DataType::Float).unwrap().into(),
+            dimensions: Dimensions::new(3, 1, 0),
+            kind: TextureKind::Texture2D,
+            format: uncompressed(Channels::Rgba, DataType::Float, false),
+            mipmaps: Vec::new(),
+        };
+
+        let mut exr = Vec::new();
+
+        exr::write_exr(&mut exr, &texture).unwrap();
+
+        let read = exr::read_exr(&exr[..]).unwrap();
+
+        assert_eq!(read.format, texture.format);
+        assert_eq!(read.dimensions, texture.dimensions);
+        assert_eq!(read.data.as_slice(), texture.data.as_slice());
+
+        assert!(exr::read_exr(&exr[..exr.len() - 1]).is_err());
+    }
+}
@@ -0,0 +1,166 @@
+//! Baseline TIFF export
+//!
+//! The `image` crate can read TIFF files but not write them, so uncompressed little-endian TIFFs
+//! with a single strip are written here. 8-bit, 16-bit and 32-bit float samples are supported.
+
+use std::io::Write;
+
+use protocols::texture::protocol::{Channels, DataType, TextureKind};
+use protocols::texture::data::format::Which;
+use protocols::texture::data::texture::Texture;
+
+use ::error::{AssetResult, AssetError};
+
+use super::push_u32;
+
+const TYPE_SHORT: u16 = 3;
+const TYPE_LONG: u16 = 4;
+
+const TAG_IMAGE_WIDTH: u16 = 256;
+const TAG_IMAGE_LENGTH: u16 = 257;
+const TAG_BITS_PER_SAMPLE: u16 = 258;
+const TAG_COMPRESSION: u16 = 259;
+const TAG_PHOTOMETRIC_INTERPRETATION: u16 = 262;
+const TAG_STRIP_OFFSETS: u16 = 273;
+const TAG_SAMPLES_PER_PIXEL: u16 = 277;
+const TAG_ROWS_PER_STRIP: u16 = 278;
+const TAG_STRIP_BYTE_COUNTS: u16 = 279;
+const TAG_PLANAR_CONFIGURATION: u16 = 284;
+const TAG_EXTRA_SAMPLES: u16 = 338;
+const TAG_SAMPLE_FORMAT: u16 = 339;
+
+const PHOTOMETRIC_BLACK_IS_ZERO: u16 = 1;
+const PHOTOMETRIC_RGB: u16 = 2;
+
+const EXTRA_SAMPLES_UNASSOCIATED_ALPHA: u16 = 2;
+
+const SAMPLE_FORMAT_UINT: u16 = 1;
+const SAMPLE_FORMAT_IEEE_FP: u16 = 3;
+
+/// Single IFD entry, with values that don't fit in four bytes stored out of line
+struct Entry {
+    tag: u16,
+    field_type: u16,
+    count: u32,
+    values: Vec<u8>,
+}
+
+impl Entry {
+    fn long(tag: u16, value: u32) -> Entry {
+        let mut values = Vec::with_capacity(4);
+
+        push_u32(&mut values, value);
+
+        Entry { tag: tag, field_type: TYPE_LONG, count: 1, values: values }
+    }
+
+    fn shorts(tag: u16, shorts: &[u16]) -> Entry {
+        Entry {
+            tag: tag,
+            field_type: TYPE_SHORT,
+            count: shorts.len() as u32,
+            values: shorts.iter().flat_map(|&value| vec![value as u8, (value >> 8) as u8]).collect(),
+        }
+    }
+}
+
+/// Writes a single uncompressed 1D or 2D texture as a TIFF image, ignoring any mipmaps
+pub fn write_tiff<W: Write>(mut writer: W, texture: &Texture) -> AssetResult<()> {
+    let uncompressed = match texture.format.which {
+        Which::None(uncompressed) => uncompressed,
+        _ => throw!(AssetError::Unimplemented("Saving compressed textures to standard image formats")),
+    };
+
+    if texture.kind == TextureKind::Texture3D {
+        throw!(AssetError::Unimplemented("3D texture exporting to standard image formats"));
+    }
+
+    let (bits, sample_format) = match uncompressed.data_type {
+        DataType::UnsignedByte => (8, SAMPLE_FORMAT_UINT),
+        DataType::UnsignedShort => (16, SAMPLE_FORMAT_UINT),
+        DataType::Float => (32, SAMPLE_FORMAT_IEEE_FP),
+        _ => throw!(AssetError::Unimplemented("Exporting textures with this data type to TIFF")),
+    };
+
+    let (width, height) = (texture.dimensions.width, texture.dimensions.height.max(1));
+
+    let samples = uncompressed.channels.num_channels();
+
+    let data = texture.data.as_slice();
+
+    if data.len() != width as usize * height as usize * samples * (bits as usize / 8) {
+        throw!(AssetError::InvalidValue);
+    }
+
+    let photometric = match uncompressed.channels {
+        Channels::R | Channels::Rg => PHOTOMETRIC_BLACK_IS_ZERO,
+        Channels::Rgb | Channels::Rgba => PHOTOMETRIC_RGB,
+    };
+
+    let mut entries = vec![
+        Entry::long(TAG_IMAGE_WIDTH, width),
+        Entry::long(TAG_IMAGE_LENGTH, height),
+        Entry::shorts(TAG_BITS_PER_SAMPLE, &vec![bits; samples]),
+        Entry::shorts(TAG_COMPRESSION, &[1]),
+        Entry::shorts(TAG_PHOTOMETRIC_INTERPRETATION, &[photometric]),
+        // Placeholder, filled in once the size of the header is known
+        Entry::long(TAG_STRIP_OFFSETS, 0),
+        Entry::shorts(TAG_SAMPLES_PER_PIXEL, &[samples as u16]),
+        Entry::long(TAG_ROWS_PER_STRIP, height),
+        Entry::long(TAG_STRIP_BYTE_COUNTS, data.len() as u32),
+        Entry::shorts(TAG_PLANAR_CONFIGURATION, &[1]),
+    ];
+
+    if uncompressed.channels == Channels::Rg || uncompressed.channels == Channels::Rgba {
+        entries.push(Entry::shorts(TAG_EXTRA_SAMPLES, &[EXTRA_SAMPLES_UNASSOCIATED_ALPHA]));
+    }
+
+    entries.push(Entry::shorts(TAG_SAMPLE_FORMAT, &vec![sample_format; samples]));
+
+    // Header, then the IFD, then out of line values, then the pixel data
+    let ifd_size = 2 + entries.len() * 12 + 4;
+
+    let extra_size: usize = entries.iter().filter(|entry| entry.values.len() > 4).map(|entry| entry.values.len()).sum();
+
+    let data_offset = 8 + ifd_size + extra_size;
+
+    entries[5] = Entry::long(TAG_STRIP_OFFSETS, data_offset as u32);
+
+    let mut header = Vec::with_capacity(data_offset);
+
+    header.extend_from_slice(b"II");
+    header.extend_from_slice(&[42, 0]);
+    push_u32(&mut header, 8);
+
+    header.extend_from_slice(&[entries.len() as u8, (entries.len() >> 8) as u8]);
+
+    let mut extra = Vec::with_capacity(extra_size);
+
+    for entry in &entries {
+        header.extend_from_slice(&[entry.tag as u8, (entry.tag >> 8) as u8, entry.field_type as u8, (entry.field_type >> 8) as u8]);
+
+        push_u32(&mut header, entry.count);
+
+        if entry.values.len() > 4 {
+            push_u32(&mut header, (8 + ifd_size + extra.len()) as u32);
+
+            extra.extend_from_slice(&entry.values);
+        } else {
+            let mut value = entry.values.clone();
+
+            value.resize(4, 0);
+
+            header.extend_from_slice(&value);
+        }
+    }
+
+    // No further IFDs
+    push_u32(&mut header, 0);
+
+    header.extend_from_slice(&extra);
+
+    try_throw!(writer.write_all(&header));
+    try_throw!(writer.write_all(data));
+
+    Ok(())
+}
@@ -13,6 +13,14 @@ use ::assets::standard::formats::StandardFileFormat;
 pub enum TextureFileFormat {
     /// Native Combustion file format
     Native,
+    /// DirectDraw Surface container, with or without the DX10 header
+    Dds,
+    /// Khronos Texture container version 1. KTX2 files using this extension are also read.
+    Ktx,
+    /// Khronos Texture container version 2
+    Ktx2,
+    /// OpenEXR image, uncompressed scanline files only
+    OpenExr,
     /// Images that can be used with the `image` library
     Image(ImageFormat),
     /// Any standard file format
@@ -56,7 +64,9 @@ impl AssetFileFormat for ImageFormat {
             ImageFormat::ICO |
             ImageFormat::JPEG |
             ImageFormat::PNG |
-            ImageFormat::PPM => true,
+            ImageFormat::PPM |
+            ImageFormat::TIFF |
+            ImageFormat::HDR => true,
             _ => false,
         }
     }
@@ -66,6 +76,14 @@ impl AssetFileFormat for TextureFileFormat {
     fn from_extension(ext: &str) -> Option<TextureFileFormat> {
         Some(if ext == EXTENSION {
             TextureFileFormat::Native
+        } else if ext == "dds" {
+            TextureFileFormat::Dds
+        } else if ext == "ktx" {
+            TextureFileFormat::Ktx
+        } else if ext == "ktx2" {
+            TextureFileFormat::Ktx2
+        } else if ext == "exr" {
+            TextureFileFormat::OpenExr
         } else if let Some(image_format) = ImageFormat::from_extension(ext) {
             TextureFileFormat::Image(image_format)
         } else if let Some(standard_format) = StandardFileFormat::from_extension(ext) {
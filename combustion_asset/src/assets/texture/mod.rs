@@ -2,5 +2,9 @@
 
 pub mod formats;
 pub mod asset;
+pub mod external;
+pub mod cubemap;
+pub mod pixels;
+pub mod bc6h;
 
-pub use self::asset::{TextureAsset, TextureAssetQuery, TextureAssetLoadArgs, TextureAssetSaveArgs};
\ No newline at end of file
+pub use self::asset::{TextureAsset, TextureAssetQuery, TextureAssetQueryResult, TextureAssetLoadArgs, TextureAssetSaveArgs};
\ No newline at end of file
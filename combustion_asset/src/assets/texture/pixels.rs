@@ -0,0 +1,103 @@
+//! Conversion between raw texture data and floating point channel values
+//!
+//! Multi-byte values in texture data are always stored little-endian.
+//! Unsigned integer values are normalized to the 0-1 range, while floats are passed through as-is.
+
+use std::mem;
+
+use protocols::texture::protocol::DataType;
+
+use ::error::{AssetResult, AssetError};
+
+/// Decodes raw texture data into one `f32` per channel value
+pub fn decode_values(data: &[u8], data_type: DataType) -> AssetResult<Vec<f32>> {
+    let size = match data_type {
+        DataType::UnsignedShort => 2,
+        DataType::Float => 4,
+        _ => 1,
+    };
+
+    if data.len() % size != 0 {
+        throw!(AssetError::InvalidValue);
+    }
+
+    Ok(match data_type {
+        DataType::UnsignedByte => data.iter().map(|&value| value as f32 / 255.0).collect(),
+        DataType::UnsignedShort => {
+            data.chunks(2).map(|value| (value[0] as u16 | (value[1] as u16) << 8) as f32 / 65535.0).collect()
+        },
+        DataType::Float => {
+            data.chunks(4).map(|value| {
+                f32_from_bits(value[0] as u32 | (value[1] as u32) << 8 | (value[2] as u32) << 16 | (value[3] as u32) << 24)
+            }).collect()
+        },
+        _ => throw!(AssetError::Unimplemented("Converting textures with this data type")),
+    })
+}
+
+/// Encodes channel values into raw texture data, clamping integer types to the 0-1 range
+pub fn encode_values(values: &[f32], data_type: DataType) -> AssetResult<Vec<u8>> {
+    let mut data = Vec::with_capacity(values.len() * 4);
+
+    for &value in values {
+        match data_type {
+            DataType::UnsignedByte => data.push((value.max(0.0).min(1.0) * 255.0).round() as u8),
+            DataType::UnsignedShort => {
+                let value = (value.max(0.0).min(1.0) * 65535.0).round() as u16;
+
+                data.extend_from_slice(&[value as u8, (value >> 8) as u8]);
+            },
+            DataType::Float => {
+                let bits = f32_to_bits(value);
+
+                data.extend_from_slice(&[bits as u8, (bits >> 8) as u8, (bits >> 16) as u8, (bits >> 24) as u8]);
+            },
+            _ => throw!(AssetError::Unimplemented("Converting textures with this data type")),
+        }
+    }
+
+    Ok(data)
+}
+
+/// Reverses the byte order of every 16-bit value, converting little-endian texture data to big-endian and back
+pub fn swap_u16_bytes(data: &[u8]) -> Vec<u8> {
+    let mut swapped = Vec::with_capacity(data.len());
+
+    for value in data.chunks(2) {
+        if value.len() == 2 {
+            swapped.extend_from_slice(&[value[1], value[0]]);
+        }
+    }
+
+    swapped
+}
+
+/// Converts a half-precision float to `f32`
+pub fn half_to_f32(half: u16) -> f32 {
+    let sign = (half as u32 & 0x8000) << 16;
+    let exponent = (half as u32 >> 10) & 0x1F;
+    let mantissa = half as u32 & 0x3FF;
+
+    match exponent {
+        0 => {
+            // Zero or subnormal, which are exact multiples of 2^-24
+            let value = mantissa as f32 * (1.0 / 16777216.0);
+
+            if sign != 0 { -value } else { value }
+        },
+        0x1F => f32_from_bits(sign | 0x7F80_0000 | (mantissa << 13)),
+        _ => f32_from_bits(sign | ((exponent + 112) << 23) | (mantissa << 13)),
+    }
+}
+
+/// Reinterprets the bits of an `f32`
+#[inline]
+pub fn f32_to_bits(value: f32) -> u32 {
+    unsafe { mem::transmute::<f32, u32>(value) }
+}
+
+/// Reinterprets bits as an `f32`
+#[inline]
+pub fn f32_from_bits(bits: u32) -> f32 {
+    unsafe { mem::transmute::<u32, f32>(bits) }
+}
@@ -1,32 +1,562 @@
 //! Types and traits for asset caching
+//!
+//! Cached assets are handed out as shared `AssetHandle`s. The cache itself holds one reference to each asset,
+//! so an asset is considered unreferenced when the cache's reference is the only one left.
+//!
+//! When the approximate memory used by all cached assets exceeds the configured budget,
+//! the least-recently-used unreferenced assets are evicted until the cache fits within the budget again.
 
 use std::marker::PhantomData;
-use std::hash::Hash;
+use std::hash::{Hash, Hasher};
+use std::fmt::{Debug, Formatter, Result as FmtResult};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard, Condvar};
 
 use fnv::FnvHashMap;
 
+use common::vfs::BoxedVFS;
+
+use ::error::AssetResult;
+use ::asset::{Asset, AssetMedium};
+
+/// Shared handle to a cached asset
+pub type AssetHandle<T> = Arc<T>;
+
+/// Trait for assets that can report roughly how much memory they use
+pub trait AssetSize {
+    /// Approximate size of the asset in memory, in bytes
+    fn approximate_size(&self) -> usize;
+}
+
+/// Hit/miss and memory statistics for a cache
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct CacheStats {
+    /// Number of lookups that found a cached asset
+    pub hits: u64,
+    /// Number of lookups that had to load the asset
+    pub misses: u64,
+    /// Number of assets evicted to stay within the memory budget
+    pub evictions: u64,
+    /// Number of assets currently cached
+    pub entries: usize,
+    /// Approximate memory used by all cached assets, in bytes
+    pub memory: usize,
+}
+
+impl CacheStats {
+    /// Ratio of hits to total lookups, or `0.0` if there have been no lookups
+    pub fn hit_ratio(&self) -> f64 {
+        let total = self.hits + self.misses;
+
+        if total == 0 { 0.0 } else { self.hits as f64 / total as f64 }
+    }
+}
+
 /// Some cache type
 pub trait AssetCache<'a> {
     /// The Asset type being cached
     type Asset: 'a;
+    /// Key type the assets are cached by
+    type Key;
+
+    /// Get a handle to a cached asset, if it exists
+    fn get(&self, key: &Self::Key) -> Option<AssetHandle<Self::Asset>>;
+
+    /// Insert an asset into the cache, returning a handle to it.
+    ///
+    /// Any existing asset with the same key is replaced.
+    fn insert(&self, key: Self::Key, asset: Self::Asset) -> AssetHandle<Self::Asset>;
+
+    /// Remove an asset from the cache, returning the cache's handle to it
+    fn remove(&self, key: &Self::Key) -> Option<AssetHandle<Self::Asset>>;
+
+    /// Remove all assets from the cache
+    fn clear(&self);
+
+    /// Get cache statistics
+    fn stats(&self) -> CacheStats;
 }
 
-/// Hashmap cache structure
+/// Cache key for an asset loaded from a file on some virtual filesystem with specific load arguments
+///
+/// The virtual filesystem is identified by its shared instance, which the key keeps alive,
+/// so the same path on two different `BoxedVFS` instances is cached separately.
 #[derive(Debug, Clone)]
+pub struct AssetKey<L> {
+    vfs: Arc<BoxedVFS>,
+    /// Path of the asset on the virtual filesystem
+    pub path: PathBuf,
+    /// Arguments the asset was loaded with
+    pub args: L,
+}
+
+impl<L> AssetKey<L> {
+    /// Create a new `AssetKey`
+    pub fn new(path: &Path, vfs: &Arc<BoxedVFS>, args: L) -> AssetKey<L> {
+        AssetKey {
+            vfs: vfs.clone(),
+            path: path.to_path_buf(),
+            args: args,
+        }
+    }
+}
+
+impl<L: PartialEq> PartialEq for AssetKey<L> {
+    fn eq(&self, other: &AssetKey<L>) -> bool {
+        Arc::ptr_eq(&self.vfs, &other.vfs) && self.path == other.path && self.args == other.args
+    }
+}
+
+impl<L: Eq> Eq for AssetKey<L> {}
+
+impl<L: Hash> Hash for AssetKey<L> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        // The key holds a reference to the instance, so its address can't be reused while the key exists
+        (&*self.vfs as *const BoxedVFS as usize).hash(state);
+        self.path.hash(state);
+        self.args.hash(state);
+    }
+}
+
+enum Slot<T> {
+    // Some thread is currently loading the asset
+    Loading,
+    Loaded {
+        asset: AssetHandle<T>,
+        size: usize,
+        last_used: u64,
+    },
+}
+
+struct CacheState<K, T> where K: Hash + Eq {
+    slots: FnvHashMap<K, Slot<T>>,
+    budget: Option<usize>,
+    memory: usize,
+    tick: u64,
+    hits: u64,
+    misses: u64,
+    evictions: u64,
+}
+
+impl<K, T> CacheState<K, T> where K: Hash + Eq + Clone, T: AssetSize {
+    fn next_tick(&mut self) -> u64 {
+        self.tick += 1;
+        self.tick
+    }
+
+    fn insert(&mut self, key: K, asset: T) -> AssetHandle<T> {
+        let size = asset.approximate_size();
+        let asset = Arc::new(asset);
+        let last_used = self.next_tick();
+
+        let previous = self.slots.insert(key, Slot::Loaded {
+            asset: asset.clone(),
+            size: size,
+            last_used: last_used,
+        });
+
+        if let Some(Slot::Loaded { size: previous_size, .. }) = previous {
+            self.memory -= previous_size;
+        }
+
+        self.memory += size;
+
+        self.evict();
+
+        asset
+    }
+
+    fn remove(&mut self, key: &K) -> Option<AssetHandle<T>> {
+        match self.slots.remove(key) {
+            Some(Slot::Loaded { asset, size, .. }) => {
+                self.memory -= size;
+                Some(asset)
+            },
+            Some(Slot::Loading) => {
+                // Don't disturb an in-progress load
+                self.slots.insert(key.clone(), Slot::Loading);
+                None
+            },
+            None => None,
+        }
+    }
+
+    /// Evict least-recently-used unreferenced assets until within the memory budget
+    fn evict(&mut self) {
+        let budget = match self.budget {
+            Some(budget) => budget,
+            None => return,
+        };
+
+        while self.memory > budget {
+            let key = {
+                let mut lru: Option<(&K, u64)> = None;
+
+                for (key, slot) in self.slots.iter() {
+                    if let Slot::Loaded { ref asset, last_used, .. } = *slot {
+                        // Only the cache holds a reference, so it's safe to drop
+                        if Arc::strong_count(asset) == 1 {
+                            match lru {
+                                Some((_, lru_used)) if lru_used <= last_used => {},
+                                _ => { lru = Some((key, last_used)); }
+                            }
+                        }
+                    }
+                }
+
+                match lru {
+                    Some((key, _)) => key.clone(),
+                    // Everything left is still in use
+                    None => break,
+                }
+            };
+
+            if self.remove(&key).is_some() {
+                self.evictions += 1;
+            }
+        }
+    }
+
+    fn stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.hits,
+            misses: self.misses,
+            evictions: self.evictions,
+            entries: self.slots.values().filter(|slot| matches!(**slot, Slot::Loaded { .. })).count(),
+            memory: self.memory,
+        }
+    }
+}
+
+/// Hashmap cache structure
+///
+/// This is safe to share between threads, and concurrent loads of the same asset
+/// will only load it once while the other threads wait for the result.
 pub struct AssetHashMapCache<'a, K, T: 'a> where K: Hash + PartialEq + Eq {
-    map: FnvHashMap<K, T>,
+    state: Mutex<CacheState<K, T>>,
+    loaded: Condvar,
     _marker: PhantomData<&'a ()>,
 }
 
-impl<'a, K, T: 'a> AssetCache<'a> for AssetHashMapCache<'a, K, T> where K: Hash + PartialEq + Eq {
-    type Asset = T;
-}
+impl<'a, K, T: 'a> AssetHashMapCache<'a, K, T> where K: Hash + Eq + Clone, T: AssetSize {
+    /// Create a new cache with no memory budget
+    pub fn new() -> AssetHashMapCache<'a, K, T> {
+        AssetHashMapCache::with_budget(None)
+    }
 
-impl<'a, K, T: 'a> Default for AssetHashMapCache<'a, K, T> where K: Hash + PartialEq + Eq {
-    fn default() -> AssetHashMapCache<'a, K, T> {
+    /// Create a new cache with the given memory budget in bytes
+    pub fn with_budget(budget: Option<usize>) -> AssetHashMapCache<'a, K, T> {
         AssetHashMapCache {
-            map: FnvHashMap::default(),
+            state: Mutex::new(CacheState {
+                slots: FnvHashMap::default(),
+                budget: budget,
+                memory: 0,
+                tick: 0,
+                hits: 0,
+                misses: 0,
+                evictions: 0,
+            }),
+            loaded: Condvar::new(),
             _marker: PhantomData,
         }
     }
-}
\ No newline at end of file
+
+    fn lock(&self) -> MutexGuard<CacheState<K, T>> {
+        // A panic while holding the lock can't leave the map in an inconsistent state, so ignore poisoning
+        match self.state.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+
+    /// Get the current memory budget in bytes
+    pub fn budget(&self) -> Option<usize> {
+        self.lock().budget
+    }
+
+    /// Set the memory budget in bytes, evicting assets if required.
+    ///
+    /// `None` disables the budget entirely.
+    pub fn set_budget(&self, budget: Option<usize>) {
+        let mut state = self.lock();
+
+        state.budget = budget;
+        state.evict();
+    }
+
+    /// Evict any unreferenced assets that no longer fit in the memory budget.
+    ///
+    /// Eviction is also performed automatically whenever an asset is inserted,
+    /// but assets that were still referenced at that time could not be evicted.
+    pub fn trim(&self) {
+        self.lock().evict();
+    }
+
+    /// Get a cached asset, or load it using `load` if it isn't cached.
+    ///
+    /// If another thread is already loading the same asset,
+    /// this will block until it has finished and return that result instead.
+    pub fn get_or_load<F>(&self, key: K, load: F) -> AssetResult<AssetHandle<T>> where F: FnOnce() -> AssetResult<T> {
+        let mut state = self.lock();
+
+        loop {
+            let tick = state.tick + 1;
+
+            let found = match state.slots.get_mut(&key) {
+                Some(&mut Slot::Loaded { ref asset, ref mut last_used, .. }) => {
+                    *last_used = tick;
+
+                    Some(Some(asset.clone()))
+                },
+                Some(&mut Slot::Loading) => Some(None),
+                None => None,
+            };
+
+            match found {
+                Some(Some(asset)) => {
+                    state.tick = tick;
+                    state.hits += 1;
+
+                    return Ok(asset);
+                },
+                // Wait for the other thread to finish loading, then check again
+                Some(None) => {
+                    state = match self.loaded.wait(state) {
+                        Ok(guard) => guard,
+                        Err(poisoned) => poisoned.into_inner(),
+                    };
+                },
+                None => break,
+            }
+        }
+
+        state.misses += 1;
+        state.slots.insert(key.clone(), Slot::Loading);
+
+        // Don't hold the lock while loading so other assets can be loaded in parallel
+        drop(state);
+
+        let mut guard = LoadingGuard { cache: self, key: Some(key) };
+
+        match load() {
+            Ok(asset) => {
+                let key = guard.key.take().expect("Loading guard without a key");
+
+                // Replaces the `Loading` slot, or an asset inserted for the same key while loading
+                let handle = self.lock().insert(key, asset);
+
+                self.loaded.notify_all();
+
+                Ok(handle)
+            },
+            // Dropping the guard clears the slot and wakes any waiting threads
+            Err(err) => Err(err),
+        }
+    }
+}
+
+// Clears a `Loading` slot and wakes waiting threads if a load doesn't complete, including when it panics
+struct LoadingGuard<'c, 'a: 'c, K: 'c, T: 'a> where K: Hash + Eq + Clone, T: AssetSize {
+    cache: &'c AssetHashMapCache<'a, K, T>,
+    key: Option<K>,
+}
+
+impl<'c, 'a: 'c, K: 'c, T: 'a> Drop for LoadingGuard<'c, 'a, K, T> where K: Hash + Eq + Clone, T: AssetSize {
+    fn drop(&mut self) {
+        if let Some(key) = self.key.take() {
+            {
+                let mut state = self.cache.lock();
+
+                // Another thread may have inserted an asset for the same key in the meantime, so keep that
+                if matches!(state.slots.get(&key), Some(&Slot::Loading)) {
+                    state.slots.remove(&key);
+                }
+            }
+
+            self.cache.loaded.notify_all();
+        }
+    }
+}
+
+impl<'a, L, T: 'a> AssetHashMapCache<'a, AssetKey<L>, T> where L: Hash + Eq + Clone + 'a, T: Asset<'a, LoadArgs = L> + AssetSize {
+    /// Load an asset from `path` on the given `vfs`, or return the cached handle if it has already been loaded
+    /// with the same arguments.
+    pub fn load(&self, path: &'a Path, vfs: Arc<BoxedVFS>, args: L) -> AssetResult<AssetHandle<T>> {
+        let key = AssetKey::new(path, &vfs, args.clone());
+
+        self.get_or_load(key, move || T::load(AssetMedium::File(path, vfs), args))
+    }
+}
+
+impl<'a, K, T: 'a> AssetCache<'a> for AssetHashMapCache<'a, K, T> where K: Hash + PartialEq + Eq + Clone, T: AssetSize {
+    type Asset = T;
+    type Key = K;
+
+    fn get(&self, key: &K) -> Option<AssetHandle<T>> {
+        let mut state = self.lock();
+
+        let tick = state.tick + 1;
+
+        let found = match state.slots.get_mut(key) {
+            Some(&mut Slot::Loaded { ref asset, ref mut last_used, .. }) => {
+                *last_used = tick;
+                Some(asset.clone())
+            },
+            _ => None,
+        };
+
+        if found.is_some() {
+            state.tick = tick;
+            state.hits += 1;
+        } else {
+            state.misses += 1;
+        }
+
+        found
+    }
+
+    fn insert(&self, key: K, asset: T) -> AssetHandle<T> {
+        self.lock().insert(key, asset)
+    }
+
+    fn remove(&self, key: &K) -> Option<AssetHandle<T>> {
+        self.lock().remove(key)
+    }
+
+    fn clear(&self) {
+        let mut state = self.lock();
+
+        // Keep in-progress loads so their waiters are still notified correctly
+        state.slots.retain(|_, slot| matches!(*slot, Slot::Loading));
+        state.memory = 0;
+    }
+
+    fn stats(&self) -> CacheStats {
+        self.lock().stats()
+    }
+}
+
+impl<'a, K, T: 'a> Default for AssetHashMapCache<'a, K, T> where K: Hash + Eq + Clone, T: AssetSize {
+    fn default() -> AssetHashMapCache<'a, K, T> {
+        AssetHashMapCache::new()
+    }
+}
+
+impl<'a, K, T: 'a> Debug for AssetHashMapCache<'a, K, T> where K: Hash + Eq + Clone, T: AssetSize {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "AssetHashMapCache {{budget: {:?}, stats: {:?}}}", self.budget(), self.stats())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+    use std::path::Path;
+    use std::panic::{self, AssertUnwindSafe};
+
+    use common::vfs::BoxedVFS;
+    use common::vfs::null::NullFS;
+
+    use ::error::AssetError;
+
+    use super::*;
+
+    struct Blob(usize);
+
+    impl AssetSize for Blob {
+        fn approximate_size(&self) -> usize { self.0 }
+    }
+
+    #[test]
+    fn hits_and_misses() {
+        let cache: AssetHashMapCache<&'static str, Blob> = AssetHashMapCache::new();
+
+        let a = cache.get_or_load("a", || Ok(Blob(10))).unwrap();
+        let b = cache.get_or_load("a", || throw!(AssetError::InvalidValue)).unwrap();
+
+        assert!(Arc::ptr_eq(&a, &b));
+
+        let stats = cache.stats();
+
+        assert_eq!(stats.hits, 1);
+        assert_eq!(stats.misses, 1);
+        assert_eq!(stats.memory, 10);
+
+        // Failed loads are not cached
+        assert!(cache.get_or_load("b", || throw!(AssetError::InvalidValue)).is_err());
+        assert_eq!(cache.stats().entries, 1);
+    }
+
+    #[test]
+    fn evicts_unreferenced_lru() {
+        let cache: AssetHashMapCache<&'static str, Blob> = AssetHashMapCache::with_budget(Some(25));
+
+        let a = cache.insert("a", Blob(10));
+        cache.insert("b", Blob(10));
+        cache.insert("c", Blob(10));
+
+        // "a" is the oldest, but it's still referenced, so "b" gets evicted instead
+        assert!(cache.get(&"a").is_some());
+        assert!(cache.get(&"b").is_none());
+        assert!(cache.get(&"c").is_some());
+
+        assert_eq!(cache.stats().evictions, 1);
+        assert_eq!(cache.stats().memory, 20);
+
+        drop(a);
+
+        cache.set_budget(Some(10));
+
+        // "a" was touched before "c", so it goes first
+        assert!(cache.get(&"a").is_none());
+        assert!(cache.get(&"c").is_some());
+    }
+
+    #[test]
+    fn panicking_load_clears_slot() {
+        let cache: AssetHashMapCache<&'static str, Blob> = AssetHashMapCache::new();
+
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            cache.get_or_load("a", || panic!("Failed to load"))
+        }));
+
+        assert!(result.is_err());
+
+        // This would wait forever if the slot was still marked as loading
+        let a = cache.get_or_load("a", || Ok(Blob(10))).unwrap();
+
+        assert_eq!(a.0, 10);
+        assert_eq!(cache.stats().memory, 10);
+    }
+
+    #[test]
+    fn insert_during_load() {
+        let cache: AssetHashMapCache<&'static str, Blob> = AssetHashMapCache::new();
+
+        let a = cache.get_or_load("a", || {
+            // Same key inserted by another caller while the load is in progress
+            cache.insert("a", Blob(10));
+
+            Ok(Blob(5))
+        }).unwrap();
+
+        assert_eq!(a.0, 5);
+
+        let stats = cache.stats();
+
+        assert_eq!(stats.entries, 1);
+        assert_eq!(stats.memory, 5);
+    }
+
+    #[test]
+    fn keys_per_vfs() {
+        let first: Arc<BoxedVFS> = Arc::new(box NullFS);
+        let second: Arc<BoxedVFS> = Arc::new(box NullFS);
+
+        let key = AssetKey::new(Path::new("a.png"), &first, ());
+
+        assert_eq!(key, AssetKey::new(Path::new("a.png"), &first.clone(), ()));
+        assert!(key != AssetKey::new(Path::new("a.png"), &second, ()));
+        assert!(key != AssetKey::new(Path::new("b.png"), &first, ()));
+    }
+}
@@ -0,0 +1,654 @@
+//! Asset dependency resolution
+//!
+//! Scenes reference materials and models, models reference materials by name, and materials reference textures by path.
+//! The `DependencyResolver` walks those references, starting from a scene or model file,
+//! and builds a `DependencyGraph` of every file needed to display it.
+//!
+//! Paths are resolved relative to the directory of the file that references them on the same virtual filesystem.
+//! Models referenced from scene nodes by GUID are looked up in the `GuidIndex` given to the resolver, if any.
+//! Material names are looked up in the material libraries (serialized `MaterialMap` files) given to the resolver.
+//!
+//! Missing references don't stop resolution, so every missing file or material can be reported at once,
+//! along with the chain of files that led to it. Each missing reference is only reported once.
+//!
+//! Scenes, models and material libraries have to be read while resolving to find what they reference,
+//! but nothing is handed out until the whole graph has been resolved. Then every node is loaded in dependency order.
+
+use std::ascii::AsciiExt;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use fnv::FnvHashMap;
+
+use serde::Deserialize;
+
+use common::vfs::BoxedVFS;
+
+use protocols::material::{Material, MaterialMap};
+use protocols::scene::{Scene, Node, NodePayload, AssetReference, ItemReference};
+
+use ::error::{AssetResult, AssetError};
+use ::asset::{Asset, AssetMedium, AssetFileFormat};
+
+use ::assets::standard::formats::StandardFileFormat;
+use ::assets::standard::generic::deserialize_standard_format;
+use ::assets::model::ModelAsset;
+use ::assets::texture::{TextureAsset, TextureAssetLoadArgs};
+
+#[cfg(feature = "json")]
+use ::meta::{Guid, GuidIndex};
+
+/// How a texture is used by a material
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TextureRole {
+    /// Base color texture
+    Color,
+    /// Normal map
+    Normal,
+    /// Tangent map
+    Tangent,
+    /// Height map
+    Height,
+    /// Roughness map
+    Roughness,
+    /// Metallic map
+    Metallic,
+}
+
+impl TextureRole {
+    /// Only color textures are stored in sRGB color space, everything else is linear data
+    pub fn is_srgb(&self) -> bool {
+        *self == TextureRole::Color
+    }
+}
+
+/// Kind of file in the dependency graph
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DependencyKind {
+    /// Scene description
+    Scene,
+    /// Model
+    Model,
+    /// Material library containing a serialized `MaterialMap`
+    MaterialLibrary,
+    /// Texture used by some material
+    Texture(TextureRole),
+}
+
+/// A single file in the dependency graph
+#[derive(Debug, Clone)]
+pub struct DependencyNode {
+    /// Path of the file on the virtual filesystem
+    pub path: PathBuf,
+    /// What kind of file this is
+    pub kind: DependencyKind,
+    /// Indices of the nodes this file depends on
+    pub dependencies: Vec<usize>,
+}
+
+/// What a missing reference was referring to
+#[derive(Debug, Clone, PartialEq)]
+pub enum MissingReference {
+    /// A file that could not be found
+    File(PathBuf),
+    /// A material name that was not found in any material library
+    Material(String),
+    /// An asset GUID that was not found in the resolver's `GuidIndex`
+    Guid(String),
+    /// A light or material referenced by a scene node that is not part of the scene
+    SceneItem(ItemReference),
+}
+
+/// A reference that could not be resolved
+#[derive(Debug, Clone)]
+pub struct MissingDependency {
+    /// The missing reference
+    pub reference: MissingReference,
+    /// Chain of files that led to the missing reference, starting with the root file
+    pub chain: Vec<PathBuf>,
+}
+
+impl Display for MissingDependency {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match self.reference {
+            MissingReference::File(ref path) => write!(f, "Missing file {:?}", path)?,
+            MissingReference::Material(ref name) => write!(f, "Missing material \"{}\"", name)?,
+            MissingReference::Guid(ref guid) => write!(f, "Missing asset with GUID {}", guid)?,
+            MissingReference::SceneItem(ItemReference::Index(index)) => write!(f, "Missing scene item #{}", index)?,
+            MissingReference::SceneItem(ItemReference::Name(ref name)) => write!(f, "Missing scene item \"{}\"", name)?,
+        }
+
+        for path in self.chain.iter().rev() {
+            write!(f, "\n    referenced by {:?}", path)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Directed acyclic graph of asset files and the files they depend on
+#[derive(Debug, Clone, Default)]
+pub struct DependencyGraph {
+    nodes: Vec<DependencyNode>,
+    index: FnvHashMap<PathBuf, usize>,
+    missing: Vec<MissingDependency>,
+}
+
+impl DependencyGraph {
+    /// All nodes in the graph. The first node is the root.
+    pub fn nodes(&self) -> &[DependencyNode] {
+        &self.nodes
+    }
+
+    /// Find the node index for a path
+    pub fn find(&self, path: &Path) -> Option<usize> {
+        self.index.get(path).cloned()
+    }
+
+    /// All references that could not be resolved
+    pub fn missing(&self) -> &[MissingDependency] {
+        &self.missing
+    }
+
+    /// Check if all references were resolved
+    pub fn is_complete(&self) -> bool {
+        self.missing.is_empty()
+    }
+
+    /// Node indices ordered so that every node comes after all of its dependencies
+    pub fn load_order(&self) -> Vec<usize> {
+        let mut order = Vec::with_capacity(self.nodes.len());
+        let mut visited = vec![false; self.nodes.len()];
+
+        fn visit(graph: &DependencyGraph, node: usize, visited: &mut Vec<bool>, order: &mut Vec<usize>) {
+            if visited[node] {
+                return;
+            }
+
+            visited[node] = true;
+
+            for &dependency in &graph.nodes[node].dependencies {
+                visit(graph, dependency, visited, order);
+            }
+
+            order.push(node);
+        }
+
+        for node in 0..self.nodes.len() {
+            visit(self, node, &mut visited, &mut order);
+        }
+
+        order
+    }
+
+    fn add_node(&mut self, path: PathBuf, kind: DependencyKind) -> (usize, bool) {
+        if let Some(&existing) = self.index.get(&path) {
+            return (existing, false);
+        }
+
+        let node = self.nodes.len();
+
+        self.index.insert(path.clone(), node);
+        self.nodes.push(DependencyNode { path: path, kind: kind, dependencies: Vec::new() });
+
+        (node, true)
+    }
+
+    fn add_missing(&mut self, reference: MissingReference, chain: &[PathBuf]) {
+        if !self.missing.iter().any(|missing| missing.reference == reference) {
+            self.missing.push(MissingDependency { reference: reference, chain: chain.to_vec() });
+        }
+    }
+
+    fn add_edge(&mut self, from: usize, to: usize) {
+        let dependencies = &mut self.nodes[from].dependencies;
+
+        if !dependencies.contains(&to) {
+            dependencies.push(to);
+        }
+    }
+}
+
+/// All assets loaded from a dependency graph
+#[derive(Default)]
+pub struct LoadedDependencies {
+    /// The resolved dependency graph
+    pub graph: DependencyGraph,
+    /// Loaded scenes by path
+    pub scenes: FnvHashMap<PathBuf, Scene>,
+    /// Loaded models by path
+    pub models: FnvHashMap<PathBuf, ModelAsset>,
+    /// Loaded material libraries by path
+    pub materials: FnvHashMap<PathBuf, MaterialMap>,
+    /// Loaded textures by path
+    pub textures: FnvHashMap<PathBuf, TextureAsset>,
+}
+
+impl LoadedDependencies {
+    /// Look up a material by name in any of the loaded material libraries
+    pub fn material(&self, name: &str) -> Option<&Material> {
+        self.materials.values().filter_map(|library| library.get(name)).next()
+    }
+}
+
+/// Resolves and loads asset dependencies
+#[derive(Debug, Clone)]
+pub struct DependencyResolver {
+    vfs: Arc<BoxedVFS>,
+    material_libraries: Vec<PathBuf>,
+    #[cfg(feature = "json")]
+    guids: Option<Arc<GuidIndex>>,
+}
+
+// Mutable state for a single resolution pass
+struct Resolution {
+    loaded: LoadedDependencies,
+    // Files read while resolving, moved into `loaded` by `finish`
+    scenes: FnvHashMap<PathBuf, Scene>,
+    models: FnvHashMap<PathBuf, ModelAsset>,
+    materials: FnvHashMap<PathBuf, MaterialMap>,
+    // Material name to the library node it was found in
+    material_names: FnvHashMap<String, usize>,
+    chain: Vec<PathBuf>,
+}
+
+/// Resolve `reference` relative to the directory containing `referrer`
+pub fn resolve_relative(referrer: &Path, reference: &Path) -> PathBuf {
+    if reference.is_absolute() {
+        return reference.to_path_buf();
+    }
+
+    match referrer.parent() {
+        Some(parent) => parent.join(reference),
+        None => reference.to_path_buf(),
+    }
+}
+
+fn standard_format(path: &Path) -> AssetResult<StandardFileFormat> {
+    let ext = match path.extension() {
+        Some(ext) => try_throw!(ext.to_str().ok_or(AssetError::InvalidValue)).to_ascii_lowercase(),
+        None => throw!(AssetError::UnsupportedFormat),
+    };
+
+    match StandardFileFormat::from_extension(ext.as_str()) {
+        Some(format) if format.can_import() => Ok(format),
+        _ => throw!(AssetError::UnsupportedFormat),
+    }
+}
+
+impl DependencyResolver {
+    /// Create a new resolver for files on the given virtual filesystem
+    pub fn new(vfs: Arc<BoxedVFS>) -> DependencyResolver {
+        DependencyResolver {
+            vfs: vfs,
+            material_libraries: Vec::new(),
+            #[cfg(feature = "json")]
+            guids: None,
+        }
+    }
+
+    /// Use a `GuidIndex` to find models referenced from scene nodes by GUID
+    #[cfg(feature = "json")]
+    pub fn with_guid_index(mut self, guids: Arc<GuidIndex>) -> DependencyResolver {
+        self.guids = Some(guids);
+        self
+    }
+
+    /// Add a material library to look up material names in.
+    ///
+    /// Libraries are searched in the order they were added.
+    pub fn with_material_library<P: Into<PathBuf>>(mut self, path: P) -> DependencyResolver {
+        self.material_libraries.push(path.into());
+        self
+    }
+
+    /// Resolve and load everything needed for the scene at `path`
+    pub fn load_scene(&self, path: &Path) -> AssetResult<LoadedDependencies> {
+        let mut resolution = self.begin();
+
+        try_rethrow!(self.resolve_node(&mut resolution, path, DependencyKind::Scene, None));
+
+        self.finish(resolution)
+    }
+
+    /// Resolve and load everything needed for the model at `path`
+    pub fn load_model(&self, path: &Path) -> AssetResult<LoadedDependencies> {
+        let mut resolution = self.begin();
+
+        try_rethrow!(self.resolve_node(&mut resolution, path, DependencyKind::Model, None));
+
+        self.finish(resolution)
+    }
+
+    fn begin(&self) -> Resolution {
+        Resolution {
+            loaded: LoadedDependencies::default(),
+            scenes: FnvHashMap::default(),
+            models: FnvHashMap::default(),
+            materials: FnvHashMap::default(),
+            material_names: FnvHashMap::default(),
+            chain: Vec::new(),
+        }
+    }
+
+    // Fails if any references are missing, otherwise loads every node in dependency order
+    fn finish(&self, mut resolution: Resolution) -> AssetResult<LoadedDependencies> {
+        let mut loaded = resolution.loaded;
+
+        if !loaded.graph.is_complete() {
+            for missing in loaded.graph.missing() {
+                warn!("{}", missing);
+            }
+
+            throw!(AssetError::MissingDependencies(loaded.graph.missing.clone()));
+        }
+
+        for node in loaded.graph.load_order() {
+            let (path, kind) = {
+                let node = &loaded.graph.nodes[node];
+                (node.path.clone(), node.kind)
+            };
+
+            match kind {
+                DependencyKind::Scene => {
+                    let scene = try_throw!(resolution.scenes.remove(&path).ok_or(AssetError::InvalidValue));
+
+                    loaded.scenes.insert(path, scene);
+                },
+                DependencyKind::Model => {
+                    let model = try_throw!(resolution.models.remove(&path).ok_or(AssetError::InvalidValue));
+
+                    loaded.models.insert(path, model);
+                },
+                DependencyKind::MaterialLibrary => {
+                    let library = try_throw!(resolution.materials.remove(&path).ok_or(AssetError::InvalidValue));
+
+                    loaded.materials.insert(path, library);
+                },
+                DependencyKind::Texture(role) => {
+                    let args = TextureAssetLoadArgs {
+                        srgb: role.is_srgb(),
+                        ..TextureAssetLoadArgs::default()
+                    };
+
+                    let texture = try_rethrow!(TextureAsset::load(AssetMedium::File(&path, self.vfs.clone()), args));
+
+                    loaded.textures.insert(path, texture);
+                },
+            }
+        }
+
+        Ok(loaded)
+    }
+
+    fn deserialize<T: Deserialize>(&self, path: &Path) -> AssetResult<T> {
+        let format = try_rethrow!(standard_format(path));
+
+        let reader = BufReader::new(try_throw!(self.vfs.open(path)));
+
+        deserialize_standard_format(reader, format)
+    }
+
+    // Returns `None` and records a missing dependency if the file doesn't exist
+    fn resolve_node(&self, resolution: &mut Resolution, path: &Path, kind: DependencyKind, parent: Option<usize>) -> AssetResult<Option<usize>> {
+        if self.vfs.metadata(path).is_err() {
+            resolution.loaded.graph.add_missing(MissingReference::File(path.to_path_buf()), &resolution.chain);
+
+            return Ok(None);
+        }
+
+        let (node, added) = resolution.loaded.graph.add_node(path.to_path_buf(), kind);
+
+        if let Some(parent) = parent {
+            resolution.loaded.graph.add_edge(parent, node);
+        }
+
+        if added {
+            resolution.chain.push(path.to_path_buf());
+
+            let result = self.resolve_references(resolution, path, kind, node);
+
+            resolution.chain.pop();
+
+            try_rethrow!(result);
+        }
+
+        Ok(Some(node))
+    }
+
+    fn resolve_references(&self, resolution: &mut Resolution, path: &Path, kind: DependencyKind, node: usize) -> AssetResult<()> {
+        match kind {
+            DependencyKind::Scene => {
+                let scene: Scene = try_rethrow!(self.deserialize(path));
+
+                for material in &scene.materials {
+                    try_rethrow!(self.resolve_material_name(resolution, &material.name, node));
+                }
+
+                try_rethrow!(self.resolve_scene_node(resolution, path, &scene, &scene.root, node));
+
+                resolution.scenes.insert(path.to_path_buf(), scene);
+            },
+            DependencyKind::Model => {
+                let model = try_rethrow!(ModelAsset::load(AssetMedium::File(path, self.vfs.clone()), Default::default()));
+
+                for name in &model.materials {
+                    try_rethrow!(self.resolve_material_name(resolution, name, node));
+                }
+
+                resolution.models.insert(path.to_path_buf(), model);
+            },
+            DependencyKind::MaterialLibrary => {
+                let library: MaterialMap = try_rethrow!(self.deserialize(path));
+
+                for material in library.values() {
+                    try_rethrow!(self.resolve_material_textures(resolution, path, material, node));
+                }
+
+                resolution.materials.insert(path.to_path_buf(), library);
+            },
+            // Textures are leaves in the graph
+            DependencyKind::Texture(_) => {},
+        }
+
+        Ok(())
+    }
+
+    fn resolve_scene_node(&self, resolution: &mut Resolution, path: &Path, scene: &Scene, scene_node: &Node, node: usize) -> AssetResult<()> {
+        for payload in &scene_node.payloads {
+            match *payload {
+                NodePayload::Model(ref instance) => {
+                    if let Some(model_path) = self.resolve_asset_reference(resolution, path, &instance.model) {
+                        try_rethrow!(self.resolve_node(resolution, &model_path, DependencyKind::Model, Some(node)));
+                    }
+
+                    for reference in instance.materials.values() {
+                        match scene.material(reference) {
+                            Some(material) => try_rethrow!(self.resolve_material_name(resolution, &material.name, node)),
+                            None => self.missing_scene_item(resolution, reference),
+                        }
+                    }
+                },
+                NodePayload::Light(ref reference) => {
+                    if scene.light(reference).is_none() {
+                        self.missing_scene_item(resolution, reference);
+                    }
+                },
+                NodePayload::Camera(_) => {},
+            }
+        }
+
+        for child in &scene_node.children {
+            try_rethrow!(self.resolve_scene_node(resolution, path, scene, child, node));
+        }
+
+        Ok(())
+    }
+
+    fn missing_scene_item(&self, resolution: &mut Resolution, reference: &ItemReference) {
+        resolution.loaded.graph.add_missing(MissingReference::SceneItem(reference.clone()), &resolution.chain);
+    }
+
+    // Returns `None` and records a missing dependency if a GUID isn't in the index
+    fn resolve_asset_reference(&self, resolution: &mut Resolution, referrer: &Path, reference: &AssetReference) -> Option<PathBuf> {
+        match *reference {
+            AssetReference::Path(ref path) => Some(resolve_relative(referrer, path)),
+            AssetReference::Guid(ref guid) => {
+                let path = self.guid_path(guid);
+
+                if path.is_none() {
+                    resolution.loaded.graph.add_missing(MissingReference::Guid(guid.clone()), &resolution.chain);
+                }
+
+                path
+            }
+        }
+    }
+
+    #[cfg(feature = "json")]
+    fn guid_path(&self, guid: &str) -> Option<PathBuf> {
+        match (Guid::parse(guid), self.guids.as_ref()) {
+            (Some(guid), Some(guids)) => guids.path(&guid).map(Path::to_path_buf),
+            _ => None,
+        }
+    }
+
+    // Without sidecar support there is nothing to look GUIDs up in
+    #[cfg(not(feature = "json"))]
+    fn guid_path(&self, _: &str) -> Option<PathBuf> {
+        None
+    }
+
+    fn resolve_material_textures(&self, resolution: &mut Resolution, library: &Path, material: &Material, node: usize) -> AssetResult<()> {
+        let textures = [
+            (&material.texture, TextureRole::Color),
+            (&material.normal_map, TextureRole::Normal),
+            (&material.tangent_map, TextureRole::Tangent),
+            (&material.height_map, TextureRole::Height),
+            (&material.roughness_map, TextureRole::Roughness),
+            (&material.metallic_map, TextureRole::Metallic),
+        ];
+
+        for &(texture, role) in textures.iter() {
+            if let Some(ref texture) = *texture {
+                let texture_path = resolve_relative(library, texture);
+
+                try_rethrow!(self.resolve_node(resolution, &texture_path, DependencyKind::Texture(role), Some(node)));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn resolve_material_name(&self, resolution: &mut Resolution, name: &str, node: usize) -> AssetResult<()> {
+        if let Some(&library) = resolution.material_names.get(name) {
+            resolution.loaded.graph.add_edge(node, library);
+
+            return Ok(());
+        }
+
+        for library_path in &self.material_libraries {
+            let library = match resolution.loaded.graph.find(library_path) {
+                Some(library) => Some(library),
+                None => try_rethrow!(self.resolve_node(resolution, library_path, DependencyKind::MaterialLibrary, None)),
+            };
+
+            if let Some(library) = library {
+                let found = resolution.materials.get(library_path).map_or(false, |map| map.contains_key(name));
+
+                if found {
+                    resolution.material_names.insert(name.to_string(), library);
+                    resolution.loaded.graph.add_edge(node, library);
+
+                    return Ok(());
+                }
+            }
+        }
+
+        resolution.loaded.graph.add_missing(MissingReference::Material(name.to_string()), &resolution.chain);
+
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "json"))]
+mod test {
+    use std::io::prelude::*;
+    use std::sync::Arc;
+    use std::env;
+    use std::fs;
+
+    use common::vfs::BoxedVFS;
+    use common::vfs::default::DefaultFS;
+
+    use super::*;
+
+    const LIBRARY: &'static str = r#"{
+        "materials": {
+            "brick": { "texture": "shared.png", "normal_map": "gone.png" },
+            "stone": { "texture": "shared.png", "normal_map": "gone.png" }
+        }
+    }"#;
+
+    const SCENE: &'static str = r#"{
+        "name": "Dependencies",
+        "lights": [],
+        "materials": [{ "name": "brick" }, { "name": "stone" }],
+        "root": {
+            "payloads": [{ "model": { "path": "absent.obj" } }],
+            "children": [{ "payloads": [{ "model": { "path": "absent.obj" } }] }]
+        }
+    }"#;
+
+    #[test]
+    fn shared_and_missing() {
+        let root = env::temp_dir().join("combustion_dependencies_test");
+
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+
+        fs::File::create(root.join("materials.json")).unwrap().write_all(LIBRARY.as_bytes()).unwrap();
+        fs::File::create(root.join("scene.json")).unwrap().write_all(SCENE.as_bytes()).unwrap();
+        fs::File::create(root.join("shared.png")).unwrap();
+
+        let vfs: Arc<BoxedVFS> = Arc::new(box DefaultFS);
+
+        let resolver = DependencyResolver::new(vfs).with_material_library(root.join("materials.json"));
+
+        let scene = root.join("scene.json");
+
+        let mut resolution = resolver.begin();
+
+        resolver.resolve_node(&mut resolution, &scene, DependencyKind::Scene, None).unwrap();
+
+        let graph = &resolution.loaded.graph;
+
+        // Both materials use the same texture, which is only added once
+        assert_eq!(graph.nodes().len(), 3);
+
+        let shared = graph.find(&root.join("shared.png")).unwrap();
+        let library = graph.find(&root.join("materials.json")).unwrap();
+
+        assert_eq!(graph.nodes()[library].dependencies, vec![shared]);
+        assert_eq!(graph.load_order(), vec![shared, library, graph.find(&scene).unwrap()]);
+
+        // Each missing file is reported once, no matter how often it is referenced
+        let missing: Vec<MissingReference> = graph.missing().iter().map(|missing| missing.reference.clone()).collect();
+
+        assert_eq!(missing, vec![MissingReference::File(root.join("gone.png")), MissingReference::File(root.join("absent.obj"))]);
+
+        assert_eq!(graph.missing()[0].chain, vec![scene.clone(), root.join("materials.json")]);
+
+        match resolver.load_scene(&scene) {
+            Err(ref err) => match *err.error() {
+                AssetError::MissingDependencies(ref missing) => assert_eq!(missing.len(), 2),
+                _ => panic!("Expected missing dependencies"),
+            },
+            Ok(_) => panic!("Expected missing dependencies"),
+        }
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -32,6 +32,8 @@ use assimp::error::AiError;
 
 use protocols::error::ProtocolError;
 
+use ::dependencies::MissingDependency;
+
 /// Result type for assets
 pub type AssetResult<T> = TraceResult<T, AssetError>;
 
@@ -101,6 +103,8 @@ pub enum AssetError {
     NulError(NulError),
     /// Unsupported format of some kind
     UnsupportedFormat,
+    /// Asset dependencies that could not be resolved
+    MissingDependencies(Vec<MissingDependency>),
     /// Flate2 DataError
     #[cfg(feature = "flate2")]
     Flate2DataError(flate2::DataError),
@@ -125,6 +129,15 @@ impl Display for AssetError {
     fn fmt(&self, f: &mut Formatter) -> FmtResult {
         match *self {
             AssetError::Unimplemented(feature) => write!(f, "Unimplemented {}", feature),
+            AssetError::MissingDependencies(ref missing) => {
+                write!(f, "Missing Asset Dependencies:")?;
+
+                for dependency in missing {
+                    write!(f, "\n{}", dependency)?;
+                }
+
+                Ok(())
+            },
             _ => f.write_str(self.description()),
         }
     }
@@ -146,6 +159,7 @@ impl Error for AssetError {
             #[cfg(feature = "assimp")]
             AssetError::AssimpError(ref err) => err.description(),
             AssetError::UnsupportedFormat => "Unsupported Format",
+            AssetError::MissingDependencies(_) => "Missing Asset Dependencies",
             #[cfg(feature = "flate2")]
             AssetError::Flate2DataError(ref err) => err.description(),
             #[cfg(feature = "zip")]
@@ -22,6 +22,8 @@ extern crate serde_derive;
 extern crate image;
 extern crate lz4;
 extern crate fnv;
+extern crate rand;
+extern crate futures;
 #[macro_use]
 extern crate matches;
 
@@ -43,6 +45,8 @@ extern crate serde_yaml as yaml;
 extern crate bincode;
 #[cfg(feature = "toml")]
 extern crate toml;
+#[cfg(feature = "base64")]
+extern crate base64;
 
 #[cfg(feature = "mmap")]
 extern crate memmap;
@@ -50,10 +54,18 @@ extern crate memmap;
 #[macro_use]
 extern crate trace_error;
 
+#[macro_use]
 extern crate combustion_common as common;
 extern crate combustion_protocols as protocols;
+extern crate combustion_events as events;
 
 pub mod error;
 pub mod asset;
 pub mod cache;
+pub mod watcher;
+pub mod loader;
+pub mod dependencies;
+pub mod native;
+#[cfg(feature = "json")]
+pub mod meta;
 pub mod assets;
\ No newline at end of file
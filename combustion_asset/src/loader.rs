@@ -0,0 +1,752 @@
+//! Background asset loading
+//!
+//! The `AsyncLoader` queues asset load requests by priority and decodes them on a rayon thread pool,
+//! returning a `LoadHandle` for each request that can be polled from the main loop without blocking.
+//!
+//! Each request is spawned on the pool as soon as a thread is free, highest priority first,
+//! and requests with equal priority are loaded in the order they were queued.
+//! A slow load only occupies its own thread, and a load that panics fails its request instead of the loader.
+//!
+//! Progress is reported from how much of the asset file has been read while loading it.
+
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::fmt::{Debug, Formatter, Result as FmtResult};
+use std::io;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard, Condvar};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering as AtomicOrdering};
+use std::thread::{self, JoinHandle};
+
+use rayon::{self, ThreadPool, Configuration};
+
+use common::vfs::{VirtualFS, BoxedVFS, BoxedMetadata, OpenOptions};
+use common::streams::{BoxedStream, Progress, ProgressStream};
+
+use ::error::{AssetResult, AssetError};
+use ::asset::{Asset, AssetMedium};
+
+#[cfg(feature = "json")]
+use ::meta::{AssetMeta, Importable, prepare_import};
+
+/// Priority of a load request
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum LoadPriority {
+    /// Load whenever nothing else is queued, e.g. speculative streaming
+    Low,
+    /// Default priority
+    Normal,
+    /// Load before normal requests, e.g. assets near the camera
+    High,
+    /// Load before everything else, e.g. assets needed for the next frame
+    Critical,
+}
+
+impl Default for LoadPriority {
+    fn default() -> LoadPriority {
+        LoadPriority::Normal
+    }
+}
+
+/// Current state of a load request
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LoadStatus {
+    /// Waiting in the queue
+    Queued,
+    /// Currently being loaded on the thread pool
+    Loading,
+    /// Successfully loaded
+    Loaded,
+    /// The load failed with an error
+    Failed,
+    /// The request was cancelled before it finished
+    Cancelled,
+}
+
+impl LoadStatus {
+    /// Check if the request has finished, successfully or not
+    pub fn is_finished(&self) -> bool {
+        match *self {
+            LoadStatus::Queued | LoadStatus::Loading => false,
+            _ => true,
+        }
+    }
+}
+
+/// Maximum value of the internal progress counter
+const PROGRESS_SCALE: usize = 1000;
+
+struct LoadShared<T> {
+    status: Mutex<(LoadStatus, Option<AssetResult<T>>)>,
+    finished: Condvar,
+    cancelled: AtomicBool,
+    progress: Arc<AtomicUsize>,
+}
+
+impl<T> LoadShared<T> {
+    fn new() -> Arc<LoadShared<T>> {
+        Arc::new(LoadShared {
+            status: Mutex::new((LoadStatus::Queued, None)),
+            finished: Condvar::new(),
+            cancelled: AtomicBool::new(false),
+            progress: Arc::new(AtomicUsize::new(0)),
+        })
+    }
+
+    fn lock(&self) -> MutexGuard<(LoadStatus, Option<AssetResult<T>>)> {
+        match self.status.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+
+    fn finish(&self, status: LoadStatus, result: Option<AssetResult<T>>) {
+        {
+            let mut guard = self.lock();
+
+            // Cancellation may have already finished the request
+            if !guard.0.is_finished() {
+                *guard = (status, result);
+            }
+        }
+
+        self.progress.store(PROGRESS_SCALE, AtomicOrdering::SeqCst);
+        self.finished.notify_all();
+    }
+}
+
+/// Handle to a queued asset load
+///
+/// Handles can be cloned, but the loaded asset can only be taken out of one of them.
+pub struct LoadHandle<T> {
+    shared: Arc<LoadShared<T>>,
+    path: Arc<PathBuf>,
+}
+
+impl<T> Clone for LoadHandle<T> {
+    fn clone(&self) -> LoadHandle<T> {
+        LoadHandle { shared: self.shared.clone(), path: self.path.clone() }
+    }
+}
+
+impl<T> Debug for LoadHandle<T> {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "LoadHandle {{path: {:?}, status: {:?}, progress: {}}}", self.path, self.status(), self.progress())
+    }
+}
+
+impl<T> LoadHandle<T> {
+    /// Path of the asset being loaded
+    pub fn path(&self) -> &PathBuf {
+        &self.path
+    }
+
+    /// Get the current status of the request without blocking
+    pub fn status(&self) -> LoadStatus {
+        self.shared.lock().0
+    }
+
+    /// Approximate progress of the request, from `0.0` to `1.0`
+    ///
+    /// While loading, this is based on how much of the asset file has been read.
+    pub fn progress(&self) -> f32 {
+        self.shared.progress.load(AtomicOrdering::SeqCst) as f32 / PROGRESS_SCALE as f32
+    }
+
+    /// Cancel the request.
+    ///
+    /// Requests that are still queued will never be loaded. Requests that are already loading will
+    /// finish in the background, but their result is discarded.
+    ///
+    /// Returns `false` if the request had already finished.
+    pub fn cancel(&self) -> bool {
+        self.shared.cancelled.store(true, AtomicOrdering::SeqCst);
+
+        let cancelled = {
+            let mut guard = self.shared.lock();
+
+            if guard.0.is_finished() { false } else {
+                *guard = (LoadStatus::Cancelled, None);
+                true
+            }
+        };
+
+        if cancelled {
+            self.shared.finished.notify_all();
+        }
+
+        cancelled
+    }
+
+    /// If the request has finished, take the result out of it.
+    ///
+    /// Returns `None` if the request has not finished yet, was cancelled,
+    /// or if the result was already taken from another handle.
+    pub fn try_take(&self) -> Option<AssetResult<T>> {
+        self.shared.lock().1.take()
+    }
+
+    /// Block until the request has finished, then take the result out of it.
+    ///
+    /// Returns `None` if the request was cancelled, or if the result was already taken from another handle.
+    pub fn wait(&self) -> Option<AssetResult<T>> {
+        let mut guard = self.shared.lock();
+
+        while !guard.0.is_finished() {
+            guard = match self.shared.finished.wait(guard) {
+                Ok(guard) => guard,
+                Err(poisoned) => poisoned.into_inner(),
+            };
+        }
+
+        guard.1.take()
+    }
+}
+
+// Reports how much of the asset file has been read back to its `LoadHandle`
+#[derive(Debug)]
+struct ProgressFS {
+    vfs: Arc<BoxedVFS>,
+    path: Arc<PathBuf>,
+    progress: Arc<AtomicUsize>,
+}
+
+impl VirtualFS for ProgressFS {
+    fn open_with(&self, path: &Path, options: OpenOptions) -> io::Result<BoxedStream> {
+        let stream = self.vfs.open_with(path, options)?;
+
+        // Dependencies and anything being written aren't part of the progress
+        if options.write || path != self.path.as_path() {
+            return Ok(stream);
+        }
+
+        let progress = self.progress.clone();
+
+        let stream: BoxedStream = box ProgressStream::with_length(stream, move |update: Progress| {
+            if let Some(fraction) = update.fraction() {
+                // The last step is left for when the asset has been decoded
+                progress.store((fraction * (PROGRESS_SCALE - 1) as f32) as usize, AtomicOrdering::SeqCst);
+            }
+        })?;
+
+        Ok(stream)
+    }
+
+    fn metadata(&self, path: &Path) -> io::Result<BoxedMetadata> {
+        self.vfs.metadata(path)
+    }
+
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<PathBuf>> {
+        self.vfs.read_dir(path)
+    }
+}
+
+// Type-erased load request
+trait LoadJob: Send {
+    fn run(self: Box<Self>);
+    fn cancel(self: Box<Self>);
+}
+
+struct TypedLoadJob<T, L> {
+    shared: Arc<LoadShared<T>>,
+    path: Arc<PathBuf>,
+    vfs: Arc<BoxedVFS>,
+    args: L,
+}
+
+impl<T, L> LoadJob for TypedLoadJob<T, L> where T: for<'a> Asset<'a, LoadArgs = L> + Send + 'static, L: Send + 'static {
+    fn run(self: Box<Self>) {
+        let job = *self;
+        let args = job.args;
+
+        run_load(&job.shared, &job.path, &job.vfs, move |path, vfs| T::load(AssetMedium::File(path, vfs), args));
+    }
+
+    fn cancel(self: Box<Self>) {
+        self.shared.cancelled.store(true, AtomicOrdering::SeqCst);
+        self.shared.finish(LoadStatus::Cancelled, None);
+    }
+}
+
+// Load request that reads the sidecar of the asset before loading it
+#[cfg(feature = "json")]
+struct MetaLoadJob<T> {
+    shared: Arc<LoadShared<(T, AssetMeta)>>,
+    path: Arc<PathBuf>,
+    vfs: Arc<BoxedVFS>,
+}
+
+#[cfg(feature = "json")]
+impl<T, L> LoadJob for MetaLoadJob<T> where T: Importable<Args = L> + for<'a> Asset<'a, LoadArgs = L> + Send + 'static, L: Send + 'static {
+    fn run(self: Box<Self>) {
+        let job = *self;
+
+        // Hashing the source shouldn't count towards the progress, so the sidecar is prepared without it
+        let raw_vfs = job.vfs.clone();
+
+        run_load(&job.shared, &job.path, &job.vfs, move |path, vfs| {
+            let (meta, args) = try_rethrow!(prepare_import::<T>(path, &raw_vfs));
+
+            let asset = try_rethrow!(T::load(AssetMedium::File(path, vfs), args));
+
+            Ok((asset, meta))
+        });
+    }
+
+    fn cancel(self: Box<Self>) {
+        self.shared.cancelled.store(true, AtomicOrdering::SeqCst);
+        self.shared.finish(LoadStatus::Cancelled, None);
+    }
+}
+
+// Runs `load` on the current pool thread with a VFS that reports progress, unless the request was cancelled
+fn run_load<T, F>(shared: &LoadShared<T>, path: &Arc<PathBuf>, vfs: &Arc<BoxedVFS>, load: F)
+    where F: FnOnce(&Path, Arc<BoxedVFS>) -> AssetResult<T>
+{
+    if shared.cancelled.load(AtomicOrdering::SeqCst) {
+        return;
+    }
+
+    {
+        let mut guard = shared.lock();
+
+        if guard.0.is_finished() {
+            return;
+        }
+
+        guard.0 = LoadStatus::Loading;
+    }
+
+    let progress_vfs: Arc<BoxedVFS> = Arc::new(box ProgressFS {
+        vfs: vfs.clone(),
+        path: path.clone(),
+        progress: shared.progress.clone(),
+    });
+
+    let load_path = path.clone();
+
+    let result = match panic::catch_unwind(AssertUnwindSafe(move || load(load_path.as_path(), progress_vfs))) {
+        Ok(result) => result,
+        Err(_) => panicked(path),
+    };
+
+    if shared.cancelled.load(AtomicOrdering::SeqCst) {
+        return;
+    }
+
+    match result {
+        Ok(asset) => shared.finish(LoadStatus::Loaded, Some(Ok(asset))),
+        Err(err) => {
+            warn!("Failed to load asset {:?}", path);
+
+            shared.finish(LoadStatus::Failed, Some(Err(err)))
+        },
+    }
+}
+
+// Turns a panic while loading into an error for the request
+fn panicked<T>(path: &Path) -> AssetResult<T> {
+    throw!(AssetError::Other(format!("Loading {:?} panicked", path)))
+}
+
+struct QueuedJob {
+    priority: LoadPriority,
+    sequence: u64,
+    job: Box<LoadJob>,
+}
+
+// Higher priorities first, then lower sequence numbers first
+impl Ord for QueuedJob {
+    fn cmp(&self, other: &QueuedJob) -> Ordering {
+        self.priority.cmp(&other.priority).then_with(|| other.sequence.cmp(&self.sequence))
+    }
+}
+
+impl PartialOrd for QueuedJob {
+    fn partial_cmp(&self, other: &QueuedJob) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for QueuedJob {
+    fn eq(&self, other: &QueuedJob) -> bool {
+        self.priority == other.priority && self.sequence == other.sequence
+    }
+}
+
+impl Eq for QueuedJob {}
+
+struct Queue {
+    jobs: BinaryHeap<QueuedJob>,
+    sequence: u64,
+    // Number of jobs currently running on the pool
+    running: usize,
+    shutdown: bool,
+}
+
+struct QueueShared {
+    queue: Mutex<Queue>,
+    available: Condvar,
+}
+
+impl QueueShared {
+    fn lock(&self) -> MutexGuard<Queue> {
+        match self.queue.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+}
+
+/// Loads assets in the background on a rayon thread pool
+pub struct AsyncLoader {
+    shared: Arc<QueueShared>,
+    dispatcher: Option<JoinHandle<()>>,
+}
+
+impl Debug for AsyncLoader {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "AsyncLoader {{queued: {}}}", self.queued())
+    }
+}
+
+impl AsyncLoader {
+    /// Create a new `AsyncLoader` with a thread pool of `num_threads` threads.
+    ///
+    /// If `num_threads` is zero, rayon will choose the number of threads.
+    pub fn new(num_threads: usize) -> AssetResult<AsyncLoader> {
+        let mut configuration = Configuration::new();
+
+        if num_threads > 0 {
+            configuration = configuration.num_threads(num_threads);
+        }
+
+        let pool = match ThreadPool::new(configuration) {
+            Ok(pool) => pool,
+            Err(err) => throw!(AssetError::Other(format!("Could not create asset loader thread pool: {:?}", err))),
+        };
+
+        let shared = Arc::new(QueueShared {
+            queue: Mutex::new(Queue {
+                jobs: BinaryHeap::new(),
+                sequence: 0,
+                running: 0,
+                shutdown: false,
+            }),
+            available: Condvar::new(),
+        });
+
+        let dispatcher = {
+            let shared = shared.clone();
+
+            try_throw!(thread::Builder::new().name("asset loader".to_string()).spawn(move || dispatch(shared, pool)))
+        };
+
+        Ok(AsyncLoader {
+            shared: shared,
+            dispatcher: Some(dispatcher),
+        })
+    }
+
+    /// Queue an asset to be loaded from `path` on the given `vfs` with the given `priority`
+    pub fn load<T, L>(&self, path: PathBuf, vfs: Arc<BoxedVFS>, args: L, priority: LoadPriority) -> LoadHandle<T>
+        where T: for<'a> Asset<'a, LoadArgs = L> + Send + 'static,
+              L: Send + 'static
+    {
+        let shared = LoadShared::new();
+        let path = Arc::new(path);
+
+        self.push(box TypedLoadJob {
+            shared: shared.clone(),
+            path: path.clone(),
+            vfs: vfs,
+            args: args,
+        }, priority);
+
+        LoadHandle { shared: shared, path: path }
+    }
+
+    /// Queue an asset to be loaded with the settings from its `.meta` sidecar
+    ///
+    /// The sidecar is read, and created or updated if needed, on the thread pool before loading the asset,
+    /// and is returned along with it. See `meta::prepare_import` for details.
+    #[cfg(feature = "json")]
+    pub fn load_with_meta<T, L>(&self, path: PathBuf, vfs: Arc<BoxedVFS>, priority: LoadPriority) -> LoadHandle<(T, AssetMeta)>
+        where T: Importable<Args = L> + for<'a> Asset<'a, LoadArgs = L> + Send + 'static,
+              L: Send + 'static
+    {
+        let shared = LoadShared::new();
+        let path = Arc::new(path);
+
+        self.push(box MetaLoadJob {
+            shared: shared.clone(),
+            path: path.clone(),
+            vfs: vfs,
+        }, priority);
+
+        LoadHandle { shared: shared, path: path }
+    }
+
+    fn push(&self, job: Box<LoadJob>, priority: LoadPriority) {
+        {
+            let mut queue = self.shared.lock();
+
+            let sequence = queue.sequence;
+
+            queue.sequence += 1;
+
+            queue.jobs.push(QueuedJob {
+                priority: priority,
+                sequence: sequence,
+                job: job,
+            });
+        }
+
+        self.shared.available.notify_one();
+    }
+
+    /// Number of requests waiting in the queue
+    pub fn queued(&self) -> usize {
+        self.shared.lock().jobs.len()
+    }
+}
+
+impl Drop for AsyncLoader {
+    fn drop(&mut self) {
+        self.shared.lock().shutdown = true;
+        self.shared.available.notify_all();
+
+        if let Some(dispatcher) = self.dispatcher.take() {
+            if dispatcher.join().is_err() {
+                error!("Asset loader dispatch thread panicked");
+            }
+        }
+    }
+}
+
+// Spawns queued jobs on the thread pool, at most one per pool thread at a time so priorities are respected
+fn dispatch(shared: Arc<QueueShared>, pool: ThreadPool) {
+    let max_running = pool.install(rayon::current_num_threads);
+
+    loop {
+        let queued = {
+            let mut queue = shared.lock();
+
+            while !queue.shutdown && (queue.jobs.is_empty() || queue.running >= max_running) {
+                queue = match shared.available.wait(queue) {
+                    Ok(guard) => guard,
+                    Err(poisoned) => poisoned.into_inner(),
+                };
+            }
+
+            // Cancel anything left in the queue so nothing waits on it forever
+            if queue.shutdown {
+                while let Some(queued) = queue.jobs.pop() {
+                    queued.job.cancel();
+                }
+
+                return;
+            }
+
+            match queue.jobs.pop() {
+                Some(queued) => {
+                    queue.running += 1;
+                    queued
+                },
+                None => continue,
+            }
+        };
+
+        let shared = shared.clone();
+
+        pool.spawn(move || {
+            let job = queued.job;
+
+            // Panics while loading are already reported to the handle, this only keeps the dispatcher going
+            if panic::catch_unwind(AssertUnwindSafe(move || job.run())).is_err() {
+                error!("Asset load job panicked");
+            }
+
+            shared.lock().running -= 1;
+            shared.available.notify_all();
+        });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+
+    use common::vfs::BoxedVFS;
+    use common::vfs::null::NullFS;
+
+    use ::error::{AssetResult, AssetError};
+    use ::asset::{Asset, AssetMedium};
+
+    use super::*;
+
+    #[derive(Clone)]
+    struct TestArgs {
+        id: u32,
+        // Loads block until this is unlocked
+        gate: Option<Arc<Mutex<()>>>,
+        panic: bool,
+        completed: Arc<Mutex<Vec<u32>>>,
+    }
+
+    struct TestAsset(u32);
+
+    impl<'a> Asset<'a> for TestAsset {
+        type LoadArgs = TestArgs;
+        type SaveArgs = ();
+        type Query = ();
+
+        fn load(_: AssetMedium<'a>, args: TestArgs) -> AssetResult<TestAsset> {
+            if let Some(ref gate) = args.gate {
+                drop(gate.lock());
+            }
+
+            if args.panic {
+                panic!("Test asset failed to load");
+            }
+
+            args.completed.lock().unwrap().push(args.id);
+
+            Ok(TestAsset(args.id))
+        }
+
+        fn save(&self, _: AssetMedium<'a>, _: ()) -> AssetResult<()> {
+            throw!(AssetError::UnsupportedMedium)
+        }
+
+        fn query(_: ()) -> AssetResult<()> {
+            Ok(())
+        }
+    }
+
+    struct Fixture {
+        loader: AsyncLoader,
+        vfs: Arc<BoxedVFS>,
+        completed: Arc<Mutex<Vec<u32>>>,
+    }
+
+    impl Fixture {
+        fn new(num_threads: usize) -> Fixture {
+            Fixture {
+                loader: AsyncLoader::new(num_threads).unwrap(),
+                vfs: Arc::new(box NullFS as BoxedVFS),
+                completed: Arc::new(Mutex::new(Vec::new())),
+            }
+        }
+
+        fn load(&self, id: u32, gate: Option<&Arc<Mutex<()>>>, panic: bool, priority: LoadPriority) -> LoadHandle<TestAsset> {
+            let args = TestArgs {
+                id: id,
+                gate: gate.cloned(),
+                panic: panic,
+                completed: self.completed.clone(),
+            };
+
+            self.loader.load(PathBuf::from(format!("{}.test", id)), self.vfs.clone(), args, priority)
+        }
+
+        fn completed(&self) -> Vec<u32> {
+            self.completed.lock().unwrap().clone()
+        }
+    }
+
+    fn wait_until_loading<T>(handle: &LoadHandle<T>) {
+        while handle.status() == LoadStatus::Queued {
+            thread::yield_now();
+        }
+    }
+
+    #[test]
+    fn completion_order() {
+        let fixture = Fixture::new(1);
+
+        let gate = Arc::new(Mutex::new(()));
+        let guard = gate.lock().unwrap();
+
+        let blocker = fixture.load(0, Some(&gate), false, LoadPriority::Low);
+
+        wait_until_loading(&blocker);
+
+        let handles = vec![
+            fixture.load(1, None, false, LoadPriority::Low),
+            fixture.load(2, None, false, LoadPriority::Critical),
+            fixture.load(3, None, false, LoadPriority::Normal),
+            fixture.load(4, None, false, LoadPriority::Normal),
+        ];
+
+        drop(guard);
+
+        for (i, handle) in handles.iter().enumerate() {
+            assert_eq!(handle.wait().unwrap().unwrap().0, i as u32 + 1);
+        }
+
+        assert_eq!(fixture.completed(), vec![0, 2, 3, 4, 1]);
+    }
+
+    #[test]
+    fn slow_load_does_not_block() {
+        let fixture = Fixture::new(2);
+
+        let gate = Arc::new(Mutex::new(()));
+        let guard = gate.lock().unwrap();
+
+        let slow = fixture.load(0, Some(&gate), false, LoadPriority::Normal);
+
+        wait_until_loading(&slow);
+
+        // Finishes on the other thread while the slow load is still blocked
+        assert!(fixture.load(1, None, false, LoadPriority::Normal).wait().unwrap().is_ok());
+        assert_eq!(slow.status(), LoadStatus::Loading);
+
+        drop(guard);
+
+        assert!(slow.wait().unwrap().is_ok());
+    }
+
+    #[test]
+    fn panicking_load() {
+        let fixture = Fixture::new(1);
+
+        let handle = fixture.load(0, None, true, LoadPriority::Normal);
+
+        assert!(handle.wait().unwrap().is_err());
+        assert_eq!(handle.status(), LoadStatus::Failed);
+
+        // The loader keeps working afterwards
+        assert!(fixture.load(1, None, false, LoadPriority::Normal).wait().unwrap().is_ok());
+    }
+
+    #[test]
+    fn cancellation() {
+        let fixture = Fixture::new(1);
+
+        let gate = Arc::new(Mutex::new(()));
+        let guard = gate.lock().unwrap();
+
+        let blocker = fixture.load(0, Some(&gate), false, LoadPriority::Normal);
+
+        wait_until_loading(&blocker);
+
+        let cancelled = fixture.load(1, None, false, LoadPriority::Normal);
+
+        assert!(cancelled.cancel());
+        assert!(!cancelled.cancel());
+        assert_eq!(cancelled.status(), LoadStatus::Cancelled);
+        assert!(cancelled.wait().is_none());
+
+        drop(guard);
+
+        assert!(blocker.wait().unwrap().is_ok());
+        assert!(fixture.load(2, None, false, LoadPriority::Normal).wait().unwrap().is_ok());
+
+        assert_eq!(fixture.completed(), vec![0, 2]);
+    }
+}
@@ -0,0 +1,451 @@
+//! Import settings sidecar files
+//!
+//! Every source asset can have a `.meta` sidecar next to it, named after the full file name of the asset,
+//! so `textures/brick.png` has its settings in `textures/brick.png.meta`. Sidecars are stored as JSON and hold:
+//!
+//! * A stable GUID, so references to the asset survive the file being renamed or moved along with its sidecar
+//! * The import settings for the asset type, which are turned into load arguments automatically
+//! * A hash of the source file contents, to detect when the source has changed since it was last imported
+//!
+//! `load_with_meta` reads the sidecar, creating one with default settings if none exists yet, and loads the asset.
+//! `GuidIndex` maps GUIDs back to the current path of each asset.
+
+use std::ffi::OsString;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::io::{self, Write, BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use fnv::FnvHashMap;
+
+use rand;
+
+use serde::{Serialize, Serializer, Deserialize, Deserializer};
+use serde::de::Error as DeError;
+
+use image::ImageFormat;
+
+use json;
+
+use common::vfs::BoxedVFS;
+use common::vfs::pack::ContentHasher;
+use common::streams::HashingStream;
+
+use ::error::{AssetResult, AssetError};
+use ::asset::{Asset, AssetMedium, AssetFileFormat};
+
+use ::assets::standard::formats::StandardFileFormat;
+use ::assets::standard::generic::deserialize_standard_format;
+use ::assets::texture::{TextureAsset, TextureAssetLoadArgs};
+use ::assets::model::{ModelAsset, ModelAssetLoadArgs};
+
+/// Extension appended to the full file name of an asset to get its sidecar
+pub const META_EXTENSION: &'static str = "meta";
+
+/// Globally unique asset identifier
+///
+/// New GUIDs are random (version 4) UUIDs, and are written in the usual hyphenated hexadecimal form.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Guid(pub u64, pub u64);
+
+impl Guid {
+    /// Generate a new random GUID
+    pub fn new() -> Guid {
+        let (high, low) = (rand::random::<u64>(), rand::random::<u64>());
+
+        // Version 4, RFC 4122 variant
+        Guid((high & !0xF000) | 0x4000, (low & 0x3FFF_FFFF_FFFF_FFFF) | 0x8000_0000_0000_0000)
+    }
+
+    /// Parse a GUID from hexadecimal, with or without hyphens
+    pub fn parse(text: &str) -> Option<Guid> {
+        let digits: String = text.trim().chars().filter(|&c| c != '-').collect();
+
+        if digits.len() != 32 || !digits.is_char_boundary(16) {
+            return None;
+        }
+
+        match (u64::from_str_radix(&digits[..16], 16), u64::from_str_radix(&digits[16..], 16)) {
+            (Ok(high), Ok(low)) => Some(Guid(high, low)),
+            _ => None,
+        }
+    }
+}
+
+impl Display for Guid {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "{:08x}-{:04x}-{:04x}-{:04x}-{:012x}",
+               self.0 >> 32, (self.0 >> 16) & 0xFFFF, self.0 & 0xFFFF, self.1 >> 48, self.1 & 0xFFFF_FFFF_FFFF)
+    }
+}
+
+impl Serialize for Guid {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error> where S: Serializer {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl Deserialize for Guid {
+    fn deserialize<D>(deserializer: D) -> Result<Guid, D::Error> where D: Deserializer {
+        let text = try!(String::deserialize(deserializer));
+
+        Guid::parse(&text).ok_or_else(|| D::Error::custom(format!("invalid GUID \"{}\"", text)))
+    }
+}
+
+/// Import settings for textures
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TextureImportSettings {
+    /// Only allow 2D textures
+    #[serde(default)]
+    pub only2d: bool,
+    /// Consider the loaded images as in sRGB color space
+    #[serde(default)]
+    pub srgb: bool,
+    /// Image format extension to use if the format can't be determined from the path, e.g. `"png"`
+    #[serde(default)]
+    pub format_hint: Option<String>,
+}
+
+impl Default for TextureImportSettings {
+    fn default() -> TextureImportSettings {
+        TextureImportSettings { only2d: false, srgb: false, format_hint: None }
+    }
+}
+
+/// Import settings for each asset type
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ImportSettings {
+    /// Texture import settings
+    Texture(TextureImportSettings),
+    /// Models have no import settings yet
+    Model,
+}
+
+/// Contents of a `.meta` sidecar file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AssetMeta {
+    /// Stable identifier of the asset
+    pub guid: Guid,
+    /// Import settings for the asset type
+    pub settings: ImportSettings,
+    /// Hash of the source file contents when it was last imported, as 32 hexadecimal digits
+    #[serde(default)]
+    pub content_hash: Option<String>,
+}
+
+impl AssetMeta {
+    /// Create metadata for a new asset with a fresh GUID and no content hash
+    pub fn new(settings: ImportSettings) -> AssetMeta {
+        AssetMeta { guid: Guid::new(), settings: settings, content_hash: None }
+    }
+
+    /// Path of the sidecar for the asset at `path`
+    pub fn sidecar_path(path: &Path) -> PathBuf {
+        let mut file_name = path.file_name().map(|name| name.to_os_string()).unwrap_or_else(OsString::new);
+
+        file_name.push(".");
+        file_name.push(META_EXTENSION);
+
+        path.with_file_name(file_name)
+    }
+
+    /// Read the sidecar for the asset at `path`, returning `None` if there is none
+    pub fn read(path: &Path, vfs: &Arc<BoxedVFS>) -> AssetResult<Option<AssetMeta>> {
+        let sidecar = AssetMeta::sidecar_path(path);
+
+        if vfs.metadata(&sidecar).is_err() {
+            return Ok(None);
+        }
+
+        let reader = BufReader::new(try_throw!(vfs.open(&sidecar)));
+
+        Ok(Some(try_rethrow!(deserialize_standard_format(reader, StandardFileFormat::Json))))
+    }
+
+    /// Write the sidecar for the asset at `path`
+    pub fn write(&self, path: &Path, vfs: &Arc<BoxedVFS>) -> AssetResult<()> {
+        let mut writer = BufWriter::new(try_throw!(vfs.create_or_truncate(&AssetMeta::sidecar_path(path))));
+
+        try_throw!(json::to_writer_pretty(&mut writer, self));
+
+        // Dropping the writer would flush it too, but silently ignore any error
+        try_throw!(writer.flush());
+
+        Ok(())
+    }
+
+    /// Hash the contents of the source file at `path`
+    ///
+    /// This is the same `ContentHash` used by pack files, which is stable across platforms and toolchains.
+    pub fn hash_source(path: &Path, vfs: &Arc<BoxedVFS>) -> AssetResult<String> {
+        let mut reader = HashingStream::new(try_throw!(vfs.open(path)), ContentHasher::new());
+
+        try_throw!(io::copy(&mut reader, &mut io::sink()));
+
+        Ok(reader.hasher().content_hash().to_string())
+    }
+}
+
+/// Asset types that can be loaded with the settings from a sidecar
+pub trait Importable {
+    /// Load arguments produced from the settings
+    type Args;
+
+    /// Settings used when an asset has no sidecar yet
+    fn default_settings() -> ImportSettings;
+
+    /// Convert settings into load arguments, failing if they are for another asset type
+    fn import_args(settings: &ImportSettings) -> AssetResult<Self::Args>;
+}
+
+impl Importable for TextureAsset {
+    type Args = TextureAssetLoadArgs;
+
+    fn default_settings() -> ImportSettings {
+        ImportSettings::Texture(TextureImportSettings::default())
+    }
+
+    fn import_args(settings: &ImportSettings) -> AssetResult<TextureAssetLoadArgs> {
+        match *settings {
+            ImportSettings::Texture(ref settings) => Ok(TextureAssetLoadArgs {
+                only2d: settings.only2d,
+                srgb: settings.srgb,
+                format_hint: settings.format_hint.as_ref().and_then(|ext| ImageFormat::from_extension(ext.as_str())),
+                ..TextureAssetLoadArgs::default()
+            }),
+            _ => throw!(AssetError::InvalidValue),
+        }
+    }
+}
+
+impl Importable for ModelAsset {
+    type Args = ModelAssetLoadArgs;
+
+    fn default_settings() -> ImportSettings {
+        ImportSettings::Model
+    }
+
+    fn import_args(settings: &ImportSettings) -> AssetResult<ModelAssetLoadArgs> {
+        match *settings {
+            ImportSettings::Model => Ok(ModelAssetLoadArgs::default()),
+            _ => throw!(AssetError::InvalidValue),
+        }
+    }
+}
+
+/// Read the sidecar for the asset at `path` and produce its load arguments
+///
+/// If there is no sidecar, one is created with a new GUID and the default settings for `T`.
+/// The content hash is updated whenever the source file has changed.
+/// Failing to write the sidecar, e.g. on a read-only filesystem, is only logged.
+pub fn prepare_import<T: Importable>(path: &Path, vfs: &Arc<BoxedVFS>) -> AssetResult<(AssetMeta, T::Args)> {
+    let (mut meta, mut dirty) = match try_rethrow!(AssetMeta::read(path, vfs)) {
+        Some(meta) => (meta, false),
+        None => (AssetMeta::new(T::default_settings()), true),
+    };
+
+    let args = try_rethrow!(T::import_args(&meta.settings));
+
+    let hash = try_rethrow!(AssetMeta::hash_source(path, vfs));
+
+    if meta.content_hash.as_ref() != Some(&hash) {
+        meta.content_hash = Some(hash);
+        dirty = true;
+    }
+
+    if dirty {
+        if let Err(err) = meta.write(path, vfs) {
+            warn!("Could not write sidecar for {:?}: {:?}", path, err);
+        }
+    }
+
+    Ok((meta, args))
+}
+
+/// Load the asset at `path` using the settings from its sidecar, as described in `prepare_import`
+pub fn load_with_meta<T, L>(path: &Path, vfs: Arc<BoxedVFS>) -> AssetResult<(T, AssetMeta)>
+    where T: Importable<Args = L> + for<'a> Asset<'a, LoadArgs = L>
+{
+    let (meta, args) = try_rethrow!(prepare_import::<T>(path, &vfs));
+
+    let asset = try_rethrow!(T::load(AssetMedium::File(path, vfs), args));
+
+    Ok((asset, meta))
+}
+
+/// Maps asset GUIDs to their current paths and back
+#[derive(Debug, Clone, Default)]
+pub struct GuidIndex {
+    paths: FnvHashMap<Guid, PathBuf>,
+    guids: FnvHashMap<PathBuf, Guid>,
+}
+
+impl GuidIndex {
+    /// Create an empty index
+    pub fn new() -> GuidIndex {
+        GuidIndex::default()
+    }
+
+    /// Build an index from every sidecar in a directory tree
+    ///
+    /// Paths in the index are relative to `root`, so they can be used with a virtual filesystem rooted there.
+    /// Two sidecars with the same GUID, usually from copying an asset along with its sidecar, are an error.
+    pub fn scan(root: &Path, vfs: &Arc<BoxedVFS>) -> AssetResult<GuidIndex> {
+        let mut index = GuidIndex::new();
+
+        let mut directories = vec![root.to_path_buf()];
+
+        while let Some(directory) = directories.pop() {
+            for path in try_throw!(vfs.read_dir(&directory)) {
+                if try_throw!(vfs.metadata(&path)).is_dir() {
+                    directories.push(path);
+                } else if path.extension().map_or(false, |ext| ext == META_EXTENSION) {
+                    let reader = BufReader::new(try_throw!(vfs.open(&path)));
+
+                    let meta: AssetMeta = try_rethrow!(deserialize_standard_format(reader, StandardFileFormat::Json));
+
+                    // Strip the `.meta` extension to get the asset path
+                    let asset = path.with_extension("");
+
+                    let relative = asset.strip_prefix(root).map(Path::to_path_buf).unwrap_or_else(|_| asset.clone());
+
+                    if let Some(existing) = index.path(&meta.guid) {
+                        throw!(AssetError::Other(format!("Duplicate GUID {} for {:?} and {:?}", meta.guid, existing, relative)));
+                    }
+
+                    index.insert(meta.guid, relative);
+                }
+            }
+        }
+
+        Ok(index)
+    }
+
+    /// Record the path of an asset, replacing any previous path for the same GUID
+    ///
+    /// Returns the previous path if the asset was moved.
+    pub fn insert(&mut self, guid: Guid, path: PathBuf) -> Option<PathBuf> {
+        let previous = self.paths.insert(guid, path.clone());
+
+        if let Some(ref previous) = previous {
+            self.guids.remove(previous);
+        }
+
+        if let Some(other) = self.guids.insert(path, guid) {
+            if other != guid {
+                self.paths.remove(&other);
+            }
+        }
+
+        previous
+    }
+
+    /// Remove an asset from the index
+    pub fn remove(&mut self, guid: &Guid) -> Option<PathBuf> {
+        let path = self.paths.remove(guid);
+
+        if let Some(ref path) = path {
+            self.guids.remove(path);
+        }
+
+        path
+    }
+
+    /// Current path of an asset
+    pub fn path(&self, guid: &Guid) -> Option<&Path> {
+        self.paths.get(guid).map(|path| path.as_path())
+    }
+
+    /// GUID of the asset at `path`
+    pub fn guid(&self, path: &Path) -> Option<Guid> {
+        self.guids.get(path).cloned()
+    }
+
+    /// Number of assets in the index
+    pub fn len(&self) -> usize {
+        self.paths.len()
+    }
+
+    /// Check if the index is empty
+    pub fn is_empty(&self) -> bool {
+        self.paths.is_empty()
+    }
+
+    /// Iterate over every GUID and path in the index
+    pub fn iter<'a>(&'a self) -> impl Iterator<Item = (&'a Guid, &'a PathBuf)> + 'a {
+        self.paths.iter()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::{Path, PathBuf};
+    use std::sync::Arc;
+    use std::env;
+    use std::fs;
+
+    use common::vfs::BoxedVFS;
+    use common::vfs::default::DefaultFS;
+
+    use super::*;
+
+    #[test]
+    fn guid_format() {
+        let guid = Guid::new();
+
+        let text = guid.to_string();
+
+        assert_eq!(text.len(), 36);
+        assert_eq!(&text[14..15], "4");
+        assert_eq!(Guid::parse(&text), Some(guid));
+        assert_eq!(Guid::parse(&text.replace("-", "")), Some(guid));
+        assert_eq!(Guid::parse("not a guid"), None);
+    }
+
+    #[test]
+    fn sidecar_path() {
+        assert_eq!(AssetMeta::sidecar_path(Path::new("textures/brick.png")), PathBuf::from("textures/brick.png.meta"));
+    }
+
+    #[test]
+    fn index_moves() {
+        let mut index = GuidIndex::new();
+
+        let guid = Guid::new();
+
+        assert_eq!(index.insert(guid, PathBuf::from("a.png")), None);
+        assert_eq!(index.insert(guid, PathBuf::from("b.png")), Some(PathBuf::from("a.png")));
+
+        assert_eq!(index.path(&guid), Some(Path::new("b.png")));
+        assert_eq!(index.guid(Path::new("a.png")), None);
+        assert_eq!(index.len(), 1);
+    }
+
+    #[test]
+    fn scan_duplicates() {
+        let root = env::temp_dir().join("combustion_guid_scan_test");
+
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("textures")).unwrap();
+
+        let vfs: Arc<BoxedVFS> = Arc::new(box DefaultFS);
+
+        let meta = AssetMeta::new(ImportSettings::Model);
+
+        meta.write(&root.join("a.obj"), &vfs).unwrap();
+        AssetMeta::new(TextureAsset::default_settings()).write(&root.join("textures/b.png"), &vfs).unwrap();
+
+        let index = GuidIndex::scan(&root, &vfs).unwrap();
+
+        assert_eq!(index.len(), 2);
+        assert_eq!(index.path(&meta.guid), Some(Path::new("a.obj")));
+
+        // Copying an asset along with its sidecar duplicates the GUID
+        meta.write(&root.join("textures/c.obj"), &vfs).unwrap();
+
+        assert!(GuidIndex::scan(&root, &vfs).is_err());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,294 @@
+//! Reading native Cap'n Proto assets
+//!
+//! Cap'n Proto messages declare their own size, so without limits a malformed or hostile file
+//! could make the reader allocate or traverse far more memory than the file itself takes up.
+//! `ReaderLimits` bounds that work, by default relative to the size of the file being read.
+//!
+//! Native assets may optionally be compressed as a whole with LZ4, in which case the packed message
+//! is preceded by `LZ4_MAGIC` and stored as a single LZ4 frame. Loading detects this and decompresses transparently.
+
+use std::io::{Read, Write, BufReader, Seek, SeekFrom};
+use std::path::Path;
+
+use capnp::serialize::OwnedSegments;
+use capnp::serialize_packed;
+use capnp::message::{Allocator, Builder, Reader, ReaderOptions};
+
+use lz4::{EncoderBuilder, Decoder, ContentChecksum};
+
+use common::vfs::BoxedVFS;
+use common::compression::CompressionOptions;
+
+use ::error::AssetResult;
+
+/// Smallest traversal limit derived from a file size, which is also the Cap'n Proto default of 64 MiB
+pub const MIN_TRAVERSAL_LIMIT_IN_WORDS: u64 = 8 * 1024 * 1024;
+
+/// How many times larger than the file a message may be once unpacked, since packing compresses runs of zeroes
+pub const MAX_PACKED_EXPANSION: u64 = 64;
+
+/// How many times larger than the file a message may be once decompressed, which is the LZ4 worst case
+pub const MAX_LZ4_EXPANSION: u64 = 255;
+
+/// Flag at the start of native assets compressed with LZ4.
+///
+/// As a packed message header this would declare tens of thousands of segments, so it can't be mistaken for one.
+pub const LZ4_MAGIC: [u8; 4] = [b'C', b'L', b'Z', b'4'];
+
+/// Default limit on how deeply structures may be nested
+pub const DEFAULT_NESTING_LIMIT: i32 = 64;
+
+/// Limits applied when reading native assets
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ReaderLimits {
+    /// Maximum number of words that may be read from the message.
+    ///
+    /// If `None`, it's derived from the file size using `MAX_PACKED_EXPANSION`,
+    /// but never less than `MIN_TRAVERSAL_LIMIT_IN_WORDS`.
+    pub traversal_limit_in_words: Option<u64>,
+    /// Maximum depth of nested structures and lists
+    pub nesting_limit: i32,
+}
+
+impl Default for ReaderLimits {
+    fn default() -> ReaderLimits {
+        ReaderLimits {
+            traversal_limit_in_words: None,
+            nesting_limit: DEFAULT_NESTING_LIMIT,
+        }
+    }
+}
+
+impl ReaderLimits {
+    /// Creates `ReaderOptions` for reading a file of `file_size` bytes
+    pub fn reader_options(&self, file_size: u64) -> ReaderOptions {
+        let derived = || (file_size / 8).saturating_mul(MAX_PACKED_EXPANSION).max(MIN_TRAVERSAL_LIMIT_IN_WORDS);
+
+        ReaderOptions {
+            traversal_limit_in_words: self.traversal_limit_in_words.unwrap_or_else(derived),
+            nesting_limit: self.nesting_limit,
+        }
+    }
+}
+
+/// Reads a packed Cap'n Proto message from the file at `path`, applying the given limits.
+///
+/// If the file was compressed by `write_native_message`, it's decompressed while reading.
+pub fn read_native_message(path: &Path, vfs: &BoxedVFS, limits: ReaderLimits) -> AssetResult<Reader<OwnedSegments>> {
+    read_native_stream(try_throw!(vfs.open(path)), limits)
+}
+
+/// Reads a packed, and possibly compressed, Cap'n Proto message from the start of `stream`, as `read_native_message` does
+pub fn read_native_stream<S: Read + Seek>(mut stream: S, limits: ReaderLimits) -> AssetResult<Reader<OwnedSegments>> {
+    let file_size = try_throw!(stream.seek(SeekFrom::End(0)));
+
+    try_throw!(stream.seek(SeekFrom::Start(0)));
+
+    let mut magic = [0; 4];
+
+    // Files too short for the flag are left for the message reader to reject
+    let compressed = stream.read_exact(&mut magic).is_ok() && magic == LZ4_MAGIC;
+
+    if compressed {
+        // The decoder already buffers the compressed data, so only its output needs buffering
+        let mut reader = BufReader::new(try_throw!(Decoder::new(stream)));
+
+        let options = limits.reader_options(file_size.saturating_mul(MAX_LZ4_EXPANSION));
+
+        Ok(try_throw!(serialize_packed::read_message(&mut reader, options)))
+    } else {
+        try_throw!(stream.seek(SeekFrom::Start(0)));
+
+        let mut reader = BufReader::new(stream);
+
+        Ok(try_throw!(serialize_packed::read_message(&mut reader, limits.reader_options(file_size))))
+    }
+}
+
+/// Writes a packed Cap'n Proto message to the file at `path`, compressing it with LZ4 if `compression` is given
+pub fn write_native_message<A>(path: &Path, vfs: &BoxedVFS, message: &Builder<A>, compression: Option<CompressionOptions>) -> AssetResult<()> where A: Allocator {
+    let mut stream = try_throw!(vfs.create_or_truncate(path));
+
+    if let Some(options) = compression {
+        try_throw!(stream.write_all(&LZ4_MAGIC));
+
+        let mut encoder = try_throw!(EncoderBuilder::new()
+            .level(options.level)
+            .block_size(options.blocksize)
+            .block_mode(options.blockmode)
+            .checksum(ContentChecksum::ChecksumEnabled)
+            .build(stream));
+
+        try_throw!(serialize_packed::write_message(&mut encoder, message));
+
+        let (_, result) = encoder.finish();
+
+        try_throw!(result);
+    } else {
+        try_throw!(serialize_packed::write_message(&mut stream, message));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::prelude::*;
+    use std::io::Cursor;
+    use std::path::Path;
+    use std::sync::Arc;
+    use std::env;
+    use std::fs;
+
+    use capnp::message::Builder;
+    use capnp::serialize_packed;
+
+    use common::vfs::BoxedVFS;
+    use common::vfs::default::DefaultFS;
+    use common::compression::CompressionOptions;
+
+    use protocols::traits::Storage;
+    use protocols::error::ProtocolError;
+    use protocols::model::protocol;
+    use protocols::model::data::{Model, Node, LodGroup, LodLevel, LodThreshold};
+
+    use ::error::{AssetResult, AssetError};
+
+    use super::*;
+
+    fn sample_model(meshes: Vec<u32>) -> Model {
+        Model {
+            root: Node { name: "root".to_string(), meshes: meshes, ..Node::default() },
+            meshes: Vec::new(),
+            materials: (0..32).map(|i| format!("material {}", i)).collect(),
+        }
+    }
+
+    fn save_model(model: &Model, path: &Path, vfs: &BoxedVFS, compression: Option<CompressionOptions>) {
+        let mut message = Builder::new_default();
+
+        model.save_to_builder(message.init_root::<protocol::model::Builder>()).unwrap();
+
+        write_native_message(path, vfs, &message, compression).unwrap();
+    }
+
+    fn load_model(path: &Path, vfs: &BoxedVFS, limits: ReaderLimits) -> AssetResult<Model> {
+        let message = try_rethrow!(read_native_message(path, vfs, limits));
+
+        let reader = try_throw!(message.get_root::<protocol::model::Reader>());
+
+        Ok(try_rethrow!(Model::load_from_reader(reader)))
+    }
+
+    fn is_out_of_bounds(result: AssetResult<Model>) -> bool {
+        match result {
+            Err(ref err) => matches!(*err.error(), AssetError::ProtocolError(ProtocolError::OutOfBounds)),
+            Ok(_) => false,
+        }
+    }
+
+    #[test]
+    fn rejects_malformed_messages() {
+        let root = env::temp_dir().join("combustion_native_test");
+
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+
+        let vfs: Arc<BoxedVFS> = Arc::new(box DefaultFS);
+
+        // Mesh indices past the end of the mesh list
+        let path = root.join("out_of_range.cmdl");
+
+        save_model(&sample_model(vec![3]), &path, &vfs, None);
+
+        assert!(is_out_of_bounds(load_model(&path, &vfs, ReaderLimits::default())));
+
+        let mut model = sample_model(vec![]);
+
+        model.root.children.push(Node {
+            lod: Some(LodGroup {
+                levels: vec![LodLevel { meshes: vec![1], threshold: LodThreshold::Distance(10.0) }],
+                transition: None,
+            }),
+            ..Node::default()
+        });
+
+        save_model(&model, &path, &vfs, None);
+
+        assert!(is_out_of_bounds(load_model(&path, &vfs, ReaderLimits::default())));
+
+        // Messages larger than the traversal limit
+        let path = root.join("oversized.cmdl");
+
+        save_model(&sample_model(vec![]), &path, &vfs, None);
+
+        assert!(load_model(&path, &vfs, ReaderLimits::default()).is_ok());
+        assert!(load_model(&path, &vfs, ReaderLimits { traversal_limit_in_words: Some(4), ..ReaderLimits::default() }).is_err());
+
+        // Truncated messages, both plain and compressed
+        let mut data = Vec::new();
+
+        fs::File::open(&path).unwrap().read_to_end(&mut data).unwrap();
+
+        for &length in &[0, 1, 7, data.len() / 2, data.len() - 1] {
+            assert!(read_native_stream(Cursor::new(&data[..length]), ReaderLimits::default()).is_err());
+        }
+
+        save_model(&sample_model(vec![]), &path, &vfs, Some(CompressionOptions::with_level(4)));
+
+        data.clear();
+
+        fs::File::open(&path).unwrap().read_to_end(&mut data).unwrap();
+
+        for &length in &[LZ4_MAGIC.len(), data.len() / 2] {
+            assert!(read_native_stream(Cursor::new(&data[..length]), ReaderLimits::default()).is_err());
+        }
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn compressed_round_trip() {
+        let root = env::temp_dir().join("combustion_native_lz4_test");
+
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+
+        let vfs: Arc<BoxedVFS> = Arc::new(box DefaultFS);
+
+        let model = sample_model(vec![]);
+
+        let compressed = root.join("compressed.cmdl");
+
+        save_model(&model, &compressed, &vfs, Some(CompressionOptions::with_level(4)));
+
+        let mut magic = [0; 4];
+
+        fs::File::open(&compressed).unwrap().read_exact(&mut magic).unwrap();
+
+        assert_eq!(magic, LZ4_MAGIC);
+
+        let loaded = load_model(&compressed, &vfs, ReaderLimits::default()).unwrap();
+
+        assert_eq!(loaded.root.name, model.root.name);
+        assert_eq!(loaded.materials, model.materials);
+
+        // Files written before compression was supported are plain packed messages
+        let legacy = root.join("legacy.cmdl");
+
+        {
+            let mut message = Builder::new_default();
+
+            model.save_to_builder(message.init_root::<protocol::model::Builder>()).unwrap();
+
+            serialize_packed::write_message(&mut fs::File::create(&legacy).unwrap(), &message).unwrap();
+        }
+
+        let loaded = load_model(&legacy, &vfs, ReaderLimits::default()).unwrap();
+
+        assert_eq!(loaded.root.name, model.root.name);
+        assert_eq!(loaded.materials, model.materials);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
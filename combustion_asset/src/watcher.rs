@@ -0,0 +1,390 @@
+//! Poll-based asset file watching and hot reloading
+//!
+//! The `AssetWatcher` keeps track of the paths of loaded assets and the last modified time
+//! reported by their virtual filesystem. Each call to `AssetWatcher::poll` checks every watched entry,
+//! and if it has changed the asset is loaded again with the same arguments it was originally loaded with.
+//!
+//! Reloaded assets are published through a `ParallelEventEmitter` as `AssetEvent::Reloaded` events,
+//! keyed by the asset type, so systems can listen for the asset types they care about and swap in the new data.
+
+use std::any::TypeId;
+use std::fmt::{Debug, Formatter, Result as FmtResult};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::SystemTime;
+
+use fnv::FnvHashMap;
+use futures::Future;
+
+use common::vfs::BoxedVFS;
+
+use events::ParallelEventEmitter;
+
+use ::error::AssetResult;
+use ::asset::{Asset, AssetMedium};
+
+/// Unique identifier for a watched asset
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct WatchId(u64);
+
+/// Event keys emitted by the `AssetWatcher`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AssetEvent {
+    /// An asset of the given type was successfully reloaded.
+    ///
+    /// The event value is a `ReloadedAsset<T>` for that type.
+    Reloaded(TypeId),
+    /// An asset of the given type was modified but could not be reloaded.
+    ///
+    /// The event value is a `ReloadFailure`.
+    ReloadFailed(TypeId),
+}
+
+impl AssetEvent {
+    /// Event key for successful reloads of asset type `T`
+    pub fn reloaded<T: 'static>() -> AssetEvent {
+        AssetEvent::Reloaded(TypeId::of::<T>())
+    }
+
+    /// Event key for failed reloads of asset type `T`
+    pub fn reload_failed<T: 'static>() -> AssetEvent {
+        AssetEvent::ReloadFailed(TypeId::of::<T>())
+    }
+}
+
+/// Event value for a successfully reloaded asset
+#[derive(Debug)]
+pub struct ReloadedAsset<T> {
+    /// Watch entry that was reloaded
+    pub id: WatchId,
+    /// Path of the reloaded asset
+    pub path: PathBuf,
+    /// The newly loaded asset
+    pub asset: Arc<T>,
+}
+
+impl<T> Clone for ReloadedAsset<T> {
+    fn clone(&self) -> ReloadedAsset<T> {
+        ReloadedAsset {
+            id: self.id,
+            path: self.path.clone(),
+            asset: self.asset.clone(),
+        }
+    }
+}
+
+/// Event value for an asset that failed to reload
+#[derive(Debug, Clone)]
+pub struct ReloadFailure {
+    /// Watch entry that failed to reload
+    pub id: WatchId,
+    /// Path of the asset
+    pub path: PathBuf,
+    /// Description of the error
+    pub error: String,
+}
+
+/// Outcome of a single reload attempt, as returned from `AssetWatcher::poll`
+#[derive(Debug, Clone)]
+pub enum ReloadStatus {
+    /// The asset was reloaded and a `Reloaded` event was emitted
+    Reloaded(WatchId),
+    /// The asset could not be reloaded and a `ReloadFailed` event was emitted
+    Failed(ReloadFailure),
+}
+
+// Type-erased reload routine for a single watched asset
+type ReloadFn = Box<FnMut(WatchId, &Path, &Arc<BoxedVFS>, &mut ParallelEventEmitter<AssetEvent>) -> ReloadStatus + Send>;
+
+struct WatchEntry {
+    path: PathBuf,
+    vfs: Arc<BoxedVFS>,
+    modified: Option<SystemTime>,
+    reload: ReloadFn,
+}
+
+impl Debug for WatchEntry {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "WatchEntry {{path: {:?}, vfs: {:?}, modified: {:?}}}", self.path, self.vfs, self.modified)
+    }
+}
+
+/// Polls watched asset files for modifications and reloads them
+pub struct AssetWatcher {
+    entries: FnvHashMap<WatchId, WatchEntry>,
+    events: ParallelEventEmitter<AssetEvent>,
+    next_id: u64,
+}
+
+impl Debug for AssetWatcher {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "AssetWatcher {{entries: {:?}}}", self.entries)
+    }
+}
+
+impl Default for AssetWatcher {
+    fn default() -> AssetWatcher {
+        AssetWatcher::new()
+    }
+}
+
+// Returns `None` if the modification time could not be determined
+fn modified_time(vfs: &Arc<BoxedVFS>, path: &Path) -> Option<SystemTime> {
+    vfs.metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
+
+impl AssetWatcher {
+    /// Create a new `AssetWatcher` with no watched assets
+    pub fn new() -> AssetWatcher {
+        AssetWatcher {
+            entries: FnvHashMap::default(),
+            events: ParallelEventEmitter::new(),
+            next_id: 0,
+        }
+    }
+
+    /// Get the event emitter so listeners for `AssetEvent`s can be added
+    pub fn events(&mut self) -> &mut ParallelEventEmitter<AssetEvent> {
+        &mut self.events
+    }
+
+    /// Watch an asset at `path` on the given `vfs`.
+    ///
+    /// When the file is modified, `T::load` will be called again with a clone of `args`.
+    pub fn watch<T, L>(&mut self, path: &Path, vfs: Arc<BoxedVFS>, args: L) -> WatchId
+        where T: for<'a> Asset<'a, LoadArgs = L> + Send + Sync + 'static,
+              L: Clone + Send + 'static
+    {
+        let id = WatchId(self.next_id);
+
+        self.next_id += 1;
+
+        let reload: ReloadFn = Box::new(move |id, path, vfs, events| {
+            match T::load(AssetMedium::File(path, vfs.clone()), args.clone()) {
+                Ok(asset) => {
+                    let value = ReloadedAsset {
+                        id: id,
+                        path: path.to_path_buf(),
+                        asset: Arc::new(asset),
+                    };
+
+                    if let Err(err) = events.emit_value(AssetEvent::reloaded::<T>(), value).wait() {
+                        warn!("Error emitting asset reload event: {:?}", err);
+                    }
+
+                    ReloadStatus::Reloaded(id)
+                },
+                Err(err) => {
+                    let failure = ReloadFailure {
+                        id: id,
+                        path: path.to_path_buf(),
+                        error: err.to_string(),
+                    };
+
+                    if let Err(err) = events.emit_value(AssetEvent::reload_failed::<T>(), failure.clone()).wait() {
+                        warn!("Error emitting asset reload failure event: {:?}", err);
+                    }
+
+                    ReloadStatus::Failed(failure)
+                }
+            }
+        });
+
+        let modified = modified_time(&vfs, path);
+
+        self.entries.insert(id, WatchEntry {
+            path: path.to_path_buf(),
+            vfs: vfs,
+            modified: modified,
+            reload: reload,
+        });
+
+        id
+    }
+
+    /// Load an asset and immediately begin watching it for modifications
+    pub fn load_and_watch<T, L>(&mut self, path: &Path, vfs: Arc<BoxedVFS>, args: L) -> AssetResult<(WatchId, T)>
+        where T: for<'a> Asset<'a, LoadArgs = L> + Send + Sync + 'static,
+              L: Clone + Send + 'static
+    {
+        let asset = T::load(AssetMedium::File(path, vfs.clone()), args.clone())?;
+
+        Ok((self.watch::<T, L>(path, vfs, args), asset))
+    }
+
+    /// Stop watching an asset. Returns `false` if it was not being watched.
+    pub fn unwatch(&mut self, id: WatchId) -> bool {
+        self.entries.remove(&id).is_some()
+    }
+
+    /// Check if any asset at `path` is being watched
+    pub fn is_watched(&self, path: &Path) -> bool {
+        self.entries.values().any(|entry| entry.path == path)
+    }
+
+    /// Get the path of a watched asset
+    pub fn path(&self, id: WatchId) -> Option<&Path> {
+        self.entries.get(&id).map(|entry| entry.path.as_path())
+    }
+
+    /// Number of watched assets
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Check if no assets are being watched
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Check every watched asset for modifications, and reload any that have changed.
+    ///
+    /// Returns the status of every reload that was attempted.
+    pub fn poll(&mut self) -> Vec<ReloadStatus> {
+        let mut results = Vec::new();
+
+        let AssetWatcher { ref mut entries, ref mut events, .. } = *self;
+
+        for (id, entry) in entries.iter_mut() {
+            let modified = modified_time(&entry.vfs, &entry.path);
+
+            // Entries that can't be checked right now (e.g. mid-save) are skipped until the next poll
+            if modified.is_some() && modified != entry.modified {
+                entry.modified = modified;
+
+                info!("Reloading modified asset {:?}", entry.path);
+
+                results.push((entry.reload)(*id, &entry.path, &entry.vfs, events));
+            }
+        }
+
+        results
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::{self, Cursor};
+    use std::io::prelude::*;
+    use std::path::Path;
+    use std::sync::{Arc, Mutex};
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+    use common::streams::BoxedStream;
+    use common::vfs::{VirtualFS, VirtualMetadata, BoxedMetadata, BoxedVFS, OpenOptions};
+
+    use ::error::{AssetResult, AssetError};
+    use ::asset::{Asset, AssetMedium};
+
+    use super::*;
+
+    // Single-file VFS with controllable contents and modification time
+    #[derive(Debug, Clone, Default)]
+    struct MockFS {
+        state: Arc<Mutex<(String, u64)>>,
+    }
+
+    struct MockMetadata(u64);
+
+    impl VirtualMetadata for MockMetadata {
+        fn is_file(&self) -> bool { true }
+        fn is_dir(&self) -> bool { false }
+        fn modified(&self) -> io::Result<SystemTime> {
+            Ok(UNIX_EPOCH + Duration::from_secs(self.0))
+        }
+    }
+
+    impl MockFS {
+        fn set(&self, contents: &str, modified: u64) {
+            *self.state.lock().unwrap() = (contents.to_string(), modified);
+        }
+    }
+
+    impl VirtualFS for MockFS {
+        fn open_with(&self, _: &Path, _: OpenOptions) -> io::Result<BoxedStream> {
+            Ok(Box::new(Cursor::new(self.state.lock().unwrap().0.clone().into_bytes())))
+        }
+
+        fn metadata(&self, _: &Path) -> io::Result<BoxedMetadata> {
+            Ok(Box::new(MockMetadata(self.state.lock().unwrap().1)))
+        }
+    }
+
+    struct TextAsset(String);
+
+    impl<'a> Asset<'a> for TextAsset {
+        type LoadArgs = bool;
+        type SaveArgs = ();
+        type Query = ();
+
+        fn load(medium: AssetMedium<'a>, uppercase: bool) -> AssetResult<TextAsset> {
+            if let AssetMedium::File(path, vfs) = medium {
+                let mut text = String::new();
+
+                try_throw!(try_throw!(vfs.open(path)).read_to_string(&mut text));
+
+                if text.is_empty() {
+                    throw!(AssetError::InvalidValue);
+                }
+
+                return Ok(TextAsset(if uppercase { text.to_uppercase() } else { text }));
+            }
+
+            throw!(AssetError::UnsupportedMedium)
+        }
+
+        fn save(&self, _: AssetMedium<'a>, _: ()) -> AssetResult<()> {
+            throw!(AssetError::Unimplemented("saving text assets"))
+        }
+
+        fn query(_: ()) -> AssetResult<()> { Ok(()) }
+    }
+
+    #[test]
+    fn reload_on_modification() {
+        let mock = MockFS::default();
+
+        mock.set("first", 1);
+
+        let vfs = Arc::new(Box::new(mock.clone()) as BoxedVFS);
+
+        let mut watcher = AssetWatcher::new();
+
+        let (id, asset) = watcher.load_and_watch::<TextAsset, _>(Path::new("test.txt"), vfs, true).unwrap();
+
+        assert_eq!(asset.0, "FIRST");
+        assert!(watcher.is_watched(Path::new("test.txt")));
+
+        let reloaded = Arc::new(Mutex::new(Vec::new()));
+
+        {
+            let reloaded = reloaded.clone();
+
+            watcher.events().add_listener_value(AssetEvent::reloaded::<TextAsset>(), move |value: Option<ReloadedAsset<TextAsset>>| {
+                reloaded.lock().unwrap().push(value.unwrap().asset.0.clone());
+
+                Ok(())
+            }).unwrap();
+        }
+
+        // Nothing changed yet
+        assert!(watcher.poll().is_empty());
+
+        mock.set("second", 2);
+
+        let results = watcher.poll();
+
+        assert_eq!(results.len(), 1);
+        assert!(matches!(results[0], ReloadStatus::Reloaded(reloaded_id) if reloaded_id == id));
+        assert_eq!(*reloaded.lock().unwrap(), vec!["SECOND".to_string()]);
+
+        // Failed reloads are reported, and not retried until the file changes again
+        mock.set("", 3);
+
+        assert!(matches!(watcher.poll()[0], ReloadStatus::Failed(_)));
+        assert!(watcher.poll().is_empty());
+
+        assert!(watcher.unwatch(id));
+        assert!(watcher.is_empty());
+    }
+}
@@ -2,6 +2,9 @@ extern crate ogg;
 extern crate lewton;
 extern crate openal as al;
 
+#[macro_use]
+extern crate trace_error;
+
 #[macro_use]
 extern crate combustion_common as common;
 #[macro_use]
@@ -11,4 +14,5 @@ extern crate combustion_ecs as ecs;
 #[macro_use]
 extern crate combustion_macros;
 
-pub mod components;
\ No newline at end of file
+pub mod components;
+pub mod sound;
\ No newline at end of file
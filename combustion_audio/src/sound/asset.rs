@@ -0,0 +1,127 @@
+//! Sound asset implementation
+
+use std::ops::{Deref, DerefMut};
+use std::ascii::AsciiExt;
+use std::io::BufWriter;
+
+use asset::error::{AssetResult, AssetError};
+use asset::asset::{Asset, AssetMedium, AssetQuery, AssetFileFormat};
+use asset::cache::AssetSize;
+
+use super::formats::SoundFileFormat;
+use super::samples::Sound;
+use super::stream::SoundStream;
+use super::wav;
+
+/// Sound Asset queries
+#[derive(Debug, Clone, Copy)]
+pub enum SoundAssetQuery<'a> {
+    /// Check if a file extension for a sound file is supported for importing
+    SupportedImportExtension(&'a str),
+    /// Check if a file extension for a sound file is supported for exporting
+    SupportedExportExtension(&'a str),
+    /// Check if a file extension for a sound file is supported for both importing and exporting
+    SupportedExtension(&'a str),
+}
+
+impl<'a> AssetQuery for SoundAssetQuery<'a> {
+    type Arguments = SoundAssetQuery<'a>;
+    type Result = bool;
+}
+
+/// Sound Asset, holding a fully decoded sound
+///
+/// Use `SoundStream` instead for long sounds that should be decoded as they are played.
+pub struct SoundAsset(Sound);
+
+impl<'a> Asset<'a> for SoundAsset {
+    type LoadArgs = ();
+    type SaveArgs = ();
+
+    type Query = SoundAssetQuery<'a>;
+
+    fn query(query: SoundAssetQuery<'a>) -> AssetResult<bool> {
+        Ok(match query {
+            SoundAssetQuery::SupportedImportExtension(ext) => {
+                match SoundFileFormat::from_extension(ext) {
+                    Some(format) if format.can_import() => true,
+                    _ => false
+                }
+            },
+            SoundAssetQuery::SupportedExportExtension(ext) => {
+                match SoundFileFormat::from_extension(ext) {
+                    Some(format) if format.can_export() => true,
+                    _ => false
+                }
+            },
+            SoundAssetQuery::SupportedExtension(ext) => {
+                match SoundFileFormat::from_extension(ext) {
+                    Some(format) if format.can_import() && format.can_export() => true,
+                    _ => false
+                }
+            },
+        })
+    }
+
+    fn load(medium: AssetMedium<'a>, _: ()) -> AssetResult<SoundAsset> {
+        let stream = try_rethrow!(SoundStream::open(medium));
+
+        Ok(SoundAsset(try_rethrow!(stream.read_to_end())))
+    }
+
+    fn save(&self, medium: AssetMedium<'a>, _: ()) -> AssetResult<()> {
+        if let AssetMedium::File(path, vfs) = medium {
+            if let Some(ext) = path.extension() {
+                let ext = try_throw!(ext.to_str().ok_or(AssetError::InvalidValue)).to_ascii_lowercase();
+
+                let format = match SoundFileFormat::from_extension(ext.as_str()) {
+                    Some(format) if format.can_export() => format,
+                    _ => throw!(AssetError::UnsupportedFormat),
+                };
+
+                match format {
+                    SoundFileFormat::Wav => {
+                        let writer = BufWriter::new(try_throw!(vfs.create_or_truncate(path)));
+
+                        return wav::write_wav(writer, &self.0);
+                    },
+                    SoundFileFormat::Vorbis => throw!(AssetError::UnsupportedFormat),
+                }
+            }
+        }
+
+        throw!(AssetError::UnsupportedMedium)
+    }
+}
+
+impl SoundAsset {
+    /// Wraps an already decoded sound
+    pub fn new(sound: Sound) -> SoundAsset {
+        SoundAsset(sound)
+    }
+
+    /// Unwraps the decoded sound
+    pub fn into_inner(self) -> Sound {
+        self.0
+    }
+}
+
+impl AssetSize for SoundAsset {
+    fn approximate_size(&self) -> usize {
+        self.0.samples.size_in_bytes()
+    }
+}
+
+impl Deref for SoundAsset {
+    type Target = Sound;
+
+    fn deref(&self) -> &Sound {
+        &self.0
+    }
+}
+
+impl DerefMut for SoundAsset {
+    fn deref_mut(&mut self) -> &mut Sound {
+        &mut self.0
+    }
+}
@@ -0,0 +1,31 @@
+//! Sound asset formats
+
+use asset::asset::AssetFileFormat;
+
+/// Supported file formats
+#[derive(Debug, Clone, Copy, PartialEq, Hash, PartialOrd)]
+pub enum SoundFileFormat {
+    /// Ogg Vorbis
+    Vorbis,
+    /// RIFF WAVE
+    Wav,
+}
+
+impl AssetFileFormat for SoundFileFormat {
+    fn from_extension(ext: &str) -> Option<SoundFileFormat> {
+        match ext {
+            "ogg" | "oga" => Some(SoundFileFormat::Vorbis),
+            "wav" | "wave" => Some(SoundFileFormat::Wav),
+            _ => None,
+        }
+    }
+
+    fn can_import(&self) -> bool {
+        true
+    }
+
+    fn can_export(&self) -> bool {
+        // There is no Vorbis encoder
+        *self == SoundFileFormat::Wav
+    }
+}
@@ -0,0 +1,12 @@
+//! Sound assets and decoding
+
+pub mod formats;
+pub mod samples;
+pub mod wav;
+pub mod stream;
+pub mod asset;
+
+pub use self::formats::SoundFileFormat;
+pub use self::samples::{Samples, Sound};
+pub use self::stream::SoundStream;
+pub use self::asset::{SoundAsset, SoundAssetQuery};
@@ -0,0 +1,90 @@
+//! Decoded audio samples
+
+use std::mem;
+
+/// Interleaved audio samples, in the sample format they were decoded with
+#[derive(Debug, Clone, PartialEq)]
+pub enum Samples {
+    /// Signed 16-bit samples, used for Ogg Vorbis and 8-bit or 16-bit PCM
+    I16(Vec<i16>),
+    /// Float samples nominally in the range `[-1, 1]`, used for float WAV and 24-bit or 32-bit PCM
+    F32(Vec<f32>),
+}
+
+impl Samples {
+    /// Number of samples across all channels
+    pub fn len(&self) -> usize {
+        match *self {
+            Samples::I16(ref samples) => samples.len(),
+            Samples::F32(ref samples) => samples.len(),
+        }
+    }
+
+    /// Checks if there are no samples
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Size of the sample data in bytes
+    pub fn size_in_bytes(&self) -> usize {
+        match *self {
+            Samples::I16(ref samples) => samples.len() * mem::size_of::<i16>(),
+            Samples::F32(ref samples) => samples.len() * mem::size_of::<f32>(),
+        }
+    }
+
+    /// Appends other samples, converting them to this sample format if they differ
+    pub fn append(&mut self, other: Samples) {
+        match *self {
+            Samples::I16(ref mut samples) => match other {
+                Samples::I16(mut other) => samples.append(&mut other),
+                Samples::F32(other) => samples.extend(other.into_iter().map(f32_to_i16)),
+            },
+            Samples::F32(ref mut samples) => samples.extend(other.to_f32()),
+        }
+    }
+
+    /// Converts the samples to floats in the range `[-1, 1]`
+    pub fn to_f32(self) -> Vec<f32> {
+        match self {
+            Samples::I16(samples) => samples.into_iter().map(|sample| sample as f32 / 32768.0).collect(),
+            Samples::F32(samples) => samples,
+        }
+    }
+
+    /// Converts the samples to signed 16-bit samples, clamping any float samples outside `[-1, 1]`
+    pub fn to_i16(self) -> Vec<i16> {
+        match self {
+            Samples::I16(samples) => samples,
+            Samples::F32(samples) => samples.into_iter().map(f32_to_i16).collect(),
+        }
+    }
+}
+
+#[inline]
+fn f32_to_i16(sample: f32) -> i16 {
+    (sample * 32768.0).max(-32768.0).min(32767.0) as i16
+}
+
+/// A fully decoded sound
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sound {
+    /// Interleaved samples, with one sample per channel for each frame
+    pub samples: Samples,
+    /// Frames per second
+    pub sample_rate: u32,
+    /// Number of interleaved channels
+    pub channels: u16,
+}
+
+impl Sound {
+    /// Number of frames, where each frame has one sample for every channel
+    pub fn frames(&self) -> usize {
+        if self.channels == 0 { 0 } else { self.samples.len() / self.channels as usize }
+    }
+
+    /// Length of the sound in seconds
+    pub fn duration(&self) -> f64 {
+        if self.sample_rate == 0 { 0.0 } else { self.frames() as f64 / self.sample_rate as f64 }
+    }
+}
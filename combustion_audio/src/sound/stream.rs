@@ -0,0 +1,145 @@
+//! Incremental sound decoding
+//!
+//! Long music tracks take a lot of memory once fully decoded,
+//! so `SoundStream` decodes them a chunk at a time as they are played.
+
+use std::ascii::AsciiExt;
+use std::io::BufReader;
+
+use lewton::VorbisError;
+use lewton::inside_ogg::OggStreamReader;
+
+use common::streams::definitions::BoxedStream;
+
+use asset::error::{AssetResult, AssetError};
+use asset::asset::{AssetMedium, AssetFileFormat};
+
+use super::formats::SoundFileFormat;
+use super::samples::{Samples, Sound};
+use super::wav::{WavReader, WavEncoding};
+
+/// Number of frames read from WAV files at a time
+pub const WAV_CHUNK_FRAMES: usize = 4096;
+
+/// Converts a Vorbis decoding error into an `AssetError`
+pub fn vorbis_error(err: VorbisError) -> AssetError {
+    AssetError::Other(format!("Vorbis decoding error: {:?}", err))
+}
+
+enum Decoder {
+    Vorbis(OggStreamReader<BufReader<BoxedStream>>),
+    Wav(WavReader<BufReader<BoxedStream>>),
+}
+
+/// Decodes a sound incrementally from a stream
+pub struct SoundStream {
+    decoder: Decoder,
+    sample_rate: u32,
+    channels: u16,
+}
+
+impl SoundStream {
+    /// Reads the headers of a sound in the given format, leaving the stream at the start of the sample data
+    pub fn new(stream: BoxedStream, format: SoundFileFormat) -> AssetResult<SoundStream> {
+        let stream = BufReader::new(stream);
+
+        Ok(match format {
+            SoundFileFormat::Vorbis => {
+                let reader = try_throw!(OggStreamReader::new(stream).map_err(vorbis_error));
+
+                let (sample_rate, channels) = (reader.ident_hdr.audio_sample_rate, reader.ident_hdr.audio_channels as u16);
+
+                SoundStream { decoder: Decoder::Vorbis(reader), sample_rate: sample_rate, channels: channels }
+            },
+            SoundFileFormat::Wav => {
+                let reader = try_rethrow!(WavReader::new(stream));
+
+                let (sample_rate, channels) = (reader.format().sample_rate, reader.format().channels);
+
+                SoundStream { decoder: Decoder::Wav(reader), sample_rate: sample_rate, channels: channels }
+            },
+        })
+    }
+
+    /// Opens a sound file for streaming, determining its format from the file extension
+    pub fn open(medium: AssetMedium) -> AssetResult<SoundStream> {
+        if let AssetMedium::File(path, vfs) = medium {
+            if let Some(ext) = path.extension() {
+                let ext = try_throw!(ext.to_str().ok_or(AssetError::InvalidValue)).to_ascii_lowercase();
+
+                let format = match SoundFileFormat::from_extension(ext.as_str()) {
+                    Some(format) if format.can_import() => format,
+                    _ => throw!(AssetError::UnsupportedFormat),
+                };
+
+                return SoundStream::new(try_throw!(vfs.open(path)), format);
+            }
+        }
+
+        throw!(AssetError::UnsupportedMedium)
+    }
+
+    /// Frames per second
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// Number of interleaved channels
+    pub fn channels(&self) -> u16 {
+        self.channels
+    }
+
+    /// Decodes the next chunk of interleaved samples, returning `None` at the end of the stream
+    ///
+    /// Vorbis streams are decoded one packet at a time, and WAV streams `WAV_CHUNK_FRAMES` frames at a time.
+    pub fn next_chunk(&mut self) -> AssetResult<Option<Samples>> {
+        match self.decoder {
+            Decoder::Vorbis(ref mut reader) => {
+                // Some packets, such as the first one, decode to no samples at all
+                loop {
+                    let packet = match try_throw!(reader.read_dec_packet().map_err(vorbis_error)) {
+                        Some(packet) => packet,
+                        None => return Ok(None),
+                    };
+
+                    let frames = packet.first().map_or(0, |channel| channel.len());
+
+                    if frames == 0 {
+                        continue;
+                    }
+
+                    let mut samples = Vec::with_capacity(frames * packet.len());
+
+                    for frame in 0..frames {
+                        for channel in &packet {
+                            samples.push(channel[frame]);
+                        }
+                    }
+
+                    return Ok(Some(Samples::I16(samples)));
+                }
+            },
+            Decoder::Wav(ref mut reader) => reader.read_frames(WAV_CHUNK_FRAMES),
+        }
+    }
+
+    /// Decodes the remainder of the stream into a single `Sound`
+    pub fn read_to_end(mut self) -> AssetResult<Sound> {
+        let mut samples = match self.decoder {
+            Decoder::Wav(ref reader) if reader.format().encoding == WavEncoding::Float || reader.format().bits_per_sample > 16 => {
+                Samples::F32(Vec::new())
+            },
+            _ => Samples::I16(Vec::new()),
+        };
+
+        while let Some(chunk) = try_rethrow!(self.next_chunk()) {
+            samples.append(chunk);
+        }
+
+        Ok(Sound {
+            samples: samples,
+            sample_rate: self.sample_rate,
+            channels: self.channels,
+        })
+    }
+}
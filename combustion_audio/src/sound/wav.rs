@@ -0,0 +1,347 @@
+//! RIFF WAVE reading and writing
+//!
+//! Integer PCM with 8, 16, 24 or 32 bits per sample and IEEE float with 32 or 64 bits per sample are supported,
+//! including their `WAVE_FORMAT_EXTENSIBLE` variants. Any chunks other than `fmt ` and `data` are skipped.
+
+use std::io::{Read, Write, Seek, SeekFrom};
+
+use asset::error::{AssetResult, AssetError};
+
+use super::samples::{Samples, Sound};
+
+const FORMAT_PCM: u16 = 1;
+const FORMAT_IEEE_FLOAT: u16 = 3;
+const FORMAT_EXTENSIBLE: u16 = 0xFFFE;
+
+/// Largest `fmt ` chunk accepted, well above the 40 bytes of `WAVE_FORMAT_EXTENSIBLE`
+const MAX_FORMAT_SIZE: usize = 1024;
+
+/// Sample encoding of a WAV file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WavEncoding {
+    /// Integer PCM samples, unsigned for 8 bits per sample and signed otherwise
+    Pcm,
+    /// IEEE float samples
+    Float,
+}
+
+/// Contents of the `fmt ` chunk
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WavFormat {
+    /// Sample encoding
+    pub encoding: WavEncoding,
+    /// Number of interleaved channels
+    pub channels: u16,
+    /// Frames per second
+    pub sample_rate: u32,
+    /// Bits per sample
+    pub bits_per_sample: u16,
+}
+
+impl WavFormat {
+    /// Size in bytes of a single sample
+    pub fn sample_size(&self) -> usize {
+        self.bits_per_sample as usize / 8
+    }
+
+    /// Size in bytes of a single frame, with one sample for every channel
+    pub fn frame_size(&self) -> usize {
+        self.sample_size() * self.channels as usize
+    }
+}
+
+// Reads incrementally rather than allocating `len` bytes up front, since `len` may come from a corrupt header
+fn read_bytes<R: Read>(reader: &mut R, len: usize) -> AssetResult<Vec<u8>> {
+    let mut bytes = Vec::new();
+
+    try_throw!(reader.by_ref().take(len as u64).read_to_end(&mut bytes));
+
+    if bytes.len() != len {
+        throw!(AssetError::InvalidValue);
+    }
+
+    Ok(bytes)
+}
+
+#[inline]
+fn le_u16(bytes: &[u8], offset: usize) -> u16 {
+    bytes[offset] as u16 | (bytes[offset + 1] as u16) << 8
+}
+
+#[inline]
+fn le_u32(bytes: &[u8], offset: usize) -> u32 {
+    le_u16(bytes, offset) as u32 | (le_u16(bytes, offset + 2) as u32) << 16
+}
+
+fn parse_format(chunk: &[u8]) -> AssetResult<WavFormat> {
+    if chunk.len() < 16 {
+        throw!(AssetError::InvalidValue);
+    }
+
+    let mut tag = le_u16(chunk, 0);
+
+    // The actual format of extensible files is given by the first two bytes of the subformat GUID
+    if tag == FORMAT_EXTENSIBLE {
+        if chunk.len() < 26 {
+            throw!(AssetError::InvalidValue);
+        }
+
+        tag = le_u16(chunk, 24);
+    }
+
+    let format = WavFormat {
+        encoding: match tag {
+            FORMAT_PCM => WavEncoding::Pcm,
+            FORMAT_IEEE_FLOAT => WavEncoding::Float,
+            _ => throw!(AssetError::Unimplemented("Compressed WAV files")),
+        },
+        channels: le_u16(chunk, 2),
+        sample_rate: le_u32(chunk, 4),
+        bits_per_sample: le_u16(chunk, 14),
+    };
+
+    match (format.encoding, format.bits_per_sample) {
+        (WavEncoding::Pcm, 8) | (WavEncoding::Pcm, 16) | (WavEncoding::Pcm, 24) | (WavEncoding::Pcm, 32) |
+        (WavEncoding::Float, 32) | (WavEncoding::Float, 64) => {},
+        _ => throw!(AssetError::Unimplemented("WAV bit depth")),
+    }
+
+    if format.channels == 0 {
+        throw!(AssetError::InvalidValue);
+    }
+
+    Ok(format)
+}
+
+/// Reads WAV samples incrementally
+pub struct WavReader<R: Read + Seek> {
+    reader: R,
+    format: WavFormat,
+    remaining: usize,
+}
+
+impl<R: Read + Seek> WavReader<R> {
+    /// Reads the WAV headers up to the start of the sample data
+    pub fn new(mut reader: R) -> AssetResult<WavReader<R>> {
+        let header = try_rethrow!(read_bytes(&mut reader, 12));
+
+        if &header[0..4] != b"RIFF" || &header[8..12] != b"WAVE" {
+            throw!(AssetError::InvalidValue);
+        }
+
+        let mut format = None;
+
+        loop {
+            let chunk_header = try_rethrow!(read_bytes(&mut reader, 8));
+
+            let size = le_u32(&chunk_header, 4) as usize;
+
+            let id = &chunk_header[0..4];
+
+            if id == b"fmt " {
+                if size > MAX_FORMAT_SIZE {
+                    throw!(AssetError::InvalidValue);
+                }
+
+                let chunk = try_rethrow!(read_bytes(&mut reader, size));
+
+                format = Some(try_rethrow!(parse_format(&chunk)));
+            } else if id == b"data" {
+                let format = match format {
+                    Some(format) => format,
+                    None => throw!(AssetError::InvalidValue),
+                };
+
+                // Streamed files may not know their final length and give 0xFFFFFFFF instead,
+                // so the data never extends past the end of the stream
+                let position = try_throw!(reader.seek(SeekFrom::Current(0)));
+                let end = try_throw!(reader.seek(SeekFrom::End(0)));
+
+                try_throw!(reader.seek(SeekFrom::Start(position)));
+
+                let size = (end - position).min(size as u64) as usize;
+
+                return Ok(WavReader {
+                    reader: reader,
+                    format: format,
+                    // Whole frames only, ignoring any trailing partial frame
+                    remaining: size - size % format.frame_size(),
+                });
+            } else {
+                try_throw!(reader.seek(SeekFrom::Current(size as i64)));
+            }
+
+            // Chunks are padded to an even size
+            if size % 2 == 1 {
+                try_throw!(reader.seek(SeekFrom::Current(1)));
+            }
+        }
+    }
+
+    /// Format of the samples
+    pub fn format(&self) -> &WavFormat {
+        &self.format
+    }
+
+    /// Number of frames left to read
+    pub fn remaining_frames(&self) -> usize {
+        self.remaining / self.format.frame_size()
+    }
+
+    /// Reads and decodes up to `frames` frames, returning `None` once all samples have been read
+    pub fn read_frames(&mut self, frames: usize) -> AssetResult<Option<Samples>> {
+        let size = frames.checked_mul(self.format.frame_size()).unwrap_or(usize::max_value()).min(self.remaining);
+
+        if size == 0 {
+            return Ok(None);
+        }
+
+        let bytes = try_rethrow!(read_bytes(&mut self.reader, size));
+
+        self.remaining -= size;
+
+        Ok(Some(decode_samples(&bytes, &self.format)))
+    }
+}
+
+fn decode_samples(bytes: &[u8], format: &WavFormat) -> Samples {
+    let samples = bytes.chunks(format.sample_size());
+
+    match (format.encoding, format.bits_per_sample) {
+        (WavEncoding::Pcm, 8) => Samples::I16(samples.map(|sample| (sample[0] as i16 - 128) << 8).collect()),
+        (WavEncoding::Pcm, 16) => Samples::I16(samples.map(|sample| le_u16(sample, 0) as i16).collect()),
+        (WavEncoding::Pcm, 24) => Samples::F32(samples.map(|sample| {
+            // Shift into the top of an `i32` so the sign is extended
+            let value = ((sample[0] as u32) << 8 | (sample[1] as u32) << 16 | (sample[2] as u32) << 24) as i32;
+
+            value as f32 / 2147483648.0
+        }).collect()),
+        (WavEncoding::Pcm, _) => Samples::F32(samples.map(|sample| le_u32(sample, 0) as i32 as f32 / 2147483648.0).collect()),
+        (WavEncoding::Float, 32) => Samples::F32(samples.map(|sample| f32::from_bits(le_u32(sample, 0))).collect()),
+        (WavEncoding::Float, _) => Samples::F32(samples.map(|sample| {
+            let bits = le_u32(sample, 0) as u64 | (le_u32(sample, 4) as u64) << 32;
+
+            f64::from_bits(bits) as f32
+        }).collect()),
+    }
+}
+
+/// Reads and decodes an entire WAV file
+pub fn read_wav<R: Read + Seek>(reader: R) -> AssetResult<Sound> {
+    let mut reader = try_rethrow!(WavReader::new(reader));
+
+    let format = *reader.format();
+
+    let frames = reader.remaining_frames();
+
+    let samples = match try_rethrow!(reader.read_frames(frames)) {
+        Some(samples) => samples,
+        None if format.encoding == WavEncoding::Pcm && format.bits_per_sample <= 16 => Samples::I16(Vec::new()),
+        None => Samples::F32(Vec::new()),
+    };
+
+    Ok(Sound {
+        samples: samples,
+        sample_rate: format.sample_rate,
+        channels: format.channels,
+    })
+}
+
+fn push_u16(buffer: &mut Vec<u8>, value: u16) {
+    buffer.extend_from_slice(&[value as u8, (value >> 8) as u8]);
+}
+
+fn push_u32(buffer: &mut Vec<u8>, value: u32) {
+    push_u16(buffer, value as u16);
+    push_u16(buffer, (value >> 16) as u16);
+}
+
+/// Writes a sound as a WAV file, with 16-bit PCM or 32-bit float samples depending on the sample format
+pub fn write_wav<W: Write>(mut writer: W, sound: &Sound) -> AssetResult<()> {
+    let (tag, bits_per_sample) = match sound.samples {
+        Samples::I16(_) => (FORMAT_PCM, 16),
+        Samples::F32(_) => (FORMAT_IEEE_FLOAT, 32),
+    };
+
+    let data_size = sound.samples.size_in_bytes();
+    let block_align = sound.channels as u32 * (bits_per_sample / 8);
+
+    let mut header = Vec::with_capacity(44);
+
+    header.extend_from_slice(b"RIFF");
+    push_u32(&mut header, (36 + data_size) as u32);
+    header.extend_from_slice(b"WAVE");
+
+    header.extend_from_slice(b"fmt ");
+    push_u32(&mut header, 16);
+    push_u16(&mut header, tag);
+    push_u16(&mut header, sound.channels);
+    push_u32(&mut header, sound.sample_rate);
+    push_u32(&mut header, sound.sample_rate * block_align);
+    push_u16(&mut header, block_align as u16);
+    push_u16(&mut header, bits_per_sample as u16);
+
+    header.extend_from_slice(b"data");
+    push_u32(&mut header, data_size as u32);
+
+    try_throw!(writer.write_all(&header));
+
+    let mut data = Vec::with_capacity(data_size);
+
+    match sound.samples {
+        Samples::I16(ref samples) => for &sample in samples { push_u16(&mut data, sample as u16); },
+        Samples::F32(ref samples) => for &sample in samples { push_u32(&mut data, sample.to_bits()); },
+    }
+
+    try_throw!(writer.write_all(&data));
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::Cursor;
+
+    use super::*;
+
+    #[test]
+    fn wav_round_trip() {
+        for samples in vec![Samples::I16(vec![0, -32768, 32767, 1234, -5, 7]), Samples::F32(vec![0.0, -1.0, 0.5, 0.25, -0.125, 1.0])] {
+            let sound = Sound { samples: samples, sample_rate: 44100, channels: 2 };
+
+            let mut wav = Vec::new();
+
+            write_wav(&mut wav, &sound).unwrap();
+
+            assert_eq!(read_wav(Cursor::new(wav)).unwrap(), sound);
+        }
+    }
+
+    #[test]
+    fn wav_sizes() {
+        let sound = Sound { samples: Samples::I16(vec![0, -32768, 32767, 1234, -5, 7]), sample_rate: 44100, channels: 2 };
+
+        let mut wav = Vec::new();
+
+        write_wav(&mut wav, &sound).unwrap();
+
+        // Unknown data size from a streamed file
+        let mut streamed = wav.clone();
+
+        streamed[40..44].copy_from_slice(&[0xFF; 4]);
+
+        assert_eq!(read_wav(Cursor::new(streamed)).unwrap(), sound);
+
+        let mut reader = WavReader::new(Cursor::new(wav.clone())).unwrap();
+
+        assert_eq!(reader.read_frames(usize::max_value()).unwrap().unwrap(), sound.samples);
+        assert!(reader.read_frames(1).unwrap().is_none());
+
+        // Huge format chunk
+        let mut format = wav.clone();
+
+        format[16..20].copy_from_slice(&[0xFF; 4]);
+
+        assert!(read_wav(Cursor::new(format)).is_err());
+    }
+}
@@ -83,6 +83,42 @@ impl Color {
         Color::new(0.0, 0.0, 0.0, 0.0)
     }
 
+    /// Create an opaque color approximating the light emitted by a black body at the given temperature in Kelvin.
+    ///
+    /// Temperatures are clamped to the range of 1000K to 40000K, and 6600K is roughly white.
+    pub fn from_temperature(kelvin: f32) -> Color {
+        // Tanner Helland's fit to the CIE 1964 10 degree color matching functions, giving sRGB values from 0 to 255
+        let t = kelvin.max(1000.0).min(40000.0) / 100.0;
+
+        let r = if t <= 66.0 { 255.0 } else {
+            329.698727446 * (t - 60.0).powf(-0.1332047592)
+        };
+
+        let g = if t <= 66.0 {
+            99.4708025861 * t.ln() - 161.1195681661
+        } else {
+            288.1221695283 * (t - 60.0).powf(-0.0755148492)
+        };
+
+        let b = if t >= 66.0 { 255.0 } else if t <= 19.0 { 0.0 } else {
+            138.5177312231 * (t - 10.0).ln() - 305.0447927307
+        };
+
+        let linear = |srgb: f32| {
+            let srgb = (srgb / 255.0).max(0.0).min(1.0);
+
+            if srgb <= 0.04045 { srgb / 12.92 } else { ((srgb + 0.055) / 1.055).powf(2.4) }
+        };
+
+        Color::new(linear(r), linear(g), linear(b), 1.0)
+    }
+
+    /// Multiply the red, green and blue components by those of `other`, keeping the alpha of `self`
+    #[inline]
+    pub fn tinted(&self, other: Color) -> Color {
+        Color::new(self.r * other.r, self.g * other.g, self.b * other.b, self.a)
+    }
+
     /// Create a white opaque color
     #[inline(always)]
     pub fn white() -> Color {
@@ -136,6 +136,12 @@ impl CompressedMemory {
         self.data.get_ref().is_empty()
     }
 
+    /// Get the raw compressed data, which can later be given to `from_compressed_buffer`
+    #[inline]
+    pub fn compressed_data(&self) -> &[u8] {
+        self.data.get_ref().as_slice()
+    }
+
     /// Create a reader that decompresses the data on the fly.
     ///
     /// The lifetime of the reader shall not exceed the lifetime of the `CompressedMemory` instance.
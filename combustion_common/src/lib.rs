@@ -50,6 +50,7 @@ pub mod humanize;
 pub mod structures;
 pub mod log;
 pub mod color;
+pub mod photometry;
 pub mod ext;
 pub mod streams;
 pub mod vfs;
\ No newline at end of file
@@ -0,0 +1,144 @@
+//! Photometric light units and exposure
+//!
+//! Lights can be given in photometric units, which measure light as perceived by the human eye,
+//! so real-world values like the lumens on a light bulb's packaging can be used directly.
+//! The renderer works with radiometric values instead, which are converted from photometric values
+//! using the maximum luminous efficacy of `LUMINOUS_EFFICACY` lumens per watt.
+//!
+//! Rendered radiance is scaled by an `Exposure` before tonemapping. It follows the same model as a physical camera,
+//! so a scene lit with physical units looks right with physical camera settings. The default exposure doesn't scale
+//! radiance at all, so lights with unitless intensities look the same as they always have.
+
+use std::f32::consts::PI;
+
+/// Maximum luminous efficacy, in lumens per watt, used to convert between photometric and radiometric values
+pub const LUMINOUS_EFFICACY: f32 = 683.0;
+
+/// Reflected-light meter calibration constant used for automatic exposure
+pub const METER_CALIBRATION: f32 = 12.5;
+
+/// Photometric intensity of a light or emissive surface
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Photometric {
+    /// Luminous power in lumens, for point lights and spotlights
+    #[serde(rename = "lumens")]
+    Lumens(f32),
+    /// Illuminance in lux, or lumens per square meter, for directional lights
+    #[serde(rename = "lux")]
+    Lux(f32),
+    /// Luminance in nits, or candela per square meter, for emissive surfaces
+    #[serde(rename = "nits")]
+    Nits(f32),
+}
+
+/// How an emitter spreads out its light, which determines how its photometric intensity converts
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Emitter {
+    /// Emits equally in all directions from a point
+    Point,
+    /// Emits from a point within a cone with the given outer half-angle, in radians
+    Spot {
+        /// Outer cone angle, in radians
+        outer_cone: f32,
+    },
+    /// Infinitely far away, with all rays parallel
+    Directional,
+    /// Emissive surface
+    Surface,
+}
+
+impl Photometric {
+    /// Convert to the radiometric value used by the renderer for the given emitter.
+    ///
+    /// Lumens are converted to radiant intensity for point lights and spotlights, lux to irradiance for directional lights,
+    /// and nits to radiance for emissive surfaces. Spotlights concentrate all their power within their cone,
+    /// so narrowing the cone makes them brighter.
+    ///
+    /// Returns `None` if the unit doesn't apply to the emitter, such as lux for a point light.
+    pub fn to_radiometric(&self, emitter: Emitter) -> Option<f32> {
+        let photometric = match (*self, emitter) {
+            (Photometric::Lumens(lumens), Emitter::Point) => lumens / (4.0 * PI),
+            (Photometric::Lumens(lumens), Emitter::Spot { outer_cone }) => {
+                let solid_angle = 2.0 * PI * (1.0 - outer_cone.cos());
+
+                lumens / solid_angle.max(1e-6)
+            },
+            (Photometric::Lux(lux), Emitter::Directional) => lux,
+            (Photometric::Nits(nits), Emitter::Surface) => nits,
+            _ => return None,
+        };
+
+        Some(photometric / LUMINOUS_EFFICACY)
+    }
+}
+
+/// Camera exposure, as an exposure value at ISO 100
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Exposure {
+    /// Exposure value at ISO 100
+    pub ev100: f32,
+}
+
+impl Default for Exposure {
+    /// The exposure at which radiance is not scaled at all, about that of a brightly lit interior
+    fn default() -> Exposure {
+        Exposure::from_ev100((LUMINOUS_EFFICACY / 1.2).log2())
+    }
+}
+
+impl Exposure {
+    /// Create an `Exposure` from an exposure value at ISO 100
+    #[inline]
+    pub fn from_ev100(ev100: f32) -> Exposure {
+        Exposure { ev100: ev100 }
+    }
+
+    /// Create an `Exposure` from physical camera settings
+    ///
+    /// `aperture` is the f-number, `shutter_time` is in seconds and `iso` is the sensor sensitivity.
+    pub fn from_camera(aperture: f32, shutter_time: f32, iso: f32) -> Exposure {
+        Exposure::from_ev100((aperture * aperture / shutter_time * 100.0 / iso).log2())
+    }
+
+    /// Create an `Exposure` for a scene with the given average luminance in nits, as a light meter would
+    pub fn from_average_luminance(luminance: f32) -> Exposure {
+        Exposure::from_ev100((luminance.max(1e-6) * 100.0 / METER_CALIBRATION).log2())
+    }
+
+    /// Luminance in nits at which the camera sensor saturates
+    #[inline]
+    pub fn max_luminance(&self) -> f32 {
+        // 78 / (ISO * lens transmittance of 0.65) * N^2 / t at ISO 100
+        1.2 * self.ev100.exp2()
+    }
+
+    /// Scale applied to rendered radiance before tonemapping, such as the `exposure` shader uniform
+    #[inline]
+    pub fn multiplier(&self) -> f32 {
+        LUMINOUS_EFFICACY / self.max_luminance()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn default_exposure_is_neutral() {
+        assert!((Exposure::default().multiplier() - 1.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn units_match_emitters() {
+        let lumens = Photometric::Lumens(800.0);
+
+        let point = lumens.to_radiometric(Emitter::Point).unwrap();
+        let spot = lumens.to_radiometric(Emitter::Spot { outer_cone: PI }).unwrap();
+
+        // A spotlight with a cone covering every direction is just a point light
+        assert!((point - spot).abs() < 1e-6);
+
+        assert_eq!(lumens.to_radiometric(Emitter::Directional), None);
+        assert_eq!(Photometric::Lux(1.0).to_radiometric(Emitter::Directional), Some(1.0 / LUMINOUS_EFFICACY));
+    }
+}
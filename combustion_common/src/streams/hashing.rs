@@ -0,0 +1,136 @@
+//! `HashingStream` adapter
+
+use std::io::prelude::*;
+use std::io::{self, SeekFrom};
+use std::hash::Hasher;
+use std::fmt::{Debug, Formatter, Result as FmtResult};
+
+/// Stream adapter that hashes all data read from or written to the underlying stream
+///
+/// Seeking is forwarded to the underlying stream, but the hash only covers bytes in the order they passed through the adapter,
+/// so to hash the content of a stream it should be read from start to end exactly once.
+///
+/// Use `vfs::pack::ContentHasher` to compute a `ContentHash` on the fly.
+pub struct HashingStream<S, H> where H: Hasher {
+    stream: S,
+    hasher: H,
+}
+
+impl<S, H> Debug for HashingStream<S, H> where S: Debug, H: Hasher {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        f.debug_struct("HashingStream")
+            .field("stream", &self.stream)
+            .field("hash", &self.hasher.finish())
+            .finish()
+    }
+}
+
+impl<S, H> HashingStream<S, H> where H: Hasher {
+    /// Create a new `HashingStream` with the given `hasher`
+    pub fn new(stream: S, hasher: H) -> HashingStream<S, H> {
+        HashingStream { stream: stream, hasher: hasher }
+    }
+
+    /// Gets a reference to the hasher
+    pub fn hasher(&self) -> &H {
+        &self.hasher
+    }
+
+    /// Hash of the data so far
+    #[inline]
+    pub fn finish(&self) -> u64 {
+        self.hasher.finish()
+    }
+
+    /// Gets a reference to the underlying stream.
+    pub fn get_ref(&self) -> &S {
+        &self.stream
+    }
+
+    /// Gets a mutable reference to the underlying stream.
+    ///
+    /// Data read or written directly through it is not hashed.
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.stream
+    }
+
+    /// Unwraps the `HashingStream` and returns the underlying stream and the hasher.
+    pub fn into_inner(self) -> (S, H) {
+        (self.stream, self.hasher)
+    }
+}
+
+impl<S, H> Read for HashingStream<S, H> where S: Read, H: Hasher {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let bytes = self.stream.read(buf)?;
+
+        self.hasher.write(&buf[..bytes]);
+
+        Ok(bytes)
+    }
+}
+
+impl<S, H> Write for HashingStream<S, H> where S: Write, H: Hasher {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let bytes = self.stream.write(buf)?;
+
+        self.hasher.write(&buf[..bytes]);
+
+        Ok(bytes)
+    }
+
+    #[inline(always)]
+    fn flush(&mut self) -> io::Result<()> {
+        self.stream.flush()
+    }
+}
+
+impl<S, H> Seek for HashingStream<S, H> where S: Seek, H: Hasher {
+    #[inline(always)]
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.stream.seek(pos)
+    }
+}
+
+impl<S, H> BufRead for HashingStream<S, H> where S: BufRead, H: Hasher {
+    #[inline(always)]
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        self.stream.fill_buf()
+    }
+
+    fn consume(&mut self, amt: usize) {
+        // Already buffered, so this doesn't touch the underlying reader
+        if let Ok(buf) = self.stream.fill_buf() {
+            self.hasher.write(&buf[..amt.min(buf.len())]);
+        }
+
+        self.stream.consume(amt)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::io::{self, Cursor, BufReader};
+
+    use ::vfs::pack::{ContentHash, ContentHasher};
+
+    #[test]
+    fn test_hashing_matches_content_hash() {
+        let data: Vec<u8> = (0..10000u32).map(|i| (i * 7 % 251) as u8).collect();
+
+        // Small buffer so the data is hashed in many pieces
+        let mut reader = HashingStream::new(BufReader::with_capacity(13, Cursor::new(data.clone())), ContentHasher::new());
+
+        io::copy(&mut reader, &mut io::sink()).unwrap();
+
+        assert_eq!(reader.hasher().content_hash(), ContentHash::of(&data));
+
+        let mut writer = HashingStream::new(io::sink(), ContentHasher::new());
+
+        writer.write_all(&data).unwrap();
+
+        assert_eq!(writer.hasher().content_hash(), ContentHash::of(&data));
+    }
+}
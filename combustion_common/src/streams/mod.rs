@@ -4,7 +4,11 @@ pub mod definitions;
 pub mod utils;
 pub mod lazy_buffer;
 pub mod read_only;
+pub mod progress;
+pub mod hashing;
 
 pub use self::definitions::{Stream, BoxedStream};
 pub use self::lazy_buffer::LazyBuffer;
-pub use self::read_only::ReadOnlySink;
\ No newline at end of file
+pub use self::read_only::ReadOnlySink;
+pub use self::progress::{Progress, ProgressSink, ChannelProgress, ProgressStream};
+pub use self::hashing::HashingStream;
\ No newline at end of file
@@ -0,0 +1,203 @@
+//! `ProgressStream` adapter
+
+use std::io::prelude::*;
+use std::io::{self, SeekFrom};
+use std::fmt::{Debug, Formatter, Result as FmtResult};
+use std::sync::mpsc::Sender;
+
+/// Progress of a `ProgressStream`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Progress {
+    /// Number of bytes read or written so far
+    pub bytes: u64,
+    /// Total number of bytes expected, if known
+    pub total: Option<u64>,
+}
+
+impl Progress {
+    /// Fraction of the expected bytes that have been read or written, from `0.0` to `1.0`
+    ///
+    /// Returns `None` if the total is unknown.
+    pub fn fraction(&self) -> Option<f32> {
+        self.total.map(|total| {
+            if total == 0 { 1.0 } else {
+                (self.bytes as f64 / total as f64).min(1.0) as f32
+            }
+        })
+    }
+}
+
+/// Receives progress updates from a `ProgressStream`
+pub trait ProgressSink {
+    /// Called after every read or write that transferred any bytes
+    fn progress(&mut self, progress: Progress);
+}
+
+impl<F> ProgressSink for F where F: FnMut(Progress) {
+    #[inline]
+    fn progress(&mut self, progress: Progress) {
+        self(progress)
+    }
+}
+
+/// `ProgressSink` that sends updates over a channel
+///
+/// Updates are silently dropped once the receiver has hung up.
+#[derive(Debug, Clone)]
+pub struct ChannelProgress(pub Sender<Progress>);
+
+impl ProgressSink for ChannelProgress {
+    #[inline]
+    fn progress(&mut self, progress: Progress) {
+        let _ = self.0.send(progress);
+    }
+}
+
+/// Stream adapter that counts the bytes read from or written to the underlying stream,
+/// and reports them to a `ProgressSink`.
+///
+/// Bytes are counted as they pass through, so data read again after seeking backwards is counted again.
+pub struct ProgressStream<S, P> where P: ProgressSink {
+    stream: S,
+    sink: P,
+    bytes: u64,
+    total: Option<u64>,
+}
+
+impl<S, P> Debug for ProgressStream<S, P> where S: Debug, P: ProgressSink {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        f.debug_struct("ProgressStream")
+            .field("stream", &self.stream)
+            .field("bytes", &self.bytes)
+            .field("total", &self.total)
+            .finish()
+    }
+}
+
+impl<S, P> ProgressStream<S, P> where P: ProgressSink {
+    /// Create a new `ProgressStream` expecting `total` bytes, if known
+    pub fn new(stream: S, total: Option<u64>, sink: P) -> ProgressStream<S, P> {
+        ProgressStream {
+            stream: stream,
+            sink: sink,
+            bytes: 0,
+            total: total,
+        }
+    }
+
+    /// Current progress of the stream
+    #[inline]
+    pub fn progress(&self) -> Progress {
+        Progress { bytes: self.bytes, total: self.total }
+    }
+
+    /// Gets a reference to the underlying stream.
+    pub fn get_ref(&self) -> &S {
+        &self.stream
+    }
+
+    /// Gets a mutable reference to the underlying stream.
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.stream
+    }
+
+    /// Unwraps the `ProgressStream` and returns the underlying stream.
+    pub fn into_inner(self) -> S {
+        self.stream
+    }
+
+    fn advance(&mut self, bytes: usize) {
+        if bytes > 0 {
+            self.bytes += bytes as u64;
+
+            let progress = self.progress();
+
+            self.sink.progress(progress);
+        }
+    }
+}
+
+impl<S, P> ProgressStream<S, P> where S: Seek, P: ProgressSink {
+    /// Create a new `ProgressStream` expecting the remainder of the stream after its current position
+    pub fn with_length(mut stream: S, sink: P) -> io::Result<ProgressStream<S, P>> {
+        let position = stream.seek(SeekFrom::Current(0))?;
+        let end = stream.seek(SeekFrom::End(0))?;
+
+        stream.seek(SeekFrom::Start(position))?;
+
+        Ok(ProgressStream::new(stream, Some(end.saturating_sub(position)), sink))
+    }
+}
+
+impl<S, P> Read for ProgressStream<S, P> where S: Read, P: ProgressSink {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let bytes = self.stream.read(buf)?;
+
+        self.advance(bytes);
+
+        Ok(bytes)
+    }
+}
+
+impl<S, P> Write for ProgressStream<S, P> where S: Write, P: ProgressSink {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let bytes = self.stream.write(buf)?;
+
+        self.advance(bytes);
+
+        Ok(bytes)
+    }
+
+    #[inline(always)]
+    fn flush(&mut self) -> io::Result<()> {
+        self.stream.flush()
+    }
+}
+
+impl<S, P> Seek for ProgressStream<S, P> where S: Seek, P: ProgressSink {
+    #[inline(always)]
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.stream.seek(pos)
+    }
+}
+
+impl<S, P> BufRead for ProgressStream<S, P> where S: BufRead, P: ProgressSink {
+    #[inline(always)]
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        self.stream.fill_buf()
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.stream.consume(amt);
+        self.advance(amt);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::io::{self, Cursor};
+    use std::sync::mpsc::channel;
+
+    #[test]
+    fn test_progress_channel() {
+        let (sender, receiver) = channel();
+
+        let mut stream = ProgressStream::with_length(Cursor::new(vec![0u8; 100]), ChannelProgress(sender)).unwrap();
+
+        let mut buffer = [0; 40];
+
+        stream.read_exact(&mut buffer).unwrap();
+
+        io::copy(&mut stream, &mut io::sink()).unwrap();
+
+        drop(stream);
+
+        let updates: Vec<Progress> = receiver.iter().collect();
+
+        assert_eq!(updates.first().unwrap().bytes, 40);
+        assert_eq!(updates.last().unwrap().bytes, 100);
+        assert_eq!(updates.last().unwrap().fraction(), Some(1.0));
+    }
+}
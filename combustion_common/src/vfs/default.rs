@@ -2,7 +2,7 @@
 
 use std::io;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::time::SystemTime;
 use std::ops::Deref;
 
@@ -54,4 +54,19 @@ impl VirtualFS for DefaultFS {
     fn metadata(&self, path: &Path) -> io::Result<BoxedMetadata> {
         fs::metadata(path).map(|metadata| Box::new(DefaultMetadata(metadata)) as BoxedMetadata)
     }
+
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<PathBuf>> {
+        read_dir_paths(path)
+    }
+}
+
+/// List a directory on the real filesystem, as used by `VirtualFS::read_dir`
+pub fn read_dir_paths(path: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut paths = Vec::new();
+
+    for entry in fs::read_dir(path)? {
+        paths.push(entry?.path());
+    }
+
+    Ok(paths)
 }
\ No newline at end of file
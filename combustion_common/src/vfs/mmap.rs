@@ -1,17 +1,21 @@
 //! Memory mapped buffers as a virtual filesystem
 
-use std::io;
+use std::io::prelude::*;
+use std::io::{self, SeekFrom};
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 use memmap;
 
 use ::streams::{BoxedStream, ReadOnlySink};
 
 use super::{VirtualFS, BoxedMetadata, OpenOptions};
-use super::default::DefaultMetadata;
+use super::default::{DefaultMetadata, read_dir_paths};
 
-/// Read-only memory mapped buffer virtual filesystem
+/// Memory mapped buffer virtual filesystem
+///
+/// Read-only streams are mapped with read protection only, while write streams are mapped
+/// with read/write protection and will grow the underlying file if data is written past the end.
 #[derive(Debug, Clone, Copy)]
 pub struct MmapFS;
 
@@ -30,21 +34,209 @@ impl AsRef<[u8]> for MmapStream {
     }
 }
 
+/// Minimum number of bytes a writable mapping will grow by
+const MIN_GROWTH: u64 = 1 << 16;
+
+// Internal read/write stream representation.
+//
+// The mapped region (capacity) may be larger than the logical length of the stream,
+// so the file is truncated back down to the logical length when the stream is dropped.
+//
+// Empty files cannot be mapped, so `mmap` is `None` until the first write grows it.
+#[derive(Debug)]
+struct MmapWriteStream {
+    file: fs::File,
+    mmap: Option<memmap::Mmap>,
+    position: u64,
+    len: u64,
+    capacity: u64,
+}
+
+impl MmapWriteStream {
+    fn new(file: fs::File, append: bool) -> io::Result<MmapWriteStream> {
+        let len = file.metadata()?.len();
+
+        let mmap = if len > 0 {
+            Some(memmap::Mmap::open(&file, memmap::Protection::ReadWrite)?)
+        } else { None };
+
+        Ok(MmapWriteStream {
+            file: file,
+            mmap: mmap,
+            position: if append { len } else { 0 },
+            len: len,
+            capacity: len,
+        })
+    }
+
+    /// Grow the file and remap it so at least `required` bytes are available
+    fn reserve(&mut self, required: u64) -> io::Result<()> {
+        if required <= self.capacity {
+            return Ok(());
+        }
+
+        // Grow geometrically so repeated small writes don't remap every time
+        let mut capacity = if self.capacity < MIN_GROWTH { MIN_GROWTH } else { self.capacity * 2 };
+
+        while capacity < required {
+            capacity *= 2;
+        }
+
+        // Flush and unmap the old region before resizing the file
+        if let Some(mmap) = self.mmap.take() {
+            mmap.flush()?;
+        }
+
+        self.file.set_len(capacity)?;
+
+        self.mmap = Some(memmap::Mmap::open(&self.file, memmap::Protection::ReadWrite)?);
+        self.capacity = capacity;
+
+        Ok(())
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        if let Some(mmap) = self.mmap.take() {
+            mmap.flush()?;
+        }
+
+        // Trim off any excess capacity
+        if self.capacity != self.len {
+            self.file.set_len(self.len)?;
+            self.capacity = self.len;
+        }
+
+        self.file.sync_data()
+    }
+}
+
+impl Read for MmapWriteStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.position >= self.len {
+            return Ok(0);
+        }
+
+        let start = self.position as usize;
+        let available = (self.len - self.position) as usize;
+        let amt = if buf.len() < available { buf.len() } else { available };
+
+        if let Some(ref mmap) = self.mmap {
+            let slice = unsafe { mmap.as_slice() };
+
+            buf[..amt].copy_from_slice(&slice[start..start + amt]);
+        }
+
+        self.position += amt as u64;
+
+        Ok(amt)
+    }
+}
+
+impl Write for MmapWriteStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        let end = self.position + buf.len() as u64;
+
+        self.reserve(end)?;
+
+        let start = self.position as usize;
+
+        if let Some(ref mut mmap) = self.mmap {
+            let slice = unsafe { mmap.as_mut_slice() };
+
+            slice[start..start + buf.len()].copy_from_slice(buf);
+        }
+
+        self.position = end;
+
+        if end > self.len {
+            self.len = end;
+        }
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self.mmap {
+            Some(ref mmap) => mmap.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Seek for MmapWriteStream {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let position = match pos {
+            SeekFrom::Start(offset) => offset as i64,
+            SeekFrom::Current(offset) => self.position as i64 + offset,
+            SeekFrom::End(offset) => self.len as i64 + offset,
+        };
+
+        if position < 0 {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                      "invalid seek to a negative position"));
+        }
+
+        // Seeking past the end is allowed, and the gap will be zero-filled on the next write
+        self.position = position as u64;
+
+        Ok(self.position)
+    }
+}
+
+impl Drop for MmapWriteStream {
+    fn drop(&mut self) {
+        if let Err(err) = self.finish() {
+            error!("Error flushing memory mapped file: {}", err);
+        }
+    }
+}
+
+impl MmapFS {
+    /// Open a read/write stream to a new or truncated file that is pre-allocated to `size` bytes.
+    ///
+    /// The stream starts out with a length of zero, but writes up to `size` bytes will not need
+    /// to grow and remap the file. Any unused space is trimmed off when the stream is dropped.
+    pub fn create_with_size(&self, path: &Path, size: u64) -> io::Result<BoxedStream> {
+        let file = fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(path)?;
+
+        let mut stream = MmapWriteStream::new(file, false)?;
+
+        stream.reserve(size)?;
+
+        Ok(Box::new(stream))
+    }
+}
+
 impl VirtualFS for MmapFS {
     fn open_with(&self, path: &Path, options: OpenOptions) -> io::Result<BoxedStream> {
-        //TODO: Add Write functionality
-        if options.write || options.append || options.create || options.create_new {
-            return Err(io::Error::new(io::ErrorKind::Other,
-                                      "Cannot open write streams for memory mapped files at this time"));
+        if options.write || options.append {
+            // Mapping with write protection requires read access to the file as well
+            let file = fs::OpenOptions::new()
+                .read(true)
+                .write(true)
+                .create(options.create)
+                .truncate(options.truncate && !options.append)
+                .create_new(options.create_new)
+                .open(path)?;
+
+            return Ok(Box::new(MmapWriteStream::new(file, options.append)?));
         }
 
-        let file = fs::OpenOptions::new().read(true).open(path)?;
+        if options.create || options.create_new {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                      "Creating memory mapped files requires write access"));
+        }
 
-        //let protection = if options.write {
-        //    memmap::Protection::ReadWrite
-        //} else {
-        //    memmap::Protection::Read
-        //};
+        let file = fs::OpenOptions::new().read(true).open(path)?;
 
         let mmap = memmap::Mmap::open(&file, memmap::Protection::Read)?;
 
@@ -59,4 +251,49 @@ impl VirtualFS for MmapFS {
     fn metadata(&self, path: &Path) -> io::Result<BoxedMetadata> {
         fs::metadata(path).map(|metadata| Box::new(DefaultMetadata(metadata)) as BoxedMetadata)
     }
-}
\ No newline at end of file
+
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<PathBuf>> {
+        read_dir_paths(path)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::prelude::*;
+    use std::io::SeekFrom;
+    use std::env;
+    use std::fs;
+
+    use super::*;
+
+    #[test]
+    fn write_grow_and_read_back() {
+        let path = env::temp_dir().join("combustion_mmap_write_test.bin");
+
+        let data: Vec<u8> = (0..200_000u32).map(|i| i as u8).collect();
+
+        {
+            let mut stream = MmapFS.create_or_truncate(&path).unwrap();
+
+            stream.write_all(&data).unwrap();
+            stream.seek(SeekFrom::Start(0)).unwrap();
+
+            let mut read_back = Vec::new();
+
+            stream.read_to_end(&mut read_back).unwrap();
+
+            assert_eq!(read_back, data);
+        }
+
+        // Excess capacity should have been trimmed on drop
+        assert_eq!(fs::metadata(&path).unwrap().len(), data.len() as u64);
+
+        let mut read_back = Vec::new();
+
+        MmapFS.open(&path).unwrap().read_to_end(&mut read_back).unwrap();
+
+        assert_eq!(read_back, data);
+
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -8,12 +8,13 @@
 //! just that the data exists and can be read.
 
 use std::io;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::time::SystemTime;
 use std::fmt::Debug;
 
 pub mod default;
 pub mod null;
+pub mod pack;
 
 #[cfg(feature = "mmap")]
 pub mod mmap;
@@ -102,6 +103,16 @@ pub trait VirtualFS: Debug + Send + Sync + 'static {
 
     /// Returns metadata for a specific entry
     fn metadata(&self, path: &Path) -> io::Result<BoxedMetadata>;
+
+    /// Returns the paths of all entries directly inside a directory
+    ///
+    /// The returned paths include `path` itself as a prefix, like `std::fs::read_dir`.
+    /// Filesystems without directory listings return an error by default.
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<PathBuf>> {
+        let _ = path;
+
+        Err(io::Error::new(io::ErrorKind::Other, "Directory listing is not supported by this filesystem"))
+    }
 }
 
 /// A Boxed `VirtualFS` instance
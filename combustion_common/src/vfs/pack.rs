@@ -0,0 +1,812 @@
+//! Content-addressed pack files as a virtual filesystem
+//!
+//! A pack file bundles many small asset files into a single archive for shipping builds.
+//! Each unique blob of data is stored once, optionally LZ4 compressed, and any number of paths can refer to it.
+//!
+//! The layout of a pack file is:
+//!
+//! ```text
+//! header      magic "CPAK", version: u32, entry count: u32, blob count: u32, index offset: u64, created: u64
+//! blobs       raw or LZ4 compressed data for every unique blob
+//! index       blob table followed by the path table
+//! ```
+//!
+//! The blob table contains the content hash, offset, stored length, uncompressed length and flags for every blob,
+//! and the path table contains the path hash, blob index and path string for every entry, sorted by path hash.
+//!
+//! All integers are little endian.
+
+use std::io::prelude::*;
+use std::io::{self, SeekFrom};
+use std::collections::HashMap;
+use std::hash::Hasher;
+use std::fmt::{Debug, Display, Formatter, Result as FmtResult};
+use std::fs;
+use std::path::{Path, PathBuf, Component};
+use std::sync::Mutex;
+use std::time::{SystemTime, Duration, UNIX_EPOCH};
+
+use ::compression::{CompressedMemory, CompressionOptions};
+use ::streams::{BoxedStream, ReadOnlySink};
+
+use super::{VirtualFS, VirtualMetadata, BoxedMetadata, OpenOptions};
+
+/// Magic bytes at the start of every pack file
+pub const PACK_MAGIC: &'static [u8; 4] = b"CPAK";
+
+/// Current pack file format version
+pub const PACK_VERSION: u32 = 1;
+
+const HEADER_SIZE: u64 = 32;
+
+// Sizes of blob table entries and the smallest possible path table entries
+const BLOB_ENTRY_SIZE: u64 = 48;
+const MIN_PATH_ENTRY_SIZE: u64 = 16;
+
+// Blob is stored with LZ4 compression
+const BLOB_COMPRESSED: u32 = 1;
+
+const FNV_OFFSET: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+
+// Offset basis for the second half of a `ContentHash`, which is the FNV-1a hash of "combustion content hash"
+const FNV_CONTENT_OFFSET: u64 = 0x8cdf7ba2f0583119;
+
+fn fnv1a(data: &[u8]) -> u64 {
+    fnv1a_continue(FNV_OFFSET, data)
+}
+
+fn fnv1a_continue(hash: u64, data: &[u8]) -> u64 {
+    data.iter().fold(hash, |hash, byte| (hash ^ *byte as u64).wrapping_mul(FNV_PRIME))
+}
+
+fn read_u32<R: Read + ?Sized>(reader: &mut R) -> io::Result<u32> {
+    let mut bytes = [0; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(bytes.iter().rev().fold(0, |value, byte| (value << 8) | *byte as u32))
+}
+
+fn read_u64<R: Read + ?Sized>(reader: &mut R) -> io::Result<u64> {
+    let mut bytes = [0; 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(bytes.iter().rev().fold(0, |value, byte| (value << 8) | *byte as u64))
+}
+
+fn write_u32<W: Write + ?Sized>(writer: &mut W, value: u32) -> io::Result<()> {
+    let mut bytes = [0; 4];
+    for (i, byte) in bytes.iter_mut().enumerate() {
+        *byte = (value >> (i * 8)) as u8;
+    }
+    writer.write_all(&bytes)
+}
+
+fn write_u64<W: Write + ?Sized>(writer: &mut W, value: u64) -> io::Result<()> {
+    let mut bytes = [0; 8];
+    for (i, byte) in bytes.iter_mut().enumerate() {
+        *byte = (value >> (i * 8)) as u8;
+    }
+    writer.write_all(&bytes)
+}
+
+fn invalid_data(message: &'static str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// Normalize a path into the form stored in pack files, with `/` separators and no leading root
+pub fn normalize_pack_path(path: &Path) -> io::Result<String> {
+    let mut normalized = String::new();
+
+    for component in path.components() {
+        match component {
+            Component::Normal(part) => {
+                let part = part.to_str().ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "Pack file paths must be valid UTF-8"))?;
+
+                if !normalized.is_empty() {
+                    normalized.push('/');
+                }
+
+                normalized.push_str(part);
+            },
+            Component::ParentDir => {
+                return Err(io::Error::new(io::ErrorKind::InvalidInput, "Pack file paths cannot contain parent directory components"));
+            },
+            _ => {}
+        }
+    }
+
+    Ok(normalized)
+}
+
+/// 128-bit content hash used to deduplicate blobs
+///
+/// It combines two 64-bit FNV-1a digests of the data with different offset bases.
+/// Both are fully specified, so the same data hashes the same on every platform and toolchain.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ContentHash(pub u64, pub u64);
+
+impl ContentHash {
+    /// Hash the given data
+    pub fn of(data: &[u8]) -> ContentHash {
+        let mut hasher = ContentHasher::new();
+
+        hasher.write(data);
+
+        hasher.content_hash()
+    }
+}
+
+/// Formats the hash as 32 hexadecimal digits
+impl Display for ContentHash {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "{:016x}{:016x}", self.0, self.1)
+    }
+}
+
+/// Computes a `ContentHash` incrementally, such as with a `HashingStream`
+///
+/// The result is the same no matter how the data is split up between writes.
+#[derive(Debug, Clone)]
+pub struct ContentHasher {
+    fnv: u64,
+    fnv_content: u64,
+}
+
+impl Default for ContentHasher {
+    fn default() -> ContentHasher {
+        ContentHasher::new()
+    }
+}
+
+impl ContentHasher {
+    /// Create a new hasher for empty data
+    pub fn new() -> ContentHasher {
+        ContentHasher { fnv: FNV_OFFSET, fnv_content: FNV_CONTENT_OFFSET }
+    }
+
+    /// `ContentHash` of the data written so far
+    pub fn content_hash(&self) -> ContentHash {
+        ContentHash(self.fnv, self.fnv_content)
+    }
+}
+
+impl Hasher for ContentHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        self.fnv = fnv1a_continue(self.fnv, bytes);
+        self.fnv_content = fnv1a_continue(self.fnv_content, bytes);
+    }
+
+    /// Combines both halves of the `ContentHash`
+    fn finish(&self) -> u64 {
+        self.fnv ^ self.fnv_content
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct PackBlob {
+    hash: ContentHash,
+    offset: u64,
+    len: u64,
+    size: u64,
+    flags: u32,
+}
+
+impl PackBlob {
+    fn read<R: Read + ?Sized>(reader: &mut R) -> io::Result<PackBlob> {
+        let hash = ContentHash(read_u64(reader)?, read_u64(reader)?);
+        let offset = read_u64(reader)?;
+        let len = read_u64(reader)?;
+        let size = read_u64(reader)?;
+        let flags = read_u32(reader)?;
+        let _reserved = read_u32(reader)?;
+
+        Ok(PackBlob { hash: hash, offset: offset, len: len, size: size, flags: flags })
+    }
+
+    fn write<W: Write + ?Sized>(&self, writer: &mut W) -> io::Result<()> {
+        write_u64(writer, self.hash.0)?;
+        write_u64(writer, self.hash.1)?;
+        write_u64(writer, self.offset)?;
+        write_u64(writer, self.len)?;
+        write_u64(writer, self.size)?;
+        write_u32(writer, self.flags)?;
+        write_u32(writer, 0)
+    }
+}
+
+#[derive(Debug, Clone)]
+struct PackEntry {
+    hash: u64,
+    path: String,
+    blob: u32,
+}
+
+/// Writes pack files
+///
+/// Entries are added one at a time, with identical content only stored once.
+/// Content with a matching hash is read back and compared before it's reused, so the writer must be readable too.
+/// The index is written when `finish` is called.
+pub struct PackWriter<W: Read + Write + Seek> {
+    writer: W,
+    options: Option<CompressionOptions>,
+    base: u64,
+    position: u64,
+    blobs: Vec<PackBlob>,
+    blob_index: HashMap<ContentHash, u32>,
+    entries: HashMap<String, u32>,
+}
+
+impl<W: Read + Write + Seek> Debug for PackWriter<W> {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        f.debug_struct("PackWriter")
+            .field("options", &self.options)
+            .field("blobs", &self.blobs.len())
+            .field("entries", &self.entries.len())
+            .finish()
+    }
+}
+
+impl<W: Read + Write + Seek> PackWriter<W> {
+    /// Start writing a pack file at the current position of `writer`.
+    ///
+    /// Blobs are compressed with the given options, or stored uncompressed if `options` is `None`.
+    pub fn new(mut writer: W, options: Option<CompressionOptions>) -> io::Result<PackWriter<W>> {
+        let base = writer.seek(SeekFrom::Current(0))?;
+
+        // Reserve space for the header, which is filled in by `finish`
+        writer.write_all(&[0; HEADER_SIZE as usize])?;
+
+        Ok(PackWriter {
+            writer: writer,
+            options: options,
+            base: base,
+            position: HEADER_SIZE,
+            blobs: Vec::new(),
+            blob_index: HashMap::new(),
+            entries: HashMap::new(),
+        })
+    }
+
+    /// Number of entries added so far
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Check if no entries have been added
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Number of unique blobs written so far
+    pub fn blob_count(&self) -> usize {
+        self.blobs.len()
+    }
+
+    /// Add an entry at `path` with the given data.
+    ///
+    /// Returns `true` if the data was already present in the pack and was not written again.
+    /// Adding the same path twice replaces the previous entry.
+    pub fn add_buffer<P: AsRef<Path>>(&mut self, path: P, data: &[u8]) -> io::Result<bool> {
+        let path = normalize_pack_path(path.as_ref())?;
+
+        if path.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "Pack file entries must have a file name"));
+        }
+
+        let hash = ContentHash::of(data);
+
+        if let Some(&blob) = self.blob_index.get(&hash) {
+            // The hash isn't cryptographic, so make sure it isn't a collision
+            if self.blob_matches(blob, data)? {
+                self.entries.insert(path, blob);
+
+                return Ok(true);
+            }
+        }
+
+        let compressed = match self.options {
+            Some(options) => {
+                let compressed = CompressedMemory::from_buffer(data, options)?;
+
+                // Don't bother storing compressed data if it didn't help
+                if compressed.len() < data.len() { Some(compressed) } else { None }
+            },
+            None => None,
+        };
+
+        let (stored, flags) = match compressed {
+            Some(ref compressed) => (compressed.compressed_data(), BLOB_COMPRESSED),
+            None => (data, 0),
+        };
+
+        self.writer.write_all(stored)?;
+
+        let blob = self.blobs.len() as u32;
+
+        self.blobs.push(PackBlob {
+            hash: hash,
+            offset: self.position,
+            len: stored.len() as u64,
+            size: data.len() as u64,
+            flags: flags,
+        });
+
+        self.position += stored.len() as u64;
+
+        // On a collision, the first blob with the hash stays indexed
+        self.blob_index.entry(hash).or_insert(blob);
+        self.entries.insert(path, blob);
+
+        Ok(false)
+    }
+
+    // Reads back a blob that has already been written and compares it to `data`
+    fn blob_matches(&mut self, blob: u32, data: &[u8]) -> io::Result<bool> {
+        let blob = self.blobs[blob as usize];
+
+        if blob.size != data.len() as u64 {
+            return Ok(false);
+        }
+
+        let mut stored = vec![0; blob.len as usize];
+
+        self.writer.seek(SeekFrom::Start(self.base + blob.offset))?;
+
+        let result = self.writer.read_exact(&mut stored);
+
+        // Always return to the end, where the next blob will be written
+        self.writer.seek(SeekFrom::Start(self.base + self.position))?;
+
+        result?;
+
+        if blob.flags & BLOB_COMPRESSED != 0 {
+            let mut unpacked = Vec::with_capacity(data.len());
+
+            CompressedMemory::from_compressed_buffer(&stored)?.create_reader()?.take(blob.size + 1).read_to_end(&mut unpacked)?;
+
+            Ok(unpacked == data)
+        } else {
+            Ok(stored == data)
+        }
+    }
+
+    /// Add an entry at `path` with all the data from `reader`.
+    ///
+    /// See `add_buffer` for more information.
+    pub fn add_reader<P: AsRef<Path>, R: Read>(&mut self, path: P, mut reader: R) -> io::Result<bool> {
+        let mut data = Vec::new();
+
+        reader.read_to_end(&mut data)?;
+
+        self.add_buffer(path, &data)
+    }
+
+    /// Write the index and header and return the underlying writer
+    pub fn finish(mut self) -> io::Result<W> {
+        let index_offset = self.position;
+
+        for blob in &self.blobs {
+            blob.write(&mut self.writer)?;
+        }
+
+        let mut entries: Vec<PackEntry> = self.entries.drain().map(|(path, blob)| {
+            PackEntry { hash: fnv1a(path.as_bytes()), path: path, blob: blob }
+        }).collect();
+
+        entries.sort_by(|a, b| (a.hash, &a.path).cmp(&(b.hash, &b.path)));
+
+        for entry in &entries {
+            write_u64(&mut self.writer, entry.hash)?;
+            write_u32(&mut self.writer, entry.blob)?;
+            write_u32(&mut self.writer, entry.path.len() as u32)?;
+            self.writer.write_all(entry.path.as_bytes())?;
+        }
+
+        let created = SystemTime::now().duration_since(UNIX_EPOCH).map(|duration| duration.as_secs()).unwrap_or(0);
+
+        let end = self.writer.seek(SeekFrom::Current(0))?;
+
+        self.writer.seek(SeekFrom::Start(self.base))?;
+
+        self.writer.write_all(PACK_MAGIC)?;
+        write_u32(&mut self.writer, PACK_VERSION)?;
+        write_u32(&mut self.writer, entries.len() as u32)?;
+        write_u32(&mut self.writer, self.blobs.len() as u32)?;
+        write_u64(&mut self.writer, index_offset)?;
+        write_u64(&mut self.writer, created)?;
+
+        self.writer.seek(SeekFrom::Start(end))?;
+        self.writer.flush()?;
+
+        Ok(self.writer)
+    }
+}
+
+/// Any seekable source a pack file can be read from
+pub trait PackSource: Read + Seek + Send {}
+
+impl<T> PackSource for T where T: Read + Seek + Send {}
+
+/// `VirtualMetadata` for pack file entries
+pub struct PackMetadata {
+    is_dir: bool,
+    modified: SystemTime,
+}
+
+impl VirtualMetadata for PackMetadata {
+    fn is_file(&self) -> bool { !self.is_dir }
+    fn is_dir(&self) -> bool { self.is_dir }
+    fn modified(&self) -> io::Result<SystemTime> {
+        Ok(self.modified)
+    }
+}
+
+/// Read-only virtual filesystem backed by a pack file
+///
+/// Only the index is read up front. Opening an entry seeks directly to its blob and decompresses it,
+/// so entries can be opened in any order.
+pub struct PackFS {
+    source: Mutex<(Box<PackSource>, u64)>,
+    blobs: Vec<PackBlob>,
+    entries: Vec<PackEntry>,
+    created: SystemTime,
+}
+
+impl Debug for PackFS {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        f.debug_struct("PackFS")
+            .field("blobs", &self.blobs.len())
+            .field("entries", &self.entries.len())
+            .field("created", &self.created)
+            .finish()
+    }
+}
+
+impl PackFS {
+    /// Read the pack file index from the current position of `source`
+    pub fn new<R: PackSource + 'static>(mut source: R) -> io::Result<PackFS> {
+        let base = source.seek(SeekFrom::Current(0))?;
+
+        let mut magic = [0; 4];
+
+        source.read_exact(&mut magic)?;
+
+        if &magic != PACK_MAGIC {
+            return Err(invalid_data("Not a pack file"));
+        }
+
+        if read_u32(&mut source)? != PACK_VERSION {
+            return Err(invalid_data("Unsupported pack file version"));
+        }
+
+        let entry_count = read_u32(&mut source)?;
+        let blob_count = read_u32(&mut source)?;
+        let index_offset = read_u64(&mut source)?;
+        let created = UNIX_EPOCH + Duration::from_secs(read_u64(&mut source)?);
+
+        let pack_len = source.seek(SeekFrom::End(0))?.saturating_sub(base);
+
+        if index_offset < HEADER_SIZE || index_offset > pack_len {
+            return Err(invalid_data("Pack file index out of bounds"));
+        }
+
+        // Reject counts that can't possibly fit in the index before reading or allocating anything for them
+        let min_index_len = blob_count as u64 * BLOB_ENTRY_SIZE + entry_count as u64 * MIN_PATH_ENTRY_SIZE;
+
+        if min_index_len > pack_len - index_offset {
+            return Err(invalid_data("Pack file index truncated"));
+        }
+
+        source.seek(SeekFrom::Start(base + index_offset))?;
+
+        let mut index = io::BufReader::new(source);
+
+        let mut blobs = Vec::with_capacity(blob_count as usize);
+
+        for _ in 0..blob_count {
+            let blob = PackBlob::read(&mut index)?;
+
+            match blob.offset.checked_add(blob.len) {
+                Some(end) if blob.offset >= HEADER_SIZE && end <= index_offset => {},
+                _ => return Err(invalid_data("Pack file blob out of bounds")),
+            }
+
+            blobs.push(blob);
+        }
+
+        let mut entries = Vec::with_capacity(entry_count as usize);
+
+        for _ in 0..entry_count {
+            let hash = read_u64(&mut index)?;
+            let blob = read_u32(&mut index)?;
+            let path_len = read_u32(&mut index)?;
+
+            if blob >= blob_count {
+                return Err(invalid_data("Pack file entry refers to missing blob"));
+            }
+
+            let mut path = Vec::new();
+
+            (&mut index).take(path_len as u64).read_to_end(&mut path)?;
+
+            if path.len() != path_len as usize {
+                return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "Pack file index truncated"));
+            }
+
+            let path = String::from_utf8(path).map_err(|_| invalid_data("Pack file path is not valid UTF-8"))?;
+
+            entries.push(PackEntry { hash: hash, path: path, blob: blob });
+        }
+
+        // The writer sorts entries, but don't rely on it
+        entries.sort_by(|a, b| (a.hash, &a.path).cmp(&(b.hash, &b.path)));
+
+        Ok(PackFS {
+            source: Mutex::new((Box::new(index.into_inner()), base)),
+            blobs: blobs,
+            entries: entries,
+            created: created,
+        })
+    }
+
+    /// Open a pack file on disk
+    pub fn open_file<P: AsRef<Path>>(path: P) -> io::Result<PackFS> {
+        PackFS::new(fs::File::open(path)?)
+    }
+
+    /// Number of entries in the pack
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Check if the pack has no entries
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Number of unique blobs in the pack
+    pub fn blob_count(&self) -> usize {
+        self.blobs.len()
+    }
+
+    /// Iterate over the paths of all entries in the pack
+    pub fn paths<'a>(&'a self) -> Box<Iterator<Item = &'a str> + 'a> {
+        Box::new(self.entries.iter().map(|entry| entry.path.as_str()))
+    }
+
+    fn find(&self, path: &Path) -> io::Result<&PackEntry> {
+        let path = normalize_pack_path(path)?;
+        let hash = fnv1a(path.as_bytes());
+
+        match self.entries.binary_search_by(|entry| (entry.hash, entry.path.as_str()).cmp(&(hash, path.as_str()))) {
+            Ok(index) => Ok(&self.entries[index]),
+            Err(_) => Err(io::Error::new(io::ErrorKind::NotFound, "Entry not found in pack file")),
+        }
+    }
+
+    fn is_dir(&self, path: &Path) -> io::Result<bool> {
+        let mut prefix = normalize_pack_path(path)?;
+
+        if prefix.is_empty() {
+            return Ok(true);
+        }
+
+        prefix.push('/');
+
+        Ok(self.entries.iter().any(|entry| entry.path.starts_with(prefix.as_str())))
+    }
+
+    /// Check if an entry exists at `path`
+    pub fn contains<P: AsRef<Path>>(&self, path: P) -> bool {
+        self.find(path.as_ref()).is_ok()
+    }
+
+    /// Read and decompress the entry at `path`
+    pub fn read_entry<P: AsRef<Path>>(&self, path: P) -> io::Result<Vec<u8>> {
+        let blob = self.blobs[self.find(path.as_ref())?.blob as usize];
+
+        let stored = {
+            let mut guard = match self.source.lock() {
+                Ok(guard) => guard,
+                Err(poisoned) => poisoned.into_inner(),
+            };
+
+            let (ref mut source, base) = *guard;
+
+            source.seek(SeekFrom::Start(base + blob.offset))?;
+
+            let mut stored = Vec::new();
+
+            source.take(blob.len).read_to_end(&mut stored)?;
+
+            stored
+        };
+
+        if stored.len() as u64 != blob.len {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "Pack file blob truncated"));
+        }
+
+        let data = if blob.flags & BLOB_COMPRESSED != 0 {
+            let compressed = CompressedMemory::from_compressed_buffer(&stored)?;
+
+            // The uncompressed size comes from the file, so don't trust it for allocation,
+            // and stop just past it so a hostile blob can't expand without bound
+            let mut data = Vec::new();
+
+            compressed.create_reader()?.take(blob.size.saturating_add(1)).read_to_end(&mut data)?;
+
+            data
+        } else {
+            stored
+        };
+
+        if data.len() as u64 != blob.size {
+            return Err(invalid_data("Pack file blob has incorrect size"));
+        }
+
+        Ok(data)
+    }
+}
+
+impl VirtualFS for PackFS {
+    fn open_with(&self, path: &Path, options: OpenOptions) -> io::Result<BoxedStream> {
+        if options.write || options.append || options.create || options.create_new || options.truncate {
+            return Err(io::Error::new(io::ErrorKind::PermissionDenied, "Pack files are read-only"));
+        }
+
+        let data = self.read_entry(path)?;
+
+        Ok(Box::new(ReadOnlySink::new(io::Cursor::new(data))))
+    }
+
+    fn metadata(&self, path: &Path) -> io::Result<BoxedMetadata> {
+        let is_dir = if self.find(path).is_ok() {
+            false
+        } else if self.is_dir(path)? {
+            true
+        } else {
+            return Err(io::Error::new(io::ErrorKind::NotFound, "Entry not found in pack file"));
+        };
+
+        Ok(Box::new(PackMetadata { is_dir: is_dir, modified: self.created }) as BoxedMetadata)
+    }
+
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<PathBuf>> {
+        let mut prefix = normalize_pack_path(path)?;
+
+        if !prefix.is_empty() {
+            prefix.push('/');
+        }
+
+        // Directories are implied by entry paths, so list the first component after the prefix
+        let mut names: Vec<&str> = self.entries.iter()
+            .filter(|entry| entry.path.starts_with(prefix.as_str()))
+            .filter_map(|entry| entry.path[prefix.len()..].split('/').next())
+            .collect();
+
+        if names.is_empty() && !prefix.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::NotFound, "Directory not found in pack file"));
+        }
+
+        names.sort();
+        names.dedup();
+
+        Ok(names.into_iter().map(|name| path.join(name)).collect())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::prelude::*;
+    use std::io;
+    use std::path::{Path, PathBuf};
+
+    use ::compression::CompressionOptions;
+
+    use super::*;
+    use super::super::VirtualFS;
+
+    #[test]
+    fn write_dedup_and_read_back() {
+        let texture: Vec<u8> = (0..10_000u32).map(|i| (i % 7) as u8).collect();
+        let model: Vec<u8> = (0..3_000u32).map(|i| (i * 31) as u8).collect();
+
+        let mut writer = PackWriter::new(io::Cursor::new(Vec::new()), Some(CompressionOptions::with_level(4))).unwrap();
+
+        assert!(!writer.add_buffer("textures/a.ctex", &texture).unwrap());
+        assert!(writer.add_buffer("textures/b.ctex", &texture).unwrap());
+        assert!(!writer.add_buffer("/models/c.cmdl", &model).unwrap());
+
+        assert_eq!(writer.blob_count(), 2);
+
+        let mut buffer = writer.finish().unwrap();
+
+        buffer.set_position(0);
+
+        let pack = PackFS::new(buffer).unwrap();
+
+        assert_eq!(pack.len(), 3);
+        assert_eq!(pack.blob_count(), 2);
+
+        // Random access in any order
+        let mut read_back = Vec::new();
+        pack.open(Path::new("models/c.cmdl")).unwrap().read_to_end(&mut read_back).unwrap();
+        assert_eq!(read_back, model);
+
+        assert_eq!(pack.read_entry("textures/b.ctex").unwrap(), texture);
+        assert_eq!(pack.read_entry("textures/a.ctex").unwrap(), texture);
+
+        assert!(pack.metadata(Path::new("textures")).unwrap().is_dir());
+        assert!(pack.metadata(Path::new("textures/a.ctex")).unwrap().is_file());
+
+        assert_eq!(pack.read_dir(Path::new("")).unwrap(), vec![PathBuf::from("models"), PathBuf::from("textures")]);
+        assert_eq!(pack.read_dir(Path::new("textures")).unwrap(),
+                   vec![PathBuf::from("textures/a.ctex"), PathBuf::from("textures/b.ctex")]);
+        assert!(pack.read_dir(Path::new("missing")).is_err());
+
+        assert!(pack.open(Path::new("missing.ctex")).is_err());
+        assert!(pack.open_write(Path::new("textures/a.ctex")).is_err());
+    }
+
+    #[test]
+    fn content_hash_is_stable() {
+        // Pack files and sidecars store these, so they must never change
+        assert_eq!(ContentHash::of(b""), ContentHash(FNV_OFFSET, FNV_CONTENT_OFFSET));
+        assert_eq!(ContentHash::of(b"a").0, 0xaf63dc4c8601ec8c);
+        assert_eq!(ContentHash::of(b"").to_string(), "cbf29ce4842223258cdf7ba2f0583119");
+    }
+
+    #[test]
+    fn rejects_out_of_bounds_index() {
+        let mut writer = PackWriter::new(io::Cursor::new(Vec::new()), None).unwrap();
+
+        writer.add_buffer("a.bin", &[1, 2, 3, 4]).unwrap();
+
+        let pack = writer.finish().unwrap().into_inner();
+
+        // Blob offset overflowing when the length is added
+        let mut overflow = pack.clone();
+        let blob_offset = HEADER_SIZE as usize + 4 + 16;
+        for byte in &mut overflow[blob_offset..blob_offset + 8] {
+            *byte = 0xFF;
+        }
+        assert!(PackFS::new(io::Cursor::new(overflow)).is_err());
+
+        // Entry count far larger than the index
+        let mut huge = pack.clone();
+        for byte in &mut huge[8..12] {
+            *byte = 0xFF;
+        }
+        assert!(PackFS::new(io::Cursor::new(huge)).is_err());
+
+        // Index offset past the end of the file
+        let mut truncated = pack.clone();
+        truncated.truncate(HEADER_SIZE as usize + 10);
+        assert!(PackFS::new(io::Cursor::new(truncated)).is_err());
+
+        assert!(PackFS::new(io::Cursor::new(pack)).is_ok());
+    }
+
+    #[test]
+    fn rejects_oversized_blob() {
+        let data = vec![0u8; 10_000];
+
+        let mut writer = PackWriter::new(io::Cursor::new(Vec::new()), Some(CompressionOptions::with_level(4))).unwrap();
+
+        writer.add_buffer("zeroes.bin", &data).unwrap();
+
+        // Same contents under another path reuse the blob after comparing it
+        assert!(writer.add_buffer("copy.bin", &data).unwrap());
+        assert!(!writer.add_buffer("other.bin", &[1, 2, 3]).unwrap());
+
+        let mut pack = writer.finish().unwrap().into_inner();
+
+        assert_eq!(PackFS::new(io::Cursor::new(pack.clone())).unwrap().read_entry("copy.bin").unwrap(), data);
+
+        // Claim the compressed blob is much smaller than what it decompresses to
+        let size_offset = read_u64(&mut &pack[16..24]).unwrap() as usize + 32;
+
+        pack[size_offset..size_offset + 8].copy_from_slice(&[100, 0, 0, 0, 0, 0, 0, 0]);
+
+        assert!(PackFS::new(io::Cursor::new(pack)).unwrap().read_entry("zeroes.bin").is_err());
+    }
+}
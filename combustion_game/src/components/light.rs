@@ -3,6 +3,9 @@
 use specs;
 use nalgebra::Point3;
 
+use core::common::color::Color;
+use core::common::photometry::{Photometric, Emitter};
+
 #[derive(Copy, Clone, Debug)]
 pub enum Kind {
     /// Directional light infinitely far away, with all rays parallel
@@ -59,8 +62,48 @@ pub struct Component {
     pub enabled: bool,
     /// Light type
     pub kind: Kind,
-    /// Light intensity
+    /// Light intensity, in the renderer's radiometric units
     pub intensity: f32,
+    /// Light intensity in photometric units, used instead of `intensity` if given
+    pub photometric: Option<Photometric>,
+    /// Color temperature in Kelvin
+    pub temperature: Option<f32>,
+}
+
+impl Component {
+    /// How the light spreads out its light, for converting photometric units.
+    ///
+    /// Returns `None` for light kinds without photometric support yet.
+    pub fn emitter(&self) -> Option<Emitter> {
+        match self.kind {
+            Kind::Directional => Some(Emitter::Directional),
+            Kind::Point { .. } => Some(Emitter::Point),
+            Kind::Spotlight { outer_cone, .. } => Some(Emitter::Spot { outer_cone: outer_cone }),
+            _ => None,
+        }
+    }
+
+    /// Intensity in the renderer's radiometric units, converted from `photometric` if given.
+    ///
+    /// For spotlights, light lost to the reflector is taken into account.
+    pub fn radiometric_intensity(&self) -> Option<f32> {
+        match self.photometric {
+            Some(photometric) => {
+                let intensity = self.emitter().and_then(|emitter| photometric.to_radiometric(emitter));
+
+                match self.kind {
+                    Kind::Spotlight { reflector_efficiency, .. } => intensity.map(|intensity| intensity * reflector_efficiency),
+                    _ => intensity,
+                }
+            },
+            None => Some(self.intensity),
+        }
+    }
+
+    /// Tint for the light from its color temperature, or white if it has none
+    pub fn temperature_tint(&self) -> Color {
+        self.temperature.map_or_else(Color::white, Color::from_temperature)
+    }
 }
 
 impl specs::Component for Component {
@@ -0,0 +1,38 @@
+//! Writes JSON Schemas for the human-editable formats into the given directory,
+//! or the current directory if none is given.
+//!
+//! These can be given to editors for autocompletion and validation of material and scene files.
+
+extern crate combustion_protocols as protocols;
+extern crate serde_json;
+
+use std::env;
+use std::fs::File;
+use std::path::PathBuf;
+
+use serde_json::Value;
+
+use protocols::schema::schema_for;
+use protocols::material::MaterialMap;
+use protocols::scene::Scene;
+use protocols::model::data::Model;
+
+fn main() {
+    let dir = PathBuf::from(env::args().nth(1).unwrap_or_else(|| ".".to_string()));
+
+    let schemas: Vec<(&str, Value)> = vec![
+        ("material.schema.json", schema_for::<MaterialMap>()),
+        ("scene.schema.json", schema_for::<Scene>()),
+        ("model.schema.json", schema_for::<Model>()),
+    ];
+
+    for (name, schema) in schemas {
+        let path = dir.join(name);
+
+        let mut file = File::create(&path).unwrap();
+
+        serde_json::to_writer_pretty(&mut file, &schema).unwrap();
+
+        println!("Wrote {}", path.display());
+    }
+}
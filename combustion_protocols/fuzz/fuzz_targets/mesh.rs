@@ -0,0 +1,23 @@
+#![no_main]
+
+#[macro_use]
+extern crate libfuzzer_sys;
+extern crate combustion_protocols as protocols;
+extern crate combustion_asset as asset;
+
+use std::io::Cursor;
+
+use asset::native::{self, ReaderLimits};
+
+use protocols::traits::Storage;
+use protocols::mesh::protocol;
+use protocols::mesh::data::Mesh;
+
+fuzz_target!(|data: &[u8]| {
+    // Same path as loading a native asset file, including LZ4 detection and the limits derived from the size
+    if let Ok(message) = native::read_native_stream(Cursor::new(data), ReaderLimits::default()) {
+        if let Ok(reader) = message.get_root::<protocol::mesh::Reader>() {
+            let _ = Mesh::load_from_reader(reader);
+        }
+    }
+});
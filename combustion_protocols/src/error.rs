@@ -25,6 +25,8 @@ pub enum ProtocolError {
     InvalidFormat,
     /// Indicates a value was not present
     NotPresent,
+    /// Indicates an index referred to an element that doesn't exist
+    OutOfBounds,
     /// UTF-8 codec error
     Utf8Error(Utf8Error),
     /// Arbitrary error message
@@ -52,6 +54,7 @@ impl Error for ProtocolError {
             ProtocolError::InvalidLength => "Length of data is invalid",
             ProtocolError::InvalidFormat => "Invalid format",
             ProtocolError::NotPresent => "Value is not present",
+            ProtocolError::OutOfBounds => "Index is out of bounds",
             ProtocolError::Utf8Error(ref err) => err.description(),
             ProtocolError::Other(description) => description,
             ProtocolError::CapnpError(ref err) => err.description(),
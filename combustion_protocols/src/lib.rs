@@ -21,7 +21,8 @@ extern crate serde_derive;
 extern crate trace_error;
 extern crate base64;
 extern crate blob;
-#[cfg(test)]
+#[cfg(any(test, feature = "schema"))]
+#[macro_use]
 extern crate serde_json;
 
 #[macro_use]
@@ -39,6 +40,9 @@ pub mod scene;
 pub mod texture;
 pub mod material;
 
+#[cfg(feature = "schema")]
+pub mod schema;
+
 /// Protocol utilities
 pub mod utils {
     pub mod protocol {
@@ -21,6 +21,7 @@ impl Default for Material {
             metallic: None,
             color: Color::none(),
             emission: None,
+            luminance: None,
             translucency: None,
             ior: None,
             shader: None,
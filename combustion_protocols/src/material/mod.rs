@@ -8,6 +8,7 @@ use std::path::PathBuf;
 
 use common::color::Color;
 use common::color::de as color_de;
+use common::photometry::{Photometric, Emitter};
 
 pub mod defaults;
 pub mod anisotropy;
@@ -15,6 +16,9 @@ pub mod anisotropy;
 #[cfg(feature = "sample")]
 pub mod sample;
 
+#[cfg(feature = "schema")]
+pub mod schema;
+
 pub use self::defaults::*;
 pub use self::anisotropy::MaterialAnisotropy;
 
@@ -110,6 +114,10 @@ pub struct Material {
     #[serde(skip_serializing_if = "Option::is_none")]
     #[serde(default = "Option::default")]
     pub emission: Option<f32>,
+    /// Emitted luminance in nits, used instead of `emission` if given.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default = "Option::default")]
+    pub luminance: Option<f32>,
     /// Overall translucency for the material. 0.0 is totally transparent and 1.0 is fully opaque.
     ///
     /// This entry can be omitted or set to `None` to assume fully opaque materials.
@@ -137,6 +145,17 @@ pub struct Material {
     pub render: Option<RenderMethod>,
 }
 
+impl Material {
+    /// Emission of the material in the renderer's radiometric units,
+    /// converted from `luminance` if given, otherwise just `emission`.
+    pub fn radiometric_emission(&self) -> Option<f32> {
+        match self.luminance {
+            Some(nits) => Photometric::Nits(nits).to_radiometric(Emitter::Surface),
+            None => self.emission,
+        }
+    }
+}
+
 /// Preferred rendering pipeline to use for the material
 #[derive(Debug, Serialize, Deserialize)]
 pub enum RenderMethod {
@@ -0,0 +1,84 @@
+//! JSON Schemas for material structures
+
+use nalgebra::Vector3;
+
+use serde_json::Value;
+
+use common::color::Color;
+
+use ::schema::{JsonSchema, Definitions, object, map_of, tuple, optional, string_enum, describe, number, string};
+
+use super::{MaterialMap, Material, MaterialAnisotropy, MaterialShader, RenderMethod};
+
+impl JsonSchema for MaterialMap {
+    fn schema_name() -> &'static str { "MaterialMap" }
+
+    fn schema(definitions: &mut Definitions) -> Value {
+        object(vec![("materials", map_of(definitions.reference::<Material>()))], &["materials"])
+    }
+}
+
+impl JsonSchema for Material {
+    fn schema_name() -> &'static str { "Material" }
+
+    fn schema(definitions: &mut Definitions) -> Value {
+        let path = || optional(describe(string(), "Path to a texture"));
+
+        object(vec![
+            ("preset", optional(describe(string(), "Name of the material to inherit properties from"))),
+            ("texture", path()),
+            ("normal_map", path()),
+            ("tangent_map", path()),
+            ("height_map", path()),
+            ("roughness_map", path()),
+            ("metallic_map", path()),
+            ("roughness", optional(number())),
+            ("smoothness", optional(number())),
+            ("metallic", optional(number())),
+            ("color", definitions.reference::<Color>()),
+            ("emission", optional(number())),
+            ("luminance", optional(describe(number(), "Emitted luminance in nits"))),
+            ("translucency", optional(number())),
+            ("ior", optional(number())),
+            ("anisotropy", definitions.reference::<MaterialAnisotropy>()),
+            ("shader", optional(definitions.reference::<MaterialShader>())),
+            ("render", optional(definitions.reference::<RenderMethod>())),
+        ], &[])
+    }
+}
+
+/// Anisotropy can be given as just an amount, or as a structure, as with `anisotropy::de::from_num_or_value`
+impl JsonSchema for MaterialAnisotropy {
+    fn schema_name() -> &'static str { "MaterialAnisotropy" }
+
+    fn schema(definitions: &mut Definitions) -> Value {
+        let rotation = definitions.reference::<Vector3<f32>>();
+
+        json!({
+            "oneOf": [
+                describe(number(), "Amount of anisotropy"),
+                object(vec![
+                    ("amount", optional(number())),
+                    ("rotation", optional(rotation.clone())),
+                ], &[]),
+                tuple(vec![optional(number()), optional(rotation)]),
+            ]
+        })
+    }
+}
+
+impl JsonSchema for MaterialShader {
+    fn schema_name() -> &'static str { "MaterialShader" }
+
+    fn schema(_: &mut Definitions) -> Value {
+        string_enum(&["uber", "mirror", "metal", "matte", "substrate", "glass", "hair"])
+    }
+}
+
+impl JsonSchema for RenderMethod {
+    fn schema_name() -> &'static str { "RenderMethod" }
+
+    fn schema(_: &mut Definitions) -> Value {
+        string_enum(&["forward", "deferred", "forward_plus", "clustered"])
+    }
+}
@@ -1,6 +1,12 @@
 //! Data structures for manipulating math data
+//!
+//! Transforms are applied to column vectors, so a sequence of transforms applied in order
+//! composes into a single matrix as `last * ... * first`.
 
-use nalgebra::{Vector3, Matrix4};
+use nalgebra::{Vector3, Matrix4, Quaternion, Eye};
+
+/// Tolerance used when decomposing matrices
+const EPSILON: f32 = 1e-4;
 
 /// 3D Transformations
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
@@ -8,7 +14,7 @@ pub enum Transform {
     /// translation
     #[serde(rename = "translation")]
     Translation(Vector3<f32>),
-    /// rotation (using Euler angles)
+    /// rotation (using Euler angles in radians, applied around the X, then Y, then Z axis)
     #[serde(rename = "rotation")]
     Rotation(Vector3<f32>),
     /// scale on each axis
@@ -17,4 +23,246 @@ pub enum Transform {
     /// Arbitrary matrix transform
     #[serde(rename = "matrix")]
     Matrix(Matrix4<f32>),
-}
\ No newline at end of file
+    /// rotation using a quaternion, which does not have to be normalized
+    #[serde(rename = "quaternion")]
+    Quaternion(Quaternion<f32>),
+    /// rotation by an angle around an axis
+    #[serde(rename = "axis_angle")]
+    AxisAngle {
+        /// Axis of rotation, which does not have to be normalized
+        axis: Vector3<f32>,
+        /// Angle of rotation, in radians
+        angle: f32,
+    },
+    /// scale, then rotation, then translation
+    #[serde(rename = "trs")]
+    Trs {
+        /// translation
+        translation: Vector3<f32>,
+        /// rotation
+        rotation: Quaternion<f32>,
+        /// scale on each axis
+        scale: Vector3<f32>,
+    },
+}
+
+/// Compose a sequence of transforms, in applied order, into a single matrix
+pub fn compose(transforms: &[Transform]) -> Matrix4<f32> {
+    transforms.iter().fold(Matrix4::new_identity(4), |matrix, transform| transform.to_matrix() * matrix)
+}
+
+impl Transform {
+    /// Convert the transform into a matrix
+    pub fn to_matrix(&self) -> Matrix4<f32> {
+        match *self {
+            Transform::Translation(ref translation) => trs_matrix(translation, &identity_quaternion(), &Vector3::new(1.0, 1.0, 1.0)),
+            Transform::Rotation(ref angles) => euler_matrix(angles),
+            Transform::Scale(ref scale) => trs_matrix(&Vector3::new(0.0, 0.0, 0.0), &identity_quaternion(), scale),
+            Transform::Matrix(ref matrix) => *matrix,
+            Transform::Quaternion(ref rotation) => trs_matrix(&Vector3::new(0.0, 0.0, 0.0), rotation, &Vector3::new(1.0, 1.0, 1.0)),
+            Transform::AxisAngle { ref axis, angle } => {
+                trs_matrix(&Vector3::new(0.0, 0.0, 0.0), &axis_angle_quaternion(axis, angle), &Vector3::new(1.0, 1.0, 1.0))
+            },
+            Transform::Trs { ref translation, ref rotation, ref scale } => trs_matrix(translation, rotation, scale),
+        }
+    }
+
+    /// Decompose an affine matrix into a `Trs` transform.
+    ///
+    /// Returns `None` if the matrix has a projection, a degenerate scale or shear,
+    /// since those can't be represented by translation, rotation and scale alone.
+    /// Mirroring is represented by a negative X scale.
+    pub fn decompose(matrix: &Matrix4<f32>) -> Option<Transform> {
+        let m = matrix;
+
+        if m.m41.abs() > EPSILON || m.m42.abs() > EPSILON || m.m43.abs() > EPSILON || (m.m44 - 1.0).abs() > EPSILON {
+            return None;
+        }
+
+        let mut columns = [
+            [m.m11, m.m21, m.m31],
+            [m.m12, m.m22, m.m32],
+            [m.m13, m.m23, m.m33],
+        ];
+
+        let mut scale = [0.0; 3];
+
+        for (column, scale) in columns.iter_mut().zip(scale.iter_mut()) {
+            *scale = dot(column, column).sqrt();
+
+            if *scale < EPSILON {
+                return None;
+            }
+
+            for value in column.iter_mut() {
+                *value /= *scale;
+            }
+        }
+
+        if dot(&cross(&columns[0], &columns[1]), &columns[2]) < 0.0 {
+            scale[0] = -scale[0];
+
+            for value in columns[0].iter_mut() {
+                *value = -*value;
+            }
+        }
+
+        // The remaining basis must be orthonormal, otherwise the matrix has shear
+        if dot(&columns[0], &columns[1]).abs() > EPSILON ||
+            dot(&columns[0], &columns[2]).abs() > EPSILON ||
+            dot(&columns[1], &columns[2]).abs() > EPSILON {
+            return None;
+        }
+
+        Some(Transform::Trs {
+            translation: Vector3::new(m.m14, m.m24, m.m34),
+            rotation: rotation_quaternion(&columns),
+            scale: Vector3::new(scale[0], scale[1], scale[2]),
+        })
+    }
+
+    /// Decompose a matrix into a `Trs` transform if possible, otherwise keep the `Matrix`
+    pub fn from_matrix(matrix: Matrix4<f32>) -> Transform {
+        Transform::decompose(&matrix).unwrap_or(Transform::Matrix(matrix))
+    }
+}
+
+fn identity_quaternion() -> Quaternion<f32> {
+    Quaternion::new(1.0, 0.0, 0.0, 0.0)
+}
+
+fn dot(a: &[f32; 3], b: &[f32; 3]) -> f32 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn cross(a: &[f32; 3], b: &[f32; 3]) -> [f32; 3] {
+    [a[1] * b[2] - a[2] * b[1], a[2] * b[0] - a[0] * b[2], a[0] * b[1] - a[1] * b[0]]
+}
+
+fn axis_angle_quaternion(axis: &Vector3<f32>, angle: f32) -> Quaternion<f32> {
+    let len = (axis.x * axis.x + axis.y * axis.y + axis.z * axis.z).sqrt();
+
+    if len < EPSILON {
+        return identity_quaternion();
+    }
+
+    let (sin, cos) = (angle * 0.5).sin_cos();
+    let s = sin / len;
+
+    Quaternion::new(cos, axis.x * s, axis.y * s, axis.z * s)
+}
+
+// Converts an orthonormal basis, given as columns, into a unit quaternion
+fn rotation_quaternion(c: &[[f32; 3]; 3]) -> Quaternion<f32> {
+    // Elements are c[column][row]
+    let trace = c[0][0] + c[1][1] + c[2][2];
+
+    // Pick the largest diagonal term to avoid dividing by a small number
+    let (w, i, j, k) = if trace > 0.0 {
+        let s = (trace + 1.0).sqrt() * 2.0;
+        (0.25 * s, (c[1][2] - c[2][1]) / s, (c[2][0] - c[0][2]) / s, (c[0][1] - c[1][0]) / s)
+    } else if c[0][0] > c[1][1] && c[0][0] > c[2][2] {
+        let s = (1.0 + c[0][0] - c[1][1] - c[2][2]).sqrt() * 2.0;
+        ((c[1][2] - c[2][1]) / s, 0.25 * s, (c[1][0] + c[0][1]) / s, (c[2][0] + c[0][2]) / s)
+    } else if c[1][1] > c[2][2] {
+        let s = (1.0 + c[1][1] - c[0][0] - c[2][2]).sqrt() * 2.0;
+        ((c[2][0] - c[0][2]) / s, (c[1][0] + c[0][1]) / s, 0.25 * s, (c[2][1] + c[1][2]) / s)
+    } else {
+        let s = (1.0 + c[2][2] - c[0][0] - c[1][1]).sqrt() * 2.0;
+        ((c[0][1] - c[1][0]) / s, (c[2][0] + c[0][2]) / s, (c[2][1] + c[1][2]) / s, 0.25 * s)
+    };
+
+    let len = (w * w + i * i + j * j + k * k).sqrt();
+
+    Quaternion::new(w / len, i / len, j / len, k / len)
+}
+
+fn euler_matrix(angles: &Vector3<f32>) -> Matrix4<f32> {
+    let (sx, cx) = angles.x.sin_cos();
+    let (sy, cy) = angles.y.sin_cos();
+    let (sz, cz) = angles.z.sin_cos();
+
+    let mut matrix: Matrix4<f32> = Matrix4::new_identity(4);
+
+    // Rz * Ry * Rx
+    matrix.m11 = cy * cz;
+    matrix.m12 = sx * sy * cz - cx * sz;
+    matrix.m13 = cx * sy * cz + sx * sz;
+    matrix.m21 = cy * sz;
+    matrix.m22 = sx * sy * sz + cx * cz;
+    matrix.m23 = cx * sy * sz - sx * cz;
+    matrix.m31 = -sy;
+    matrix.m32 = sx * cy;
+    matrix.m33 = cx * cy;
+
+    matrix
+}
+
+fn trs_matrix(translation: &Vector3<f32>, rotation: &Quaternion<f32>, scale: &Vector3<f32>) -> Matrix4<f32> {
+    let len = (rotation.w * rotation.w + rotation.i * rotation.i + rotation.j * rotation.j + rotation.k * rotation.k).sqrt();
+
+    let (w, x, y, z) = if len < EPSILON {
+        (1.0, 0.0, 0.0, 0.0)
+    } else {
+        (rotation.w / len, rotation.i / len, rotation.j / len, rotation.k / len)
+    };
+
+    let mut matrix: Matrix4<f32> = Matrix4::new_identity(4);
+
+    matrix.m11 = (1.0 - 2.0 * (y * y + z * z)) * scale.x;
+    matrix.m12 = (2.0 * (x * y - z * w)) * scale.y;
+    matrix.m13 = (2.0 * (x * z + y * w)) * scale.z;
+    matrix.m21 = (2.0 * (x * y + z * w)) * scale.x;
+    matrix.m22 = (1.0 - 2.0 * (x * x + z * z)) * scale.y;
+    matrix.m23 = (2.0 * (y * z - x * w)) * scale.z;
+    matrix.m31 = (2.0 * (x * z - y * w)) * scale.x;
+    matrix.m32 = (2.0 * (y * z + x * w)) * scale.y;
+    matrix.m33 = (1.0 - 2.0 * (x * x + y * y)) * scale.z;
+
+    matrix.m14 = translation.x;
+    matrix.m24 = translation.y;
+    matrix.m34 = translation.z;
+
+    matrix
+}
+
+#[cfg(test)]
+mod test {
+    use nalgebra::{Vector3, Matrix4};
+
+    use super::*;
+
+    fn assert_matrix_eq(a: &Matrix4<f32>, b: &Matrix4<f32>) {
+        let a = [a.m11, a.m12, a.m13, a.m14, a.m21, a.m22, a.m23, a.m24, a.m31, a.m32, a.m33, a.m34];
+        let b = [b.m11, b.m12, b.m13, b.m14, b.m21, b.m22, b.m23, b.m24, b.m31, b.m32, b.m33, b.m34];
+
+        for (a, b) in a.iter().zip(b.iter()) {
+            assert!((a - b).abs() < 1e-4, "{:?} != {:?}", a, b);
+        }
+    }
+
+    #[test]
+    fn test_decompose_roundtrip() {
+        let transforms = [
+            Transform::Scale(Vector3::new(-2.0, 0.5, 3.0)),
+            Transform::AxisAngle { axis: Vector3::new(1.0, 2.0, -0.5), angle: 2.5 },
+            Transform::Rotation(Vector3::new(0.3, -1.2, 2.9)),
+            Transform::Translation(Vector3::new(1.0, -4.0, 10.0)),
+        ];
+
+        let matrix = compose(&transforms);
+
+        let trs = Transform::decompose(&matrix).expect("Matrix should be decomposable");
+
+        assert_matrix_eq(&trs.to_matrix(), &matrix);
+    }
+
+    #[test]
+    fn test_decompose_shear() {
+        let mut matrix: Matrix4<f32> = compose(&[]);
+
+        matrix.m12 = 0.5;
+
+        assert!(Transform::decompose(&matrix).is_none());
+    }
+}
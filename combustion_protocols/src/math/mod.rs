@@ -2,4 +2,7 @@
 
 pub mod data;
 pub mod protocol;
-pub mod storage;
\ No newline at end of file
+pub mod storage;
+
+#[cfg(feature = "schema")]
+pub mod schema;
\ No newline at end of file
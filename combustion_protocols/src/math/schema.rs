@@ -0,0 +1,82 @@
+//! JSON Schemas for math structures
+
+use nalgebra::{Vector3, Point3, Matrix4, Quaternion};
+
+use serde_json::Value;
+
+use ::schema::{JsonSchema, Definitions, object, describe, number};
+
+use super::data::Transform;
+
+impl JsonSchema for Vector3<f32> {
+    fn schema_name() -> &'static str { "Vector3" }
+
+    fn schema(_: &mut Definitions) -> Value {
+        object(vec![("x", number()), ("y", number()), ("z", number())], &["x", "y", "z"])
+    }
+}
+
+impl JsonSchema for Point3<f32> {
+    fn schema_name() -> &'static str { "Point3" }
+
+    fn schema(_: &mut Definitions) -> Value {
+        object(vec![("x", number()), ("y", number()), ("z", number())], &["x", "y", "z"])
+    }
+}
+
+impl JsonSchema for Matrix4<f32> {
+    fn schema_name() -> &'static str { "Matrix4" }
+
+    fn schema(_: &mut Definitions) -> Value {
+        const ELEMENTS: [&'static str; 16] = [
+            "m11", "m21", "m31", "m41",
+            "m12", "m22", "m32", "m42",
+            "m13", "m23", "m33", "m43",
+            "m14", "m24", "m34", "m44",
+        ];
+
+        object(ELEMENTS.iter().map(|&element| (element, number())).collect(), &ELEMENTS)
+    }
+}
+
+impl JsonSchema for Quaternion<f32> {
+    fn schema_name() -> &'static str { "Quaternion" }
+
+    fn schema(_: &mut Definitions) -> Value {
+        object(vec![
+            ("w", describe(number(), "Real part")),
+            ("i", number()),
+            ("j", number()),
+            ("k", number()),
+        ], &["w", "i", "j", "k"])
+    }
+}
+
+impl JsonSchema for Transform {
+    fn schema_name() -> &'static str { "Transform" }
+
+    fn schema(definitions: &mut Definitions) -> Value {
+        let vector = definitions.reference::<Vector3<f32>>();
+        let matrix = definitions.reference::<Matrix4<f32>>();
+        let quaternion = definitions.reference::<Quaternion<f32>>();
+
+        json!({
+            "oneOf": [
+                object(vec![("translation", vector.clone())], &["translation"]),
+                object(vec![("rotation", vector.clone())], &["rotation"]),
+                object(vec![("scale", vector.clone())], &["scale"]),
+                object(vec![("matrix", matrix)], &["matrix"]),
+                object(vec![("quaternion", quaternion.clone())], &["quaternion"]),
+                object(vec![("axis_angle", object(vec![
+                    ("axis", vector.clone()),
+                    ("angle", describe(number(), "Angle in radians")),
+                ], &["axis", "angle"]))], &["axis_angle"]),
+                object(vec![("trs", object(vec![
+                    ("translation", vector.clone()),
+                    ("rotation", quaternion),
+                    ("scale", vector),
+                ], &["translation", "rotation", "scale"]))], &["trs"]),
+            ]
+        })
+    }
+}
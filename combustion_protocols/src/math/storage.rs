@@ -18,6 +18,12 @@ pub enum TransformQuery {
     Scale,
     /// Matrix
     Matrix,
+    /// Quaternion
+    Quaternion,
+    /// Axis-angle
+    AxisAngle,
+    /// Translation, rotation and scale
+    Trs,
 }
 
 impl StorageQuery for TransformQuery {
@@ -47,6 +53,26 @@ impl<'a> Storage<'a> for Transform {
             protocol::transform::transform::Matrix(matrix) => {
                 Transform::Matrix(try_throw!(matrix).get_matrix())
             },
+            protocol::transform::transform::Quaternion(quaternion) => {
+                Transform::Quaternion(try_throw!(quaternion).get_quaternion())
+            },
+            protocol::transform::transform::AxisAngle(axis_angle) => {
+                let axis_angle = try_throw!(axis_angle);
+
+                Transform::AxisAngle {
+                    axis: try_throw!(axis_angle.get_axis()).get_vector(),
+                    angle: axis_angle.get_angle(),
+                }
+            },
+            protocol::transform::transform::Trs(trs) => {
+                let trs = try_throw!(trs);
+
+                Transform::Trs {
+                    translation: try_throw!(trs.get_translation()).get_vector(),
+                    rotation: try_throw!(trs.get_rotation()).get_quaternion(),
+                    scale: try_throw!(trs.get_scale()).get_vector(),
+                }
+            },
         })
     }
 
@@ -66,6 +92,22 @@ impl<'a> Storage<'a> for Transform {
             Transform::Matrix(ref matrix) => {
                 transform_builder.init_matrix().set_matrix(matrix);
             },
+            Transform::Quaternion(ref quaternion) => {
+                transform_builder.init_quaternion().set_quaternion(quaternion);
+            },
+            Transform::AxisAngle { ref axis, angle } => {
+                let mut axis_angle_builder = transform_builder.init_axis_angle();
+
+                axis_angle_builder.borrow().init_axis().set_vector(axis);
+                axis_angle_builder.set_angle(angle);
+            },
+            Transform::Trs { ref translation, ref rotation, ref scale } => {
+                let mut trs_builder = transform_builder.init_trs();
+
+                trs_builder.borrow().init_translation().set_vector(translation);
+                trs_builder.borrow().init_rotation().set_quaternion(rotation);
+                trs_builder.init_scale().set_vector(scale);
+            },
         }
 
         Ok(())
@@ -77,6 +119,9 @@ impl<'a> Storage<'a> for Transform {
             protocol::transform::transform::Rotation(_) => TransformQuery::Rotation,
             protocol::transform::transform::Scale(_) => TransformQuery::Scale,
             protocol::transform::transform::Matrix(_) => TransformQuery::Matrix,
+            protocol::transform::transform::Quaternion(_) => TransformQuery::Quaternion,
+            protocol::transform::transform::AxisAngle(_) => TransformQuery::AxisAngle,
+            protocol::transform::transform::Trs(_) => TransformQuery::Trs,
         })
     }
 }
\ No newline at end of file
@@ -63,6 +63,21 @@ pub enum MeshVertices {
     Interleaved(Vec<Vertex>),
 }
 
+impl MeshVertices {
+    /// Number of vertices, as given by the vertex positions for discrete vertices
+    pub fn len(&self) -> usize {
+        match *self {
+            MeshVertices::Discrete(ref vertices) => vertices.positions.len(),
+            MeshVertices::Interleaved(ref vertices) => vertices.len(),
+        }
+    }
+
+    /// Checks if there are no vertices
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
 impl Debug for MeshVertices {
     fn fmt(&self, f: &mut Formatter) -> FmtResult {
         write!(f, "MeshVertices {{ {} }}", match *self {
@@ -0,0 +1,81 @@
+//! JSON Schemas for mesh structures
+
+use nalgebra::{Vector3, Point3};
+
+use serde_json::Value;
+
+use ::schema::{JsonSchema, Definitions, object, array_of, optional, string_enum, number, index};
+
+use super::protocol::MeshPrimitive;
+use super::data::{Mesh, MeshVertices, Vertices, Vertex, TexCoord};
+
+impl JsonSchema for Mesh {
+    fn schema_name() -> &'static str { "Mesh" }
+
+    fn schema(definitions: &mut Definitions) -> Value {
+        object(vec![
+            ("vertices", definitions.reference::<MeshVertices>()),
+            ("indices", optional(array_of(index()))),
+            ("materials", array_of(index())),
+            ("primitive", definitions.reference::<MeshPrimitive>()),
+        ], &["vertices", "primitive"])
+    }
+}
+
+/// `MeshPrimitive` is generated from mesh.capnp, and is (de)serialized by its variant names
+impl JsonSchema for MeshPrimitive {
+    fn schema_name() -> &'static str { "MeshPrimitive" }
+
+    fn schema(_: &mut Definitions) -> Value {
+        string_enum(&[
+            "Points", "Lines", "LineStrip", "LineLoop", "Triangles",
+            "TriangleStrip", "TriangleFan", "Quads", "QuadStrip", "Polygon",
+        ])
+    }
+}
+
+/// `MeshVertices` is untagged, so either form is accepted as-is
+impl JsonSchema for MeshVertices {
+    fn schema_name() -> &'static str { "MeshVertices" }
+
+    fn schema(definitions: &mut Definitions) -> Value {
+        json!({
+            "anyOf": [
+                definitions.reference::<Vertices>(),
+                array_of(definitions.reference::<Vertex>()),
+            ]
+        })
+    }
+}
+
+impl JsonSchema for Vertices {
+    fn schema_name() -> &'static str { "Vertices" }
+
+    fn schema(definitions: &mut Definitions) -> Value {
+        object(vec![
+            ("positions", array_of(definitions.reference::<Point3<f32>>())),
+            ("normals", optional(array_of(definitions.reference::<Vector3<f32>>()))),
+            ("uvs", optional(array_of(definitions.reference::<TexCoord>()))),
+        ], &["positions"])
+    }
+}
+
+impl JsonSchema for Vertex {
+    fn schema_name() -> &'static str { "Vertex" }
+
+    fn schema(definitions: &mut Definitions) -> Value {
+        object(vec![
+            ("position", definitions.reference::<Point3<f32>>()),
+            ("normal", definitions.reference::<Vector3<f32>>()),
+            ("uv", definitions.reference::<TexCoord>()),
+        ], &["position", "normal", "uv"])
+    }
+}
+
+impl JsonSchema for TexCoord {
+    fn schema_name() -> &'static str { "TexCoord" }
+
+    fn schema(_: &mut Definitions) -> Value {
+        object(vec![("u", number()), ("v", number())], &["u", "v"])
+    }
+}
@@ -1,6 +1,7 @@
 //! Storage routines for meshes
 
 use std::mem;
+use std::ptr;
 use std::slice;
 
 use nalgebra::*;
@@ -28,6 +29,29 @@ impl Default for MeshSaveArgs {
     }
 }
 
+/// Copies raw vertex data into a new `Vec`, checking that it holds a whole number of elements
+///
+/// The data is copied rather than reinterpreted in place, because it isn't guaranteed to be aligned for `T`.
+fn copy_raw<T: Copy>(data: &[u8]) -> ProtocolResult<Vec<T>> {
+    let size = mem::size_of::<T>();
+
+    if data.len() % size != 0 {
+        throw!(ProtocolError::InvalidLength);
+    }
+
+    let len = data.len() / size;
+
+    let mut elements = Vec::with_capacity(len);
+
+    unsafe {
+        ptr::copy_nonoverlapping(data.as_ptr(), elements.as_mut_ptr() as *mut u8, data.len());
+
+        elements.set_len(len);
+    }
+
+    Ok(elements)
+}
+
 impl<'a> Storage<'a> for Mesh {
     type Builder = protocol::mesh::Builder<'a>;
     type Reader = protocol::mesh::Reader<'a>;
@@ -40,13 +64,13 @@ impl<'a> Storage<'a> for Mesh {
     ///
     /// This is expensive for non-raw meshes, but is safe. It basically has to iterate through every single number.
     ///
-    /// This is cheap for raw meshes, but is unsafe, obviously. It basically just casts the pointers and copy the data directly.
+    /// This is cheap for raw meshes, as their data is copied directly, but is not portable between platforms.
     fn load_from_reader_args(reader: Self::Reader, _: ()) -> ProtocolResult<Self> {
         let vertices_reader = reader.get_vertices();
 
         let indices_option = try_throw!(reader.get_indices());
 
-        let indices = match try_throw!(indices_option.which()) {
+        let indices: Option<Vec<u32>> = match try_throw!(indices_option.which()) {
             utils::protocol::option::Some(indices) => {
                 Some(try_throw!(indices).iter().collect())
             },
@@ -135,23 +159,7 @@ impl<'a> Storage<'a> for Mesh {
                 })
             },
             protocol::mesh::vertices::InterleavedRaw(vertices_data) => {
-                let vertices_data = try_throw!(vertices_data);
-
-                let vertex_size = mem::size_of::<Vertex>();
-                let vertices_data_len = vertices_data.len() as usize;
-
-                // Check that this is probably even vertex data in the first place
-                if vertices_data_len % vertex_size != 0 {
-                    throw!(ProtocolError::InvalidLength);
-                }
-
-                let num_vertices = vertices_data_len / vertex_size;
-
-                // Coerce to Vertex slice
-                let vertices = unsafe { slice::from_raw_parts(vertices_data.as_ptr() as *const Vertex, num_vertices) };
-
-                // Convert into Vec<Vertex>
-                MeshVertices::Interleaved(vertices.into())
+                MeshVertices::Interleaved(copy_raw(try_throw!(vertices_data))?)
             },
             protocol::mesh::vertices::DiscreteRaw(vertices) => {
                 let vertices = try_throw!(vertices);
@@ -161,56 +169,16 @@ impl<'a> Storage<'a> for Mesh {
                 let uvs_data_option = try_throw!(vertices.get_uvs());
 
                 MeshVertices::Discrete(Vertices {
-                    positions: {
-                        let point_size = mem::size_of::<Point3<f32>>();
-
-                        if positions_data.len() % point_size != 0 {
-                            throw!(ProtocolError::InvalidLength);
-                        }
-
-                        let num_positions = positions_data.len() / point_size;
-
-                        let positions = unsafe { slice::from_raw_parts(positions_data.as_ptr() as *const Point3<f32>, num_positions) };
-
-                        positions.into()
-                    },
+                    positions: copy_raw(positions_data)?,
                     normals: {
                         match try_throw!(normals_data_option.which()) {
-                            utils::protocol::option::Some(normals_data) => {
-                                let normals_data = try_throw!(normals_data);
-
-                                let normal_size = mem::size_of::<Vector3<f32>>();
-
-                                if normals_data.len() % normal_size != 0 {
-                                    throw!(ProtocolError::InvalidLength);
-                                }
-
-                                let num_normals = normals_data.len() / normal_size;
-
-                                let normals = unsafe { slice::from_raw_parts(normals_data.as_ptr() as *const Vector3<f32>, num_normals) };
-
-                                Some(normals.into())
-                            },
+                            utils::protocol::option::Some(normals_data) => Some(copy_raw(try_throw!(normals_data))?),
                             _ => None,
                         }
                     },
                     uvs: {
                         match try_throw!(uvs_data_option.which()) {
-                            utils::protocol::option::Some(uvs_data) => {
-                                let uvs_data = try_throw!(uvs_data);
-
-                                let uv_size = mem::size_of::<TexCoord>();
-
-                                if uvs_data.len() % uv_size != 0 {
-                                    throw!(ProtocolError::InvalidLength);
-                                }
-
-                                let num_uvs = uvs_data.len() / uv_size;
-
-                                let uvs = unsafe { slice::from_raw_parts(uvs_data.as_ptr() as *const TexCoord, num_uvs) };
-
-                                Some(uvs.into())
-                            },
+                            utils::protocol::option::Some(uvs_data) => Some(copy_raw(try_throw!(uvs_data))?),
                             _ => None,
                         }
                     }
@@ -218,6 +186,24 @@ impl<'a> Storage<'a> for Mesh {
             },
         };
 
+        // Every attribute stream must cover every vertex, and every index must refer to one
+        if let MeshVertices::Discrete(ref vertices) = vertices {
+            let count = vertices.positions.len();
+
+            if vertices.normals.as_ref().map_or(false, |normals| normals.len() != count) ||
+                vertices.uvs.as_ref().map_or(false, |uvs| uvs.len() != count) {
+                throw!(ProtocolError::InvalidLength);
+            }
+        }
+
+        if let Some(ref indices) = indices {
+            let count = vertices.len();
+
+            if indices.iter().any(|&index| index as usize >= count) {
+                throw!(ProtocolError::OutOfBounds);
+            }
+        }
+
         Ok(Mesh {
             vertices: vertices,
             indices: indices,
@@ -359,6 +345,6 @@ impl<'a> Storage<'a> for Mesh {
     }
 
     fn query_reader_args(_: Self::Reader, _: ()) -> ProtocolResult<()> {
-        unimplemented!()
+        throw!(ProtocolError::Unsupported)
     }
 }
\ No newline at end of file
@@ -25,12 +25,132 @@ pub struct Node {
     #[serde(skip_serializing_if = "Vec::is_empty")]
     #[serde(default)]
     pub transforms: Vec<Transform>,
+    /// Levels of detail for this node, used instead of `meshes` if given
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub lod: Option<LodGroup>,
 }
 
 impl Debug for Node {
     fn fmt(&self, f: &mut Formatter) -> FmtResult {
-        write!(f, r#"Node {{name: "{}", meshes: {:?}, children: {:?}, transforms: {}}}"#,
-               self.name, self.meshes, self.children, self.transforms.len())
+        write!(f, r#"Node {{name: "{}", meshes: {:?}, children: {:?}, transforms: {}, lod: {:?}}}"#,
+               self.name, self.meshes, self.children, self.transforms.len(), self.lod)
+    }
+}
+
+impl Node {
+    /// Meshes to draw for this node, selecting a level of detail if the node has any.
+    ///
+    /// Returns no meshes if the node has levels of detail and is beyond the last of them.
+    pub fn active_meshes(&self, metric: LodMetric) -> &[u32] {
+        match self.lod {
+            Some(ref lod) => lod.select(metric).map_or(&[][..], |selection| &lod.levels[selection.level].meshes[..]),
+            None => &self.meshes,
+        }
+    }
+}
+
+/// Threshold up to which a level of detail is active
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum LodThreshold {
+    /// Maximum distance from the viewer
+    #[serde(rename = "distance")]
+    Distance(f32),
+    /// Minimum projected size, as a fraction of the screen height
+    #[serde(rename = "screen_size")]
+    ScreenSize(f32),
+}
+
+/// Single level of detail
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LodLevel {
+    /// Meshes to draw at this level
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[serde(default)]
+    pub meshes: Vec<u32>,
+    /// Threshold up to which this level is active
+    pub threshold: LodThreshold,
+}
+
+/// Alternative sets of meshes for a node, drawn depending on how far away or large it appears
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LodGroup {
+    /// Levels of detail, from most to least detailed
+    pub levels: Vec<LodLevel>,
+    /// Width of the range before each threshold over which to blend into the next level,
+    /// in the same units as the threshold
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub transition: Option<f32>,
+}
+
+/// Measurement of how detailed a node should appear, to select a level of detail with
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LodMetric {
+    /// Distance from the viewer
+    Distance(f32),
+    /// Projected size, as a fraction of the screen height
+    ScreenSize(f32),
+}
+
+impl LodMetric {
+    /// Projected size of a bounding sphere with the given radius and distance from the viewer,
+    /// for a perspective projection with the given vertical field of view in radians
+    pub fn projected(radius: f32, distance: f32, fov: f32) -> LodMetric {
+        let height = distance.max(1e-6) * (fov * 0.5).tan();
+
+        LodMetric::ScreenSize(radius / height)
+    }
+}
+
+/// Level of detail selected by `LodGroup::select`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LodSelection {
+    /// Index of the active level
+    pub level: usize,
+    /// How far into the transition to the next level, from 0 to 1.
+    ///
+    /// For the last level, this fades out the node entirely.
+    pub blend: f32,
+}
+
+impl LodThreshold {
+    // How far past the start of the transition range the metric is, or `None` if the threshold is exceeded.
+    // Thresholds of a different kind than the metric never match.
+    fn remaining(&self, metric: LodMetric) -> Option<f32> {
+        match (*self, metric) {
+            (LodThreshold::Distance(threshold), LodMetric::Distance(distance)) if distance <= threshold => {
+                Some(threshold - distance)
+            },
+            (LodThreshold::ScreenSize(threshold), LodMetric::ScreenSize(size)) if size >= threshold => {
+                Some(size - threshold)
+            },
+            _ => None,
+        }
+    }
+}
+
+impl LodGroup {
+    /// Select the most detailed level whose threshold the metric is within.
+    ///
+    /// Levels are expected to all use the same kind of threshold as the metric.
+    /// Returns `None` if the metric is beyond every level, in which case nothing should be drawn.
+    pub fn select(&self, metric: LodMetric) -> Option<LodSelection> {
+        let transition = self.transition.unwrap_or(0.0);
+
+        for (i, level) in self.levels.iter().enumerate() {
+            if let Some(remaining) = level.threshold.remaining(metric) {
+                let blend = if transition > 0.0 && remaining < transition {
+                    1.0 - remaining / transition
+                } else {
+                    0.0
+                };
+
+                return Some(LodSelection { level: i, blend: blend });
+            }
+        }
+
+        None
     }
 }
 
@@ -57,4 +177,30 @@ impl Debug for Model {
     fn fmt(&self, f: &mut Formatter) -> FmtResult {
         write!(f, "Model {{root: {:?}, meshes: {:?}}}", self.root, self.meshes)
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_lod_select() {
+        let lod = LodGroup {
+            levels: vec![
+                LodLevel { meshes: vec![0], threshold: LodThreshold::Distance(10.0) },
+                LodLevel { meshes: vec![1], threshold: LodThreshold::Distance(50.0) },
+            ],
+            transition: Some(5.0),
+        };
+
+        assert_eq!(lod.select(LodMetric::Distance(2.0)), Some(LodSelection { level: 0, blend: 0.0 }));
+        assert_eq!(lod.select(LodMetric::Distance(7.5)), Some(LodSelection { level: 0, blend: 0.5 }));
+        assert_eq!(lod.select(LodMetric::Distance(20.0)), Some(LodSelection { level: 1, blend: 0.0 }));
+        assert_eq!(lod.select(LodMetric::Distance(60.0)), None);
+        assert_eq!(lod.select(LodMetric::ScreenSize(0.5)), None);
+
+        let node = Node { meshes: vec![2], lod: Some(lod), ..Node::default() };
+
+        assert_eq!(node.active_meshes(LodMetric::Distance(20.0)), &[1]);
+    }
+}
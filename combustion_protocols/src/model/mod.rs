@@ -5,5 +5,8 @@ pub mod data;
 pub mod defaults;
 pub mod storage;
 
+#[cfg(feature = "schema")]
+pub mod schema;
+
 /// File extension to Combustion model files
 pub const EXTENSION: &'static str = "cmodel";
\ No newline at end of file
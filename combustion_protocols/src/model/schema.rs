@@ -0,0 +1,70 @@
+//! JSON Schemas for model structures
+
+use serde_json::Value;
+
+use ::schema::{JsonSchema, Definitions, object, array_of, optional, describe, number, string, index};
+use ::math::data::Transform;
+use ::mesh::data::Mesh;
+
+use super::data::{Model, Node, LodGroup, LodLevel, LodThreshold};
+
+impl JsonSchema for Model {
+    fn schema_name() -> &'static str { "Model" }
+
+    fn schema(definitions: &mut Definitions) -> Value {
+        object(vec![
+            ("root", definitions.reference::<Node>()),
+            ("meshes", array_of(definitions.reference::<Mesh>())),
+            ("materials", array_of(string())),
+        ], &["root"])
+    }
+}
+
+impl JsonSchema for Node {
+    fn schema_name() -> &'static str { "ModelNode" }
+
+    fn schema(definitions: &mut Definitions) -> Value {
+        object(vec![
+            ("name", string()),
+            ("meshes", array_of(index())),
+            ("children", array_of(definitions.reference::<Node>())),
+            ("transforms", array_of(definitions.reference::<Transform>())),
+            ("lod", optional(definitions.reference::<LodGroup>())),
+        ], &[])
+    }
+}
+
+impl JsonSchema for LodGroup {
+    fn schema_name() -> &'static str { "LodGroup" }
+
+    fn schema(definitions: &mut Definitions) -> Value {
+        object(vec![
+            ("levels", array_of(definitions.reference::<LodLevel>())),
+            ("transition", optional(describe(number(), "Width of the range to blend between levels over"))),
+        ], &["levels"])
+    }
+}
+
+impl JsonSchema for LodLevel {
+    fn schema_name() -> &'static str { "LodLevel" }
+
+    fn schema(definitions: &mut Definitions) -> Value {
+        object(vec![
+            ("meshes", array_of(index())),
+            ("threshold", definitions.reference::<LodThreshold>()),
+        ], &["threshold"])
+    }
+}
+
+impl JsonSchema for LodThreshold {
+    fn schema_name() -> &'static str { "LodThreshold" }
+
+    fn schema(_: &mut Definitions) -> Value {
+        json!({
+            "oneOf": [
+                object(vec![("distance", describe(number(), "Maximum distance from the viewer"))], &["distance"]),
+                object(vec![("screen_size", describe(number(), "Minimum projected size as a fraction of the screen height"))], &["screen_size"]),
+            ]
+        })
+    }
+}
@@ -1,6 +1,6 @@
 //! Storage routines for models
 
-use ::error::ProtocolResult;
+use ::error::{ProtocolResult, ProtocolError};
 
 use ::traits::Storage;
 
@@ -10,7 +10,7 @@ use ::mesh::data::Mesh;
 use ::mesh::storage::MeshSaveArgs;
 
 use super::protocol;
-use super::data::{Node, Model};
+use super::data::{Node, Model, LodGroup, LodLevel, LodThreshold};
 
 /// Arguments to pass to the model storage routines
 #[derive(Debug, Clone, Copy)]
@@ -25,6 +25,90 @@ impl Default for ModelSaveArgs {
     }
 }
 
+/// Checks that every mesh index in the node tree refers to one of `num_meshes` meshes
+fn check_mesh_indices(node: &Node, num_meshes: usize) -> ProtocolResult<()> {
+    if node.meshes.iter().any(|&mesh| mesh as usize >= num_meshes) {
+        throw!(ProtocolError::OutOfBounds);
+    }
+
+    if let Some(ref lod) = node.lod {
+        if lod.levels.iter().flat_map(|level| level.meshes.iter()).any(|&mesh| mesh as usize >= num_meshes) {
+            throw!(ProtocolError::OutOfBounds);
+        }
+    }
+
+    for child in &node.children {
+        check_mesh_indices(child, num_meshes)?;
+    }
+
+    Ok(())
+}
+
+impl<'a> Storage<'a> for LodGroup {
+    type Builder = protocol::lod_group::Builder<'a>;
+    type Reader = protocol::lod_group::Reader<'a>;
+
+    type LoadArgs = ();
+    type SaveArgs = ();
+    type Query = ();
+
+    fn load_from_reader_args(reader: Self::Reader, _: ()) -> ProtocolResult<Self> {
+        let raw_levels = try_throw!(reader.get_levels());
+
+        let mut levels = Vec::with_capacity(raw_levels.len() as usize);
+
+        for level_reader in raw_levels.iter() {
+            let threshold = match try_throw!(level_reader.get_threshold().which()) {
+                protocol::lod_level::threshold::Distance(distance) => LodThreshold::Distance(distance),
+                protocol::lod_level::threshold::ScreenSize(size) => LodThreshold::ScreenSize(size),
+            };
+
+            levels.push(LodLevel {
+                meshes: try_throw!(level_reader.get_meshes()).iter().collect(),
+                threshold: threshold,
+            });
+        }
+
+        let transition = reader.get_transition();
+
+        Ok(LodGroup {
+            levels: levels,
+            transition: if transition > 0.0 { Some(transition) } else { None },
+        })
+    }
+
+    fn save_to_builder_args(&self, mut builder: Self::Builder, _: ()) -> ProtocolResult<()> {
+        {
+            let mut level_list_builder = builder.borrow().init_levels(self.levels.len() as u32);
+
+            for (i, level) in self.levels.iter().enumerate() {
+                let mut level_builder = level_list_builder.borrow().get(i as u32);
+
+                {
+                    let mut mesh_list_builder = level_builder.borrow().init_meshes(level.meshes.len() as u32);
+
+                    for (j, mesh) in level.meshes.iter().enumerate() {
+                        mesh_list_builder.set(j as u32, *mesh);
+                    }
+                }
+
+                match level.threshold {
+                    LodThreshold::Distance(distance) => level_builder.init_threshold().set_distance(distance),
+                    LodThreshold::ScreenSize(size) => level_builder.init_threshold().set_screen_size(size),
+                }
+            }
+        }
+
+        builder.set_transition(self.transition.unwrap_or(0.0));
+
+        Ok(())
+    }
+
+    fn query_reader_args(_: Self::Reader, _: ()) -> ProtocolResult<()> {
+        throw!(ProtocolError::Unsupported)
+    }
+}
+
 impl<'a> Storage<'a> for Node {
     type Builder = protocol::node::Builder<'a>;
     type Reader = protocol::node::Reader<'a>;
@@ -51,11 +135,18 @@ impl<'a> Storage<'a> for Node {
             transforms.push(Transform::load_from_reader(transform_reader)?)
         }
 
+        let lod = if reader.has_lod() {
+            Some(LodGroup::load_from_reader(try_throw!(reader.get_lod()))?)
+        } else {
+            None
+        };
+
         let node = Node {
             name: raw_name.to_string(),
             meshes: raw_meshes.iter().collect(),
             children: children,
             transforms: transforms,
+            lod: lod,
         };
 
         Ok(node)
@@ -90,13 +181,17 @@ impl<'a> Storage<'a> for Node {
             }
         }
 
+        if let Some(ref lod) = self.lod {
+            try_rethrow!(lod.save_to_builder(builder.borrow().init_lod()));
+        }
+
         builder.set_name(self.name.as_str());
 
         Ok(())
     }
 
     fn query_reader_args(_: Self::Reader, _: ()) -> ProtocolResult<()> {
-        unimplemented!()
+        throw!(ProtocolError::Unsupported)
     }
 }
 
@@ -127,6 +222,12 @@ impl<'a> Storage<'a> for Model {
             materials.push(try_throw!(material).into());
         }
 
+        check_mesh_indices(&root, meshes.len())?;
+
+        if meshes.iter().any(|mesh| mesh.materials.iter().any(|&material| material as usize >= materials.len())) {
+            throw!(ProtocolError::OutOfBounds);
+        }
+
         let model = Model {
             meshes: meshes,
             root: root,
@@ -161,6 +262,6 @@ impl<'a> Storage<'a> for Model {
     }
 
     fn query_reader_args(_: Self::Reader, _: ()) -> ProtocolResult<()> {
-        unimplemented!()
+        throw!(ProtocolError::Unsupported)
     }
 }
\ No newline at end of file
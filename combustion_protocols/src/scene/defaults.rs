@@ -28,6 +28,12 @@ impl DefaultName for Light {
     }
 }
 
+impl DefaultName for Camera {
+    fn default_name() -> String {
+        "Untitled Camera".to_string()
+    }
+}
+
 impl DefaultName for Material {
     fn default_name() -> String {
         "Untitled Material".to_string()
@@ -106,11 +112,41 @@ impl Default for Light {
             inner_cone: Light::default_inner_cone(),
             outer_cone: Light::default_outer_cone(),
             intensity: Light::default_intensity(),
+            photometric: None,
+            temperature: None,
             properties: HashMap::default(),
         }
     }
 }
 
+/// Associated functions for getting default values for camera structures
+pub trait DefaultCamera {
+    /// Returns the default value for fov
+    #[inline(always)]
+    fn default_fov() -> f32 {
+        ::std::f32::consts::FRAC_PI_3
+    }
+
+    /// Returns the default value for zdistance
+    #[inline(always)]
+    fn default_zdistance() -> (f32, f32) {
+        (0.1, 1000.0)
+    }
+}
+
+impl DefaultCamera for Camera {}
+
+impl Default for Camera {
+    fn default() -> Camera {
+        Camera {
+            name: Camera::default_name(),
+            fov: Camera::default_fov(),
+            zdistance: Camera::default_zdistance(),
+            exposure: None,
+        }
+    }
+}
+
 impl Default for Material {
     fn default() -> Material {
         Material {
@@ -1,12 +1,15 @@
 //! Scene description structures
 
 use std::collections::HashMap;
+use std::path::PathBuf;
 
 use nalgebra::*;
 
 use common::color::Color;
 use common::color::de as color_de;
 use common::traits::named::DefaultName;
+use common::traits::named::Named;
+use common::photometry::{Photometric, Emitter, Exposure};
 
 use ::math::data::Transform;
 
@@ -15,6 +18,9 @@ pub mod defaults;
 #[cfg(feature = "sample")]
 pub mod sample;
 
+#[cfg(feature = "schema")]
+pub mod schema;
+
 pub use self::defaults::*;
 
 /// Entire scene description
@@ -46,7 +52,101 @@ pub struct Node {
     /// Transforms to apply to node children, in applied order
     #[serde(skip_serializing_if = "Vec::is_empty")]
     #[serde(default)]
-    pub transform: Vec<Transform>
+    pub transform: Vec<Transform>,
+    /// Models, lights and cameras hosted by this node, placed by its transforms
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[serde(default)]
+    pub payloads: Vec<NodePayload>,
+    /// Any arbitrary properties the engine might check for
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    #[serde(default)]
+    pub properties: HashMap<String, String>,
+}
+
+/// Something hosted by a scene node
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum NodePayload {
+    /// Instance of a model asset
+    #[serde(rename = "model")]
+    Model(ModelInstance),
+    /// Light from the scene's `lights`
+    #[serde(rename = "light")]
+    Light(ItemReference),
+    /// Camera
+    #[serde(rename = "camera")]
+    Camera(Camera),
+}
+
+/// Reference to an asset file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum AssetReference {
+    /// Path to the asset, relative to the scene file
+    #[serde(rename = "path")]
+    Path(PathBuf),
+    /// GUID of the asset, as given in its `.meta` sidecar file
+    #[serde(rename = "guid")]
+    Guid(String),
+}
+
+/// Reference to an item within the scene, such as a light or material, either by name or by index
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ItemReference {
+    /// Index of the item in its list
+    Index(usize),
+    /// Name of the item
+    Name(String),
+}
+
+impl ItemReference {
+    /// Find the referenced item in a list of named items
+    pub fn find<'a, T: Named>(&self, items: &'a [T]) -> Option<&'a T> {
+        match *self {
+            ItemReference::Index(index) => items.get(index),
+            ItemReference::Name(ref name) => items.iter().find(|item| item.name() == name),
+        }
+    }
+}
+
+/// Instance of a model placed in the scene
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModelInstance {
+    /// Model asset to instantiate
+    pub model: AssetReference,
+    /// Material overrides, from the name of a material used by the model to a material of the scene
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    #[serde(default)]
+    pub materials: HashMap<String, ItemReference>,
+}
+
+/// Camera structure
+#[derive(Debug, Named, Clone, Serialize, Deserialize)]
+pub struct Camera {
+    /// Name of the camera. Will default to `"Untitled Camera"` if one is not specified.
+    #[serde(default = "Camera::default_name")]
+    pub name: String,
+    /// Vertical field of view, in radians
+    #[serde(default = "Camera::default_fov")]
+    pub fov: f32,
+    /// Near and far clipping distances
+    #[serde(default = "Camera::default_zdistance")]
+    pub zdistance: (f32, f32),
+    /// Exposure of the camera. The default exposure is used if not given.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub exposure: Option<Exposure>,
+}
+
+impl Scene {
+    /// Find a light referenced by a node
+    pub fn light(&self, reference: &ItemReference) -> Option<&Light> {
+        reference.find(&self.lights)
+    }
+
+    /// Find a material referenced by a node
+    pub fn material(&self, reference: &ItemReference) -> Option<&Material> {
+        reference.find(&self.materials)
+    }
 }
 
 /// Varieties of lights
@@ -100,15 +200,55 @@ pub struct Light {
     /// Outer cone angle (in radians) for spotlights
     #[serde(default = "Light::default_outer_cone")]
     pub outer_cone: f32,
-    /// Intensity (brightness) of the light
+    /// Intensity (brightness) of the light, in the renderer's radiometric units
     #[serde(default = "Light::default_intensity")]
     pub intensity: f32,
+    /// Intensity of the light in photometric units, used instead of `intensity` if given.
+    ///
+    /// Point lights and spotlights take lumens, and directional lights take lux.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub photometric: Option<Photometric>,
+    /// Color temperature of the light in Kelvin, which tints `color` if given
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub temperature: Option<f32>,
     /// Any arbitrary properties the engine might check for
     #[serde(skip_serializing_if = "HashMap::is_empty")]
     #[serde(default)]
     pub properties: HashMap<String, String>,
 }
 
+impl Light {
+    /// How the light spreads out its light, for converting photometric units
+    pub fn emitter(&self) -> Emitter {
+        match self.kind {
+            LightKind::Directional => Emitter::Directional,
+            LightKind::Point => Emitter::Point,
+            LightKind::Spotlight => Emitter::Spot { outer_cone: self.outer_cone },
+        }
+    }
+
+    /// Intensity of the light in the renderer's radiometric units,
+    /// converted from `photometric` if given, otherwise just `intensity`.
+    ///
+    /// Returns `None` if the photometric unit doesn't apply to the kind of light.
+    pub fn radiometric_intensity(&self) -> Option<f32> {
+        match self.photometric {
+            Some(photometric) => photometric.to_radiometric(self.emitter()),
+            None => Some(self.intensity),
+        }
+    }
+
+    /// Color of the light tinted by its color temperature, if any
+    pub fn tinted_color(&self) -> Color {
+        match self.temperature {
+            Some(kelvin) => self.color.tinted(Color::from_temperature(kelvin)),
+            None => self.color,
+        }
+    }
+}
+
 /// Material structure
 #[derive(Debug, Named, Clone, Serialize, Deserialize)]
 pub struct Material {
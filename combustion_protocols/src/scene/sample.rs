@@ -1,5 +1,6 @@
 //! Utilities for generating sample scenes
 
+use std::collections::HashMap;
 use std::iter::{Iterator, repeat};
 
 use common::traits::Named;
@@ -29,7 +30,12 @@ pub fn sample() -> Scene {
             transform: vec![
                 Transform::Matrix(Matrix4::new_identity(4)),
                 Transform::Translation(Vector3::new(1.2, -0.251, 0.1456))
-            ]
+            ],
+            payloads: vec![
+                NodePayload::Light(ItemReference::Index(0)),
+                NodePayload::Camera(Camera::default()),
+            ],
+            properties: HashMap::new(),
         }
     }
 }
\ No newline at end of file
@@ -0,0 +1,161 @@
+//! JSON Schemas for scene structures
+
+use nalgebra::{Vector3, Point3};
+
+use serde_json::Value;
+
+use common::color::Color;
+use common::photometry::{Photometric, Exposure};
+
+use ::schema::{JsonSchema, Definitions, object, array_of, map_of, tuple, optional, string_enum, describe, number, index, string};
+use ::math::data::Transform;
+
+use super::{Scene, Node, NodePayload, AssetReference, ItemReference, ModelInstance, Camera, Light, LightKind, Material};
+
+impl JsonSchema for Scene {
+    fn schema_name() -> &'static str { "Scene" }
+
+    fn schema(definitions: &mut Definitions) -> Value {
+        object(vec![
+            ("name", string()),
+            ("lights", array_of(definitions.reference::<Light>())),
+            ("materials", array_of(definitions.reference::<Material>())),
+            ("root", definitions.reference::<Node>()),
+        ], &["lights", "materials", "root"])
+    }
+}
+
+impl JsonSchema for Node {
+    fn schema_name() -> &'static str { "SceneNode" }
+
+    fn schema(definitions: &mut Definitions) -> Value {
+        object(vec![
+            ("name", string()),
+            ("children", array_of(definitions.reference::<Node>())),
+            ("transform", array_of(definitions.reference::<Transform>())),
+            ("payloads", array_of(definitions.reference::<NodePayload>())),
+            ("properties", map_of(string())),
+        ], &[])
+    }
+}
+
+impl JsonSchema for NodePayload {
+    fn schema_name() -> &'static str { "NodePayload" }
+
+    fn schema(definitions: &mut Definitions) -> Value {
+        json!({
+            "oneOf": [
+                object(vec![("model", definitions.reference::<ModelInstance>())], &["model"]),
+                object(vec![("light", definitions.reference::<ItemReference>())], &["light"]),
+                object(vec![("camera", definitions.reference::<Camera>())], &["camera"]),
+            ]
+        })
+    }
+}
+
+impl JsonSchema for AssetReference {
+    fn schema_name() -> &'static str { "AssetReference" }
+
+    fn schema(_: &mut Definitions) -> Value {
+        json!({
+            "oneOf": [
+                object(vec![("path", describe(string(), "Path relative to the scene file"))], &["path"]),
+                object(vec![("guid", describe(string(), "GUID from the asset's .meta file"))], &["guid"]),
+            ]
+        })
+    }
+}
+
+/// `ItemReference` is untagged, so either form is accepted as-is
+impl JsonSchema for ItemReference {
+    fn schema_name() -> &'static str { "ItemReference" }
+
+    fn schema(_: &mut Definitions) -> Value {
+        json!({ "anyOf": [index(), string()] })
+    }
+}
+
+impl JsonSchema for ModelInstance {
+    fn schema_name() -> &'static str { "ModelInstance" }
+
+    fn schema(definitions: &mut Definitions) -> Value {
+        object(vec![
+            ("model", definitions.reference::<AssetReference>()),
+            ("materials", map_of(definitions.reference::<ItemReference>())),
+        ], &["model"])
+    }
+}
+
+impl JsonSchema for Camera {
+    fn schema_name() -> &'static str { "Camera" }
+
+    fn schema(definitions: &mut Definitions) -> Value {
+        object(vec![
+            ("name", string()),
+            ("fov", describe(number(), "Vertical field of view in radians")),
+            ("zdistance", tuple(vec![number(), number()])),
+            ("exposure", optional(definitions.reference::<Exposure>())),
+        ], &[])
+    }
+}
+
+impl JsonSchema for Exposure {
+    fn schema_name() -> &'static str { "Exposure" }
+
+    fn schema(_: &mut Definitions) -> Value {
+        object(vec![("ev100", describe(number(), "Exposure value at ISO 100"))], &["ev100"])
+    }
+}
+
+impl JsonSchema for LightKind {
+    fn schema_name() -> &'static str { "LightKind" }
+
+    fn schema(_: &mut Definitions) -> Value {
+        string_enum(&["directional", "point", "spotlight"])
+    }
+}
+
+impl JsonSchema for Light {
+    fn schema_name() -> &'static str { "Light" }
+
+    fn schema(definitions: &mut Definitions) -> Value {
+        object(vec![
+            ("name", string()),
+            ("zdistance", tuple(vec![number(), number()])),
+            ("position", definitions.reference::<Point3<f32>>()),
+            ("direction", definitions.reference::<Vector3<f32>>()),
+            ("color", definitions.reference::<Color>()),
+            ("ambient", definitions.reference::<Color>()),
+            ("kind", definitions.reference::<LightKind>()),
+            ("effect_radius", number()),
+            ("inner_cone", number()),
+            ("outer_cone", number()),
+            ("intensity", number()),
+            ("photometric", optional(definitions.reference::<Photometric>())),
+            ("temperature", optional(describe(number(), "Color temperature in Kelvin"))),
+            ("properties", map_of(string())),
+        ], &[])
+    }
+}
+
+impl JsonSchema for Photometric {
+    fn schema_name() -> &'static str { "Photometric" }
+
+    fn schema(_: &mut Definitions) -> Value {
+        json!({
+            "oneOf": [
+                object(vec![("lumens", describe(number(), "Luminous power, for point lights and spotlights"))], &["lumens"]),
+                object(vec![("lux", describe(number(), "Illuminance, for directional lights"))], &["lux"]),
+                object(vec![("nits", describe(number(), "Luminance, for emissive surfaces"))], &["nits"]),
+            ]
+        })
+    }
+}
+
+impl JsonSchema for Material {
+    fn schema_name() -> &'static str { "SceneMaterial" }
+
+    fn schema(_: &mut Definitions) -> Value {
+        object(vec![("name", string())], &[])
+    }
+}
@@ -0,0 +1,303 @@
+//! JSON Schemas for human-editable protocol structures
+//!
+//! Schemas follow JSON Schema draft 4 and mirror how each structure is deserialized with Serde,
+//! including custom deserializers like colors given by name or value.
+//!
+//! Objects in the schemas don't allow additional properties, because with `#[serde(default)]` a misspelled key
+//! would otherwise silently fall back to its default value. `unknown_keys` and `validate` report such keys
+//! along with their path in the document, which works just as well for YAML documents deserialized into a `Value`.
+
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+use serde_json::{Map, Value};
+
+use common::color::Color;
+
+/// `$schema` URI for generated schemas
+pub const DRAFT: &'static str = "http://json-schema.org/draft-04/schema#";
+
+/// Types that can describe themselves with a JSON Schema
+pub trait JsonSchema {
+    /// Name of the type's entry in a schema's `definitions`
+    fn schema_name() -> &'static str;
+
+    /// Schema for the type, adding any types it refers to into `definitions`
+    fn schema(definitions: &mut Definitions) -> Value;
+}
+
+/// Named schemas shared within a root schema
+#[derive(Debug, Default)]
+pub struct Definitions(Map<String, Value>);
+
+impl Definitions {
+    /// Create an empty set of definitions
+    pub fn new() -> Definitions {
+        Definitions(Map::new())
+    }
+
+    /// Get a `$ref` to the definition of `T`, adding the definition if it isn't there yet
+    pub fn reference<T: JsonSchema>(&mut self) -> Value {
+        let name = T::schema_name();
+
+        if !self.0.contains_key(name) {
+            // Placeholder so recursive types refer to themselves instead of recursing forever
+            self.0.insert(name.to_string(), Value::Null);
+
+            let schema = T::schema(self);
+
+            self.0.insert(name.to_string(), schema);
+        }
+
+        json!({ "$ref": format!("#/definitions/{}", name) })
+    }
+
+    /// Consume the definitions and return them as a JSON object
+    pub fn into_inner(self) -> Map<String, Value> {
+        self.0
+    }
+}
+
+/// Generate a complete schema document for `T`
+pub fn schema_for<T: JsonSchema>() -> Value {
+    let mut definitions = Definitions::new();
+
+    let reference = definitions.reference::<T>();
+
+    json!({
+        "$schema": DRAFT,
+        "title": T::schema_name(),
+        "allOf": [reference],
+        "definitions": Value::Object(definitions.into_inner()),
+    })
+}
+
+/// Schema for an object with exactly the given properties, of which `required` must be present
+pub fn object(properties: Vec<(&str, Value)>, required: &[&str]) -> Value {
+    let mut map = Map::new();
+
+    for (name, schema) in properties {
+        map.insert(name.to_string(), schema);
+    }
+
+    let mut schema = Map::new();
+
+    schema.insert("type".to_string(), json!("object"));
+    schema.insert("properties".to_string(), Value::Object(map));
+    schema.insert("additionalProperties".to_string(), Value::Bool(false));
+
+    if !required.is_empty() {
+        schema.insert("required".to_string(), Value::Array(required.iter().map(|&name| json!(name)).collect()));
+    }
+
+    Value::Object(schema)
+}
+
+/// Schema for an object with arbitrary keys, all with values matching `values`
+pub fn map_of(values: Value) -> Value {
+    json!({ "type": "object", "additionalProperties": values })
+}
+
+/// Schema for an array with all items matching `items`
+pub fn array_of(items: Value) -> Value {
+    json!({ "type": "array", "items": items })
+}
+
+/// Schema for a fixed-length array, such as a tuple
+pub fn tuple(items: Vec<Value>) -> Value {
+    let len = items.len();
+
+    json!({ "type": "array", "items": items, "minItems": len, "maxItems": len })
+}
+
+/// Schema for an `Option`, which may also be `null`
+pub fn optional(schema: Value) -> Value {
+    json!({ "anyOf": [schema, { "type": "null" }] })
+}
+
+/// Schema for one of the given strings, as used for unit enum variants
+pub fn string_enum(variants: &[&str]) -> Value {
+    let variants: Vec<Value> = variants.iter().map(|&variant| json!(variant)).collect();
+
+    json!({ "type": "string", "enum": variants })
+}
+
+/// Schema for any number
+pub fn number() -> Value {
+    json!({ "type": "number" })
+}
+
+/// Schema for an index into some list
+pub fn index() -> Value {
+    json!({ "type": "integer", "minimum": 0 })
+}
+
+/// Schema for any string
+pub fn string() -> Value {
+    json!({ "type": "string" })
+}
+
+/// Add a description to a schema
+pub fn describe(mut schema: Value, description: &str) -> Value {
+    if let Value::Object(ref mut map) = schema {
+        map.insert("description".to_string(), json!(description));
+    }
+
+    schema
+}
+
+/// Colors can be given by name or as a structure, as with `color::de::from_name_or_value`
+impl JsonSchema for Color {
+    fn schema_name() -> &'static str { "Color" }
+
+    fn schema(_: &mut Definitions) -> Value {
+        json!({
+            "oneOf": [
+                describe(string(), "Color name, such as \"coral\". Unknown names are transparent black."),
+                object(vec![
+                    ("r", number()),
+                    ("g", number()),
+                    ("b", number()),
+                    ("a", number()),
+                ], &[]),
+            ]
+        })
+    }
+}
+
+/// Key in a document that isn't part of its schema
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct UnknownKey {
+    /// JSON Pointer to the key, e.g. `/materials/sapphire/colour`
+    pub path: String,
+}
+
+impl Display for UnknownKey {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "Unknown key {}", self.path)
+    }
+}
+
+/// Check `value` against the schema of `T`, returning any unknown keys
+pub fn validate<T: JsonSchema>(value: &Value) -> Vec<UnknownKey> {
+    unknown_keys(&schema_for::<T>(), value)
+}
+
+/// Check `value` against a schema document, returning any keys not allowed by the schema.
+///
+/// Only unknown keys are reported, so this doesn't replace a full validator for values of the wrong type.
+/// Where the schema allows alternatives, the alternative matching the value most closely is used.
+pub fn unknown_keys(schema: &Value, value: &Value) -> Vec<UnknownKey> {
+    let mut unknown = Vec::new();
+
+    walk(schema, schema, value, "", &mut unknown);
+
+    unknown
+}
+
+fn field<'a>(schema: &'a Value, key: &str) -> Option<&'a Value> {
+    schema.as_object().and_then(|object| object.get(key))
+}
+
+// Follows `$ref`s to definitions in the root schema
+fn resolve<'a>(root: &'a Value, mut schema: &'a Value) -> &'a Value {
+    // Bounded in case of malformed schemas with reference cycles
+    for _ in 0..32 {
+        let name = match field(schema, "$ref").and_then(Value::as_str) {
+            Some(reference) if reference.starts_with("#/definitions/") => &reference["#/definitions/".len()..],
+            _ => break,
+        };
+
+        match field(root, "definitions").and_then(|definitions| field(definitions, name)) {
+            Some(definition) => schema = definition,
+            None => break,
+        }
+    }
+
+    schema
+}
+
+fn type_matches(root: &Value, schema: &Value, value: &Value) -> bool {
+    let schema = resolve(root, schema);
+
+    match field(schema, "type").and_then(Value::as_str) {
+        Some("object") => value.is_object(),
+        Some("array") => value.is_array(),
+        Some("string") => value.is_string(),
+        Some("number") => value.is_number(),
+        Some("integer") => value.is_u64() || value.is_i64(),
+        Some("boolean") => value.is_boolean(),
+        Some("null") => value.is_null(),
+        _ => true,
+    }
+}
+
+// Escapes a key for use in a JSON Pointer
+fn escape(key: &str) -> String {
+    key.replace("~", "~0").replace("/", "~1")
+}
+
+fn walk(root: &Value, schema: &Value, value: &Value, path: &str, unknown: &mut Vec<UnknownKey>) {
+    let schema = resolve(root, schema);
+
+    if let Some(branches) = field(schema, "allOf").and_then(Value::as_array) {
+        for branch in branches {
+            walk(root, branch, value, path, unknown);
+        }
+    }
+
+    let alternatives = field(schema, "anyOf").or_else(|| field(schema, "oneOf")).and_then(Value::as_array);
+
+    if let Some(branches) = alternatives {
+        let mut best: Option<Vec<UnknownKey>> = None;
+
+        for branch in branches.iter().filter(|branch| type_matches(root, branch, value)) {
+            let mut found = Vec::new();
+
+            walk(root, branch, value, path, &mut found);
+
+            if best.as_ref().map_or(true, |best| found.len() < best.len()) {
+                best = Some(found);
+            }
+        }
+
+        unknown.extend(best.unwrap_or_else(Vec::new));
+
+        return;
+    }
+
+    match *value {
+        Value::Object(ref map) => {
+            let properties = field(schema, "properties");
+            let additional = field(schema, "additionalProperties");
+
+            for (key, child) in map.iter() {
+                let child_path = format!("{}/{}", path, escape(key));
+
+                match properties.and_then(|properties| field(properties, key)) {
+                    Some(property) => walk(root, property, child, &child_path, unknown),
+                    None => match additional {
+                        Some(&Value::Bool(false)) => unknown.push(UnknownKey { path: child_path }),
+                        Some(additional) if additional.is_object() => walk(root, additional, child, &child_path, unknown),
+                        _ => {}
+                    }
+                }
+            }
+        },
+        Value::Array(ref values) => {
+            match field(schema, "items") {
+                Some(&Value::Array(ref items)) => {
+                    for (i, (item, child)) in items.iter().zip(values).enumerate() {
+                        walk(root, item, child, &format!("{}/{}", path, i), unknown);
+                    }
+                },
+                Some(items) => {
+                    for (i, child) in values.iter().enumerate() {
+                        walk(root, items, child, &format!("{}/{}", path, i), unknown);
+                    }
+                },
+                None => {}
+            }
+        },
+        _ => {}
+    }
+}
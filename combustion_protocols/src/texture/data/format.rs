@@ -39,6 +39,25 @@ impl Uncompressed {
     pub fn new(channels: Channels, data_type: DataType) -> Uncompressed {
         Uncompressed { channels: channels, data_type: data_type }
     }
+
+    /// Number of bytes a single pixel takes up
+    ///
+    /// Packed data types store all channels in a single value, so their size doesn't depend on the channels.
+    pub fn pixel_bytes(&self) -> usize {
+        let channels = self.channels.num_channels();
+
+        match self.data_type {
+            DataType::UnsignedByte | DataType::Byte | DataType::Unspecified => channels,
+            DataType::UnsignedShort | DataType::Short => channels * 2,
+            DataType::UnsignedInt | DataType::Int | DataType::Float => channels * 4,
+            DataType::UnsignedByte332 | DataType::UnsignedByte233Rev => 1,
+            DataType::UnsignedShort565 | DataType::UnsignedShort565Rev |
+            DataType::UnsignedShort4444 | DataType::UnsignedShort4444Rev |
+            DataType::UnsignedShort5551 | DataType::UnsignedShort1555Rev => 2,
+            DataType::UnsignedInt8888 | DataType::UnsignedInt8888Rev |
+            DataType::UnsignedInt1010102 | DataType::UnsignedInt2101010Rev => 4,
+        }
+    }
 }
 
 impl Channels {
@@ -173,6 +192,29 @@ impl Which {
             _ => DataType::Unspecified,
         }
     }
+
+    /// Returns the width and height in pixels of the blocks the format is stored in
+    ///
+    /// Uncompressed formats are treated as having 1x1 blocks.
+    pub fn block_dimensions(&self) -> (u32, u32) {
+        match *self {
+            Which::None(_) => (1, 1),
+            Which::Astc(blocksize) => blocksize.dimensions(),
+            _ => (4, 4),
+        }
+    }
+
+    /// Returns the number of bytes a single block takes up
+    pub fn block_bytes(&self) -> usize {
+        use self::protocol::{Rgtc, S3tc};
+
+        match *self {
+            Which::None(uncompressed) => uncompressed.pixel_bytes(),
+            Which::Rgtc(Rgtc::Red) | Which::Rgtc(Rgtc::RedSigned) => 8,
+            Which::S3tc(S3tc::Rgb1) | Which::S3tc(S3tc::Rgba1) => 8,
+            _ => 16,
+        }
+    }
 }
 
 /// Structure to store random properties until it needs to be converted into a `SpecificFormat`
@@ -333,6 +375,26 @@ impl SpecificFormat {
             _ => true,
         }
     }
+
+    /// Computes the number of bytes an image of the given dimensions takes up in this format
+    ///
+    /// Zero dimensions are treated as one, so 1D and 2D textures can be given a depth of zero.
+    /// Sizes too large for a `usize` saturate at `usize::max_value()`.
+    pub fn image_size(&self, width: u32, height: u32, depth: u32) -> usize {
+        self.checked_image_size(width, height, depth).unwrap_or(usize::max_value())
+    }
+
+    /// Size in bytes of a single image with the given dimensions, or `None` if it doesn't fit in a `usize`
+    pub fn checked_image_size(&self, width: u32, height: u32, depth: u32) -> Option<usize> {
+        let (block_width, block_height) = self.which.block_dimensions();
+
+        let blocks_wide = (width.max(1) - 1) / block_width + 1;
+        let blocks_high = (height.max(1) - 1) / block_height + 1;
+
+        (blocks_wide as usize).checked_mul(blocks_high as usize)
+            .and_then(|blocks| blocks.checked_mul(depth.max(1) as usize))
+            .and_then(|blocks| blocks.checked_mul(self.which.block_bytes()))
+    }
 }
 
 impl ::std::fmt::Display for SpecificFormat {
@@ -29,6 +29,19 @@ impl RootTexture {
             RootTexture::Array(ref array) => array.iter().any(|texture| texture.is_compressed())
         }
     }
+
+    /// Returns the shape of the `RootTexture`, taken from the first texture it contains
+    ///
+    /// Returns `None` for empty arrays.
+    pub fn info(&self) -> Option<TextureInfo> {
+        match *self {
+            RootTexture::Texture(ref texture) => Some(texture.info()),
+            RootTexture::Cubemap(ref cubemap) => Some(TextureInfo { faces: 6, ..cubemap.right.info() }),
+            RootTexture::Array(ref array) => array.first().map(|texture| {
+                TextureInfo { layers: array.len() as u32, ..texture.info() }
+            }),
+        }
+    }
 }
 
 /// Texture dimensions
@@ -54,6 +67,59 @@ impl Dimensions {
     pub fn to_tuple(&self) -> (u32, u32, u32) {
         (self.width, self.height, self.depth)
     }
+
+    /// Returns the dimensions of the given mipmap level, where level zero is the full texture
+    ///
+    /// Unused (zero) dimensions stay zero, and used dimensions never go below one.
+    pub fn mip_level(&self, level: u32) -> Dimensions {
+        fn shrink(dimension: u32, level: u32) -> u32 {
+            if dimension == 0 { 0 } else { (dimension.checked_shr(level).unwrap_or(0)).max(1) }
+        }
+
+        Dimensions {
+            width: shrink(self.width, level),
+            height: shrink(self.height, level),
+            depth: shrink(self.depth, level),
+        }
+    }
+}
+
+/// Shape of a texture or set of textures, without any of the image data
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TextureInfo {
+    /// Format of every image
+    pub format: SpecificFormat,
+    /// Texture kind
+    pub kind: TextureKind,
+    /// Dimensions of the largest mipmap level
+    pub dimensions: Dimensions,
+    /// Number of mipmap levels, at least one
+    pub levels: u32,
+    /// Number of array layers, or zero if the texture is not an array
+    pub layers: u32,
+    /// Number of cubemap faces, either one or six
+    pub faces: u32,
+}
+
+impl TextureInfo {
+    /// Number of array layers, treating non-arrays as a single layer
+    pub fn layer_count(&self) -> u32 {
+        self.layers.max(1)
+    }
+
+    /// Size in bytes of a single image at the given mipmap level
+    pub fn image_size(&self, level: u32) -> usize {
+        let dimensions = self.dimensions.mip_level(level);
+
+        self.format.image_size(dimensions.width, dimensions.height, dimensions.depth)
+    }
+
+    /// Total size in bytes of every image at every mipmap level
+    pub fn total_size(&self) -> usize {
+        let images = self.layer_count() as usize * self.faces as usize;
+
+        (0..self.levels).fold(0, |size, level| size + self.image_size(level) * images)
+    }
 }
 
 /// Represents a single texture
@@ -67,6 +133,9 @@ pub struct Texture {
     pub kind: TextureKind,
     /// Storage format
     pub format: SpecificFormat,
+    /// Mipmap levels below the full texture, largest first and in the same format
+    #[serde(default)]
+    pub mipmaps: Vec<Blob>,
 }
 
 impl Texture {
@@ -74,6 +143,37 @@ impl Texture {
     pub fn is_compressed(&self) -> bool {
         self.format.is_compressed()
     }
+
+    /// Number of mipmap levels, including the full texture
+    pub fn levels(&self) -> u32 {
+        self.mipmaps.len() as u32 + 1
+    }
+
+    /// Returns the data for the given mipmap level, where level zero is the full texture
+    pub fn level(&self, level: u32) -> Option<&Blob> {
+        if level == 0 {
+            Some(&self.data)
+        } else {
+            self.mipmaps.get(level as usize - 1)
+        }
+    }
+
+    /// Total size of the texture data including mipmaps
+    pub fn total_size(&self) -> usize {
+        self.mipmaps.iter().fold(self.data.len(), |size, mipmap| size + mipmap.len())
+    }
+
+    /// Returns the shape of the texture as a single non-array, non-cubemap texture
+    pub fn info(&self) -> TextureInfo {
+        TextureInfo {
+            format: self.format,
+            kind: self.kind,
+            dimensions: self.dimensions,
+            levels: self.levels(),
+            layers: 0,
+            faces: 1,
+        }
+    }
 }
 
 /// Represents a cubemap made of six unique textures
@@ -55,6 +55,28 @@ impl BlockSize {
             BlockSize::B12x12 => "12x12",
         }
     }
+
+    /// Width and height of a single block in pixels
+    ///
+    /// E.g. `BlockSize::B10x8.dimensions() == (10, 8)`
+    pub fn dimensions(&self) -> (u32, u32) {
+        match *self {
+            BlockSize::B4x4 => (4, 4),
+            BlockSize::B5x4 => (5, 4),
+            BlockSize::B5x5 => (5, 5),
+            BlockSize::B6x5 => (6, 5),
+            BlockSize::B6x6 => (6, 6),
+            BlockSize::B8x5 => (8, 5),
+            BlockSize::B8x6 => (8, 6),
+            BlockSize::B10x5 => (10, 5),
+            BlockSize::B10x6 => (10, 6),
+            BlockSize::B8x8 => (8, 8),
+            BlockSize::B10x8 => (10, 8),
+            BlockSize::B10x10 => (10, 10),
+            BlockSize::B12x10 => (12, 10),
+            BlockSize::B12x12 => (12, 12),
+        }
+    }
 }
 
 impl ::std::fmt::Display for BlockSize {
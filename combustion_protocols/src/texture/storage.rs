@@ -1,10 +1,10 @@
 //! Storage routines for textures
 
-use ::error::ProtocolResult;
+use ::error::{ProtocolResult, ProtocolError};
 use ::traits::{Storage, StorageQuery};
 
 use super::data::{format, texture};
-use super::data::texture::{Texture, RootTexture};
+use super::data::texture::{Texture, RootTexture, TextureInfo};
 use super::protocol;
 
 /// Query for determining `RootTexture` variation without actually loading the data into memory
@@ -23,56 +23,95 @@ impl StorageQuery for RootTextureQuery {
     type Result = RootTextureQuery;
 }
 
+/// Query for reading the shape of a `Texture` from its header fields, without copying the image data
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TextureInfoQuery;
+
+impl StorageQuery for TextureInfoQuery {
+    type Arguments = ();
+    type Result = TextureInfo;
+}
+
+fn load_format(reader: &protocol::texture::Reader) -> ProtocolResult<format::SpecificFormat> {
+    let compression_reader = reader.get_compression();
+
+    let which = match try_throw!(compression_reader.which()) {
+        protocol::texture::compression::None(uncompressed_reader) => {
+            let uncompressed_reader = try_throw!(uncompressed_reader);
+
+            format::Which::None(format::Uncompressed {
+                channels: try_throw!(uncompressed_reader.get_format()),
+                data_type: try_throw!(uncompressed_reader.get_type()),
+            })
+        },
+        protocol::texture::compression::Rgtc(rgtc) => format::Which::Rgtc(try_throw!(rgtc)),
+        protocol::texture::compression::Bptc(bptc) => format::Which::Bptc(try_throw!(bptc)),
+        protocol::texture::compression::S3tc(s3tc) => format::Which::S3tc(try_throw!(s3tc)),
+        protocol::texture::compression::Astc(blocksize) => format::Which::Astc(try_throw!(blocksize)),
+    };
+
+    Ok(format::SpecificFormat {
+        which: which,
+        srgb: reader.get_srgb(),
+    })
+}
+
+fn load_dimensions(reader: &protocol::texture::Reader) -> texture::Dimensions {
+    let dimensions_reader = reader.get_dimensions();
+
+    texture::Dimensions {
+        width: dimensions_reader.get_width(),
+        height: dimensions_reader.get_height(),
+        depth: dimensions_reader.get_depth(),
+    }
+}
+
 impl<'a> Storage<'a> for Texture {
     type Builder = protocol::texture::Builder<'a>;
     type Reader = protocol::texture::Reader<'a>;
 
     type LoadArgs = ();
     type SaveArgs = ();
-    type Query = ();
+    type Query = TextureInfoQuery;
 
     fn load_from_reader_args(reader: Self::Reader, _: ()) -> ProtocolResult<Texture> {
-        let format = {
-            let compression_reader = reader.get_compression();
+        let format = load_format(&reader)?;
 
-            let which = match try_throw!(compression_reader.which()) {
-                protocol::texture::compression::None(uncompressed_reader) => {
-                    let uncompressed_reader = try_throw!(uncompressed_reader);
+        let dimensions = load_dimensions(&reader);
 
-                    format::Which::None(format::Uncompressed {
-                        channels: try_throw!(uncompressed_reader.get_format()),
-                        data_type: try_throw!(uncompressed_reader.get_type()),
-                    })
-                },
-                protocol::texture::compression::Rgtc(rgtc) => format::Which::Rgtc(try_throw!(rgtc)),
-                protocol::texture::compression::Bptc(bptc) => format::Which::Bptc(try_throw!(bptc)),
-                protocol::texture::compression::S3tc(s3tc) => format::Which::S3tc(try_throw!(s3tc)),
-                protocol::texture::compression::Astc(blocksize) => format::Which::Astc(try_throw!(blocksize)),
-            };
+        let data = try_throw!(reader.get_data());
 
-            let srgb = reader.get_srgb();
+        // Sizes are checked up front so nothing downstream indexes past the end of the image data
+        if Some(data.len()) != format.checked_image_size(dimensions.width, dimensions.height, dimensions.depth) {
+            throw!(ProtocolError::InvalidLength);
+        }
 
-            format::SpecificFormat {
-                which: which,
-                srgb: srgb,
-            }
-        };
+        let mipmaps = {
+            let mipmaps_reader = try_throw!(reader.get_mipmaps());
+
+            let mut mipmaps = Vec::with_capacity(mipmaps_reader.len() as usize);
+
+            for i in 0..mipmaps_reader.len() {
+                let mipmap = try_throw!(mipmaps_reader.get(i));
 
-        let dimensions = {
-            let dimensions_reader = reader.get_dimensions();
+                let level = dimensions.mip_level(i + 1);
 
-            texture::Dimensions {
-                width: dimensions_reader.get_width(),
-                height: dimensions_reader.get_height(),
-                depth: dimensions_reader.get_depth(),
+                if Some(mipmap.len()) != format.checked_image_size(level.width, level.height, level.depth) {
+                    throw!(ProtocolError::InvalidLength);
+                }
+
+                mipmaps.push(mipmap.into());
             }
+
+            mipmaps
         };
 
         Ok(Texture {
-            data: try_throw!(reader.get_data()).into(),
+            data: data.into(),
             dimensions: dimensions,
             kind: try_throw!(reader.get_kind()),
             format: format,
+            mipmaps: mipmaps,
         })
     }
 
@@ -108,11 +147,29 @@ impl<'a> Storage<'a> for Texture {
 
         builder.set_data(self.data.as_slice());
 
+        if !self.mipmaps.is_empty() {
+            let mut mipmaps_builder = builder.borrow().init_mipmaps(self.mipmaps.len() as u32);
+
+            for (i, mipmap) in self.mipmaps.iter().enumerate() {
+                mipmaps_builder.set(i as u32, mipmap.as_slice());
+            }
+        }
+
         Ok(())
     }
 
-    fn query_reader_args(_: Self::Reader, _: ()) -> ProtocolResult<()> {
-        unimplemented!()
+    fn query_reader_args(reader: Self::Reader, _: ()) -> ProtocolResult<TextureInfo> {
+        // Only the length of the mipmap list is read, never the data itself
+        let levels = try_throw!(reader.get_mipmaps()).len() + 1;
+
+        Ok(TextureInfo {
+            format: load_format(&reader)?,
+            kind: try_throw!(reader.get_kind()),
+            dimensions: load_dimensions(&reader),
+            levels: levels,
+            layers: 0,
+            faces: 1,
+        })
     }
 }
 
@@ -208,4 +265,32 @@ impl<'a> Storage<'a> for RootTexture {
             protocol::root_texture::texture::Array(_) => RootTextureQuery::Array,
         })
     }
-}
\ No newline at end of file
+}
+
+impl RootTexture {
+    /// Reads the shape of a stored `RootTexture` from the header fields of its first texture,
+    /// without copying any image data.
+    ///
+    /// The image data is still part of the message being read, so this only saves decoding it.
+    pub fn query_info(reader: protocol::root_texture::Reader) -> ProtocolResult<TextureInfo> {
+        match try_throw!(reader.get_texture().which()) {
+            protocol::root_texture::texture::Texture(texture_reader) => {
+                Texture::query_reader(try_throw!(texture_reader))
+            },
+            protocol::root_texture::texture::Cubemap(cubemap_reader) => {
+                let right_reader = try_throw!(try_throw!(cubemap_reader).get_right());
+
+                Ok(TextureInfo { faces: 6, ..Texture::query_reader(right_reader)? })
+            },
+            protocol::root_texture::texture::Array(array_reader) => {
+                let array_reader = try_throw!(array_reader);
+
+                if array_reader.len() == 0 {
+                    throw!(ProtocolError::NotPresent);
+                }
+
+                Ok(TextureInfo { layers: array_reader.len(), ..Texture::query_reader(array_reader.get(0))? })
+            }
+        }
+    }
+}
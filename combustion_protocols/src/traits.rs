@@ -50,16 +50,12 @@ pub trait Storage<'a> where Self: Sized {
 
     /// Query the storage medium for information
     ///
-    /// # Panics
-    ///
-    /// Panics with `unimplemented!()` if called on storage mediums without query support
+    /// Storage mediums without query support return `ProtocolError::Unsupported`
     fn query_reader_args(reader: Self::Reader, args: <Self::Query as StorageQuery>::Arguments) -> ProtocolResult<<Self::Query as StorageQuery>::Result>;
 
     /// Queries the storage medium with default arguments
     ///
-    /// # Panics
-    ///
-    /// Panics with `unimplemented!()` if called on storage mediums without query support
+    /// Storage mediums without query support return `ProtocolError::Unsupported`
     fn query_reader(reader: Self::Reader) -> ProtocolResult<<Self::Query as StorageQuery>::Result> where <Self::Query as StorageQuery>::Arguments: Default {
         Self::query_reader_args(reader, Default::default())
     }
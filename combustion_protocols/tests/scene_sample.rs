@@ -8,4 +8,42 @@ pub fn json_test() {
     use serde_json::to_string_pretty;
 
     println!("Scene {}", to_string_pretty(&sample_scene()).unwrap());
-}
\ No newline at end of file
+}
+
+#[test]
+pub fn node_payloads() {
+    use serde_json::from_str;
+
+    use protocols::scene::{Scene, NodePayload, AssetReference, ItemReference};
+
+    let scene: Scene = from_str(r#"{
+        "lights": [{ "name": "Sun", "kind": "directional" }],
+        "materials": [{ "name": "Sapphire" }],
+        "root": {
+            "payloads": [
+                { "model": { "model": { "path": "models/ring.model" }, "materials": { "Stone": 0 } } },
+                { "light": "Sun" },
+                { "camera": { "fov": 1.2 } }
+            ],
+            "properties": { "spawn": "true" }
+        }
+    }"#).unwrap();
+
+    let payloads = &scene.root.payloads;
+
+    match payloads[0] {
+        NodePayload::Model(ref instance) => {
+            assert_eq!(instance.model, AssetReference::Path("models/ring.model".into()));
+            assert_eq!(scene.material(&instance.materials["Stone"]).unwrap().name, "Sapphire");
+        },
+        _ => panic!("Expected model payload"),
+    }
+
+    match payloads[1] {
+        NodePayload::Light(ref light) => assert_eq!(*light, ItemReference::Name("Sun".to_string())),
+        _ => panic!("Expected light payload"),
+    }
+
+    assert!(scene.light(&ItemReference::Index(0)).is_some());
+    assert_eq!(scene.root.properties["spawn"], "true");
+}
@@ -0,0 +1,94 @@
+extern crate combustion_protocols as protocols;
+extern crate serde_json;
+extern crate serde_yaml;
+
+use std::fs::File;
+
+use serde_json::Value;
+
+use protocols::material::MaterialMap;
+use protocols::material::sample::sample as sample_material;
+use protocols::scene::Scene;
+use protocols::scene::sample::sample as sample_scene;
+use protocols::mesh::protocol::MeshPrimitive;
+use protocols::mesh::data::{Mesh, MeshVertices, Vertex};
+use protocols::model::data::{Model, Node, LodGroup, LodLevel, LodThreshold};
+use protocols::schema::{validate, UnknownKey};
+
+#[test]
+pub fn material_yaml_test() {
+    let value: Value = serde_yaml::from_reader(File::open("tests/material.yaml").unwrap()).unwrap();
+
+    assert_eq!(validate::<MaterialMap>(&value), Vec::new());
+}
+
+#[test]
+pub fn material_sample_test() {
+    let value: Value = serde_json::from_str(&serde_json::to_string(&sample_material()).unwrap()).unwrap();
+
+    assert_eq!(validate::<MaterialMap>(&value), Vec::new());
+}
+
+#[test]
+pub fn scene_sample_test() {
+    let value: Value = serde_json::from_str(&serde_json::to_string(&sample_scene()).unwrap()).unwrap();
+
+    assert_eq!(validate::<Scene>(&value), Vec::new());
+}
+
+#[test]
+pub fn model_test() {
+    let model = Model {
+        root: Node {
+            name: "Root".to_string(),
+            meshes: vec![0],
+            children: vec![Node {
+                name: "Detail".to_string(),
+                lod: Some(LodGroup {
+                    levels: vec![
+                        LodLevel { meshes: vec![0], threshold: LodThreshold::Distance(10.0) },
+                        LodLevel { meshes: vec![], threshold: LodThreshold::ScreenSize(0.1) },
+                    ],
+                    transition: Some(2.0),
+                }),
+                ..Node::default()
+            }],
+            ..Node::default()
+        },
+        meshes: vec![Mesh {
+            vertices: MeshVertices::Interleaved(vec![Vertex::default(); 3]),
+            indices: Some(vec![0, 1, 2]),
+            materials: vec![0],
+            primitive: MeshPrimitive::Triangles,
+        }],
+        materials: vec!["Sapphire".to_string()],
+    };
+
+    let value: Value = serde_json::from_str(&serde_json::to_string(&model).unwrap()).unwrap();
+
+    assert_eq!(validate::<Model>(&value), Vec::new());
+}
+
+#[test]
+pub fn unknown_keys_test() {
+    let value: Value = serde_yaml::from_str(r#"
+materials:
+  sapphire:
+    colour: blue
+    anisotropy:
+      amount: 0.5
+      rotaton: { x: 0.0, y: 1.0, z: 0.0 }
+  coral:
+    color: { r: 1.0, alpha: 0.5 }
+"#).unwrap();
+
+    let mut unknown = validate::<MaterialMap>(&value);
+
+    unknown.sort_by(|a, b| a.path.cmp(&b.path));
+
+    assert_eq!(unknown, vec![
+        UnknownKey { path: "/materials/coral/color/alpha".to_string() },
+        UnknownKey { path: "/materials/sapphire/anisotropy/rotaton".to_string() },
+        UnknownKey { path: "/materials/sapphire/colour".to_string() },
+    ]);
+}